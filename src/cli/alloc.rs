@@ -1,22 +1,35 @@
 use super::ui;
+use crate::core::allocation::{self, AssetCategory, AssetClassConfig, TargetAllocation};
 use crate::core::analytics::{self, PortfolioValue};
 use crate::core::config::{Investment, Portfolio};
 use crate::core::currency::CurrencyRateProvider;
 use crate::core::metadata::MetadataProvider;
-use crate::core::price::{PriceProvider, PriceResult};
-use anyhow::Result;
+use crate::core::price::{PriceProvider, PriceResult, ProviderResult};
+use anyhow::{Context, Result};
 use comfy_table::Cell;
 use futures::future::join_all;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-pub async fn run(
+/// Investments and their converted value by raw category, grouped by asset class.
+type AssetClassAllocation = BTreeMap<AssetCategory, HashMap<String, Vec<(Investment, f64)>>>;
+
+/// Category allocation of each portfolio along with the fetched prices.
+pub(crate) struct AllocationData {
+    pub price_results: HashMap<String, ProviderResult<PriceResult>>,
+    pub portfolio_values: Vec<PortfolioValue>,
+    /// Investments and their converted value by category, per portfolio
+    pub allocations: Vec<HashMap<String, Vec<(Investment, f64)>>>,
+}
+
+/// Fetches prices and resolves the category of every investment.
+pub(crate) async fn load_allocations(
     portfolios: &[Portfolio],
     symbol_provider: &(dyn PriceProvider + Send + Sync),
     isin_provider: &(dyn PriceProvider + Send + Sync),
     currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
     metadata_provider: &(dyn MetadataProvider + Send + Sync),
     target_currency: &str,
-) -> Result<()> {
+) -> AllocationData {
     // Pre-fetch prices for all investments across portfolios
     let mut investments_to_fetch = HashMap::new();
     for portfolio in portfolios {
@@ -28,7 +41,12 @@ pub async fn run(
                 Investment::MutualFund(mf) => {
                     investments_to_fetch.insert(mf.isin.clone(), isin_provider);
                 }
-                Investment::FixedDeposit(_) => {} // Skip price fetch for FDs
+                Investment::Bond(b) => {
+                    if let Some(symbol) = &b.symbol {
+                        investments_to_fetch.insert(symbol.clone(), symbol_provider);
+                    }
+                }
+                Investment::FixedDeposit(_) | Investment::SmallSavings(_) => {} // Skip price fetch for FDs
             }
         }
     }
@@ -45,7 +63,7 @@ pub async fn run(
         }
     });
 
-    let price_results: HashMap<String, Result<PriceResult>> =
+    let price_results: HashMap<String, ProviderResult<PriceResult>> =
         join_all(price_futures).await.into_iter().collect();
     pb.finish_and_clear();
 
@@ -74,8 +92,53 @@ pub async fn run(
 
     pb.finish_and_clear();
 
+    AllocationData {
+        price_results,
+        portfolio_values,
+        allocations,
+    }
+}
+
+/// Validates the target allocation of each portfolio that has one.
+pub(crate) fn validate_portfolio_targets(
+    portfolios: &[Portfolio],
+    global_target: Option<&TargetAllocation>,
+) -> Result<()> {
+    for portfolio in portfolios {
+        if let Some(target) = portfolio.target_allocation(global_target) {
+            allocation::validate_targets(target)
+                .with_context(|| format!("Invalid target allocation for '{}'", portfolio.name))?;
+        }
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    portfolios: &[Portfolio],
+    symbol_provider: &(dyn PriceProvider + Send + Sync),
+    isin_provider: &(dyn PriceProvider + Send + Sync),
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    metadata_provider: &(dyn MetadataProvider + Send + Sync),
+    target_currency: &str,
+    global_target: Option<&TargetAllocation>,
+    asset_classes: &AssetClassConfig,
+) -> Result<()> {
+    validate_portfolio_targets(portfolios, global_target)?;
+    asset_classes.validate()?;
+
+    let data = load_allocations(
+        portfolios,
+        symbol_provider,
+        isin_provider,
+        currency_provider,
+        metadata_provider,
+        target_currency,
+    )
+    .await;
+
     // Display allocation for each portfolio
-    for (i, portfolio_value) in portfolio_values.iter().enumerate() {
+    for (i, portfolio_value) in data.portfolio_values.iter().enumerate() {
         // Skip empty portfolios
         if portfolio_value.investments.is_empty() {
             continue;
@@ -83,14 +146,20 @@ pub async fn run(
 
         display_allocation_table(
             &portfolios[i].name,
-            &allocations[i],
+            &data.allocations[i],
             portfolio_value.total_converted_value,
             target_currency,
-            &price_results,
+            &data.price_results,
+            portfolios[i].target_allocation(global_target),
+            asset_classes,
         );
     }
 
-    Ok(())
+    ui::report_provider_errors(
+        data.portfolio_values
+            .iter()
+            .flat_map(PortfolioValue::errors),
+    )
 }
 
 async fn calculate_allocations(
@@ -105,13 +174,10 @@ async fn calculate_allocations(
 
     // Display allocation for each portfolio
     for (i, portfolio_value) in portfolio_values.iter().enumerate() {
-        // Skip empty portfolios
-        if portfolio_value.investments.is_empty() {
-            continue;
-        }
+        // Keep one entry per portfolio, empty portfolios have no categories
+        portfolio_categories.push(HashMap::new());
 
         // Accumulate investments by category (using raw fund_category strings)
-        portfolio_categories.push(HashMap::new());
         let portfolio = &portfolios[i];
 
         for (investment, value) in portfolio
@@ -125,6 +191,8 @@ async fn calculate_allocations(
                     Investment::FixedDeposit(fd) => {
                         fd.category.clone().unwrap_or("Debt".to_string())
                     }
+                    Investment::SmallSavings(s) => s.category.clone().unwrap_or("Debt".to_string()),
+                    Investment::Bond(b) => b.category.clone().unwrap_or("Debt".to_string()),
                     Investment::MutualFund(mf) => {
                         if let Some(cat) = &mf.category {
                             cat.clone()
@@ -157,82 +225,181 @@ async fn calculate_allocations(
     portfolio_categories
 }
 
+/// Groups categories by asset class, splitting hybrid holdings if configured.
+fn group_by_asset_class(
+    allocation: &HashMap<String, Vec<(Investment, f64)>>,
+    asset_classes: &AssetClassConfig,
+) -> AssetClassAllocation {
+    let mut grouped = AssetClassAllocation::new();
+    for (category, investments) in allocation {
+        for (class, share) in asset_classes.split(asset_classes.classify(category)) {
+            grouped
+                .entry(class)
+                .or_default()
+                .entry(category.clone())
+                .or_default()
+                .extend(investments.iter().map(|(inv, v)| (inv.clone(), v * share)));
+        }
+    }
+    grouped
+}
+
+/// Target weight of the portion of a category counted under `class`, so a
+/// hybrid split into equity and debt has its target split by the same ratio.
+fn split_target(
+    target: &TargetAllocation,
+    asset_classes: &AssetClassConfig,
+    class: AssetCategory,
+    category: &str,
+) -> f64 {
+    let share = asset_classes
+        .split(asset_classes.classify(category))
+        .into_iter()
+        .find(|(split_class, _)| *split_class == class)
+        .map_or(1.0, |(_, share)| share);
+    allocation::target_for(target, category).unwrap_or(0.0) * share
+}
+
+fn percentage(value: f64, total: f64) -> f64 {
+    if total > 0.0 {
+        value / total * 100.0
+    } else {
+        0.0
+    }
+}
+
 fn display_allocation_table(
     portfolio_name: &str,
     allocation: &HashMap<String, Vec<(Investment, f64)>>,
     total_value: Option<f64>,
     target_currency: &str,
-    price_results: &HashMap<String, Result<PriceResult>>,
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
+    target: Option<&TargetAllocation>,
+    asset_classes: &AssetClassConfig,
 ) {
     let mut table = ui::new_styled_table();
-    table.set_header(vec![
+    let mut header = vec![
+        ui::header_cell("Asset Class"),
         ui::header_cell("Category"),
         ui::header_cell("Investment"),
         ui::header_cell("Allocation"),
         ui::header_cell(&format!("Value ({})", target_currency)),
-    ]);
+    ];
+    if target.is_some() {
+        header.push(ui::header_cell("Target"));
+        header.push(ui::header_cell("Drift"));
+    }
+    table.set_header(header);
+
+    let sum_values =
+        |investments: &[(Investment, f64)]| -> f64 { investments.iter().map(|(_, v)| v).sum() };
 
     // Calculate portfolio total
     let total = total_value.unwrap_or_else(|| {
         allocation
             .values()
-            .flat_map(|investments| investments.iter().map(|(_, v)| *v))
+            .map(|investments| sum_values(investments))
             .sum()
     });
 
-    // Convert to Vec for sorting
-    let mut categories: Vec<_> = allocation.iter().collect();
-    // Sort by total category value (descending)
-    categories.sort_by(|(_, a), (_, b)| {
-        let a_total: f64 = a.iter().map(|(_, v)| v).sum();
-        let b_total: f64 = b.iter().map(|(_, v)| v).sum();
-        b_total.partial_cmp(&a_total).unwrap()
-    });
-
-    for (category, investments) in &mut categories {
-        // Calculate category total
-        let category_total: f64 = investments.iter().map(|(_, v)| v).sum();
-        let category_percentage = if total > 0.0 {
-            category_total / total * 100.0
-        } else {
-            0.0
-        };
-
-        // Display category row using raw category string
+    // Sort asset classes by total value (descending)
+    let grouped = group_by_asset_class(allocation, asset_classes);
+    let mut classes: Vec<_> = grouped
+        .iter()
+        .map(|(class, categories)| {
+            let class_total: f64 = categories.values().map(|inv| sum_values(inv)).sum();
+            (class, categories, class_total)
+        })
+        .collect();
+    classes.sort_by(|(_, _, a), (_, _, b)| b.partial_cmp(a).unwrap());
+
+    for (class, categories, class_total) in classes {
+        let (class_name, emoji) = class.display_info();
         table.add_row(vec![
-            Cell::new(&category),
+            Cell::new(format!("{emoji} {class_name}")),
             Cell::new(""),
-            ui::format_percentage_cell(category_percentage, |v| format!("{:.2}%", v)),
-            ui::format_optional_cell(Some(category_total), |v| format!("{:.2}", v)),
+            Cell::new(""),
+            ui::format_percentage_cell(percentage(class_total, total), |v| format!("{:.2}%", v)),
+            ui::format_optional_cell(Some(class_total), |v| format!("{:.2}", v)),
         ]);
 
-        // Display investments in this category
-        for (investment, value) in investments.iter() {
-            let display_name = match investment {
-                Investment::Stock(stock) => price_results
-                    .get(&stock.symbol)
-                    .and_then(|pr| pr.as_ref().ok())
-                    .and_then(|pr| pr.short_name.clone())
-                    .unwrap_or_else(|| stock.symbol.clone()),
-                Investment::MutualFund(mf) => price_results
-                    .get(&mf.isin)
-                    .and_then(|pr| pr.as_ref().ok())
-                    .and_then(|pr| pr.short_name.clone())
-                    .unwrap_or_else(|| mf.isin.clone()),
-                Investment::FixedDeposit(fd) => fd.name.clone(),
-            };
+        // Sort categories by value within the asset class (descending)
+        let mut categories: Vec<_> = categories.iter().collect();
+        categories.sort_by(|(_, a), (_, b)| sum_values(b).partial_cmp(&sum_values(a)).unwrap());
 
-            let allocation_perc = if total > 0.0 {
-                *value / total * 100.0
-            } else {
-                0.0
-            };
+        for (category, investments) in categories {
+            let category_total = sum_values(investments);
+            let mut row = vec![
+                Cell::new(""),
+                Cell::new(category),
+                Cell::new(""),
+                ui::format_optional_cell(Some(percentage(category_total, total)), |v| {
+                    format!("{:.2}%", v)
+                }),
+                ui::format_optional_cell(Some(category_total), |v| format!("{:.2}", v)),
+            ];
+            if let Some(target) = target {
+                let target_pct = split_target(target, asset_classes, *class, category);
+                let actual_pct = percentage(category_total, total);
+                row.push(ui::format_optional_cell(Some(target_pct), |v| {
+                    format!("{:.2}%", v)
+                }));
+                row.push(ui::change_cell(actual_pct - target_pct));
+            }
+            table.add_row(row);
+
+            // Display investments in this category
+            for (investment, value) in investments {
+                let display_name = match investment {
+                    Investment::Stock(stock) => price_results
+                        .get(&stock.symbol)
+                        .and_then(|pr| pr.as_ref().ok())
+                        .and_then(|pr| pr.short_name.clone())
+                        .unwrap_or_else(|| stock.symbol.clone()),
+                    Investment::MutualFund(mf) => price_results
+                        .get(&mf.isin)
+                        .and_then(|pr| pr.as_ref().ok())
+                        .and_then(|pr| pr.short_name.clone())
+                        .unwrap_or_else(|| mf.isin.clone()),
+                    Investment::FixedDeposit(fd) => fd.name.clone(),
+                    Investment::SmallSavings(s) => s.name.clone(),
+                    Investment::Bond(b) => b.name.clone(),
+                };
+
+                table.add_row(vec![
+                    Cell::new(""),
+                    Cell::new(""),
+                    Cell::new(display_name),
+                    ui::format_optional_cell(Some(percentage(*value, total)), |v| {
+                        format!("{:.2}%", v)
+                    }),
+                    ui::format_optional_cell(Some(*value), |v| format!("{:.2}", v)),
+                ]);
+            }
+        }
+    }
 
+    // Targeted categories without any holdings
+    if let Some(target) = target {
+        let mut missing: Vec<_> = target
+            .iter()
+            .filter(|(name, _)| {
+                !allocation
+                    .keys()
+                    .any(|category| category.eq_ignore_ascii_case(name))
+            })
+            .collect();
+        missing.sort_by_key(|(name, _)| *name);
+        for (category, target_pct) in missing {
+            let (class_name, emoji) = asset_classes.classify(category).display_info();
             table.add_row(vec![
+                Cell::new(format!("{emoji} {class_name}")),
+                Cell::new(category),
                 Cell::new(""),
-                Cell::new(display_name),
-                ui::format_optional_cell(Some(allocation_perc), |v| format!("{:.2}%", v)),
-                ui::format_optional_cell(Some(*value), |v| format!("{:.2}", v)),
+                ui::format_percentage_cell(0.0, |v| format!("{:.2}%", v)),
+                ui::format_optional_cell(Some(0.0), |v| format!("{:.2}", v)),
+                ui::format_optional_cell(Some(*target_pct), |v| format!("{:.2}%", v)),
+                ui::change_cell(-target_pct),
             ]);
         }
     }
@@ -272,7 +439,7 @@ mod tests {
     use crate::core::config::{FixedDepositInvestment, MutualFundInvestment, StockInvestment};
     use crate::core::currency::CurrencyRateProvider;
     use crate::core::metadata::{FundMetadata, MetadataProvider};
-    use crate::core::price::PriceResult;
+    use crate::core::price::{PriceResult, ProviderError};
     use std::collections::HashMap;
 
     // Define mock currency provider
@@ -280,7 +447,7 @@ mod tests {
 
     #[async_trait::async_trait]
     impl CurrencyRateProvider for MockCurrencyProvider {
-        async fn get_rate(&self, _from: &str, _to: &str) -> anyhow::Result<f64> {
+        async fn get_rate(&self, _from: &str, _to: &str) -> ProviderResult<f64> {
             Ok(1.0)
         }
     }
@@ -290,7 +457,7 @@ mod tests {
 
     #[async_trait::async_trait]
     impl MetadataProvider for MockMetadataProviderImpl {
-        async fn fetch_metadata(&self, identifier: &str) -> anyhow::Result<FundMetadata> {
+        async fn fetch_metadata(&self, identifier: &str) -> ProviderResult<FundMetadata> {
             use chrono::NaiveDate;
             match identifier {
                 "EQUITY_FUND" => Ok(FundMetadata {
@@ -315,7 +482,7 @@ mod tests {
                     fund_rating_date: Some(NaiveDate::from_ymd_opt(2010, 1, 1).unwrap()),
                     category: "Debt".to_string(),
                 }),
-                _ => Err(anyhow::anyhow!("Unknown fund")),
+                _ => Err(ProviderError::NotFound("Unknown fund".to_string())),
             }
         }
     }
@@ -325,7 +492,7 @@ mod tests {
 
     #[async_trait::async_trait]
     impl PriceProvider for MockPriceProviderImpl {
-        async fn fetch_price(&self, symbol: &str) -> anyhow::Result<PriceResult> {
+        async fn fetch_price(&self, symbol: &str) -> ProviderResult<PriceResult> {
             let price = match symbol {
                 "AAPL" => 150.0,
                 "DEBT_FUND" => 100.0,
@@ -350,24 +517,38 @@ mod tests {
                     symbol: "AAPL".to_string(),
                     units: 10.0,
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
                 Investment::MutualFund(MutualFundInvestment {
                     isin: "EQUITY_FUND".to_string(),
                     units: 100.0,
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
                 Investment::MutualFund(MutualFundInvestment {
                     isin: "DEBT_FUND".to_string(),
                     units: 50.0,
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
                 Investment::FixedDeposit(FixedDepositInvestment {
                     name: "My FD".to_string(),
-                    value: 5000.0,
+                    value: Some(5000.0),
                     currency: Some("USD".to_string()),
                     category: None,
+                    tags: Vec::new(),
+                    ..Default::default()
                 }),
             ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
         }];
 
         let symbol_provider = MockPriceProviderImpl;
@@ -382,8 +563,70 @@ mod tests {
             &currency_provider,
             &metadata_provider,
             "USD",
+            None,
+            &AssetClassConfig::default(),
         )
         .await;
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_group_by_asset_class_splits_hybrid() {
+        let fd = |name: &str| {
+            Investment::FixedDeposit(FixedDepositInvestment {
+                name: name.to_string(),
+                value: Some(0.0),
+                currency: None,
+                category: None,
+                tags: Vec::new(),
+                ..Default::default()
+            })
+        };
+        let allocation = HashMap::from([
+            ("Equity".to_string(), vec![(fd("A"), 600.0)]),
+            ("Balanced".to_string(), vec![(fd("B"), 200.0)]),
+            ("Gold ETF".to_string(), vec![(fd("C"), 100.0)]),
+        ]);
+        let asset_classes = AssetClassConfig {
+            rules: vec![allocation::AssetClassRule {
+                pattern: "gold".to_string(),
+                asset_class: AssetCategory::Gold,
+            }],
+            hybrid_equity_ratio: Some(75.0),
+        };
+
+        let grouped = group_by_asset_class(&allocation, &asset_classes);
+        let value = |class: AssetCategory, category: &str| grouped[&class][category][0].1;
+        assert_eq!(grouped.len(), 3);
+        assert!((value(AssetCategory::Equity, "Equity") - 600.0).abs() < 1e-9);
+        assert!((value(AssetCategory::Equity, "Balanced") - 150.0).abs() < 1e-9);
+        assert!((value(AssetCategory::Debt, "Balanced") - 50.0).abs() < 1e-9);
+        assert!((value(AssetCategory::Gold, "Gold ETF") - 100.0).abs() < 1e-9);
+
+        let grouped = group_by_asset_class(&allocation, &AssetClassConfig::default());
+        assert!(grouped[&AssetCategory::Hybrid].contains_key("Balanced"));
+        assert!(grouped[&AssetCategory::Other].contains_key("Gold ETF"));
+    }
+
+    #[test]
+    fn test_split_target_follows_hybrid_ratio() {
+        let target =
+            TargetAllocation::from([("Equity".to_string(), 60.0), ("Balanced".to_string(), 40.0)]);
+        let asset_classes = AssetClassConfig {
+            rules: Vec::new(),
+            hybrid_equity_ratio: Some(75.0),
+        };
+        let split = |class, category| split_target(&target, &asset_classes, class, category);
+        assert!((split(AssetCategory::Equity, "Equity") - 60.0).abs() < 1e-9);
+        assert!((split(AssetCategory::Equity, "Balanced") - 30.0).abs() < 1e-9);
+        assert!((split(AssetCategory::Debt, "Balanced") - 10.0).abs() < 1e-9);
+
+        let unsplit = split_target(
+            &target,
+            &AssetClassConfig::default(),
+            AssetCategory::Hybrid,
+            "Balanced",
+        );
+        assert!((unsplit - 40.0).abs() < 1e-9);
+    }
 }
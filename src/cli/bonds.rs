@@ -0,0 +1,408 @@
+use super::ui;
+use crate::core::analytics;
+use crate::core::config::{BondInvestment, Investment, Portfolio};
+use crate::core::currency::CurrencyRateProvider;
+use crate::core::price::{PriceProvider, PriceResult, ProviderResult};
+use anyhow::Result;
+use chrono::{Duration, Local, NaiveDate};
+use comfy_table::{Attribute, Cell};
+use futures::future::join_all;
+use std::collections::HashMap;
+
+/// Days ahead to list coupons for when none is given.
+pub const DEFAULT_COUPON_WINDOW_DAYS: u32 = 90;
+
+struct BondResult {
+    name: String,
+    clean_price: f64,
+    accrued_interest: f64,
+    dirty_price: f64,
+    currency: String,
+    /// Value of the holding in the target currency
+    value: Option<f64>,
+    ytm: Option<f64>,
+    duration: Option<f64>,
+    error: Option<String>,
+}
+
+struct Coupon {
+    date: NaiveDate,
+    portfolio: String,
+    name: String,
+    coupon: f64,
+    redemption: f64,
+    currency: String,
+}
+
+pub async fn run(
+    portfolios: &[Portfolio],
+    symbol_provider: &(dyn PriceProvider + Send + Sync),
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    target_currency: &str,
+    within_days: u32,
+) -> Result<()> {
+    if portfolios.iter().all(|p| bonds(p).next().is_none()) {
+        println!("No bonds found.");
+        return Ok(());
+    }
+    let symbols: Vec<&String> = portfolios
+        .iter()
+        .flat_map(bonds)
+        .filter_map(|b| b.symbol.as_ref())
+        .collect();
+
+    let pb = ui::new_progress_bar(symbols.len() as u64, true);
+    pb.set_message("Fetching prices...");
+    let futures = symbols.into_iter().map(|symbol| {
+        let pb_clone = pb.clone();
+        async move {
+            let result = symbol_provider.fetch_price(symbol).await;
+            pb_clone.inc(1);
+            (symbol.clone(), result)
+        }
+    });
+    let price_results: HashMap<String, ProviderResult<PriceResult>> =
+        join_all(futures).await.into_iter().collect();
+    pb.finish_and_clear();
+
+    let today = Local::now().date_naive();
+    let mut printed = false;
+    for portfolio in portfolios {
+        if bonds(portfolio).next().is_none() {
+            continue;
+        }
+        let holdings = analytics::calculate_portfolio_value(
+            portfolio,
+            &price_results,
+            currency_provider,
+            target_currency,
+            &|| (),
+        )
+        .await;
+        let results: Vec<BondResult> = portfolio
+            .investments
+            .iter()
+            .zip(&holdings.investments)
+            .filter_map(|(investment, holding)| match investment {
+                Investment::Bond(bond) => Some(BondResult {
+                    value: holding.converted_value,
+                    error: holding.error.as_ref().map(|e| e.to_string()),
+                    ..bond_result(bond, &price_results, target_currency, today)
+                }),
+                _ => None,
+            })
+            .collect();
+
+        if printed {
+            ui::print_separator();
+        }
+        printed = true;
+        println!(
+            "\nPortfolio: {}",
+            ui::style_text(&portfolio.name, ui::StyleType::Title)
+        );
+        display_bond_results(&results, target_currency);
+    }
+
+    let coupons = upcoming_coupons(
+        portfolios,
+        &price_results,
+        target_currency,
+        today,
+        within_days,
+    );
+    if coupons.is_empty() {
+        println!("\nNo coupons due in the next {within_days} days.");
+    } else {
+        println!(
+            "\n{}",
+            ui::style_text(
+                &format!("Coupons in the next {within_days} days"),
+                ui::StyleType::Title
+            )
+        );
+        display_coupons(&coupons, today);
+    }
+
+    ui::report_provider_errors(ui::failed_lookups(&price_results))
+}
+
+fn bonds(portfolio: &Portfolio) -> impl Iterator<Item = &BondInvestment> {
+    portfolio.investments.iter().filter_map(|i| match i {
+        Investment::Bond(bond) => Some(bond),
+        _ => None,
+    })
+}
+
+/// Returns the clean price of a bond and its currency, fetched by symbol or
+/// set manually.
+fn clean_price(
+    bond: &BondInvestment,
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
+    target_currency: &str,
+) -> (f64, String) {
+    let quote = bond
+        .symbol
+        .as_ref()
+        .and_then(|symbol| price_results.get(symbol))
+        .and_then(|result| result.as_ref().ok());
+    match quote {
+        Some(price_data) => (price_data.price, price_data.currency.clone()),
+        None => (
+            bond.manual_price(),
+            bond.currency
+                .clone()
+                .unwrap_or_else(|| target_currency.to_string()),
+        ),
+    }
+}
+
+/// Prices a bond on `date`, before its value in the target currency is known.
+fn bond_result(
+    bond: &BondInvestment,
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
+    target_currency: &str,
+    date: NaiveDate,
+) -> BondResult {
+    let terms = bond.terms();
+    let (clean_price, currency) = clean_price(bond, price_results, target_currency);
+    let dirty_price = terms.dirty_price(clean_price, date);
+    let ytm = terms.yield_to_maturity(dirty_price, date);
+    BondResult {
+        name: bond.name.clone(),
+        clean_price,
+        accrued_interest: terms.accrued_interest(date),
+        dirty_price,
+        currency,
+        value: None,
+        ytm,
+        duration: ytm.and_then(|ytm| terms.modified_duration(ytm, date)),
+        error: None,
+    }
+}
+
+/// Returns the value-weighted yield to maturity and modified duration of bonds.
+fn weighted_yield_and_duration(results: &[BondResult]) -> Option<(f64, f64)> {
+    let (weight, ytm, duration) = results
+        .iter()
+        .filter_map(|r| Some((r.value?, r.ytm?, r.duration?)))
+        .fold((0.0, 0.0, 0.0), |(w, y, d), (value, ytm, duration)| {
+            (w + value, y + value * ytm, d + value * duration)
+        });
+    (weight > 0.0).then(|| (ytm / weight, duration / weight))
+}
+
+fn display_bond_results(results: &[BondResult], target_currency: &str) {
+    let total: f64 = results.iter().filter_map(|r| r.value).sum();
+    let mut table = ui::new_styled_table();
+    table.set_header(vec![
+        ui::header_cell("Bond"),
+        ui::header_cell("Clean Price"),
+        ui::header_cell("Accrued"),
+        ui::header_cell("Dirty Price"),
+        ui::header_cell(&format!("Value ({target_currency})")),
+        ui::header_cell("Weight"),
+        ui::header_cell("YTM"),
+        ui::header_cell("Mod. Duration"),
+        ui::header_cell("Duration Contribution"),
+    ]);
+    for result in results {
+        let has_error = result.error.is_some();
+        let weight = result
+            .value
+            .filter(|_| total > 0.0)
+            .map(|v| v / total * 100.0);
+        let price_cell = |price: f64| {
+            if has_error {
+                ui::na_cell(true)
+            } else {
+                Cell::new(format!("{price:.2} {}", result.currency))
+            }
+        };
+        table.add_row(vec![
+            Cell::new(&result.name),
+            price_cell(result.clean_price),
+            price_cell(result.accrued_interest),
+            price_cell(result.dirty_price),
+            ui::format_optional_cell(result.value, |v| format!("{v:.2}")),
+            ui::format_optional_cell(weight, |v| format!("{v:.2}%")),
+            ui::format_optional_cell(result.ytm.filter(|_| !has_error), |v| format!("{v:.2}%")),
+            ui::format_optional_cell(result.duration.filter(|_| !has_error), |v| {
+                format!("{v:.2}")
+            }),
+            ui::format_optional_cell(
+                result.duration.zip(weight).filter(|_| !has_error),
+                |(duration, weight)| format!("{:.2}", duration * weight / 100.0),
+            ),
+        ]);
+    }
+
+    if results.len() > 1
+        && let Some((ytm, duration)) = weighted_yield_and_duration(results)
+    {
+        table.add_row(vec![
+            Cell::new("Duration-weighted").add_attribute(Attribute::Bold),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(""),
+            ui::format_optional_cell(Some(total), |v| format!("{v:.2}")),
+            Cell::new(""),
+            ui::format_optional_cell(Some(ytm), |v| format!("{v:.2}%")),
+            ui::format_optional_cell(Some(duration), |v| format!("{v:.2}")),
+            Cell::new(""),
+        ]);
+    }
+    println!("{table}");
+}
+
+/// Coupons and redemptions due from `today` through `within_days` later, soonest first.
+fn upcoming_coupons(
+    portfolios: &[Portfolio],
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
+    target_currency: &str,
+    today: NaiveDate,
+    within_days: u32,
+) -> Vec<Coupon> {
+    let end = today + Duration::days(i64::from(within_days));
+    let mut coupons = Vec::new();
+    for portfolio in portfolios {
+        for bond in bonds(portfolio) {
+            let terms = bond.terms();
+            let (_, currency) = clean_price(bond, price_results, target_currency);
+            // Coupons due today have not been paid yet
+            let yesterday = today.pred_opt().unwrap_or(today);
+            for date in terms.coupon_dates_after(yesterday) {
+                if date > end {
+                    break;
+                }
+                coupons.push(Coupon {
+                    date,
+                    portfolio: portfolio.name.clone(),
+                    name: bond.name.clone(),
+                    coupon: bond.units * terms.coupon(),
+                    redemption: if date == terms.maturity {
+                        bond.units * terms.face_value
+                    } else {
+                        0.0
+                    },
+                    currency: currency.clone(),
+                });
+            }
+        }
+    }
+    coupons.sort_by_key(|c| c.date);
+    coupons
+}
+
+fn display_coupons(coupons: &[Coupon], today: NaiveDate) {
+    let mut table = ui::new_styled_table();
+    table.set_header(vec![
+        ui::header_cell("Date"),
+        ui::header_cell("Days"),
+        ui::header_cell("Portfolio"),
+        ui::header_cell("Bond"),
+        ui::header_cell("Coupon"),
+        ui::header_cell("Redemption"),
+    ]);
+    for coupon in coupons {
+        let currency = &coupon.currency;
+        table.add_row(vec![
+            Cell::new(coupon.date.format("%Y-%m-%d")),
+            Cell::new((coupon.date - today).num_days()),
+            Cell::new(&coupon.portfolio),
+            Cell::new(&coupon.name),
+            ui::format_optional_cell(Some(coupon.coupon), |v| format!("{v:.2} {currency}")),
+            if coupon.redemption > 0.0 {
+                Cell::new(format!("{:.2} {currency}", coupon.redemption))
+            } else {
+                Cell::new("")
+            },
+        ]);
+    }
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::deposit::Compounding;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn bond(name: &str, maturity: NaiveDate) -> BondInvestment {
+        BondInvestment {
+            name: name.to_string(),
+            units: 10.0,
+            face_value: 1000.0,
+            coupon_rate: 8.0,
+            frequency: Compounding::HalfYearly,
+            issue_date: date(2020, 1, 15),
+            maturity_date: maturity,
+            price: Some(990.0),
+            symbol: None,
+            currency: None,
+            category: None,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_upcoming_coupons() {
+        let portfolio = Portfolio {
+            name: "Bonds".to_string(),
+            investments: vec![
+                Investment::Bond(bond("Long", date(2030, 1, 15))),
+                Investment::Bond(bond("Short", date(2025, 3, 1))),
+            ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
+        };
+
+        let coupons = upcoming_coupons(&[portfolio], &HashMap::new(), "INR", date(2025, 1, 15), 60);
+        let due: Vec<_> = coupons.iter().map(|c| (c.name.as_str(), c.date)).collect();
+        assert_eq!(
+            due,
+            vec![("Long", date(2025, 1, 15)), ("Short", date(2025, 3, 1))]
+        );
+        assert_eq!(coupons[0].coupon, 400.0);
+        assert_eq!(coupons[0].redemption, 0.0);
+        assert_eq!(coupons[1].redemption, 10_000.0);
+        assert_eq!(coupons[1].currency, "INR");
+    }
+
+    #[test]
+    fn test_bond_result_and_weighted_duration() {
+        let long = bond("Long", date(2030, 1, 15));
+        let result = bond_result(&long, &HashMap::new(), "INR", date(2025, 4, 15));
+        assert_eq!(result.clean_price, 990.0);
+        // 90 of the 181 days since the January coupon
+        assert!((result.accrued_interest - 40.0 * 90.0 / 181.0).abs() < 1e-9);
+        // Below par, the yield is above the coupon rate
+        assert!(result.ytm.unwrap() > 8.0);
+
+        let short = bond_result(
+            &bond("Short", date(2026, 1, 15)),
+            &HashMap::new(),
+            "INR",
+            date(2025, 4, 15),
+        );
+        assert!(short.duration.unwrap() < result.duration.unwrap());
+
+        let results = [
+            BondResult {
+                value: Some(300.0),
+                ..result
+            },
+            BondResult {
+                value: Some(100.0),
+                ..short
+            },
+        ];
+        let (_, duration) = weighted_yield_and_duration(&results).unwrap();
+        let expected = (3.0 * results[0].duration.unwrap() + results[1].duration.unwrap()) / 4.0;
+        assert!((duration - expected).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,50 @@
+use super::ui;
+use crate::store::KeyValueStore;
+use anyhow::Result;
+use comfy_table::Cell;
+
+/// Lists cached collections with their source, size and eviction statistics.
+pub async fn list(store: &KeyValueStore) -> Result<()> {
+    let stats = store.stats().await;
+    let sources = store.sources();
+    if stats.is_empty() {
+        println!("Cache is empty.");
+        return Ok(());
+    }
+
+    let mut table = ui::new_styled_table();
+    table.set_header(vec![
+        ui::header_cell("Collection"),
+        ui::header_cell("Provider"),
+        ui::header_cell("Source"),
+        ui::header_cell("Entries"),
+        ui::header_cell("Size (KiB)"),
+        ui::header_cell("Expired"),
+        ui::header_cell("Evicted"),
+    ]);
+
+    for (name, stat) in &stats {
+        // Provider collections are named `<provider>#<source hash>`
+        let provider = name.split('#').next().unwrap_or(name);
+        let source = sources.get(name).map_or("unknown", String::as_str);
+        table.add_row(vec![
+            Cell::new(name),
+            Cell::new(provider),
+            Cell::new(source),
+            ui::format_optional_cell(Some(stat.entries), |v| v.to_string()),
+            ui::format_optional_cell(Some(stat.bytes), |v| format!("{:.1}", v as f64 / 1024.0)),
+            ui::format_optional_cell(Some(stat.expired), |v| v.to_string()),
+            ui::format_optional_cell(Some(stat.evictions), |v| v.to_string()),
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
+
+/// Removes expired entries from all cached collections.
+pub async fn sweep(store: &KeyValueStore) -> Result<()> {
+    let removed = store.sweep().await;
+    println!("Removed {removed} expired cache entries.");
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use super::ui;
 use crate::core::config::{Investment, Portfolio};
-use crate::core::{CurrencyRateProvider, HistoricalPeriod, PriceProvider, PriceResult, analytics};
-use anyhow::Result;
+use crate::core::{
+    CurrencyRateProvider, DEFAULT_PERIODS, HistoricalPeriod, PriceProvider, PriceResult,
+    ProviderResult, analytics,
+};
 use comfy_table::{Attribute, Cell};
 use futures::future::join_all;
 use std::collections::BTreeMap;
@@ -27,7 +29,10 @@ pub async fn run(
     isin_provider: &(dyn PriceProvider + Send + Sync),
     currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
     target_currency: &str,
+    periods: &[String],
 ) -> anyhow::Result<()> {
+    let periods = HistoricalPeriod::parse_list(periods, &DEFAULT_PERIODS)?;
+
     let mut investments_to_fetch = HashMap::new();
     for portfolio in portfolios {
         for investment in &portfolio.investments {
@@ -38,7 +43,12 @@ pub async fn run(
                 Investment::MutualFund(mf) => {
                     investments_to_fetch.insert(mf.isin.clone(), isin_provider);
                 }
-                Investment::FixedDeposit(_) => {}
+                Investment::Bond(b) => {
+                    if let Some(symbol) = &b.symbol {
+                        investments_to_fetch.insert(symbol.clone(), symbol_provider);
+                    }
+                }
+                _ => {}
             }
         }
     }
@@ -59,7 +69,7 @@ pub async fn run(
             (id.clone(), res)
         }
     });
-    let price_results: HashMap<String, Result<PriceResult>> =
+    let price_results: HashMap<String, ProviderResult<PriceResult>> =
         join_all(price_futures).await.into_iter().collect();
     pb.finish_and_clear();
 
@@ -71,6 +81,7 @@ pub async fn run(
             &price_results,
             currency_provider,
             target_currency,
+            &periods,
         )
         .await;
 
@@ -79,7 +90,7 @@ pub async fn run(
                 "\nPortfolio: {}",
                 ui::style_text(&result.name, ui::StyleType::Title)
             );
-            display_results(&result);
+            display_results(&result, &periods);
 
             if i < num_portfolios - 1 {
                 ui::print_separator();
@@ -87,14 +98,15 @@ pub async fn run(
         }
     }
 
-    Ok(())
+    ui::report_provider_errors(ui::failed_lookups(&price_results))
 }
 
 async fn calculate_portfolio_changes(
     portfolio: &Portfolio,
-    price_results: &HashMap<String, Result<PriceResult>>,
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
     currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
     target_currency: &str,
+    periods: &[HistoricalPeriod],
 ) -> PortfolioChangeResult {
     // First, get weights for all investments in the portfolio
     let holdings = analytics::calculate_portfolio_value(
@@ -121,24 +133,18 @@ async fn calculate_portfolio_changes(
                 identifier: holding.identifier.clone(),
                 short_name: holding.short_name.clone(),
                 changes: BTreeMap::new(),
-                error: Some(e.clone()),
+                error: Some(e.to_string()),
             });
             continue;
         }
 
         // Calculate percentage change for this investment
         let changes = if let Some(Ok(price_data)) = price_results.get(&holding.identifier) {
-            price_data
-                .historical_prices
+            periods
                 .iter()
-                .filter_map(|(period, historical_price)| {
-                    if *historical_price > 0.0 {
-                        let change =
-                            ((price_data.price - historical_price) / historical_price) * 100.0;
-                        Some((*period, change))
-                    } else {
-                        None
-                    }
+                .filter_map(|period| {
+                    let prices = price_data.period_prices(*period)?;
+                    Some((*period, prices.change()))
                 })
                 .collect()
         } else {
@@ -178,22 +184,11 @@ async fn calculate_portfolio_changes(
     }
 }
 
-fn display_results(result: &PortfolioChangeResult) {
+fn display_results(result: &PortfolioChangeResult, periods: &[HistoricalPeriod]) {
     let mut table = ui::new_styled_table();
 
-    let mut periods: Vec<HistoricalPeriod> = vec![
-        HistoricalPeriod::OneDay,
-        HistoricalPeriod::FiveDays,
-        HistoricalPeriod::OneMonth,
-        HistoricalPeriod::OneYear,
-        HistoricalPeriod::ThreeYears,
-        HistoricalPeriod::FiveYears,
-        HistoricalPeriod::TenYears,
-    ];
-    periods.sort(); // Ensure consistent order
-
     let mut header = vec![ui::header_cell("Investment")];
-    for period in &periods {
+    for period in periods {
         header.push(ui::header_cell(&period.to_string()));
     }
     table.set_header(header);
@@ -206,7 +201,7 @@ fn display_results(result: &PortfolioChangeResult) {
         };
         let mut row_cells = vec![Cell::new(name_display)];
 
-        for period in &periods {
+        for period in periods {
             let cell = match result.changes.get(period) {
                 Some(change) => ui::change_cell(*change),
                 None => ui::na_cell(result.error.is_some()),
@@ -219,7 +214,7 @@ fn display_results(result: &PortfolioChangeResult) {
     if !result.portfolio_changes.is_empty() && result.investment_changes.len() > 1 {
         let mut total_row_cells =
             vec![Cell::new("Portfolio Weighted").add_attribute(Attribute::Bold)];
-        for period in &periods {
+        for period in periods {
             let cell = match result.portfolio_changes.get(period) {
                 Some(change) => ui::change_cell(*change),
                 None => ui::na_cell(false),
@@ -237,7 +232,6 @@ mod tests {
     use super::*;
     use crate::core::config::{Investment, StockInvestment};
     use crate::core::currency::CurrencyRateProvider;
-    use anyhow::Result;
     use async_trait::async_trait;
 
     // A mock currency provider that assumes all currencies are 1:1 with target
@@ -245,7 +239,7 @@ mod tests {
 
     #[async_trait]
     impl CurrencyRateProvider for MockCurrencyProvider {
-        async fn get_rate(&self, _from: &str, _to: &str) -> Result<f64> {
+        async fn get_rate(&self, _from: &str, _to: &str) -> ProviderResult<f64> {
             Ok(1.0) // Assume 1:1 for simplicity in tests
         }
     }
@@ -259,13 +253,22 @@ mod tests {
                     symbol: "AAPL".to_string(),
                     units: 10.0, // value 1000
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
                 Investment::Stock(StockInvestment {
                     symbol: "GOOG".to_string(),
                     units: 5.0, // value 1000
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
             ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
         };
 
         let mut price_results = HashMap::new();
@@ -291,9 +294,14 @@ mod tests {
         );
 
         let currency_provider = MockCurrencyProvider;
-        let result =
-            calculate_portfolio_changes(&portfolio, &price_results, &currency_provider, "USD")
-                .await;
+        let result = calculate_portfolio_changes(
+            &portfolio,
+            &price_results,
+            &currency_provider,
+            "USD",
+            &DEFAULT_PERIODS,
+        )
+        .await;
 
         assert_eq!(result.name, "Tech");
         assert_eq!(result.investment_changes.len(), 2);
@@ -318,13 +326,22 @@ mod tests {
                     symbol: "AAPL".to_string(),
                     units: 15.0, // value 1500 (75% weight)
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
                 Investment::Stock(StockInvestment {
                     symbol: "GOOG".to_string(),
                     units: 2.5, // value 500 (25% weight)
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
             ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
         };
 
         let mut price_results = HashMap::new();
@@ -350,9 +367,14 @@ mod tests {
         );
 
         let currency_provider = MockCurrencyProvider;
-        let result =
-            calculate_portfolio_changes(&portfolio, &price_results, &currency_provider, "USD")
-                .await;
+        let result = calculate_portfolio_changes(
+            &portfolio,
+            &price_results,
+            &currency_provider,
+            "USD",
+            &DEFAULT_PERIODS,
+        )
+        .await;
 
         // Weighted average should still be the same since individual changes are the same
         let weighted_change = result.portfolio_changes[&HistoricalPeriod::OneDay];
@@ -368,13 +390,22 @@ mod tests {
                     symbol: "AAPL".to_string(),
                     units: 10.0, // value 1000 (50% weight)
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
                 Investment::Stock(StockInvestment {
                     symbol: "GOOG".to_string(),
                     units: 5.0, // value 1000 (50% weight)
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
             ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
         };
 
         let mut price_results = HashMap::new();
@@ -404,9 +435,14 @@ mod tests {
         );
 
         let currency_provider = MockCurrencyProvider;
-        let result =
-            calculate_portfolio_changes(&portfolio, &price_results, &currency_provider, "USD")
-                .await;
+        let result = calculate_portfolio_changes(
+            &portfolio,
+            &price_results,
+            &currency_provider,
+            "USD",
+            &DEFAULT_PERIODS,
+        )
+        .await;
 
         let one_day_change = result.portfolio_changes[&HistoricalPeriod::OneDay];
         assert!(
@@ -0,0 +1,232 @@
+use super::ui;
+use crate::core::ProviderError;
+use crate::core::config::{AppConfig, NetworkConfig, ProvidersConfig};
+use crate::core::edit;
+use crate::core::include;
+use crate::core::migrate::{self, CURRENT_VERSION};
+use crate::core::schema;
+use crate::core::validate::{self, Diagnostic, Path, Severity};
+use crate::providers::http::{HttpClient, HttpOptions};
+use anyhow::{Context, Result, bail};
+use serde_yaml::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::time::Duration;
+
+/// Timeout for each provider URL checked with `--check-urls`
+const URL_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Checks a config file and the files it includes, printing every problem
+/// found at its line and column.
+///
+/// Fails when any problem is an error, so it can run in a pre-commit hook.
+pub async fn validate(path: &std::path::Path, check_urls: bool) -> Result<()> {
+    let files = include::files(path).unwrap_or_else(|_| vec![path.to_path_buf()]);
+    let mut diagnostics = Vec::new();
+    for file in &files {
+        let text = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read config file: {}", file.display()))?;
+        let mut file_diagnostics = if files.len() == 1 {
+            validate::validate(&text)
+        } else {
+            validate::validate_part(&text)
+        };
+        if check_urls && file == path {
+            file_diagnostics.extend(check_provider_urls(path, &text).await);
+        }
+        file_diagnostics.sort_by_key(|d| d.location);
+        diagnostics.extend(file_diagnostics.into_iter().map(|d| (file.as_path(), d)));
+    }
+
+    if diagnostics
+        .iter()
+        .all(|(_, d)| d.severity != Severity::Error)
+    {
+        // Problems only found once the files are merged
+        let merged = match AppConfig::load_from_path(path) {
+            Ok(config) => {
+                let mut merged: Vec<_> = duplicate_portfolios(&config)
+                    .into_iter()
+                    .map(|name| {
+                        let message =
+                            format!("duplicate portfolio name '{name}' in included files");
+                        (Severity::Error, message)
+                    })
+                    .collect();
+                // Single files are checked for this on their own
+                if files.len() > 1 && config.version < CURRENT_VERSION {
+                    merged.push((Severity::Warning, migrate::deprecation(config.version)));
+                }
+                merged
+            }
+            Err(e) => vec![(Severity::Error, format!("{e:#}"))],
+        };
+        diagnostics.extend(merged.into_iter().map(|(severity, message)| {
+            let diagnostic = Diagnostic {
+                severity,
+                location: None,
+                message,
+            };
+            (path, diagnostic)
+        }));
+    }
+
+    for (file, diagnostic) in &diagnostics {
+        let position = diagnostic
+            .location
+            .map(|l| format!("{}:{}:", l.line, l.column))
+            .unwrap_or_default();
+        let severity = match diagnostic.severity {
+            Severity::Error => ui::style_text("error", ui::StyleType::Error),
+            Severity::Warning => ui::style_text("warning", ui::StyleType::TotalLabel),
+        };
+        println!(
+            "{}:{position} {severity}: {}",
+            file.display(),
+            diagnostic.message
+        );
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|(_, d)| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    if errors > 0 {
+        bail!(
+            "{} has {errors} error(s) and {warnings} warning(s)",
+            path.display()
+        );
+    }
+    println!("{} is valid, {warnings} warning(s)", path.display());
+    Ok(())
+}
+
+/// Returns portfolio names used more than once, ignoring case.
+fn duplicate_portfolios(config: &AppConfig) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    config
+        .portfolios
+        .iter()
+        .map(|p| p.name.as_str())
+        .filter(|name| !seen.insert(name.to_lowercase()))
+        .collect()
+}
+
+/// Prints the JSON Schema of the config file.
+pub fn schema() -> Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&schema::config_schema())?
+    );
+    Ok(())
+}
+
+/// Rewrites a config and the files it includes in the current format,
+/// printing the changes as a diff. Each changed file is backed up first, and
+/// nothing is written with `dry_run`.
+pub fn migrate(path: &std::path::Path, dry_run: bool) -> Result<()> {
+    let mut changes = Vec::new();
+    for file in include::files(path)? {
+        let text = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read config file: {}", file.display()))?;
+        let migrated = migrate::migrate_text(&text, file == path)
+            .with_context(|| format!("Cannot migrate config file: {}", file.display()))?;
+        if migrated != text {
+            changes.push((file, text, migrated));
+        }
+    }
+    if changes.is_empty() {
+        println!("{} is already at version {CURRENT_VERSION}", path.display());
+        return Ok(());
+    }
+
+    for (file, text, migrated) in &changes {
+        ui::print_diff(file, text, migrated);
+    }
+    if dry_run {
+        println!("Dry run, no files were changed");
+        return Ok(());
+    }
+
+    let mut backups = Vec::new();
+    for (file, _, migrated) in &changes {
+        backups.push(edit::write_with_backup(file, migrated)?);
+    }
+    if let Err(e) = AppConfig::load_from_path(path) {
+        for (file, text, _) in &changes {
+            fs::write(file, text)
+                .with_context(|| format!("Failed to restore config file: {}", file.display()))?;
+        }
+        return Err(e.context("The migrated config is invalid, so it was left unchanged"));
+    }
+    for ((file, _, _), backup) in changes.iter().zip(&backups) {
+        println!(
+            "Migrated {} to version {CURRENT_VERSION} (backup at {})",
+            file.display(),
+            backup.display()
+        );
+    }
+    Ok(())
+}
+
+/// Warns on stderr when the config is in a deprecated format.
+pub fn warn_if_outdated(config: &AppConfig) {
+    if config.version < CURRENT_VERSION {
+        ui::print_warning(&migrate::deprecation(config.version));
+    }
+}
+
+/// Reports provider base URLs that cannot be reached. Any HTTP response
+/// counts as reachable, since base URLs need not serve a page themselves.
+async fn check_provider_urls(path: &std::path::Path, text: &str) -> Vec<Diagnostic> {
+    let Ok(root) = include::load(path) else {
+        return Vec::new();
+    };
+    let section = |key: &str| root.get(key).cloned().unwrap_or(Value::Null);
+    let providers: ProvidersConfig =
+        serde_yaml::from_value(section("providers")).unwrap_or_default();
+    let network: NetworkConfig = serde_yaml::from_value(section("network")).unwrap_or_default();
+
+    let options = HttpOptions {
+        timeout: URL_CHECK_TIMEOUT,
+        retries: 0,
+        network,
+        ..HttpOptions::default()
+    };
+    let client = match HttpClient::new(&options, None) {
+        Ok(client) => client,
+        Err(e) => {
+            let path = Path::default().key("network");
+            return vec![Diagnostic::at(
+                text,
+                &path,
+                Severity::Error,
+                format!("{e:#}"),
+            )];
+        }
+    };
+
+    let defaults = ProvidersConfig::default();
+    let urls = [
+        (
+            "yahoo",
+            providers.yahoo.or(defaults.yahoo).map(|p| p.base_url),
+        ),
+        ("amfi", providers.amfi.or(defaults.amfi).map(|p| p.base_url)),
+    ];
+    let mut diagnostics = Vec::new();
+    for (name, url) in urls {
+        let Some(url) = url else { continue };
+        if let Err(ProviderError::Network(e)) = client.get(&url).await {
+            let path = Path::default().key("providers").key(name).key("base_url");
+            diagnostics.push(Diagnostic::at(
+                text,
+                &path,
+                Severity::Error,
+                format!("provider '{name}' is unreachable: {e}"),
+            ));
+        }
+    }
+    diagnostics
+}
@@ -0,0 +1,290 @@
+use super::ui;
+use crate::core::correlation;
+use crate::core::risk;
+use crate::core::{
+    CurrencyRateProvider, HistoricalPeriod, PriceProvider, PriceResult, ProviderResult, analytics,
+    config::{Investment, Portfolio},
+};
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use comfy_table::{Attribute, Cell, CellAlignment, Color};
+use futures::future::join_all;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Period used when none is given.
+pub const DEFAULT_PERIOD: &str = "1Y";
+
+struct PortfolioCorrelation {
+    name: String,
+    labels: Vec<String>,
+    matrix: Vec<Vec<Option<f64>>>,
+}
+
+impl PortfolioCorrelation {
+    /// Pairs correlated above `threshold`, most correlated first.
+    fn concentrated_pairs(&self, threshold: f64) -> Vec<(&str, &str, f64)> {
+        let mut pairs = Vec::new();
+        for i in 0..self.labels.len() {
+            for j in (i + 1)..self.labels.len() {
+                if let Some(value) = self.matrix[i][j]
+                    && value > threshold
+                {
+                    pairs.push((self.labels[i].as_str(), self.labels[j].as_str(), value));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap());
+        pairs
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    portfolios: &[Portfolio],
+    symbol_provider: &(dyn PriceProvider + Send + Sync),
+    isin_provider: &(dyn PriceProvider + Send + Sync),
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    target_currency: &str,
+    period: &str,
+    weekly: bool,
+    threshold: f64,
+    csv_path: Option<&Path>,
+) -> Result<()> {
+    let period: HistoricalPeriod = period.parse()?;
+
+    let mut investments_to_fetch = HashMap::new();
+    for portfolio in portfolios {
+        for investment in &portfolio.investments {
+            match investment {
+                Investment::Stock(s) => {
+                    investments_to_fetch.insert(s.symbol.clone(), symbol_provider);
+                }
+                Investment::MutualFund(mf) => {
+                    investments_to_fetch.insert(mf.isin.clone(), isin_provider);
+                }
+                Investment::Bond(b) => {
+                    if let Some(symbol) = &b.symbol {
+                        investments_to_fetch.insert(symbol.clone(), symbol_provider);
+                    }
+                }
+                _ => {} // No price history
+            }
+        }
+    }
+    if investments_to_fetch.is_empty() {
+        println!("No investments found to calculate correlations for.");
+        return Ok(());
+    }
+
+    let pb = ui::new_progress_bar(investments_to_fetch.len() as u64, true);
+    pb.set_message("Fetching prices...");
+
+    let futures = investments_to_fetch.into_iter().map(|(id, provider)| {
+        let pb_clone = pb.clone();
+        async move {
+            let result = provider.fetch_price(&id).await;
+            pb_clone.inc(1);
+            (id, result)
+        }
+    });
+
+    let fetched_results: HashMap<String, ProviderResult<PriceResult>> =
+        join_all(futures).await.into_iter().collect();
+    pb.finish_and_clear();
+
+    let mut results = Vec::new();
+    for portfolio in portfolios {
+        let result = calculate_portfolio_correlation(
+            portfolio,
+            &fetched_results,
+            currency_provider,
+            target_currency,
+            period,
+            weekly,
+        )
+        .await;
+        if result.labels.len() < 2 {
+            continue;
+        }
+
+        println!(
+            "\nPortfolio: {}",
+            ui::style_text(&result.name, ui::StyleType::Title)
+        );
+        display_correlation_matrix(&result, period, weekly, threshold);
+        results.push(result);
+    }
+
+    if let Some(path) = csv_path {
+        std::fs::write(path, correlations_csv(&results))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("\nCorrelations written to {}", path.display());
+    }
+
+    ui::report_provider_errors(ui::failed_lookups(&fetched_results))
+}
+
+/// Returns the price series of a holding over `period`, weekly if requested.
+fn return_series(
+    price_data: &PriceResult,
+    period: HistoricalPeriod,
+    weekly: bool,
+) -> Vec<(NaiveDate, f64)> {
+    let mut series = price_data.daily_prices.clone();
+    series.sort_by_key(|(date, _)| *date);
+    let series = risk::trim_to_period(&series, period);
+    if weekly {
+        correlation::weekly_series(series)
+    } else {
+        series.to_vec()
+    }
+}
+
+async fn calculate_portfolio_correlation(
+    portfolio: &Portfolio,
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    target_currency: &str,
+    period: HistoricalPeriod,
+    weekly: bool,
+) -> PortfolioCorrelation {
+    let holdings = analytics::calculate_portfolio_value(
+        portfolio,
+        price_results,
+        currency_provider,
+        target_currency,
+        &|| (), // No progress updates needed here
+    )
+    .await;
+
+    let mut labels = Vec::new();
+    let mut series = Vec::new();
+    for holding in &holdings.investments {
+        if let Some(Ok(price_data)) = price_results.get(&holding.identifier) {
+            labels.push(
+                holding
+                    .short_name
+                    .clone()
+                    .unwrap_or_else(|| holding.identifier.clone()),
+            );
+            series.push(return_series(price_data, period, weekly));
+        }
+    }
+
+    PortfolioCorrelation {
+        name: portfolio.name.clone(),
+        matrix: correlation::correlation_matrix(&series),
+        labels,
+    }
+}
+
+fn correlation_cell(value: Option<f64>, threshold: f64, diagonal: bool) -> Cell {
+    let Some(value) = value else {
+        return ui::na_cell(false);
+    };
+    let cell = Cell::new(format!("{value:.2}")).set_alignment(CellAlignment::Right);
+    if diagonal {
+        cell.fg(Color::DarkGrey)
+    } else if value > threshold {
+        cell.fg(Color::Red).add_attribute(Attribute::Bold)
+    } else if value > 0.5 {
+        cell.fg(Color::Yellow)
+    } else {
+        cell.fg(Color::Green)
+    }
+}
+
+fn display_correlation_matrix(
+    result: &PortfolioCorrelation,
+    period: HistoricalPeriod,
+    weekly: bool,
+    threshold: f64,
+) {
+    let interval = if weekly { "weekly" } else { "daily" };
+    println!("\n{period} correlation of {interval} returns");
+
+    let mut table = ui::new_styled_table();
+    let mut header = vec![ui::header_cell("")];
+    header.extend(result.labels.iter().map(|label| ui::header_cell(label)));
+    table.set_header(header);
+
+    for (i, label) in result.labels.iter().enumerate() {
+        let mut row_cells = vec![Cell::new(label)];
+        for (j, value) in result.matrix[i].iter().enumerate() {
+            row_cells.push(correlation_cell(*value, threshold, i == j));
+        }
+        table.add_row(row_cells);
+    }
+    println!("{table}");
+
+    let pairs = result.concentrated_pairs(threshold);
+    if !pairs.is_empty() {
+        println!(
+            "\n{}",
+            ui::style_text(
+                &format!("Concentration risk (correlation above {threshold:.2}):"),
+                ui::StyleType::Error
+            )
+        );
+        for (a, b, value) in pairs {
+            println!("  {a} / {b}: {value:.2}");
+        }
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, quote or newline.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Formats correlations as CSV with one row per pair of holdings.
+fn correlations_csv(results: &[PortfolioCorrelation]) -> String {
+    let mut csv = String::from("portfolio,investment_a,investment_b,correlation\n");
+    for result in results {
+        for (i, label_a) in result.labels.iter().enumerate() {
+            for (j, label_b) in result.labels.iter().enumerate().skip(i + 1) {
+                let value = result.matrix[i][j].map_or(String::new(), |v| format!("{v:.4}"));
+                csv.push_str(&format!(
+                    "{},{},{},{value}\n",
+                    csv_field(&result.name),
+                    csv_field(label_a),
+                    csv_field(label_b)
+                ));
+            }
+        }
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_correlations_csv_and_pairs() {
+        let result = PortfolioCorrelation {
+            name: "Core, Growth".to_string(),
+            labels: vec!["A".to_string(), "B".to_string(), "C".to_string()],
+            matrix: vec![
+                vec![Some(1.0), Some(0.9), None],
+                vec![Some(0.9), Some(1.0), Some(0.2)],
+                vec![None, Some(0.2), Some(1.0)],
+            ],
+        };
+
+        assert_eq!(
+            correlations_csv(std::slice::from_ref(&result)),
+            "portfolio,investment_a,investment_b,correlation\n\
+             \"Core, Growth\",A,B,0.9000\n\
+             \"Core, Growth\",A,C,\n\
+             \"Core, Growth\",B,C,0.2000\n"
+        );
+        assert_eq!(result.concentrated_pairs(0.8), vec![("A", "B", 0.9)]);
+        assert!(result.concentrated_pairs(0.95).is_empty());
+    }
+}
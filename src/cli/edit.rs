@@ -0,0 +1,198 @@
+use crate::core::PriceProvider;
+use crate::core::config::AppConfig;
+use crate::core::edit::{self, InvestmentId, PortfolioFile};
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
+use std::path::Path;
+
+/// Decimal places kept when adjusting units
+const UNITS_SCALE: f64 = 1e6;
+
+/// Adds a stock or fund to a portfolio, after checking a quote can be fetched
+/// for it unless `verify` is false.
+#[allow(clippy::too_many_arguments)]
+pub async fn add(
+    config_path: &Path,
+    portfolio: &str,
+    investment: &InvestmentId,
+    units: f64,
+    category: Option<&str>,
+    verify: bool,
+    symbol_provider: &(dyn PriceProvider + Send + Sync),
+    isin_provider: &(dyn PriceProvider + Send + Sync),
+) -> Result<()> {
+    let provider = match investment {
+        InvestmentId::Isin(_) => isin_provider,
+        InvestmentId::Symbol(_) => symbol_provider,
+        InvestmentId::Name(_) => {
+            bail!("Only stocks and mutual funds can be added, by --symbol or --isin")
+        }
+    };
+    check_units(units)?;
+    let target = edit::find_portfolio(config_path, portfolio)?;
+    if target.find(investment).is_some() {
+        bail!(
+            "Portfolio '{portfolio}' already holds {investment}, use set-units or add-txn instead"
+        );
+    }
+    if verify {
+        let quote = provider
+            .fetch_price(investment.value())
+            .await
+            .with_context(|| {
+                format!("Could not fetch a quote for {investment}, check it or pass --no-verify")
+            })?;
+        let name = quote.short_name.as_deref().unwrap_or(investment.value());
+        println!("Found {name} at {:.2} {}", quote.price, quote.currency);
+    }
+
+    let mut fields = vec![
+        (investment.field(), edit::quote(investment.value())),
+        ("units", units.to_string()),
+    ];
+    if let Some(investment_type) = investment.investment_type() {
+        fields.insert(0, ("type", investment_type.to_string()));
+    }
+    if let Some(category) = category {
+        fields.push(("category", edit::quote(category)));
+    }
+    let text = target.add_investment(&fields)?;
+    save(config_path, &target.file, &target.text, &text)?;
+    println!("Added {investment} with {units} units to '{portfolio}'");
+    Ok(())
+}
+
+/// Removes an investment from a portfolio.
+pub fn remove(config_path: &Path, portfolio: &str, investment: &InvestmentId) -> Result<()> {
+    let target = edit::find_portfolio(config_path, portfolio)?;
+    let index = find(&target, portfolio, investment)?;
+    let text = target.remove_investment(index)?;
+    save(config_path, &target.file, &target.text, &text)?;
+    println!("Removed {investment} from '{portfolio}'");
+    Ok(())
+}
+
+/// Sets the units held of an investment.
+pub fn set_units(
+    config_path: &Path,
+    portfolio: &str,
+    investment: &InvestmentId,
+    units: f64,
+) -> Result<()> {
+    check_units(units)?;
+    let target = edit::find_portfolio(config_path, portfolio)?;
+    let index = find(&target, portfolio, investment)?;
+    let text = target.set_field(index, "units", &units.to_string())?;
+    save(config_path, &target.file, &target.text, &text)?;
+    println!("Set {investment} in '{portfolio}' to {units} units");
+    Ok(())
+}
+
+/// Records a purchase, or a sale with negative units, of a stock or fund at
+/// `price` per unit on `date`, and adjusts the units held to match.
+pub fn add_txn(
+    config_path: &Path,
+    portfolio: &str,
+    investment: &InvestmentId,
+    units: f64,
+    price: f64,
+    date: NaiveDate,
+) -> Result<()> {
+    if investment.investment_type().is_none() {
+        bail!("Transactions are recorded for stocks and mutual funds only, by --symbol or --isin");
+    }
+    if !units.is_finite() || units == 0.0 {
+        bail!("Units must not be zero, got {units}");
+    }
+    if !price.is_finite() || price <= 0.0 {
+        bail!("Price must be positive, got {price}");
+    }
+    let target = edit::find_portfolio(config_path, portfolio)?;
+    let index = find(&target, portfolio, investment)?;
+    let held = target
+        .units(index)
+        .with_context(|| format!("{investment} has no units to adjust"))?;
+    // Round away float noise such as 0.30000000000000004
+    let total = ((held + units) * UNITS_SCALE).round() / UNITS_SCALE;
+    if total < 0.0 {
+        bail!(
+            "Cannot sell {} units of {investment}, only {held} are held",
+            -units
+        );
+    }
+    let text = target.set_field(index, "units", &total.to_string())?;
+    let transaction = [
+        ("date", date.to_string()),
+        ("units", units.to_string()),
+        ("price", price.to_string()),
+    ];
+    let text = target
+        .with_text(text)?
+        .add_to_list(index, "transactions", &transaction)?;
+    save(config_path, &target.file, &target.text, &text)?;
+    println!(
+        "Recorded {units} units of {investment} at {price} on {date} in '{portfolio}', now {total} units"
+    );
+    Ok(())
+}
+
+fn check_units(units: f64) -> Result<()> {
+    if !units.is_finite() || units < 0.0 {
+        bail!("Units must not be negative, got {units}");
+    }
+    Ok(())
+}
+
+fn find(target: &PortfolioFile, portfolio: &str, investment: &InvestmentId) -> Result<usize> {
+    target
+        .find(investment)
+        .with_context(|| format!("Portfolio '{portfolio}' does not hold {investment}"))
+}
+
+/// Writes an edited config file with a backup, restoring the original text
+/// if the config no longer loads.
+pub(crate) fn save(config_path: &Path, file: &Path, original: &str, text: &str) -> Result<()> {
+    let backup = edit::write_with_backup(file, text)?;
+    if let Err(e) = AppConfig::load_from_path(config_path) {
+        std::fs::write(file, original)
+            .with_context(|| format!("Failed to restore config file from {}", backup.display()))?;
+        return Err(e.context("The edited config is invalid, so it was left unchanged"));
+    }
+    println!("Saved {} (backup at {})", file.display(), backup.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::Investment;
+
+    #[test]
+    fn test_add_txn_records_transaction_and_adjusts_units() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(
+            &path,
+            "currency: INR\nportfolios:\n  - name: Funds\n    investments:\n      - isin: \"INF179KB1HU9\" # index fund\n        units: 10\n",
+        )
+        .unwrap();
+        let fund = InvestmentId::Isin("INF179KB1HU9".to_string());
+        let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+
+        add_txn(&path, "Funds", &fund, 5.5, 100.0, date(15)).unwrap();
+        add_txn(&path, "Funds", &fund, -0.5, 110.0, date(20)).unwrap();
+        assert!(add_txn(&path, "Funds", &fund, -20.0, 110.0, date(21)).is_err());
+
+        let config = AppConfig::load_from_path(&path).unwrap();
+        let Investment::MutualFund(mf) = &config.portfolios[0].investments[0] else {
+            panic!("expected a mutual fund");
+        };
+        assert_eq!(mf.units, 15.0);
+        assert_eq!(mf.transactions.len(), 2);
+        assert_eq!(mf.transactions[1].date, date(20));
+        assert_eq!(mf.transactions[1].units, -0.5);
+        assert_eq!(mf.transactions[1].price, 110.0);
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("# index fund"));
+    }
+}
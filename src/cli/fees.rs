@@ -3,8 +3,7 @@ use crate::core::analytics;
 use crate::core::config::{Investment, Portfolio};
 use crate::core::currency::CurrencyRateProvider;
 use crate::core::metadata::{FundMetadata, MetadataProvider};
-use crate::core::price::{PriceProvider, PriceResult};
-use anyhow::Result;
+use crate::core::price::{PriceProvider, PriceResult, ProviderResult};
 use comfy_table::{Attribute, Cell};
 use futures::future::join_all;
 use std::collections::HashMap;
@@ -46,7 +45,12 @@ pub async fn run(
                     price_fetch_map.insert(mf.isin.clone(), isin_provider);
                     metadata_isins.push(mf.isin.clone());
                 }
-                Investment::FixedDeposit(_) => {}
+                Investment::Bond(b) => {
+                    if let Some(symbol) = &b.symbol {
+                        price_fetch_map.insert(symbol.clone(), symbol_provider);
+                    }
+                }
+                _ => {}
             }
         }
     }
@@ -77,7 +81,7 @@ pub async fn run(
         }
     });
 
-    let price_results: HashMap<String, Result<PriceResult>> =
+    let price_results: HashMap<String, ProviderResult<PriceResult>> =
         join_all(price_futures).await.into_iter().collect();
 
     if let Some(pb) = pb_price {
@@ -104,7 +108,7 @@ pub async fn run(
         }
     });
 
-    let metadata_results: HashMap<String, Result<FundMetadata>> =
+    let metadata_results: HashMap<String, ProviderResult<FundMetadata>> =
         join_all(metadata_futures).await.into_iter().collect();
 
     if let Some(pb) = pb_metadata {
@@ -135,13 +139,16 @@ pub async fn run(
         }
     }
 
-    Ok(())
+    // Price errors take precedence, they also explain any missing metadata
+    ui::report_provider_errors(
+        ui::failed_lookups(&metadata_results).chain(ui::failed_lookups(&price_results)),
+    )
 }
 
 async fn calculate_portfolio_fees(
     portfolio: &Portfolio,
     holdings: &analytics::PortfolioValue,
-    metadata_results: &HashMap<String, Result<FundMetadata>>,
+    metadata_results: &HashMap<String, ProviderResult<FundMetadata>>,
 ) -> PortfolioFeeResult {
     let mut investment_fees = Vec::new();
     let mut total_weighted_fee = 0.0;
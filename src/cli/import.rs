@@ -0,0 +1,183 @@
+use super::ui;
+use crate::core::config::AppConfig;
+use crate::core::edit::{self, InvestmentId};
+use crate::core::import::{self, ColumnMap, ImportFormat};
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::Path;
+
+/// Imports the holdings in a CSV export into a portfolio, adding the
+/// portfolio if the config has none by that name.
+///
+/// Holdings the portfolio already has take the imported units, so importing
+/// a newer export again counts nothing twice. Holdings of other portfolios
+/// are skipped unless `force` is set, for a fund held through several brokers.
+#[allow(clippy::too_many_arguments)]
+pub fn import(
+    config: &AppConfig,
+    config_path: &Path,
+    portfolio: &str,
+    file: &Path,
+    format: ImportFormat,
+    map: Option<&ColumnMap>,
+    dry_run: bool,
+    force: bool,
+) -> Result<()> {
+    if map.is_some() && format != ImportFormat::Generic {
+        bail!("--map is only used with --from generic");
+    }
+    let text =
+        fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let imported = import::read(&text, format, map)
+        .with_context(|| format!("Failed to import {}", file.display()))?;
+
+    let (mut target, added) = edit::find_or_add_portfolio(config_path, portfolio)?;
+    if added {
+        println!("Add portfolio '{portfolio}'");
+    }
+    let original = if added {
+        fs::read_to_string(&target.file)
+            .with_context(|| format!("Failed to read config file: {}", target.file.display()))?
+    } else {
+        target.text.clone()
+    };
+    let mut skipped: Vec<String> = imported
+        .skipped
+        .iter()
+        .map(|s| format!("{}:{}: {}", file.display(), s.line, s.reason))
+        .collect();
+    let (mut created, mut updated, mut unchanged) = (0, 0, 0);
+    for holding in &imported.holdings {
+        let id = &holding.id;
+        if !force && let Some(other) = held_elsewhere(config, portfolio, id) {
+            skipped.push(format!(
+                "{id} is already in portfolio '{other}', pass --force to import it too"
+            ));
+            continue;
+        }
+        let text = match target.find(id) {
+            Some(index) => {
+                let held = target.units(index);
+                if held == Some(holding.units) {
+                    unchanged += 1;
+                    continue;
+                }
+                println!(
+                    "Update {id} from {} to {} units",
+                    held.map_or("?".to_string(), |units| units.to_string()),
+                    holding.units
+                );
+                updated += 1;
+                target.set_field(index, "units", &holding.units.to_string())?
+            }
+            None => {
+                println!("Add {id} with {} units", holding.units);
+                created += 1;
+                let mut fields = vec![
+                    (id.field(), edit::quote(id.value())),
+                    ("units", holding.units.to_string()),
+                ];
+                if let Some(investment_type) = id.investment_type() {
+                    fields.insert(0, ("type", investment_type.to_string()));
+                }
+                if let Some(category) = &holding.category {
+                    fields.push(("category", edit::quote(category)));
+                }
+                target.add_investment(&fields)?
+            }
+        };
+        target = target.with_text(text)?;
+    }
+
+    for reason in &skipped {
+        eprintln!(
+            "{} {reason}",
+            ui::style_text("skipped:", ui::StyleType::TotalLabel)
+        );
+    }
+    println!(
+        "{created} holding(s) to add, {updated} to update and {unchanged} unchanged in '{portfolio}', {} skipped",
+        skipped.len()
+    );
+    if created + updated == 0 {
+        return Ok(());
+    }
+    if dry_run {
+        ui::print_diff(&target.file, &original, &target.text);
+        println!("Dry run, no files were changed");
+        return Ok(());
+    }
+    super::edit::save(config_path, &target.file, &original, &target.text)
+}
+
+/// Returns the name of another portfolio holding an investment.
+fn held_elsewhere<'a>(
+    config: &'a AppConfig,
+    portfolio: &str,
+    id: &InvestmentId,
+) -> Option<&'a str> {
+    config
+        .portfolios
+        .iter()
+        .filter(|p| !p.name.eq_ignore_ascii_case(portfolio))
+        .find(|p| p.investments.iter().any(|i| id.identifies(i)))
+        .map(|p| p.name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::Investment;
+
+    #[test]
+    fn test_import_skips_holdings_of_other_portfolios() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(
+            &path,
+            "currency: INR\nportfolios:\n  - name: Zerodha\n    investments:\n      - symbol: \"INFY.NS\"\n        units: 10\n",
+        )
+        .unwrap();
+        let export = dir.path().join("holdings.csv");
+        fs::write(
+            &export,
+            "Symbol,ISIN,Quantity Available,Average Price\nINFY,INE009A01021,10,1500\nAXIS LONG TERM EQUITY FUND,INF846K01131,25.5,50\n",
+        )
+        .unwrap();
+        let import_into = |force| {
+            let config = AppConfig::load_from_path(&path).unwrap();
+            import(
+                &config,
+                &path,
+                "Groww",
+                &export,
+                ImportFormat::Zerodha,
+                None,
+                false,
+                force,
+            )
+            .unwrap();
+            AppConfig::load_from_path(&path).unwrap()
+        };
+        let identifiers = |config: &AppConfig, i: usize| -> Vec<String> {
+            config.portfolios[i]
+                .investments
+                .iter()
+                .map(|i| i.identifier().to_string())
+                .collect()
+        };
+
+        // The stock is already held through another broker, the fund is new
+        let config = import_into(false);
+        assert_eq!(config.portfolios[1].name, "Groww");
+        assert_eq!(identifiers(&config, 1), vec!["INF846K01131"]);
+        assert_eq!(identifiers(&config, 0), vec!["INFY.NS"]);
+
+        let config = import_into(true);
+        assert_eq!(identifiers(&config, 1), vec!["INF846K01131", "INFY.NS"]);
+        let Investment::Stock(stock) = &config.portfolios[0].investments[0] else {
+            panic!("expected a stock");
+        };
+        assert_eq!(stock.units, 10.0);
+    }
+}
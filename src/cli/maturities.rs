@@ -0,0 +1,138 @@
+use super::ui;
+use crate::core::config::{Investment, Portfolio};
+use anyhow::Result;
+use chrono::{Duration, Local, NaiveDate};
+use comfy_table::Cell;
+
+/// Days ahead to look for maturities when none is given.
+pub const DEFAULT_WINDOW_DAYS: u32 = 90;
+
+struct Maturity {
+    portfolio: String,
+    name: String,
+    date: NaiveDate,
+    principal: f64,
+    rate: f64,
+    value: f64,
+    currency: Option<String>,
+}
+
+pub fn run(portfolios: &[Portfolio], target_currency: &str, within_days: u32) -> Result<()> {
+    let today = Local::now().date_naive();
+    let maturities = upcoming_maturities(portfolios, today, within_days);
+    if maturities.is_empty() {
+        println!("No deposits maturing in the next {within_days} days.");
+        return Ok(());
+    }
+
+    println!(
+        "\n{}",
+        ui::style_text(
+            &format!("Maturities in the next {within_days} days"),
+            ui::StyleType::Title
+        )
+    );
+    let mut table = ui::new_styled_table();
+    table.set_header(vec![
+        ui::header_cell("Date"),
+        ui::header_cell("Days"),
+        ui::header_cell("Portfolio"),
+        ui::header_cell("Deposit"),
+        ui::header_cell("Principal"),
+        ui::header_cell("Rate"),
+        ui::header_cell("Maturity Value"),
+    ]);
+    for maturity in &maturities {
+        let currency = maturity.currency.as_deref().unwrap_or(target_currency);
+        table.add_row(vec![
+            Cell::new(maturity.date.format("%Y-%m-%d")),
+            Cell::new((maturity.date - today).num_days()),
+            Cell::new(&maturity.portfolio),
+            Cell::new(&maturity.name),
+            ui::format_optional_cell(Some(maturity.principal), |v| format!("{v:.2} {currency}")),
+            ui::format_optional_cell(Some(maturity.rate), |v| format!("{v:.2}%")),
+            ui::format_optional_cell(Some(maturity.value), |v| format!("{v:.2} {currency}")),
+        ]);
+    }
+    println!("{table}");
+    Ok(())
+}
+
+/// Deposits maturing from `today` through `within_days` later, soonest first.
+fn upcoming_maturities(
+    portfolios: &[Portfolio],
+    today: NaiveDate,
+    within_days: u32,
+) -> Vec<Maturity> {
+    let end = today + Duration::days(i64::from(within_days));
+    let mut maturities = Vec::new();
+    for portfolio in portfolios {
+        for investment in &portfolio.investments {
+            let Investment::FixedDeposit(fd) = investment else {
+                continue;
+            };
+            let Some(terms) = fd.terms() else {
+                continue;
+            };
+            let (Some(date), Some(value)) = (terms.maturity, terms.maturity_value()) else {
+                continue;
+            };
+            if date >= today && date <= end {
+                maturities.push(Maturity {
+                    portfolio: portfolio.name.clone(),
+                    name: fd.name.clone(),
+                    date,
+                    principal: terms.principal,
+                    rate: terms.rate,
+                    value,
+                    currency: fd.currency.clone(),
+                });
+            }
+        }
+    }
+    maturities.sort_by_key(|m| m.date);
+    maturities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::FixedDepositInvestment;
+
+    #[test]
+    fn test_upcoming_maturities() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let deposit = |name: &str, maturity: NaiveDate| {
+            Investment::FixedDeposit(FixedDepositInvestment {
+                name: name.to_string(),
+                principal: Some(1000.0),
+                rate: Some(6.0),
+                start_date: Some(date(2024, 1, 1)),
+                maturity_date: Some(maturity),
+                ..Default::default()
+            })
+        };
+        let portfolio = Portfolio {
+            name: "Deposits".to_string(),
+            investments: vec![
+                deposit("Later", date(2025, 3, 1)),
+                deposit("Matured", date(2024, 12, 31)),
+                deposit("Soon", date(2025, 1, 15)),
+                deposit("Beyond", date(2025, 6, 1)),
+                Investment::FixedDeposit(FixedDepositInvestment {
+                    name: "Manual".to_string(),
+                    value: Some(500.0),
+                    ..Default::default()
+                }),
+            ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
+        };
+
+        let maturities = upcoming_maturities(&[portfolio], date(2025, 1, 1), 90);
+        let names: Vec<_> = maturities.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["Soon", "Later"]);
+        assert!(maturities[0].value > 1000.0);
+    }
+}
@@ -1,7 +1,16 @@
 pub mod alloc;
+pub mod bonds;
+pub mod cache;
 pub mod change;
+pub mod config;
+pub mod correlation;
+pub mod edit;
 pub mod fees;
+pub mod import;
+pub mod maturities;
+pub mod rebalance;
 pub mod returns;
+pub mod risk;
 pub mod setup;
 pub mod summary;
 pub mod ui;
@@ -0,0 +1,267 @@
+use super::alloc::{load_allocations, validate_portfolio_targets};
+use super::ui;
+use crate::core::allocation::{self, AllocationHolding, TargetAllocation, Trade};
+use crate::core::analytics::PortfolioValue;
+use crate::core::config::{Investment, Portfolio};
+use crate::core::{
+    CurrencyRateProvider, MetadataProvider, PriceProvider, PriceResult, ProviderResult,
+};
+use anyhow::{Result, bail};
+use comfy_table::{Attribute, Cell, CellAlignment, Color};
+use std::collections::HashMap;
+
+/// Default tolerance band around target weights, in percentage points.
+pub const DEFAULT_TOLERANCE: f64 = 5.0;
+
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    portfolios: &[Portfolio],
+    symbol_provider: &(dyn PriceProvider + Send + Sync),
+    isin_provider: &(dyn PriceProvider + Send + Sync),
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    metadata_provider: &(dyn MetadataProvider + Send + Sync),
+    target_currency: &str,
+    global_target: Option<&TargetAllocation>,
+    tolerance: f64,
+    new_money: Option<f64>,
+) -> Result<()> {
+    if tolerance < 0.0 {
+        bail!("Tolerance must not be negative, got {tolerance}");
+    }
+    if new_money.is_some_and(|amount| amount <= 0.0) {
+        bail!("New money contribution must be positive");
+    }
+    if portfolios
+        .iter()
+        .all(|p| p.target_allocation(global_target).is_none())
+    {
+        bail!("No target allocation configured. Add `target_allocation` to the config.");
+    }
+    validate_portfolio_targets(portfolios, global_target)?;
+
+    let data = load_allocations(
+        portfolios,
+        symbol_provider,
+        isin_provider,
+        currency_provider,
+        metadata_provider,
+        target_currency,
+    )
+    .await;
+
+    for (i, portfolio) in portfolios.iter().enumerate() {
+        println!(
+            "\nPortfolio: {}\n",
+            ui::style_text(&portfolio.name, ui::StyleType::Title)
+        );
+
+        let Some(target) = portfolio.target_allocation(global_target) else {
+            println!("No target allocation, skipping.");
+            continue;
+        };
+        if data.portfolio_values[i].total_converted_value.is_none() {
+            println!(
+                "{}",
+                ui::style_text(
+                    "Some investments could not be valued, skipping.",
+                    ui::StyleType::Error
+                )
+            );
+            continue;
+        }
+
+        let holdings = allocation_holdings(&data.allocations[i], &data.price_results);
+        let trades = allocation::plan_rebalance(&holdings, target, tolerance, new_money);
+        if trades.is_empty()
+            && new_money.is_none()
+            && !within_tolerance(&holdings, target, tolerance)
+        {
+            println!(
+                "Only holdings that cannot be sold are over target, rebalance with --new-money instead."
+            );
+        } else if trades.is_empty() {
+            println!("All categories are within {tolerance:.2}% of target, no trades needed.");
+        } else {
+            display_trades(&holdings, &trades, target_currency);
+        }
+        ui::print_separator();
+    }
+
+    ui::report_provider_errors(
+        data.portfolio_values
+            .iter()
+            .flat_map(PortfolioValue::errors),
+    )
+}
+
+/// Returns whether every category is within `tolerance` of its target.
+fn within_tolerance(
+    holdings: &[AllocationHolding],
+    target: &TargetAllocation,
+    tolerance: f64,
+) -> bool {
+    let mut category_values: HashMap<String, f64> = HashMap::new();
+    for holding in holdings {
+        *category_values.entry(holding.category.clone()).or_default() += holding.value;
+    }
+    allocation::calculate_drift(&category_values, target)
+        .iter()
+        .all(|d| d.drift().abs() <= tolerance)
+}
+
+/// Flattens categorized investments into holdings named for display.
+fn allocation_holdings(
+    allocation: &HashMap<String, Vec<(Investment, f64)>>,
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
+) -> Vec<AllocationHolding> {
+    let short_name = |id: &str| {
+        price_results
+            .get(id)
+            .and_then(|pr| pr.as_ref().ok())
+            .and_then(|pr| pr.short_name.clone())
+            .unwrap_or_else(|| id.to_string())
+    };
+
+    let mut holdings: Vec<AllocationHolding> = allocation
+        .iter()
+        .flat_map(|(category, investments)| {
+            investments
+                .iter()
+                .map(move |(investment, value)| (category, investment, *value))
+        })
+        .map(|(category, investment, value)| AllocationHolding {
+            category: category.clone(),
+            identifier: investment.identifier().to_string(),
+            name: match investment {
+                Investment::Stock(s) => short_name(&s.symbol),
+                Investment::MutualFund(mf) => short_name(&mf.isin),
+                Investment::FixedDeposit(fd) => fd.name.clone(),
+                Investment::SmallSavings(s) => s.name.clone(),
+                Investment::Bond(b) => b.name.clone(),
+            },
+            value,
+            tradable: investment.is_tradable(),
+        })
+        .collect();
+    holdings.sort_by(|a, b| a.category.cmp(&b.category).then(a.name.cmp(&b.name)));
+    holdings
+}
+
+fn display_trades(holdings: &[AllocationHolding], trades: &[Trade], target_currency: &str) {
+    let mut table = ui::new_styled_table();
+    table.set_header(vec![
+        ui::header_cell("Category"),
+        ui::header_cell("Investment"),
+        ui::header_cell(&format!("Value ({target_currency})")),
+        ui::header_cell("Action"),
+        ui::header_cell(&format!("Amount ({target_currency})")),
+    ]);
+
+    let mut rows: Vec<(&Trade, Option<&AllocationHolding>)> = trades
+        .iter()
+        .map(|trade| {
+            let holding = trade.identifier.as_ref().and_then(|identifier| {
+                holdings
+                    .iter()
+                    .find(|h| &h.identifier == identifier && h.category == trade.category)
+            });
+            (trade, holding)
+        })
+        .collect();
+    rows.sort_by(|(a, a_holding), (b, b_holding)| {
+        a.category
+            .cmp(&b.category)
+            .then_with(|| a_holding.map(|h| &h.name).cmp(&b_holding.map(|h| &h.name)))
+    });
+    for (trade, holding) in rows {
+        let (action, color) = if trade.amount >= 0.0 {
+            ("Buy", Color::Green)
+        } else {
+            ("Sell", Color::Red)
+        };
+        table.add_row(vec![
+            Cell::new(&trade.category),
+            Cell::new(holding.map_or("(no holding)", |h| h.name.as_str())),
+            ui::format_optional_cell(Some(holding.map_or(0.0, |h| h.value)), |v| {
+                format!("{v:.2}")
+            }),
+            Cell::new(action).fg(color).add_attribute(Attribute::Bold),
+            Cell::new(format!("{:.2}", trade.amount.abs()))
+                .fg(color)
+                .set_alignment(CellAlignment::Right),
+        ]);
+    }
+
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::{FixedDepositInvestment, StockInvestment};
+
+    #[test]
+    fn test_allocation_holdings_uses_short_names() {
+        let allocation = HashMap::from([
+            (
+                "Equity".to_string(),
+                vec![(
+                    Investment::Stock(StockInvestment {
+                        symbol: "AAPL".to_string(),
+                        units: 10.0,
+                        category: None,
+                        tags: Vec::new(),
+                        benchmark: None,
+                        transactions: Vec::new(),
+                    }),
+                    1500.0,
+                )],
+            ),
+            (
+                "Debt".to_string(),
+                vec![(
+                    Investment::FixedDeposit(FixedDepositInvestment {
+                        name: "My FD".to_string(),
+                        value: Some(500.0),
+                        currency: None,
+                        category: None,
+                        tags: Vec::new(),
+                        ..Default::default()
+                    }),
+                    500.0,
+                )],
+            ),
+        ]);
+        let price_results = HashMap::from([(
+            "AAPL".to_string(),
+            Ok(PriceResult {
+                price: 150.0,
+                currency: "USD".to_string(),
+                historical_prices: HashMap::new(),
+                daily_prices: Vec::new(),
+                short_name: Some("Apple Inc.".to_string()),
+            }),
+        )]);
+
+        let holdings = allocation_holdings(&allocation, &price_results);
+        assert_eq!(
+            holdings,
+            vec![
+                AllocationHolding {
+                    category: "Debt".to_string(),
+                    identifier: "My FD".to_string(),
+                    name: "My FD".to_string(),
+                    value: 500.0,
+                    tradable: false,
+                },
+                AllocationHolding {
+                    category: "Equity".to_string(),
+                    identifier: "AAPL".to_string(),
+                    name: "Apple Inc.".to_string(),
+                    value: 1500.0,
+                    tradable: true,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,478 @@
+//! Comparisons of holdings and portfolios against their benchmarks.
+
+use crate::cli::ui;
+use crate::core::benchmark::{self, BenchmarkComparison, BenchmarkConfig};
+use crate::core::{
+    CurrencyRateProvider, HistoricalPeriod, MetadataProvider, PriceProvider, PriceResult,
+    ProviderError, ProviderResult, analytics,
+    config::{Investment, Portfolio},
+};
+use chrono::NaiveDate;
+use comfy_table::{Attribute, Cell};
+use futures::future::join_all;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use tracing::debug;
+
+pub(super) struct BenchmarkResult {
+    identifier: String,
+    short_name: Option<String>,
+    benchmark: String,
+    benchmark_name: Option<String>,
+    comparison: Option<BenchmarkComparison>,
+    rolling_excess: Option<f64>,
+    is_portfolio: bool,
+}
+
+/// Benchmark of each holding by identifier.
+pub(super) struct Benchmarks {
+    pub(super) by_holding: HashMap<String, String>,
+    /// Benchmarks chosen for a fund category rather than configured, whose
+    /// failed lookups are not errors
+    defaults: HashSet<String>,
+}
+
+impl Benchmarks {
+    /// Stops comparing holdings against default benchmarks whose prices could
+    /// not be fetched.
+    pub(super) fn drop_unavailable(
+        &mut self,
+        price_results: &HashMap<String, ProviderResult<PriceResult>>,
+    ) {
+        for benchmark in &self.defaults {
+            if let Some(Err(e)) = price_results.get(benchmark) {
+                debug!("Skipping default benchmark {}: {}", benchmark, e);
+                self.by_holding.retain(|_, b| b != benchmark);
+            }
+        }
+    }
+
+    /// Failed lookups to report, leaving out default benchmarks.
+    pub(super) fn failed_lookups<'a>(
+        &'a self,
+        price_results: &'a HashMap<String, ProviderResult<PriceResult>>,
+    ) -> impl Iterator<Item = (&'a str, &'a ProviderError)> {
+        ui::failed_lookups(price_results).filter(|(id, _)| !self.defaults.contains(*id))
+    }
+}
+
+/// Resolves the benchmark of each holding by identifier: its own, else the
+/// default for its fund category. Holdings without one are compared against
+/// their portfolio's benchmark.
+///
+/// Fund metadata is only fetched for funds without a benchmark of their own.
+/// A fund whose metadata cannot be fetched gets no default benchmark.
+pub(super) async fn resolve_benchmarks(
+    portfolios: &[Portfolio],
+    metadata_provider: &(dyn MetadataProvider + Send + Sync),
+    config: &BenchmarkConfig,
+) -> Benchmarks {
+    let mut by_holding = HashMap::new();
+    let mut uncovered = BTreeSet::new();
+    for investment in portfolios.iter().flat_map(|p| &p.investments) {
+        match investment {
+            Investment::Stock(s) => {
+                if let Some(benchmark) = &s.benchmark {
+                    by_holding.insert(s.symbol.clone(), benchmark.clone());
+                }
+            }
+            Investment::MutualFund(mf) => match &mf.benchmark {
+                Some(benchmark) => {
+                    by_holding.insert(mf.isin.clone(), benchmark.clone());
+                }
+                None => {
+                    uncovered.insert(mf.isin.as_str());
+                }
+            },
+            Investment::FixedDeposit(_) | Investment::SmallSavings(_) | Investment::Bond(_) => {}
+        }
+    }
+    uncovered.retain(|isin| !by_holding.contains_key(*isin));
+    let configured: HashSet<String> = by_holding
+        .values()
+        .chain(portfolios.iter().filter_map(|p| p.benchmark.as_ref()))
+        .cloned()
+        .collect();
+
+    let futures = uncovered
+        .into_iter()
+        .map(|isin| async move { (isin, metadata_provider.fetch_metadata(isin).await) });
+    let mut defaults = HashSet::new();
+    for (isin, meta) in join_all(futures).await {
+        let benchmark = match meta {
+            Ok(meta) => config.for_category(&meta.fund_category),
+            Err(e) => {
+                debug!("No default benchmark for {}: {}", isin, e);
+                None
+            }
+        };
+        if let Some(benchmark) = benchmark {
+            if !configured.contains(&benchmark) {
+                defaults.insert(benchmark.clone());
+            }
+            by_holding.insert(isin.to_string(), benchmark);
+        }
+    }
+    Benchmarks {
+        by_holding,
+        defaults,
+    }
+}
+
+/// Adds the benchmarks of holdings and portfolios to the prices to fetch.
+pub(super) fn add_benchmark_fetches<'a>(
+    investments_to_fetch: &mut HashMap<String, &'a (dyn PriceProvider + Send + Sync)>,
+    portfolios: &[Portfolio],
+    benchmarks: &HashMap<String, String>,
+    symbol_provider: &'a (dyn PriceProvider + Send + Sync),
+    isin_provider: &'a (dyn PriceProvider + Send + Sync),
+) {
+    let portfolio_benchmarks = portfolios.iter().filter_map(|p| p.benchmark.as_ref());
+    for benchmark in benchmarks.values().chain(portfolio_benchmarks) {
+        let provider = if benchmark::is_isin(benchmark) {
+            isin_provider
+        } else {
+            symbol_provider
+        };
+        investments_to_fetch
+            .entry(benchmark.clone())
+            .or_insert(provider);
+    }
+}
+
+/// Average rolling return of a series above that of its benchmark.
+fn rolling_excess(
+    series: &[(NaiveDate, f64)],
+    benchmark: &[(NaiveDate, f64)],
+    period: HistoricalPeriod,
+) -> Option<f64> {
+    let stats = analytics::calculate_series_rolling_returns(series, period, &[]).ok()??;
+    let benchmark_stats =
+        analytics::calculate_series_rolling_returns(benchmark, period, &[]).ok()??;
+    Some(stats.average - benchmark_stats.average)
+}
+
+pub(super) async fn calculate_benchmark_comparisons(
+    portfolio: &Portfolio,
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    target_currency: &str,
+    benchmarks: &HashMap<String, String>,
+    risk_free_rate: f64,
+    rolling_period: Option<HistoricalPeriod>,
+) -> Vec<BenchmarkResult> {
+    let holdings = analytics::calculate_portfolio_value(
+        portfolio,
+        price_results,
+        currency_provider,
+        target_currency,
+        &|| (), // No progress updates needed here
+    )
+    .await;
+    let price_data = |id: &str| price_results.get(id).and_then(|r| r.as_ref().ok());
+
+    let mut results = Vec::new();
+    let mut positions = Vec::new();
+    for holding in &holdings.investments {
+        let Some(data) = price_data(&holding.identifier) else {
+            continue;
+        };
+        if let Some(value) = holding.converted_value {
+            positions.push((data.daily_prices.as_slice(), value));
+        }
+        if holding.units.is_none() {
+            continue; // Deposits are not compared against a benchmark
+        }
+
+        let Some(benchmark) = benchmarks
+            .get(&holding.identifier)
+            .or(portfolio.benchmark.as_ref())
+        else {
+            continue;
+        };
+        let benchmark_data = price_data(benchmark);
+        results.push(BenchmarkResult {
+            identifier: holding.identifier.clone(),
+            short_name: holding.short_name.clone(),
+            benchmark: benchmark.clone(),
+            benchmark_name: benchmark_data.and_then(|b| b.short_name.clone()),
+            comparison: benchmark_data.and_then(|b| {
+                benchmark::compare(&data.daily_prices, &b.daily_prices, risk_free_rate)
+            }),
+            rolling_excess: benchmark_data.zip(rolling_period).and_then(|(b, period)| {
+                rolling_excess(&data.daily_prices, &b.daily_prices, period)
+            }),
+            is_portfolio: false,
+        });
+    }
+
+    if let Some(benchmark) = &portfolio.benchmark
+        && positions.len() > 1
+    {
+        let series = analytics::portfolio_value_series(&positions);
+        let benchmark_data = price_data(benchmark);
+        results.push(BenchmarkResult {
+            identifier: "Portfolio".to_string(),
+            short_name: None,
+            benchmark: benchmark.clone(),
+            benchmark_name: benchmark_data.and_then(|b| b.short_name.clone()),
+            comparison: benchmark_data
+                .and_then(|b| benchmark::compare(&series, &b.daily_prices, risk_free_rate)),
+            rolling_excess: benchmark_data
+                .zip(rolling_period)
+                .and_then(|(b, period)| rolling_excess(&series, &b.daily_prices, period)),
+            is_portfolio: true,
+        });
+    }
+
+    results
+}
+
+pub(super) fn display_benchmark_results(
+    results: &[BenchmarkResult],
+    rolling_period: Option<HistoricalPeriod>,
+) {
+    if results.is_empty() {
+        return;
+    }
+
+    println!("\nBenchmark Comparison");
+    let mut table = ui::new_styled_table();
+    let mut header = vec![
+        ui::header_cell("Investment"),
+        ui::header_cell("Benchmark"),
+        ui::header_cell("Excess Return"),
+        ui::header_cell("Tracking Error"),
+        ui::header_cell("Beta"),
+        ui::header_cell("Alpha"),
+        ui::header_cell("Up Capture"),
+        ui::header_cell("Down Capture"),
+    ];
+    if let Some(period) = rolling_period {
+        header.push(ui::header_cell(&format!("{period} Rolling Excess")));
+    }
+    table.set_header(header);
+
+    for result in results {
+        let name_display = result
+            .short_name
+            .clone()
+            .unwrap_or_else(|| result.identifier.clone());
+        let name_cell = if result.is_portfolio {
+            Cell::new(name_display).add_attribute(Attribute::Bold)
+        } else {
+            Cell::new(name_display)
+        };
+        let mut row_cells = vec![
+            name_cell,
+            Cell::new(result.benchmark_name.as_ref().unwrap_or(&result.benchmark)),
+        ];
+        match &result.comparison {
+            Some(c) => {
+                row_cells.push(ui::change_cell(c.excess_return));
+                row_cells.push(ui::format_optional_cell(Some(c.tracking_error), |v| {
+                    format!("{v:.2}%")
+                }));
+                row_cells.push(ui::format_optional_cell(c.beta, |v| format!("{v:.2}")));
+                row_cells.push(c.alpha.map_or(ui::na_cell(false), ui::change_cell));
+                row_cells.push(ui::format_optional_cell(c.up_capture, |v| {
+                    format!("{v:.2}%")
+                }));
+                row_cells.push(ui::format_optional_cell(c.down_capture, |v| {
+                    format!("{v:.2}%")
+                }));
+            }
+            None => {
+                for _ in 0..6 {
+                    row_cells.push(ui::na_cell(false));
+                }
+            }
+        }
+        if rolling_period.is_some() {
+            row_cells.push(
+                result
+                    .rolling_excess
+                    .map_or(ui::na_cell(false), ui::change_cell),
+            );
+        }
+        table.add_row(row_cells);
+    }
+
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::returns::testing::{MockCurrencyProvider, daily_price_result};
+    use crate::core::config::StockInvestment;
+    use crate::core::metadata::FundMetadata;
+    use async_trait::async_trait;
+
+    #[tokio::test]
+    async fn test_benchmark_comparisons_fall_back_to_portfolio_benchmark() {
+        let portfolio = Portfolio {
+            name: "Tech".to_string(),
+            investments: vec![
+                Investment::Stock(StockInvestment {
+                    symbol: "AAPL".to_string(),
+                    units: 1.0,
+                    category: None,
+                    tags: Vec::new(),
+                    benchmark: Some("^NDX".to_string()),
+                    transactions: Vec::new(),
+                }),
+                Investment::Stock(StockInvestment {
+                    symbol: "GOOG".to_string(),
+                    units: 1.0,
+                    category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
+                }),
+            ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: Some("^GSPC".to_string()),
+        };
+        let price_results = HashMap::from([
+            (
+                "AAPL".to_string(),
+                Ok(daily_price_result(&[100.0, 120.0, 96.0, 115.2])),
+            ),
+            (
+                "GOOG".to_string(),
+                Ok(daily_price_result(&[100.0, 101.0, 102.0, 103.0])),
+            ),
+            (
+                "^NDX".to_string(),
+                Ok(daily_price_result(&[100.0, 110.0, 99.0, 108.9])),
+            ),
+            (
+                "^GSPC".to_string(),
+                Ok(daily_price_result(&[100.0, 101.0, 102.0, 103.0])),
+            ),
+        ]);
+
+        let benchmarks = resolve_benchmarks(
+            std::slice::from_ref(&portfolio),
+            &NoMetadataProvider,
+            &BenchmarkConfig::default(),
+        )
+        .await;
+        let results = calculate_benchmark_comparisons(
+            &portfolio,
+            &price_results,
+            &MockCurrencyProvider,
+            "USD",
+            &benchmarks.by_holding,
+            0.0,
+            None,
+        )
+        .await;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].benchmark, "^NDX");
+        let beta = results[0].comparison.unwrap().beta.unwrap();
+        assert!((beta - 2.0).abs() < 1e-9);
+        assert_eq!(results[1].benchmark, "^GSPC");
+        assert!(results[1].comparison.unwrap().tracking_error.abs() < 1e-9);
+        assert!(results[2].is_portfolio);
+        assert!(results[2].comparison.is_some());
+    }
+
+    struct NoMetadataProvider;
+
+    #[async_trait]
+    impl MetadataProvider for NoMetadataProvider {
+        async fn fetch_metadata(&self, identifier: &str) -> ProviderResult<FundMetadata> {
+            Err(ProviderError::NotFound(identifier.to_string()))
+        }
+    }
+
+    /// Knows the category of a single fund, recording the funds looked up.
+    #[derive(Default)]
+    struct OneFundMetadataProvider {
+        requested: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl MetadataProvider for OneFundMetadataProvider {
+        async fn fetch_metadata(&self, identifier: &str) -> ProviderResult<FundMetadata> {
+            self.requested.lock().unwrap().push(identifier.to_string());
+            if identifier != "INF000000001" {
+                return Err(ProviderError::NotFound(identifier.to_string()));
+            }
+            Ok(FundMetadata {
+                isin: identifier.to_string(),
+                fund_type: "Equity".to_string(),
+                fund_category: "Large Cap Fund".to_string(),
+                expense_ratio: 0.5,
+                expense_ratio_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                aum: 0.0,
+                fund_rating: None,
+                fund_rating_date: None,
+                category: "Equity".to_string(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_benchmarks_fetches_only_uncovered_funds() {
+        let fund = |isin: &str, benchmark: Option<&str>| {
+            Investment::MutualFund(crate::core::config::MutualFundInvestment {
+                isin: isin.to_string(),
+                units: 1.0,
+                category: None,
+                tags: Vec::new(),
+                benchmark: benchmark.map(str::to_string),
+                transactions: Vec::new(),
+            })
+        };
+        let portfolio = Portfolio {
+            name: "Funds".to_string(),
+            investments: vec![
+                fund("INF000000001", None),
+                fund("INF000000002", None),
+                fund("INF000000003", Some("^NSEMDCP50")),
+            ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
+        };
+
+        let metadata_provider = OneFundMetadataProvider::default();
+        let mut benchmarks = resolve_benchmarks(
+            std::slice::from_ref(&portfolio),
+            &metadata_provider,
+            &BenchmarkConfig::default(),
+        )
+        .await;
+        assert_eq!(benchmarks.by_holding["INF000000001"], "^NSEI");
+        assert_eq!(benchmarks.by_holding["INF000000003"], "^NSEMDCP50");
+        assert!(!benchmarks.by_holding.contains_key("INF000000002"));
+        assert_eq!(benchmarks.defaults, HashSet::from(["^NSEI".to_string()]));
+        // The covered fund is not looked up
+        let mut requested = metadata_provider.requested.lock().unwrap().clone();
+        requested.sort();
+        assert_eq!(requested, vec!["INF000000001", "INF000000002"]);
+
+        // A default benchmark that cannot be fetched is dropped, not reported
+        let price_results = HashMap::from([
+            (
+                "^NSEI".to_string(),
+                Err(ProviderError::Network("timeout".to_string())),
+            ),
+            (
+                "^NSEMDCP50".to_string(),
+                Err(ProviderError::NotFound("^NSEMDCP50".to_string())),
+            ),
+        ]);
+        benchmarks.drop_unavailable(&price_results);
+        assert!(!benchmarks.by_holding.contains_key("INF000000001"));
+        let failed: Vec<&str> = benchmarks
+            .failed_lookups(&price_results)
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(failed, vec!["^NSEMDCP50"]);
+    }
+}
@@ -0,0 +1,235 @@
+//! Returns for each calendar or fiscal year.
+
+use super::Sources;
+use crate::cli::ui;
+use crate::core::{
+    CurrencyRateProvider, PriceResult, ProviderResult, analytics, config::Portfolio,
+};
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use comfy_table::{Attribute, Cell};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+struct YearlyReturnResult {
+    identifier: String,
+    short_name: Option<String>,
+    returns: BTreeMap<i32, f64>,
+    error: Option<String>,
+}
+
+struct PortfolioYearlyReturnResult {
+    name: String,
+    investment_returns: Vec<YearlyReturnResult>,
+    portfolio_returns: BTreeMap<i32, f64>,
+}
+
+/// Shows the returns of each portfolio for each year starting in `start_month`.
+pub(super) async fn show(sources: &Sources<'_>, start_month: u32) -> Result<()> {
+    let fetched_results = sources.fetch_prices(None).await;
+    let current_year = analytics::year_of(Local::now().date_naive(), start_month);
+
+    let num_portfolios = sources.portfolios.len();
+    for (i, portfolio) in sources.portfolios.iter().enumerate() {
+        let result = calculate_portfolio_yearly_returns(
+            portfolio,
+            &fetched_results,
+            sources.currency_provider,
+            sources.target_currency,
+            start_month,
+        )
+        .await;
+
+        if !result.investment_returns.is_empty() {
+            println!(
+                "\nPortfolio: {}",
+                ui::style_text(&result.name, ui::StyleType::Title)
+            );
+            display_yearly_return_results(&result, start_month, current_year);
+
+            if i < num_portfolios - 1 {
+                ui::print_separator();
+            }
+        }
+    }
+
+    ui::report_provider_errors(ui::failed_lookups(&fetched_results))
+}
+
+async fn calculate_portfolio_yearly_returns(
+    portfolio: &Portfolio,
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    target_currency: &str,
+    start_month: u32,
+) -> PortfolioYearlyReturnResult {
+    let holdings = analytics::calculate_portfolio_value(
+        portfolio,
+        price_results,
+        currency_provider,
+        target_currency,
+        &|| (), // No progress updates needed here
+    )
+    .await;
+
+    let mut investment_returns = Vec::new();
+    let mut positions = Vec::new();
+
+    for holding in &holdings.investments {
+        // Only fixed deposits with interest terms have a value history
+        if holding.units.is_none() && !price_results.contains_key(&holding.identifier) {
+            continue;
+        }
+
+        let mut result = YearlyReturnResult {
+            identifier: holding.identifier.clone(),
+            short_name: holding.short_name.clone(),
+            returns: BTreeMap::new(),
+            error: holding.error.as_ref().map(|e| e.to_string()),
+        };
+
+        if result.error.is_none() {
+            if let Some(Ok(price_data)) = price_results.get(&holding.identifier) {
+                result.returns = analytics::yearly_returns(&price_data.daily_prices, start_month);
+                if result.returns.is_empty() {
+                    result.error = Some("Not enough data".to_string());
+                }
+                if let Some(value) = holding.converted_value {
+                    positions.push((price_data.daily_prices.as_slice(), value));
+                }
+            } else {
+                result.error = Some("Price data not available".to_string());
+            }
+        }
+
+        investment_returns.push(result);
+    }
+
+    // Yearly returns of the portfolio's value series at current holdings
+    let series = analytics::portfolio_value_series(&positions);
+
+    PortfolioYearlyReturnResult {
+        name: portfolio.name.clone(),
+        investment_returns,
+        portfolio_returns: analytics::yearly_returns(&series, start_month),
+    }
+}
+
+/// Column label of a year, e.g. `2023` or `FY2023-24`, with `YTD` for the current one.
+fn year_label(year: i32, start_month: u32, current_year: i32) -> String {
+    if year == current_year {
+        "YTD".to_string()
+    } else if start_month == 1 {
+        year.to_string()
+    } else {
+        format!("FY{}-{:02}", year, (year + 1) % 100)
+    }
+}
+
+fn display_yearly_return_results(
+    result: &PortfolioYearlyReturnResult,
+    start_month: u32,
+    current_year: i32,
+) {
+    let years: BTreeSet<i32> = result
+        .investment_returns
+        .iter()
+        .flat_map(|r| r.returns.keys().copied())
+        .chain(result.portfolio_returns.keys().copied())
+        .collect();
+    if start_month == 1 {
+        println!("\nCalendar Year Returns");
+    } else {
+        let month = NaiveDate::from_ymd_opt(2000, start_month, 1).unwrap();
+        println!("\nFiscal Year Returns (starting {})", month.format("%B"));
+    }
+
+    let mut table = ui::new_styled_table();
+    let mut header = vec![ui::header_cell("Investment")];
+    header.extend(
+        years
+            .iter()
+            .map(|year| ui::header_cell(&year_label(*year, start_month, current_year))),
+    );
+    table.set_header(header);
+
+    for result in &result.investment_returns {
+        let name_display = result
+            .short_name
+            .clone()
+            .unwrap_or_else(|| result.identifier.clone());
+        let mut row_cells = vec![Cell::new(name_display)];
+        for year in &years {
+            let cell = match result.returns.get(year) {
+                Some(change) => ui::change_cell(*change),
+                None => ui::na_cell(result.error.is_some()),
+            };
+            row_cells.push(cell);
+        }
+        table.add_row(row_cells);
+    }
+
+    if !result.portfolio_returns.is_empty() && result.investment_returns.len() > 1 {
+        let mut total_row_cells = vec![Cell::new("Portfolio").add_attribute(Attribute::Bold)];
+        for year in &years {
+            let cell = match result.portfolio_returns.get(year) {
+                Some(change) => ui::change_cell(*change),
+                None => ui::na_cell(false),
+            };
+            total_row_cells.push(cell);
+        }
+        table.add_row(total_row_cells);
+    }
+
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::returns::testing::{MockCurrencyProvider, daily_price_result};
+    use crate::core::config::{Investment, StockInvestment};
+
+    #[tokio::test]
+    async fn test_portfolio_yearly_returns() {
+        let portfolio = Portfolio {
+            name: "Tech".to_string(),
+            investments: vec![Investment::Stock(StockInvestment {
+                symbol: "AAPL".to_string(),
+                units: 1.0,
+                category: None,
+                tags: Vec::new(),
+                benchmark: None,
+                transactions: Vec::new(),
+            })],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
+        };
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let mut price_data = daily_price_result(&[100.0]);
+        price_data.daily_prices = vec![
+            (date(2022, 12, 30), 100.0),
+            (date(2023, 6, 30), 90.0),
+            (date(2023, 12, 29), 110.0),
+        ];
+        let price_results = HashMap::from([("AAPL".to_string(), Ok(price_data))]);
+
+        let result = calculate_portfolio_yearly_returns(
+            &portfolio,
+            &price_results,
+            &MockCurrencyProvider,
+            "USD",
+            1,
+        )
+        .await;
+
+        let returns = &result.investment_returns[0].returns;
+        assert!((returns[&2023] - 10.0).abs() < 1e-9);
+        assert!((result.portfolio_returns[&2023] - 10.0).abs() < 1e-9);
+
+        assert_eq!(year_label(2023, 1, 2026), "2023");
+        assert_eq!(year_label(2023, 4, 2026), "FY2023-24");
+        assert_eq!(year_label(2099, 4, 2026), "FY2099-00");
+        assert_eq!(year_label(2026, 4, 2026), "YTD");
+    }
+}
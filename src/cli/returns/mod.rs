@@ -0,0 +1,241 @@
+mod benchmark;
+mod calendar;
+mod rolling;
+mod trailing;
+
+use super::ui;
+use crate::core::analytics::ReturnsConfig;
+use crate::core::benchmark::BenchmarkConfig;
+use crate::core::{
+    CurrencyRateProvider, HistoricalPeriod, MetadataProvider, PriceProvider, PriceResult,
+    ProviderResult,
+    config::{Investment, Portfolio},
+};
+use anyhow::{Result, bail};
+use benchmark::Benchmarks;
+use chrono::{Local, NaiveDate};
+use futures::future::join_all;
+use std::collections::HashMap;
+use tracing::info;
+
+/// Which returns to show.
+pub enum ReturnsMode<'a> {
+    /// Returns over trailing periods such as `6m` or `inception`, or the
+    /// default periods when none are given
+    Trailing(&'a [String]),
+    /// Rolling returns over windows of a fixed-length period such as `1y`
+    Rolling(&'a str),
+    /// Returns for each year starting in `start_month`, 1 for calendar years
+    Yearly { start_month: u32 },
+}
+
+/// What `xmf returns` shows and the settings it uses.
+pub struct ReturnsOptions<'a> {
+    pub mode: ReturnsMode<'a>,
+    pub returns_config: &'a ReturnsConfig,
+    pub benchmark_config: &'a BenchmarkConfig,
+    /// Annual risk-free rate in percent, for alpha against benchmarks
+    pub risk_free_rate: f64,
+}
+
+/// Portfolios to report on and the providers of their prices.
+struct Sources<'a> {
+    portfolios: &'a [Portfolio],
+    symbol_provider: &'a (dyn PriceProvider + Send + Sync),
+    isin_provider: &'a (dyn PriceProvider + Send + Sync),
+    currency_provider: &'a (dyn CurrencyRateProvider + Send + Sync),
+    metadata_provider: &'a (dyn MetadataProvider + Send + Sync),
+    target_currency: &'a str,
+}
+
+impl Sources<'_> {
+    /// Fetches the prices of holdings and of the benchmarks they are
+    /// compared against, with the value history of deposits.
+    async fn fetch_prices(
+        &self,
+        benchmarks: Option<&Benchmarks>,
+    ) -> HashMap<String, ProviderResult<PriceResult>> {
+        let mut investments_to_fetch =
+            investments_to_fetch(self.portfolios, self.symbol_provider, self.isin_provider);
+        if let Some(benchmarks) = benchmarks {
+            benchmark::add_benchmark_fetches(
+                &mut investments_to_fetch,
+                self.portfolios,
+                &benchmarks.by_holding,
+                self.symbol_provider,
+                self.isin_provider,
+            );
+        }
+        let deposits = deposit_series(
+            self.portfolios,
+            self.target_currency,
+            Local::now().date_naive(),
+        );
+        fetch_prices(investments_to_fetch, deposits).await
+    }
+}
+
+pub async fn run(
+    portfolios: &[Portfolio],
+    symbol_provider: &(dyn PriceProvider + Send + Sync),
+    isin_provider: &(dyn PriceProvider + Send + Sync),
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    metadata_provider: &(dyn MetadataProvider + Send + Sync),
+    target_currency: &str,
+    options: &ReturnsOptions<'_>,
+) -> Result<()> {
+    info!("Calculating returns for investments...");
+    options.returns_config.validate()?;
+    let periods = match options.mode {
+        ReturnsMode::Trailing(periods) => {
+            HistoricalPeriod::parse_list(periods, &trailing::DEFAULT_CAGR_PERIODS)?
+        }
+        _ => Vec::new(),
+    };
+
+    let has_prices = !investments_to_fetch(portfolios, symbol_provider, isin_provider).is_empty();
+    let has_value_history = portfolios
+        .iter()
+        .flat_map(|p| &p.investments)
+        .any(|i| match i {
+            Investment::FixedDeposit(fd) => fd.terms().is_some(),
+            Investment::SmallSavings(_) => true,
+            _ => false,
+        });
+    if !has_prices && !has_value_history {
+        println!("No investments found to calculate returns for.");
+        return Ok(());
+    }
+
+    let sources = Sources {
+        portfolios,
+        symbol_provider,
+        isin_provider,
+        currency_provider,
+        metadata_provider,
+        target_currency,
+    };
+    match options.mode {
+        ReturnsMode::Trailing(_) => trailing::show(&sources, &periods, options).await,
+        ReturnsMode::Rolling(period) => {
+            let period: HistoricalPeriod = period.parse()?;
+            if !period.is_trailing() {
+                bail!("Rolling returns need a fixed-length period such as 1y, got {period}");
+            }
+            rolling::show(&sources, period, options).await
+        }
+        ReturnsMode::Yearly { start_month } => calendar::show(&sources, start_month).await,
+    }
+}
+
+/// Collects the price provider of each stock, mutual fund and quoted bond by
+/// identifier.
+fn investments_to_fetch<'a>(
+    portfolios: &[Portfolio],
+    symbol_provider: &'a (dyn PriceProvider + Send + Sync),
+    isin_provider: &'a (dyn PriceProvider + Send + Sync),
+) -> HashMap<String, &'a (dyn PriceProvider + Send + Sync)> {
+    let mut investments_to_fetch = HashMap::new();
+    for portfolio in portfolios {
+        for investment in &portfolio.investments {
+            match investment {
+                Investment::Stock(s) => {
+                    investments_to_fetch.insert(s.symbol.clone(), symbol_provider);
+                }
+                Investment::MutualFund(mf) => {
+                    investments_to_fetch.insert(mf.isin.clone(), isin_provider);
+                }
+                Investment::Bond(b) => {
+                    if let Some(symbol) = &b.symbol {
+                        investments_to_fetch.insert(symbol.clone(), symbol_provider);
+                    }
+                }
+                _ => {} // Not relevant for returns
+            }
+        }
+    }
+    investments_to_fetch
+}
+
+/// Builds the accrued value history of fixed deposits with interest terms, by name.
+fn deposit_series(
+    portfolios: &[Portfolio],
+    target_currency: &str,
+    today: NaiveDate,
+) -> HashMap<String, ProviderResult<PriceResult>> {
+    let mut deposits = HashMap::new();
+    for investment in portfolios.iter().flat_map(|p| &p.investments) {
+        if let Investment::FixedDeposit(fd) = investment
+            && let Some(terms) = fd.terms()
+        {
+            let price_data = PriceResult {
+                price: terms.value_on(today),
+                currency: fd
+                    .currency
+                    .clone()
+                    .unwrap_or_else(|| target_currency.to_string()),
+                historical_prices: HashMap::new(),
+                daily_prices: terms.value_series(today),
+                short_name: None,
+            };
+            deposits.insert(fd.name.clone(), Ok(price_data));
+        }
+    }
+    deposits
+}
+
+/// Fetches all prices concurrently behind a progress bar, adding the value
+/// history of `deposits`.
+async fn fetch_prices(
+    investments_to_fetch: HashMap<String, &(dyn PriceProvider + Send + Sync)>,
+    deposits: HashMap<String, ProviderResult<PriceResult>>,
+) -> HashMap<String, ProviderResult<PriceResult>> {
+    let pb = ui::new_progress_bar(investments_to_fetch.len() as u64, true);
+    pb.set_message("Fetching prices...");
+
+    let futures = investments_to_fetch.into_iter().map(|(id, provider)| {
+        let pb_clone = pb.clone();
+        async move {
+            let result = provider.fetch_price(&id).await;
+            pb_clone.inc(1);
+            (id, result)
+        }
+    });
+
+    let mut fetched_results: HashMap<_, _> = join_all(futures).await.into_iter().collect();
+    pb.finish_and_clear();
+    fetched_results.extend(deposits);
+    fetched_results
+}
+
+/// Helpers shared by the tests of each kind of returns
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use async_trait::async_trait;
+
+    // A mock currency provider that assumes all currencies are 1:1 with target
+    pub(super) struct MockCurrencyProvider;
+
+    #[async_trait]
+    impl CurrencyRateProvider for MockCurrencyProvider {
+        async fn get_rate(&self, _from: &str, _to: &str) -> ProviderResult<f64> {
+            Ok(1.0) // Assume 1:1 for simplicity in tests
+        }
+    }
+
+    pub(super) fn daily_price_result(values: &[f64]) -> PriceResult {
+        let start = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        PriceResult {
+            price: *values.last().unwrap(),
+            currency: "USD".to_string(),
+            historical_prices: HashMap::new(),
+            daily_prices: values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (start + chrono::Duration::days(i as i64), *v))
+                .collect(),
+            short_name: None,
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Rolling returns over windows of a fixed-length period.
+
+use super::{ReturnsOptions, Sources, benchmark};
+use crate::cli::ui;
+use crate::core::analytics::RollingReturnStats;
+use crate::core::{
+    CurrencyRateProvider, HistoricalPeriod, PriceResult, ProviderResult, analytics,
+    config::Portfolio,
+};
+use anyhow::Result;
+use comfy_table::{Attribute, Cell};
+use std::collections::HashMap;
+
+#[derive(Clone)]
+struct RollingReturnResult {
+    identifier: String,
+    short_name: Option<String>,
+    stats: Option<RollingReturnStats>,
+    error: Option<String>,
+}
+
+struct PortfolioRollingReturnResult {
+    name: String,
+    investment_returns: Vec<RollingReturnResult>,
+    portfolio_stats: Option<RollingReturnStats>,
+}
+
+/// Shows the rolling returns of each portfolio over windows of `period`,
+/// compared against benchmarks.
+pub(super) async fn show(
+    sources: &Sources<'_>,
+    period: HistoricalPeriod,
+    options: &ReturnsOptions<'_>,
+) -> Result<()> {
+    let buckets = &options.returns_config.rolling_buckets;
+    let mut benchmarks = benchmark::resolve_benchmarks(
+        sources.portfolios,
+        sources.metadata_provider,
+        options.benchmark_config,
+    )
+    .await;
+    let fetched_results = sources.fetch_prices(Some(&benchmarks)).await;
+    benchmarks.drop_unavailable(&fetched_results);
+
+    let num_portfolios = sources.portfolios.len();
+    for (i, portfolio) in sources.portfolios.iter().enumerate() {
+        let result = calculate_portfolio_rolling_returns(
+            portfolio,
+            &fetched_results,
+            sources.currency_provider,
+            sources.target_currency,
+            period,
+            buckets,
+        )
+        .await;
+
+        if !result.investment_returns.is_empty() {
+            println!(
+                "\nPortfolio: {}",
+                ui::style_text(&result.name, ui::StyleType::Title)
+            );
+            display_rolling_return_results(&result, period, buckets);
+            let comparisons = benchmark::calculate_benchmark_comparisons(
+                portfolio,
+                &fetched_results,
+                sources.currency_provider,
+                sources.target_currency,
+                &benchmarks.by_holding,
+                options.risk_free_rate,
+                Some(period),
+            )
+            .await;
+            benchmark::display_benchmark_results(&comparisons, Some(period));
+
+            if i < num_portfolios - 1 {
+                ui::print_separator();
+            }
+        }
+    }
+
+    ui::report_provider_errors(benchmarks.failed_lookups(&fetched_results))
+}
+
+async fn calculate_portfolio_rolling_returns(
+    portfolio: &Portfolio,
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    target_currency: &str,
+    period: HistoricalPeriod,
+    buckets: &[f64],
+) -> PortfolioRollingReturnResult {
+    let holdings = analytics::calculate_portfolio_value(
+        portfolio,
+        price_results,
+        currency_provider,
+        target_currency,
+        &|| (), // No progress updates needed here
+    )
+    .await;
+
+    let mut investment_returns = Vec::new();
+    let mut positions = Vec::new();
+
+    for holding in &holdings.investments {
+        // Only fixed deposits with interest terms have a value history
+        if holding.units.is_none() && !price_results.contains_key(&holding.identifier) {
+            continue;
+        }
+
+        if let Some(e) = &holding.error {
+            investment_returns.push(RollingReturnResult {
+                identifier: holding.identifier.clone(),
+                short_name: holding.short_name.clone(),
+                stats: None,
+                error: Some(e.to_string()),
+            });
+            continue;
+        }
+
+        let mut result = RollingReturnResult {
+            identifier: holding.identifier.clone(),
+            short_name: holding.short_name.clone(),
+            stats: None,
+            error: None,
+        };
+
+        if let Some(Ok(price_data)) = price_results.get(&holding.identifier) {
+            if let Some(value) = holding.converted_value {
+                positions.push((price_data.daily_prices.as_slice(), value));
+            }
+            match analytics::calculate_rolling_returns(price_data, period, buckets) {
+                Ok(Some(stats)) => {
+                    result.stats = Some(stats);
+                }
+                Ok(None) => {
+                    result.error = Some("Not enough data".to_string());
+                }
+                Err(e) => {
+                    result.error = Some(format!("Rolling return calculation failed: {e}"));
+                }
+            }
+        } else {
+            result.error = Some("Price data not available".to_string());
+        }
+
+        investment_returns.push(result);
+    }
+
+    // Rolling returns of the portfolio's value series at current holdings
+    let series = analytics::portfolio_value_series(&positions);
+    let portfolio_stats = analytics::calculate_series_rolling_returns(&series, period, buckets)
+        .ok()
+        .flatten();
+
+    PortfolioRollingReturnResult {
+        name: portfolio.name.clone(),
+        investment_returns,
+        portfolio_stats,
+    }
+}
+
+/// Column headers for the rolling return distribution buckets.
+fn bucket_headers(buckets: &[f64]) -> Vec<String> {
+    let mut headers = Vec::new();
+    if let Some(first) = buckets.first() {
+        headers.push(format!("< {first}%"));
+    }
+    for pair in buckets.windows(2) {
+        headers.push(format!("{}-{}%", pair[0], pair[1]));
+    }
+    if let Some(last) = buckets.last() {
+        headers.push(format!("> {last}%"));
+    }
+    headers
+}
+
+fn display_rolling_return_results(
+    result: &PortfolioRollingReturnResult,
+    period: HistoricalPeriod,
+    buckets: &[f64],
+) {
+    println!("\n{} Rolling Returns", period);
+    let mut table = ui::new_styled_table();
+    let mut header = vec![
+        ui::header_cell("Investment"),
+        ui::header_cell("Avg"),
+        ui::header_cell("Min"),
+        ui::header_cell("Max"),
+        ui::header_cell("Std Dev"),
+    ];
+    let bucket_headers = bucket_headers(buckets);
+    header.extend(bucket_headers.iter().map(|h| ui::header_cell(h)));
+    table.set_header(header);
+
+    for result in &result.investment_returns {
+        let name_display = if let Some(name) = &result.short_name {
+            name.clone()
+        } else {
+            result.identifier.clone()
+        };
+        let mut row_cells = vec![Cell::new(name_display)];
+
+        if let Some(stats) = &result.stats {
+            row_cells.push(ui::change_cell(stats.average));
+            row_cells.push(ui::change_cell(stats.min));
+            row_cells.push(ui::change_cell(stats.max));
+            row_cells.push(ui::change_cell(stats.std_dev));
+            for val in &stats.distribution {
+                row_cells.push(ui::change_cell(*val));
+            }
+        } else {
+            for _ in 0..4 + bucket_headers.len() {
+                row_cells.push(ui::na_cell(result.error.is_some()));
+            }
+        }
+        table.add_row(row_cells);
+    }
+
+    if let Some(stats) = &result.portfolio_stats
+        && result.investment_returns.len() > 1
+    {
+        let mut total_row_cells = vec![Cell::new("Portfolio").add_attribute(Attribute::Bold)];
+        total_row_cells.push(ui::change_cell(stats.average));
+        total_row_cells.push(ui::change_cell(stats.min));
+        total_row_cells.push(ui::change_cell(stats.max));
+        total_row_cells.push(ui::change_cell(stats.std_dev));
+        for val in &stats.distribution {
+            total_row_cells.push(ui::change_cell(*val));
+        }
+        table.add_row(total_row_cells);
+    }
+
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::returns::testing::{MockCurrencyProvider, daily_price_result};
+    use crate::core::config::{Investment, StockInvestment};
+
+    #[tokio::test]
+    async fn test_portfolio_rolling_returns_use_value_series() {
+        let portfolio = Portfolio {
+            name: "Tech".to_string(),
+            investments: vec![
+                Investment::Stock(StockInvestment {
+                    symbol: "AAPL".to_string(),
+                    units: 1.0,
+                    category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
+                }),
+                Investment::Stock(StockInvestment {
+                    symbol: "GOOG".to_string(),
+                    units: 1.0,
+                    category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
+                }),
+            ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
+        };
+        // Opposite moves of equal holdings leave the portfolio flat
+        let rising: Vec<f64> = (0..10).map(|i| 100.0 + i as f64).collect();
+        let falling: Vec<f64> = (0..10).map(|i| 109.0 - i as f64).collect();
+        let price_results = HashMap::from([
+            ("AAPL".to_string(), Ok(daily_price_result(&rising))),
+            ("GOOG".to_string(), Ok(daily_price_result(&falling))),
+        ]);
+
+        let result = calculate_portfolio_rolling_returns(
+            &portfolio,
+            &price_results,
+            &MockCurrencyProvider,
+            "USD",
+            HistoricalPeriod::FiveDays,
+            &[0.0, 5.0],
+        )
+        .await;
+
+        let aapl = result.investment_returns[0].stats.as_ref().unwrap();
+        assert!(aapl.average > 0.0);
+        assert_eq!(aapl.distribution.len(), 3);
+        let stats = result.portfolio_stats.unwrap();
+        assert!(stats.average.abs() < 1e-9);
+        assert!(stats.std_dev.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bucket_headers() {
+        assert_eq!(
+            bucket_headers(&[0.0, 5.0, 12.5]),
+            vec!["< 0%", "0-5%", "5-12.5%", "> 12.5%"]
+        );
+    }
+}
@@ -0,0 +1,566 @@
+//! Returns over trailing periods, with XIRR for holdings with cash flows.
+
+use super::{ReturnsOptions, Sources, benchmark};
+use crate::cli::ui;
+use crate::core::{
+    CurrencyRateProvider, HistoricalPeriod, PriceResult, ProviderResult, analytics,
+    config::{Investment, Portfolio},
+};
+use anyhow::{Result, anyhow};
+use chrono::Local;
+use comfy_table::{Attribute, Cell};
+use rust_decimal::{Decimal, prelude::*};
+use rust_finprim::rate::cagr;
+use std::collections::{BTreeMap, HashMap};
+use tracing::debug;
+
+#[derive(Clone)]
+struct ReturnResult {
+    identifier: String,
+    short_name: Option<String>,
+    cagrs: BTreeMap<HistoricalPeriod, f64>,
+    /// Annualized return on contributions, for holdings with cash flows
+    xirr: Option<f64>,
+    error: Option<String>,
+}
+
+struct PortfolioReturnResult {
+    name: String,
+    investment_returns: Vec<ReturnResult>,
+    portfolio_cagrs: BTreeMap<HistoricalPeriod, f64>,
+    /// XIRR of the portfolio, when every holding has cash flows
+    portfolio_xirr: Option<f64>,
+}
+
+/// Periods shown when none are requested.
+pub(super) const DEFAULT_CAGR_PERIODS: [HistoricalPeriod; 4] = [
+    HistoricalPeriod::OneYear,
+    HistoricalPeriod::ThreeYears,
+    HistoricalPeriod::FiveYears,
+    HistoricalPeriod::TenYears,
+];
+
+/// Shows the returns of each portfolio over `periods`, compared against
+/// benchmarks.
+pub(super) async fn show(
+    sources: &Sources<'_>,
+    periods: &[HistoricalPeriod],
+    options: &ReturnsOptions<'_>,
+) -> Result<()> {
+    let mut benchmarks = benchmark::resolve_benchmarks(
+        sources.portfolios,
+        sources.metadata_provider,
+        options.benchmark_config,
+    )
+    .await;
+    let fetched_results = sources.fetch_prices(Some(&benchmarks)).await;
+    benchmarks.drop_unavailable(&fetched_results);
+
+    let num_portfolios = sources.portfolios.len();
+    for (i, portfolio) in sources.portfolios.iter().enumerate() {
+        let result = calculate_portfolio_returns(
+            portfolio,
+            &fetched_results,
+            sources.currency_provider,
+            sources.target_currency,
+            periods,
+        )
+        .await;
+
+        if !result.investment_returns.is_empty() {
+            println!(
+                "\nPortfolio: {}",
+                ui::style_text(&result.name, ui::StyleType::Title)
+            );
+            display_return_results(&result, periods);
+            let comparisons = benchmark::calculate_benchmark_comparisons(
+                portfolio,
+                &fetched_results,
+                sources.currency_provider,
+                sources.target_currency,
+                &benchmarks.by_holding,
+                options.risk_free_rate,
+                None,
+            )
+            .await;
+            benchmark::display_benchmark_results(&comparisons, None);
+
+            if i < num_portfolios - 1 {
+                ui::print_separator();
+            }
+        }
+    }
+
+    ui::report_provider_errors(benchmarks.failed_lookups(&fetched_results))
+}
+
+async fn calculate_portfolio_returns(
+    portfolio: &Portfolio,
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    target_currency: &str,
+    periods: &[HistoricalPeriod],
+) -> PortfolioReturnResult {
+    let holdings = analytics::calculate_portfolio_value(
+        portfolio,
+        price_results,
+        currency_provider,
+        target_currency,
+        &|| (), // No progress updates needed here
+    )
+    .await;
+
+    let mut investment_returns = Vec::new();
+    let mut portfolio_cagrs: BTreeMap<HistoricalPeriod, f64> = BTreeMap::new();
+    let mut period_contributors: BTreeMap<HistoricalPeriod, f64> = BTreeMap::new();
+    let today = Local::now().date_naive();
+    let mut portfolio_cashflows = Vec::new();
+    let mut all_have_cashflows = true;
+
+    for (investment, holding) in portfolio.investments.iter().zip(&holdings.investments) {
+        let cashflows = match investment {
+            Investment::SmallSavings(s) => Some(s.cashflows(today)),
+            _ => holding
+                .value
+                .and_then(|value| investment.transaction_cashflows(value, today)),
+        };
+        // Only fixed deposits with interest terms have a value history
+        if holding.units.is_none()
+            && cashflows.is_none()
+            && !price_results.contains_key(&holding.identifier)
+        {
+            continue;
+        }
+
+        if let Some(e) = &holding.error {
+            all_have_cashflows = false;
+            investment_returns.push(ReturnResult {
+                identifier: holding.identifier.clone(),
+                short_name: holding.short_name.clone(),
+                cagrs: BTreeMap::new(),
+                xirr: None,
+                error: Some(e.to_string()),
+            });
+            continue;
+        }
+
+        // Convert cash flows at the holding's current exchange rate
+        let conversion = holding
+            .converted_value
+            .zip(holding.value)
+            .filter(|(_, value)| *value > 0.0)
+            .map(|(converted, value)| converted / value);
+        match (&cashflows, conversion) {
+            (Some(flows), Some(rate)) => portfolio_cashflows
+                .extend(flows.iter().map(|(date, amount)| (*date, amount * rate))),
+            _ => all_have_cashflows = false,
+        }
+
+        let mut result = ReturnResult {
+            identifier: holding.identifier.clone(),
+            short_name: holding.short_name.clone(),
+            cagrs: BTreeMap::new(),
+            xirr: cashflows.as_deref().and_then(analytics::xirr),
+            error: None,
+        };
+
+        if let Some(Ok(price_data)) = price_results.get(&holding.identifier) {
+            match calculate_cagr(price_data, periods) {
+                Ok(cagrs) => {
+                    if let Some(weight) = holding.weight {
+                        for (period, cagr_val) in &cagrs {
+                            let weighted_value = cagr_val * (weight / 100.0);
+                            *portfolio_cagrs.entry(*period).or_insert(0.0) += weighted_value;
+                            *period_contributors.entry(*period).or_insert(0.0) += weight / 100.0;
+                        }
+                    }
+                    result.cagrs = cagrs;
+                }
+                Err(e) => {
+                    result.error = Some(format!("CAGR calculation failed: {e}"));
+                }
+            }
+        } else if cashflows.is_none() {
+            result.error = Some("Price data not available".to_string());
+        }
+
+        investment_returns.push(result);
+    }
+
+    for (period, total_weight) in &period_contributors {
+        if let Some(weighted_cagr) = portfolio_cagrs.get_mut(period)
+            && *total_weight > 0.0
+        {
+            *weighted_cagr /= *total_weight;
+        }
+    }
+
+    PortfolioReturnResult {
+        name: portfolio.name.clone(),
+        investment_returns,
+        portfolio_cagrs,
+        portfolio_xirr: all_have_cashflows
+            .then(|| analytics::xirr(&portfolio_cashflows))
+            .flatten(),
+    }
+}
+
+/// Calculates the return over each period, annualized for periods of a year or more.
+fn calculate_cagr(
+    price_data: &PriceResult,
+    periods: &[HistoricalPeriod],
+) -> Result<BTreeMap<HistoricalPeriod, f64>> {
+    let mut cagrs = BTreeMap::new();
+
+    for &period in periods {
+        let Some(prices) = price_data.period_prices(period) else {
+            continue;
+        };
+        if prices.end <= 0.0 || prices.days <= 0 {
+            continue;
+        }
+
+        let duration_years = prices.days as f64 / analytics::DAYS_PER_YEAR;
+        if duration_years < 1.0 {
+            cagrs.insert(period, prices.change());
+            continue;
+        }
+
+        debug!("historical price: {:?}, {duration_years}yrs", prices.start);
+        let begin_bal =
+            Decimal::from_f64(prices.start).ok_or_else(|| anyhow!("Invalid historical price"))?;
+        let end_bal =
+            Decimal::from_f64(prices.end).ok_or_else(|| anyhow!("Invalid current price"))?;
+        let n_years =
+            Decimal::from_f64(duration_years).ok_or_else(|| anyhow!("Invalid duration"))?;
+
+        let rate = cagr(begin_bal, end_bal, n_years);
+        let percentage = (rate * Decimal::from(100))
+            .to_f64()
+            .ok_or_else(|| anyhow!("CAGR percentage conversion failed"))?;
+        cagrs.insert(period, percentage);
+
+        debug!("cagr: {begin_bal}, {end_bal}, {n_years} = {rate}, {percentage}");
+    }
+
+    if cagrs.is_empty() {
+        Err(anyhow!(
+            "No historical prices available for CAGR calculation"
+        ))
+    } else {
+        Ok(cagrs)
+    }
+}
+
+fn display_return_results(result: &PortfolioReturnResult, periods: &[HistoricalPeriod]) {
+    let mut table = ui::new_styled_table();
+
+    let mut header = vec![ui::header_cell("Investment")];
+    for period in periods {
+        header.push(ui::header_cell(&period.to_string()));
+    }
+    let show_xirr = result.portfolio_xirr.is_some()
+        || result.investment_returns.iter().any(|r| r.xirr.is_some());
+    if show_xirr {
+        header.push(ui::header_cell("XIRR"));
+    }
+    table.set_header(header);
+
+    for result in &result.investment_returns {
+        let name_display = if let Some(name) = &result.short_name {
+            name.clone()
+        } else {
+            result.identifier.clone()
+        };
+        let mut row_cells = vec![Cell::new(name_display)];
+
+        for period in periods {
+            let cell = match result.cagrs.get(period) {
+                Some(cagr) => ui::change_cell(*cagr),
+                None => ui::na_cell(result.error.is_some()),
+            };
+            row_cells.push(cell);
+        }
+        if show_xirr {
+            row_cells.push(match result.xirr {
+                Some(xirr) => ui::change_cell(xirr),
+                None => ui::na_cell(result.error.is_some()),
+            });
+        }
+        table.add_row(row_cells);
+    }
+
+    let has_portfolio_returns =
+        !result.portfolio_cagrs.is_empty() || result.portfolio_xirr.is_some();
+    if has_portfolio_returns && result.investment_returns.len() > 1 {
+        let mut total_row_cells =
+            vec![Cell::new("Portfolio Weighted").add_attribute(Attribute::Bold)];
+        for period in periods {
+            let cell = match result.portfolio_cagrs.get(period) {
+                Some(cagr) => ui::change_cell(*cagr),
+                None => ui::na_cell(false),
+            };
+            total_row_cells.push(cell);
+        }
+        if show_xirr {
+            total_row_cells.push(match result.portfolio_xirr {
+                Some(xirr) => ui::change_cell(xirr),
+                None => ui::na_cell(false),
+            });
+        }
+        table.add_row(total_row_cells);
+    }
+
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::returns::testing::MockCurrencyProvider;
+    use crate::core::config::{SmallSavingsInvestment, StockInvestment};
+    use crate::core::deposit::Compounding;
+    use crate::core::savings::{RateChange, RecurringContribution, SavingsPlan, Scheme};
+
+    fn create_test_data() -> PriceResult {
+        PriceResult {
+            price: 100.0,
+            currency: "USD".to_string(),
+            historical_prices: HashMap::from([
+                (HistoricalPeriod::OneYear, 80.0),
+                (HistoricalPeriod::ThreeYears, 50.0),
+            ]),
+            daily_prices: Vec::new(),
+            short_name: Some("TEST".to_string()),
+        }
+    }
+
+    #[test]
+    fn calculates_cagr_for_all_periods() {
+        let data = create_test_data();
+        let cagrs = calculate_cagr(&data, &DEFAULT_CAGR_PERIODS).unwrap();
+
+        assert_eq!(cagrs.len(), 2);
+        assert!((cagrs[&HistoricalPeriod::OneYear] - 25.0).abs() < 0.1);
+        assert!((cagrs[&HistoricalPeriod::ThreeYears] - 25.99).abs() < 0.1);
+    }
+
+    #[test]
+    fn calculates_custom_periods_from_daily_prices() {
+        let date = |y, m, d| chrono::NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let data = PriceResult {
+            price: 121.0,
+            currency: "USD".to_string(),
+            historical_prices: HashMap::new(),
+            daily_prices: vec![
+                (date(2022, 6, 30), 100.0),
+                (date(2023, 12, 28), 110.0),
+                (date(2024, 6, 28), 121.0),
+            ],
+            short_name: None,
+        };
+        let periods = [
+            HistoricalPeriod::Months(6),
+            HistoricalPeriod::Years(2),
+            HistoricalPeriod::Inception,
+        ];
+        let cagrs = calculate_cagr(&data, &periods).unwrap();
+
+        // Periods under a year are not annualized
+        assert!((cagrs[&HistoricalPeriod::Months(6)] - 10.0).abs() < 1e-9);
+        // Two years back is before the first price
+        assert!(!cagrs.contains_key(&HistoricalPeriod::Years(2)));
+        let inception = cagrs[&HistoricalPeriod::Inception];
+        assert!((inception - ((1.21f64).powf(365.25 / 729.0) - 1.0) * 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn handles_missing_historical_data() {
+        let data = PriceResult {
+            price: 100.0,
+            currency: "USD".to_string(),
+            historical_prices: HashMap::new(),
+            daily_prices: Vec::new(),
+            short_name: None,
+        };
+
+        assert!(calculate_cagr(&data, &DEFAULT_CAGR_PERIODS).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_calculate_portfolio_returns_weighted() {
+        let portfolio = Portfolio {
+            name: "Tech".to_string(),
+            investments: vec![
+                Investment::Stock(StockInvestment {
+                    symbol: "AAPL".to_string(),
+                    units: 10.0, // value 1000
+                    category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
+                }),
+                Investment::Stock(StockInvestment {
+                    symbol: "GOOG".to_string(),
+                    units: 20.0, // value 1000
+                    category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
+                }),
+            ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
+        };
+
+        let mut price_results = HashMap::new();
+        price_results.insert(
+            "AAPL".to_string(),
+            Ok(PriceResult {
+                price: 100.0,
+                currency: "USD".to_string(),
+                short_name: Some("Apple".to_string()),
+                historical_prices: HashMap::from([(HistoricalPeriod::OneYear, 80.0)]), // +25%
+                daily_prices: Vec::new(),
+            }),
+        );
+        price_results.insert(
+            "GOOG".to_string(),
+            Ok(PriceResult {
+                price: 50.0,
+                currency: "USD".to_string(),
+                short_name: Some("Google".to_string()),
+                historical_prices: HashMap::from([(HistoricalPeriod::OneYear, 40.0)]), // +25%
+                daily_prices: Vec::new(),
+            }),
+        );
+
+        let currency_provider = MockCurrencyProvider;
+        let result = calculate_portfolio_returns(
+            &portfolio,
+            &price_results,
+            &currency_provider,
+            "USD",
+            &DEFAULT_CAGR_PERIODS,
+        )
+        .await;
+
+        // Each stock has 50% weight. (10*100 = 1000, 20*50 = 1000)
+        // Both have 25% CAGR. Weighted average should be 25%.
+        assert!((result.portfolio_cagrs[&HistoricalPeriod::OneYear] - 25.0).abs() < 0.1);
+    }
+
+    #[tokio::test]
+    async fn test_calculate_portfolio_returns_with_missing_period() {
+        let portfolio = Portfolio {
+            name: "Tech".to_string(),
+            investments: vec![
+                Investment::Stock(StockInvestment {
+                    symbol: "AAPL".to_string(),
+                    units: 10.0, // value 1000 (50% weight)
+                    category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
+                }),
+                Investment::Stock(StockInvestment {
+                    symbol: "GOOG".to_string(),
+                    units: 20.0, // value 1000 (50% weight)
+                    category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
+                }),
+            ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
+        };
+        let mut price_results = HashMap::new();
+        price_results.insert(
+            "AAPL".to_string(),
+            Ok(PriceResult {
+                price: 100.0,
+                currency: "USD".to_string(),
+                short_name: Some("Apple".to_string()),
+                historical_prices: HashMap::from([(HistoricalPeriod::OneYear, 80.0)]), // +25%
+                daily_prices: Vec::new(),
+            }),
+        );
+        price_results.insert(
+            "GOOG".to_string(),
+            Ok(PriceResult {
+                price: 50.0,
+                currency: "USD".to_string(),
+                short_name: Some("Google".to_string()),
+                historical_prices: HashMap::new(),
+                daily_prices: Vec::new(),
+            }),
+        );
+        let currency_provider = MockCurrencyProvider;
+        let result = calculate_portfolio_returns(
+            &portfolio,
+            &price_results,
+            &currency_provider,
+            "USD",
+            &DEFAULT_CAGR_PERIODS,
+        )
+        .await;
+        assert!((result.portfolio_cagrs[&HistoricalPeriod::OneYear] - 25.0).abs() < 0.1);
+    }
+
+    #[tokio::test]
+    async fn test_calculate_portfolio_returns_xirr() {
+        let today = Local::now().date_naive();
+        let start = today - chrono::Months::new(24);
+        let savings = |name: &str| {
+            Investment::SmallSavings(SmallSavingsInvestment {
+                name: name.to_string(),
+                currency: None,
+                category: None,
+                tags: Vec::new(),
+                plan: SavingsPlan {
+                    scheme: Scheme::Rd,
+                    start_date: start,
+                    maturity_date: None,
+                    contributions: Vec::new(),
+                    recurring: Some(RecurringContribution {
+                        amount: 1000.0,
+                        frequency: Compounding::Monthly,
+                        until: None,
+                    }),
+                    rates: vec![RateChange {
+                        from: start,
+                        rate: 8.0,
+                    }],
+                },
+            })
+        };
+        let portfolio = Portfolio {
+            name: "Savings".to_string(),
+            investments: vec![savings("RD 1"), savings("RD 2")],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
+        };
+
+        let result = calculate_portfolio_returns(
+            &portfolio,
+            &HashMap::new(),
+            &MockCurrencyProvider,
+            "INR",
+            &DEFAULT_CAGR_PERIODS,
+        )
+        .await;
+
+        assert_eq!(result.investment_returns.len(), 2);
+        let holding = &result.investment_returns[0];
+        assert!(holding.error.is_none());
+        assert!(holding.cagrs.is_empty());
+        // Quarterly compounding at 8% yields a little over 8% a year
+        let xirr = holding.xirr.unwrap();
+        assert!(xirr > 7.5 && xirr < 8.5, "{xirr}");
+        assert!((result.portfolio_xirr.unwrap() - xirr).abs() < 0.01);
+    }
+}
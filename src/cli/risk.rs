@@ -0,0 +1,327 @@
+use super::ui;
+use crate::core::risk::{self, RiskMetrics};
+use crate::core::{
+    CurrencyRateProvider, HistoricalPeriod, PriceProvider, PriceResult, ProviderResult, analytics,
+    config::{Investment, Portfolio},
+};
+use anyhow::Result;
+use comfy_table::{Attribute, Cell};
+use futures::future::join_all;
+use std::collections::HashMap;
+
+struct RiskResult {
+    identifier: String,
+    short_name: Option<String>,
+    metrics: Option<RiskMetrics>,
+    error: Option<String>,
+}
+
+struct PortfolioRiskResult {
+    name: String,
+    investment_risks: Vec<RiskResult>,
+    portfolio_metrics: Option<RiskMetrics>,
+}
+
+pub async fn run(
+    portfolios: &[Portfolio],
+    symbol_provider: &(dyn PriceProvider + Send + Sync),
+    isin_provider: &(dyn PriceProvider + Send + Sync),
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    target_currency: &str,
+    period: Option<&str>,
+    risk_free_rate: f64,
+) -> Result<()> {
+    let period = period.map(str::parse::<HistoricalPeriod>).transpose()?;
+
+    let mut investments_to_fetch = HashMap::new();
+    for portfolio in portfolios {
+        for investment in &portfolio.investments {
+            match investment {
+                Investment::Stock(s) => {
+                    investments_to_fetch.insert(s.symbol.clone(), symbol_provider);
+                }
+                Investment::MutualFund(mf) => {
+                    investments_to_fetch.insert(mf.isin.clone(), isin_provider);
+                }
+                Investment::Bond(b) => {
+                    if let Some(symbol) = &b.symbol {
+                        investments_to_fetch.insert(symbol.clone(), symbol_provider);
+                    }
+                }
+                _ => {} // No price history
+            }
+        }
+    }
+    if investments_to_fetch.is_empty() {
+        println!("No investments found to calculate risk for.");
+        return Ok(());
+    }
+
+    let pb = ui::new_progress_bar(investments_to_fetch.len() as u64, true);
+    pb.set_message("Fetching prices...");
+
+    let futures = investments_to_fetch.into_iter().map(|(id, provider)| {
+        let pb_clone = pb.clone();
+        async move {
+            let result = provider.fetch_price(&id).await;
+            pb_clone.inc(1);
+            (id, result)
+        }
+    });
+
+    let fetched_results: HashMap<String, ProviderResult<PriceResult>> =
+        join_all(futures).await.into_iter().collect();
+    pb.finish_and_clear();
+
+    let num_portfolios = portfolios.len();
+    for (i, portfolio) in portfolios.iter().enumerate() {
+        let result = calculate_portfolio_risk(
+            portfolio,
+            &fetched_results,
+            currency_provider,
+            target_currency,
+            period,
+            risk_free_rate,
+        )
+        .await;
+
+        if !result.investment_risks.is_empty() {
+            println!(
+                "\nPortfolio: {}",
+                ui::style_text(&result.name, ui::StyleType::Title)
+            );
+            display_risk_results(&result, risk_free_rate);
+
+            if i < num_portfolios - 1 {
+                ui::print_separator();
+            }
+        }
+    }
+
+    ui::report_provider_errors(ui::failed_lookups(&fetched_results))
+}
+
+/// Sorts daily prices by date and keeps the requested period.
+fn price_series(
+    price_data: &PriceResult,
+    period: Option<HistoricalPeriod>,
+) -> Vec<(chrono::NaiveDate, f64)> {
+    let mut series = price_data.daily_prices.clone();
+    series.sort_by_key(|(date, _)| *date);
+    match period {
+        Some(period) => risk::trim_to_period(&series, period).to_vec(),
+        None => series,
+    }
+}
+
+async fn calculate_portfolio_risk(
+    portfolio: &Portfolio,
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    target_currency: &str,
+    period: Option<HistoricalPeriod>,
+    risk_free_rate: f64,
+) -> PortfolioRiskResult {
+    let holdings = analytics::calculate_portfolio_value(
+        portfolio,
+        price_results,
+        currency_provider,
+        target_currency,
+        &|| (), // No progress updates needed here
+    )
+    .await;
+
+    let mut investment_risks = Vec::new();
+    let mut positions = Vec::new();
+
+    for holding in &holdings.investments {
+        if holding.units.is_none() {
+            continue;
+        }
+
+        let mut result = RiskResult {
+            identifier: holding.identifier.clone(),
+            short_name: holding.short_name.clone(),
+            metrics: None,
+            error: holding.error.as_ref().map(|e| e.to_string()),
+        };
+
+        if result.error.is_none() {
+            if let Some(Ok(price_data)) = price_results.get(&holding.identifier) {
+                let series = price_series(price_data, period);
+                result.metrics = risk::calculate_risk(&series, risk_free_rate);
+                if result.metrics.is_none() {
+                    result.error = Some("Not enough data".to_string());
+                }
+                if let Some(value) = holding.converted_value {
+                    positions.push((series, value));
+                }
+            } else {
+                result.error = Some("Price data not available".to_string());
+            }
+        }
+
+        investment_risks.push(result);
+    }
+
+    let positions: Vec<_> = positions
+        .iter()
+        .map(|(series, value)| (series.as_slice(), *value))
+        .collect();
+    let portfolio_series = analytics::portfolio_value_series(&positions);
+
+    PortfolioRiskResult {
+        name: portfolio.name.clone(),
+        investment_risks,
+        portfolio_metrics: risk::calculate_risk(&portfolio_series, risk_free_rate),
+    }
+}
+
+fn metric_cells(metrics: &RiskMetrics) -> Vec<Cell> {
+    let drawdown = metrics.max_drawdown;
+    let date_cell = |date: Option<chrono::NaiveDate>| {
+        ui::format_optional_cell(date, |d| d.format("%Y-%m-%d").to_string())
+    };
+    vec![
+        ui::change_cell(metrics.annual_return),
+        ui::format_optional_cell(Some(metrics.volatility), |v| format!("{v:.2}%")),
+        drawdown.map_or(ui::na_cell(false), |d| ui::change_cell(d.depth)),
+        date_cell(drawdown.map(|d| d.peak)),
+        date_cell(drawdown.map(|d| d.trough)),
+        date_cell(drawdown.and_then(|d| d.recovery)),
+        ui::format_optional_cell(metrics.sharpe, |v| format!("{v:.2}")),
+        ui::format_optional_cell(metrics.sortino, |v| format!("{v:.2}")),
+    ]
+}
+
+fn display_risk_results(result: &PortfolioRiskResult, risk_free_rate: f64) {
+    println!("\nRisk-free rate: {risk_free_rate:.2}%");
+    let mut table = ui::new_styled_table();
+    table.set_header(vec![
+        ui::header_cell("Investment"),
+        ui::header_cell("Return"),
+        ui::header_cell("Volatility"),
+        ui::header_cell("Max Drawdown"),
+        ui::header_cell("Peak"),
+        ui::header_cell("Trough"),
+        ui::header_cell("Recovery"),
+        ui::header_cell("Sharpe"),
+        ui::header_cell("Sortino"),
+    ]);
+
+    for risk in &result.investment_risks {
+        let name_display = risk
+            .short_name
+            .clone()
+            .unwrap_or_else(|| risk.identifier.clone());
+        let mut row_cells = vec![Cell::new(name_display)];
+        match &risk.metrics {
+            Some(metrics) => row_cells.extend(metric_cells(metrics)),
+            None => {
+                for _ in 0..8 {
+                    row_cells.push(ui::na_cell(risk.error.is_some()));
+                }
+            }
+        }
+        table.add_row(row_cells);
+    }
+
+    if let Some(metrics) = &result.portfolio_metrics
+        && result.investment_risks.len() > 1
+    {
+        let mut total_row_cells = vec![Cell::new("Portfolio").add_attribute(Attribute::Bold)];
+        total_row_cells.extend(metric_cells(metrics));
+        table.add_row(total_row_cells);
+    }
+
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::StockInvestment;
+    use async_trait::async_trait;
+    use chrono::NaiveDate;
+
+    struct MockCurrencyProvider;
+
+    #[async_trait]
+    impl CurrencyRateProvider for MockCurrencyProvider {
+        async fn get_rate(&self, _from: &str, _to: &str) -> ProviderResult<f64> {
+            Ok(1.0)
+        }
+    }
+
+    fn price_result(values: &[f64]) -> PriceResult {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        PriceResult {
+            price: *values.last().unwrap(),
+            currency: "USD".to_string(),
+            historical_prices: HashMap::new(),
+            daily_prices: values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (start + chrono::Duration::days(i as i64), *v))
+                .collect(),
+            short_name: None,
+        }
+    }
+
+    fn stock(symbol: &str, units: f64) -> Investment {
+        Investment::Stock(StockInvestment {
+            symbol: symbol.to_string(),
+            units,
+            category: None,
+            tags: Vec::new(),
+            benchmark: None,
+            transactions: Vec::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_portfolio_risk_uses_aligned_series() {
+        let portfolio = Portfolio {
+            name: "Test".to_string(),
+            investments: vec![stock("AAA", 1.0), stock("BBB", 1.0)],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
+        };
+        let price_results = HashMap::from([
+            (
+                "AAA".to_string(),
+                Ok(price_result(&[100.0, 110.0, 99.0, 120.0])),
+            ),
+            (
+                "BBB".to_string(),
+                Ok(price_result(&[100.0, 90.0, 99.0, 80.0])),
+            ),
+        ]);
+
+        let result = calculate_portfolio_risk(
+            &portfolio,
+            &price_results,
+            &MockCurrencyProvider,
+            "USD",
+            None,
+            0.0,
+        )
+        .await;
+
+        assert_eq!(result.investment_risks.len(), 2);
+        assert!(result.investment_risks.iter().all(|r| r.metrics.is_some()));
+        // Equal holdings moving in opposite directions offset each other
+        let portfolio = result.portfolio_metrics.unwrap();
+        let single = result.investment_risks[0].metrics.unwrap();
+        assert!(portfolio.volatility < single.volatility);
+        // Values 200, 200, 198, 200
+        let drawdown = portfolio.max_drawdown.unwrap();
+        assert!((drawdown.depth + 1.0).abs() < 1e-9);
+        assert_eq!(
+            drawdown.trough,
+            NaiveDate::from_ymd_opt(2024, 1, 3).unwrap()
+        );
+        assert_eq!(drawdown.recovery, NaiveDate::from_ymd_opt(2024, 1, 4));
+    }
+}
@@ -1,7 +1,8 @@
 use super::ui;
 use crate::core::config::{Investment, Portfolio};
 use crate::core::{
-    CurrencyRateProvider, PriceProvider, PriceResult, analytics, analytics::PortfolioValue,
+    CurrencyRateProvider, PriceProvider, PriceResult, ProviderResult, analytics,
+    analytics::PortfolioValue,
 };
 use anyhow::Result;
 use comfy_table::Cell;
@@ -81,43 +82,17 @@ impl PortfolioValue {
     }
 }
 
+/// Shows the holdings of each portfolio, or of `merged` followed by the value
+/// of each portfolio in it when consolidated.
 pub async fn run(
     portfolios: &[Portfolio],
     symbol_provider: &(dyn PriceProvider + Send + Sync),
     isin_provider: &(dyn PriceProvider + Send + Sync),
     currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
     target_currency: &str,
+    merged: Option<&Portfolio>,
 ) -> Result<()> {
-    let mut investments_to_fetch = HashMap::new();
-    for portfolio in portfolios {
-        for investment in &portfolio.investments {
-            match investment {
-                Investment::Stock(s) => {
-                    investments_to_fetch.insert(s.symbol.clone(), symbol_provider);
-                }
-                Investment::MutualFund(mf) => {
-                    investments_to_fetch.insert(mf.isin.clone(), isin_provider);
-                }
-                Investment::FixedDeposit(_) => {}
-            }
-        }
-    }
-
-    let pb = ui::new_progress_bar(investments_to_fetch.len() as u64, true);
-    pb.set_message("Fetching prices...");
-
-    let price_futures = investments_to_fetch.iter().map(|(id, provider)| {
-        let pb_clone = pb.clone();
-        async move {
-            let res = provider.fetch_price(id).await;
-            pb_clone.inc(1);
-            (id.clone(), res)
-        }
-    });
-
-    let price_results: HashMap<String, Result<PriceResult>> =
-        join_all(price_futures).await.into_iter().collect();
-    pb.finish_and_clear();
+    let price_results = fetch_prices(portfolios, symbol_provider, isin_provider).await;
 
     // Step 1: Process portfolios to calculate holdings
     let total_investments: u64 = portfolios
@@ -143,6 +118,23 @@ pub async fn run(
     });
 
     let summaries = join_all(holdings_futures).await;
+
+    if let Some(merged) = merged {
+        let merged_value = analytics::calculate_portfolio_value(
+            merged,
+            &price_results,
+            currency_provider,
+            target_currency,
+            &|| {},
+        )
+        .await;
+        pb.finish_and_clear();
+
+        println!("{}", merged_value.display_as_table());
+        ui::print_separator();
+        display_breakdown(&summaries, target_currency);
+        return ui::report_provider_errors(merged_value.errors());
+    }
     pb.finish_and_clear();
 
     // Step 2: Calculate grand total and display summaries
@@ -158,7 +150,7 @@ pub async fn run(
     }
 
     let num_summaries = summaries.len();
-    for (i, sum) in summaries.into_iter().enumerate() {
+    for (i, sum) in summaries.iter().enumerate() {
         println!("{}", sum.display_as_table());
         if i < num_summaries - 1 {
             ui::print_separator();
@@ -176,5 +168,106 @@ pub async fn run(
         println!("{styled_total:>term_width$}");
     }
 
-    Ok(())
+    ui::report_provider_errors(summaries.iter().flat_map(PortfolioValue::errors))
+}
+
+/// Fetches the prices of the holdings of all portfolios behind a progress bar.
+async fn fetch_prices(
+    portfolios: &[Portfolio],
+    symbol_provider: &(dyn PriceProvider + Send + Sync),
+    isin_provider: &(dyn PriceProvider + Send + Sync),
+) -> HashMap<String, ProviderResult<PriceResult>> {
+    let mut investments_to_fetch = HashMap::new();
+    for portfolio in portfolios {
+        for investment in &portfolio.investments {
+            match investment {
+                Investment::Stock(s) => {
+                    investments_to_fetch.insert(s.symbol.clone(), symbol_provider);
+                }
+                Investment::MutualFund(mf) => {
+                    investments_to_fetch.insert(mf.isin.clone(), isin_provider);
+                }
+                Investment::Bond(b) => {
+                    if let Some(symbol) = &b.symbol {
+                        investments_to_fetch.insert(symbol.clone(), symbol_provider);
+                    }
+                }
+                Investment::FixedDeposit(_) | Investment::SmallSavings(_) => {}
+            }
+        }
+    }
+
+    let pb = ui::new_progress_bar(investments_to_fetch.len() as u64, true);
+    pb.set_message("Fetching prices...");
+
+    let price_futures = investments_to_fetch.iter().map(|(id, provider)| {
+        let pb_clone = pb.clone();
+        async move {
+            let res = provider.fetch_price(id).await;
+            pb_clone.inc(1);
+            (id.clone(), res)
+        }
+    });
+
+    let price_results = join_all(price_futures).await.into_iter().collect();
+    pb.finish_and_clear();
+    price_results
+}
+
+/// Prints the value of each portfolio and its share of the combined total,
+/// after a consolidated view. Failed lookups are left to the view to report.
+pub async fn print_breakdown(
+    portfolios: &[Portfolio],
+    symbol_provider: &(dyn PriceProvider + Send + Sync),
+    isin_provider: &(dyn PriceProvider + Send + Sync),
+    currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
+    target_currency: &str,
+) {
+    let price_results = fetch_prices(portfolios, symbol_provider, isin_provider).await;
+    let mut summaries = Vec::new();
+    for portfolio in portfolios {
+        summaries.push(
+            analytics::calculate_portfolio_value(
+                portfolio,
+                &price_results,
+                currency_provider,
+                target_currency,
+                &|| {},
+            )
+            .await,
+        );
+    }
+    display_breakdown(&summaries, target_currency);
+}
+
+/// Prints the value of each portfolio and its share of the combined total.
+fn display_breakdown(summaries: &[PortfolioValue], target_currency: &str) {
+    let total: f64 = summaries
+        .iter()
+        .filter_map(|s| s.total_converted_value)
+        .sum();
+
+    let mut table = ui::new_styled_table();
+    table.set_header(vec![
+        ui::header_cell("Portfolio"),
+        ui::header_cell(&format!("Value ({target_currency})")),
+        ui::header_cell("Weight"),
+    ]);
+    for summary in summaries {
+        let weight = summary
+            .total_converted_value
+            .filter(|_| total > 0.0)
+            .map(|v| v / total * 100.0);
+        table.add_row(vec![
+            Cell::new(&summary.name),
+            ui::format_optional_cell(summary.total_converted_value, |v| format!("{v:.2}")),
+            ui::format_optional_cell(weight, |w| format!("{w:.2}%")),
+        ]);
+    }
+
+    println!(
+        "\n{}\n",
+        ui::style_text("Portfolio Breakdown", ui::StyleType::Title)
+    );
+    println!("{table}");
 }
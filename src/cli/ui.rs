@@ -1,8 +1,13 @@
+use crate::core::migrate;
+use crate::core::price::{ProviderError, ProviderResult};
+use anyhow::{Error, Result};
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::{Attribute, Cell, CellAlignment, Color, ContentArrangement, Table};
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 
 /// Defines different styles for text elements.
 pub enum StyleType {
@@ -25,6 +30,21 @@ pub fn style_text(text: &str, style_type: StyleType) -> String {
     styled.to_string()
 }
 
+/// Prints the changes to a file as a colored unified diff.
+pub fn print_diff(file: &Path, old: &str, new: &str) {
+    println!("--- {}", file.display());
+    println!("+++ {}", file.display());
+    for line in migrate::diff(old, new) {
+        let line = match line.chars().next() {
+            Some('-') => style_text(&line, StyleType::Error),
+            Some('+') => style_text(&line, StyleType::TotalValue),
+            Some('@') => style_text(&line, StyleType::Subtle),
+            _ => line,
+        };
+        println!("{line}");
+    }
+}
+
 /// Creates a new `comfy_table::Table` with standard styling.
 pub fn new_styled_table() -> Table {
     let mut table = Table::new();
@@ -110,3 +130,101 @@ pub fn print_separator() {
         .unwrap_or(80);
     println!("\n{}", "─".repeat(term_width));
 }
+
+/// Prints a warning on stderr.
+pub fn print_warning(message: &str) {
+    eprintln!(
+        "{} {message}",
+        style_text("warning:", StyleType::TotalLabel)
+    );
+}
+
+/// Returns an actionable hint for the class of a provider error.
+pub fn provider_error_hint(error: &ProviderError) -> &'static str {
+    match error {
+        ProviderError::NotFound(_) => {
+            "Check the symbol or ISIN in your config. Search for it at https://finance.yahoo.com/lookup or https://www.amfiindia.com."
+        }
+        ProviderError::RateLimited(_) => {
+            "The provider is throttling requests. Wait a few minutes or lower `providers.max_concurrency`."
+        }
+        ProviderError::Network(_) => {
+            "Check your connection and `network` settings, or rerun with --offline to use cached data."
+        }
+        ProviderError::InvalidResponse(_) => {
+            "The provider returned unexpected data. Rerun with --refresh, or check the provider `base_url`."
+        }
+        ProviderError::Other(_) => "Rerun with --verbose for details.",
+    }
+}
+
+/// Returns the identifiers whose lookup failed along with the error.
+pub fn failed_lookups<T>(
+    results: &HashMap<String, ProviderResult<T>>,
+) -> impl Iterator<Item = (&str, &ProviderError)> {
+    results
+        .iter()
+        .filter_map(|(id, res)| res.as_ref().err().map(|e| (id.as_str(), e)))
+}
+
+/// Ranks error classes so the most actionable one decides the exit code.
+fn severity(error: &ProviderError) -> u8 {
+    match error {
+        ProviderError::Network(_) => 4,
+        ProviderError::RateLimited(_) => 3,
+        ProviderError::NotFound(_) => 2,
+        ProviderError::InvalidResponse(_) => 1,
+        ProviderError::Other(_) => 0,
+    }
+}
+
+/// Prints failed lookups with a hint per error class to stderr.
+///
+/// Returns the most severe error, if any, so the exit code reflects its class.
+pub fn report_provider_errors<'a>(
+    errors: impl IntoIterator<Item = (&'a str, &'a ProviderError)>,
+) -> Result<()> {
+    let errors: BTreeMap<&str, &ProviderError> = errors.into_iter().collect();
+    let Some(worst) = errors.values().copied().max_by_key(|e| severity(e)) else {
+        return Ok(());
+    };
+
+    eprintln!();
+    for (identifier, error) in &errors {
+        eprintln!(
+            "{} {identifier}: {error}",
+            style_text("✗", StyleType::Error)
+        );
+    }
+    let mut hints: Vec<&ProviderError> = errors.values().copied().collect();
+    hints.sort_by_key(|e| std::cmp::Reverse(severity(e)));
+    hints.dedup_by_key(|e| severity(e));
+    for error in hints {
+        eprintln!(
+            "{}",
+            style_text(
+                &format!("hint: {}", provider_error_hint(error)),
+                StyleType::Subtle
+            )
+        );
+    }
+
+    Err(Error::new(worst.clone())
+        .context(format!("{} of the requested lookups failed", errors.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_provider_errors_returns_most_severe() {
+        assert!(report_provider_errors([]).is_ok());
+
+        let not_found = ProviderError::NotFound("Unknown symbol: AAPLX".to_string());
+        let network = ProviderError::Network("Connection refused".to_string());
+        let err = report_provider_errors([("AAPLX", &not_found), ("MSFT", &network)]).unwrap_err();
+        assert_eq!(err.downcast_ref::<ProviderError>(), Some(&network));
+        assert_eq!(err.to_string(), "2 of the requested lookups failed");
+    }
+}
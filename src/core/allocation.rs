@@ -1,19 +1,33 @@
+use anyhow::{Result, bail};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Normalized asset class that raw categories are grouped under.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
 pub enum AssetCategory {
     Equity,
     Debt,
     Hybrid,
+    Gold,
+    Cash,
+    RealEstate,
     Other,
 }
 
 impl From<&str> for AssetCategory {
     fn from(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
-            "equity" => AssetCategory::Equity,
-            "debt" | "income" | "fixed income" => AssetCategory::Debt,
-            "hybrid" | "balanced" | "dynamic" => AssetCategory::Hybrid,
+        match s.trim().to_lowercase().as_str() {
+            "equity" | "stock" | "stocks" | "elss" => AssetCategory::Equity,
+            "debt" | "income" | "fixed income" | "bond" | "bonds" | "fixed deposit" => {
+                AssetCategory::Debt
+            }
+            "hybrid" | "balanced" | "dynamic" | "multi asset" => AssetCategory::Hybrid,
+            "gold" | "commodity" | "commodities" | "precious metals" => AssetCategory::Gold,
+            "cash" | "savings" | "liquid" | "money market" => AssetCategory::Cash,
+            "real estate" | "realestate" | "reit" | "reits" => AssetCategory::RealEstate,
             _ => AssetCategory::Other,
         }
     }
@@ -26,7 +40,490 @@ impl AssetCategory {
             AssetCategory::Equity => ("Equity", "📈"),
             AssetCategory::Debt => ("Debt", "📉"),
             AssetCategory::Hybrid => ("Hybrid", "📊"),
+            AssetCategory::Gold => ("Gold", "🪙"),
+            AssetCategory::Cash => ("Cash", "💵"),
+            AssetCategory::RealEstate => ("Real Estate", "🏠"),
             AssetCategory::Other => ("Other", "❓"),
         }
     }
 }
+
+/// Maps raw categories containing `pattern`, ignoring case, to an asset class.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AssetClassRule {
+    pub pattern: String,
+    pub asset_class: AssetCategory,
+}
+
+/// How raw categories are grouped into asset classes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AssetClassConfig {
+    /// Rules tried in order before the built-in mapping
+    #[serde(default)]
+    pub rules: Vec<AssetClassRule>,
+    /// Equity share in percent of hybrid holdings, the rest is counted as debt
+    pub hybrid_equity_ratio: Option<f64>,
+}
+
+impl AssetClassConfig {
+    /// Checks that the hybrid equity ratio is within 0-100%.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(ratio) = self.hybrid_equity_ratio
+            && !(0.0..=100.0).contains(&ratio)
+        {
+            bail!("hybrid_equity_ratio must be between 0 and 100, got {ratio}");
+        }
+        Ok(())
+    }
+
+    /// Resolves the asset class of a raw category, trying the rules first.
+    pub fn classify(&self, category: &str) -> AssetCategory {
+        let lower = category.to_lowercase();
+        self.rules
+            .iter()
+            .find(|rule| lower.contains(&rule.pattern.to_lowercase()))
+            .map(|rule| rule.asset_class)
+            .unwrap_or_else(|| AssetCategory::from(category))
+    }
+
+    /// Splits an asset class into the classes its value is counted under,
+    /// with the fraction of the value for each.
+    pub fn split(&self, class: AssetCategory) -> Vec<(AssetCategory, f64)> {
+        match (class, self.hybrid_equity_ratio) {
+            (AssetCategory::Hybrid, Some(ratio)) => vec![
+                (AssetCategory::Equity, ratio / 100.0),
+                (AssetCategory::Debt, 1.0 - ratio / 100.0),
+            ],
+            _ => vec![(class, 1.0)],
+        }
+    }
+}
+
+/// Target weights in percent keyed by category, e.g. `{"Equity": 70, "Debt": 30}`.
+pub type TargetAllocation = HashMap<String, f64>;
+
+/// Tolerance for target weights not adding up to exactly 100%.
+const TARGET_SUM_EPSILON: f64 = 0.01;
+
+/// Checks that target weights are within 0-100% and add up to 100%.
+pub fn validate_targets(targets: &TargetAllocation) -> Result<()> {
+    if let Some((category, weight)) = targets.iter().find(|(_, w)| !(0.0..=100.0).contains(*w)) {
+        bail!("Target allocation for '{category}' must be between 0 and 100, got {weight}");
+    }
+    let total: f64 = targets.values().sum();
+    if (total - 100.0).abs() > TARGET_SUM_EPSILON {
+        bail!("Target allocation must add up to 100%, got {total:.2}%");
+    }
+    Ok(())
+}
+
+/// Looks up the target weight of a category, ignoring case.
+pub fn target_for(targets: &TargetAllocation, category: &str) -> Option<f64> {
+    targets
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(category))
+        .map(|(_, weight)| *weight)
+}
+
+/// Actual versus target weight of a category, in percent of the portfolio.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryDrift {
+    pub category: String,
+    pub actual: f64,
+    pub target: f64,
+}
+
+impl CategoryDrift {
+    /// Percentage points above (positive) or below (negative) target.
+    pub fn drift(&self) -> f64 {
+        self.actual - self.target
+    }
+}
+
+/// Compares category values against the target weights.
+///
+/// Categories without a target have a target of 0%, and targeted categories
+/// without holdings an actual weight of 0%. Results are ordered by category name.
+pub fn calculate_drift(
+    category_values: &HashMap<String, f64>,
+    targets: &TargetAllocation,
+) -> Vec<CategoryDrift> {
+    let total: f64 = category_values.values().sum();
+    let mut drifts: Vec<CategoryDrift> = category_values
+        .iter()
+        .map(|(category, value)| CategoryDrift {
+            category: category.clone(),
+            actual: if total > 0.0 {
+                value / total * 100.0
+            } else {
+                0.0
+            },
+            target: target_for(targets, category).unwrap_or(0.0),
+        })
+        .collect();
+    for (category, target) in targets {
+        if !drifts
+            .iter()
+            .any(|d| d.category.eq_ignore_ascii_case(category))
+        {
+            drifts.push(CategoryDrift {
+                category: category.clone(),
+                actual: 0.0,
+                target: *target,
+            });
+        }
+    }
+    drifts.sort_by(|a, b| a.category.cmp(&b.category));
+    drifts
+}
+
+/// Current value of a holding and the category it is allocated to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocationHolding {
+    pub category: String,
+    /// `Investment::identifier()` of the holding, unique within a portfolio
+    pub identifier: String,
+    /// Name shown in reports
+    pub name: String,
+    pub value: f64,
+    /// Whether the holding can be bought or sold on demand
+    pub tradable: bool,
+}
+
+/// Proposed purchase (positive amount) or sale (negative amount).
+///
+/// `identifier` is the holding's identifier, or `None` for categories with a
+/// target but no holding to buy into.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub category: String,
+    pub identifier: Option<String>,
+    pub amount: f64,
+}
+
+/// Proposes trades that bring a portfolio back to its target allocation.
+///
+/// Without `new_money`, nothing is traded while every category is within
+/// `tolerance` percentage points of its target; otherwise categories are
+/// brought back towards target by buying and selling. With `new_money`, only
+/// the contribution is invested, split across the categories furthest below
+/// target. Amounts are split across the tradable holdings of a category in
+/// proportion to value.
+///
+/// Holdings that are not tradable are never sold, so a category held in them
+/// stays above target, and buys are scaled down to what the sales raise.
+pub fn plan_rebalance(
+    holdings: &[AllocationHolding],
+    targets: &TargetAllocation,
+    tolerance: f64,
+    new_money: Option<f64>,
+) -> Vec<Trade> {
+    let mut category_values: HashMap<String, f64> = HashMap::new();
+    for holding in holdings {
+        *category_values.entry(holding.category.clone()).or_default() += holding.value;
+    }
+    let total: f64 = category_values.values().sum();
+    let drifts = calculate_drift(&category_values, targets);
+
+    let category_trades: Vec<(String, f64)> = match new_money {
+        Some(contribution) => {
+            let new_total = total + contribution;
+            let shortfalls: Vec<(String, f64)> = drifts
+                .iter()
+                .map(|d| {
+                    let value = category_values.get(&d.category).copied().unwrap_or(0.0);
+                    (
+                        d.category.clone(),
+                        (d.target / 100.0 * new_total - value).max(0.0),
+                    )
+                })
+                .collect();
+            let total_shortfall: f64 = shortfalls.iter().map(|(_, s)| s).sum();
+            if total_shortfall <= 0.0 {
+                return Vec::new();
+            }
+            shortfalls
+                .into_iter()
+                .map(|(category, shortfall)| (category, contribution * shortfall / total_shortfall))
+                .collect()
+        }
+        None => {
+            if drifts.iter().all(|d| d.drift().abs() <= tolerance) {
+                return Vec::new();
+            }
+            let tradable_value = |category: &str| -> f64 {
+                holdings
+                    .iter()
+                    .filter(|h| h.tradable && h.category == category)
+                    .map(|h| h.value)
+                    .sum()
+            };
+            let changes: Vec<(String, f64)> = drifts
+                .iter()
+                .map(|d| {
+                    let change = -d.drift() / 100.0 * total;
+                    let change = if change < 0.0 {
+                        change.max(-tradable_value(&d.category))
+                    } else {
+                        change
+                    };
+                    (d.category.clone(), change)
+                })
+                .collect();
+            let sales: f64 = changes.iter().map(|(_, c)| (-c).max(0.0)).sum();
+            let purchases: f64 = changes.iter().map(|(_, c)| c.max(0.0)).sum();
+            changes
+                .into_iter()
+                .map(|(category, change)| {
+                    if change > 0.0 {
+                        (category, change * sales / purchases)
+                    } else {
+                        (category, change)
+                    }
+                })
+                .collect()
+        }
+    };
+
+    let mut trades = Vec::new();
+    for (category, amount) in category_trades {
+        if amount.abs() < 0.005 {
+            continue;
+        }
+        let members: Vec<&AllocationHolding> = holdings
+            .iter()
+            .filter(|h| h.tradable && h.category == category)
+            .collect();
+        let category_value: f64 = members.iter().map(|h| h.value).sum();
+        if members.is_empty() || category_value <= 0.0 {
+            trades.push(Trade {
+                category,
+                identifier: None,
+                amount,
+            });
+            continue;
+        }
+        for holding in members {
+            trades.push(Trade {
+                category: category.clone(),
+                identifier: Some(holding.identifier.clone()),
+                amount: amount * holding.value / category_value,
+            });
+        }
+    }
+    trades
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holding(category: &str, name: &str, value: f64) -> AllocationHolding {
+        AllocationHolding {
+            category: category.to_string(),
+            identifier: name.to_string(),
+            name: name.to_string(),
+            value,
+            tradable: true,
+        }
+    }
+
+    fn targets(weights: &[(&str, f64)]) -> TargetAllocation {
+        weights.iter().map(|(c, w)| (c.to_string(), *w)).collect()
+    }
+
+    #[test]
+    fn test_classify_with_rules() {
+        let config = AssetClassConfig {
+            rules: vec![AssetClassRule {
+                pattern: "gold etf".to_string(),
+                asset_class: AssetCategory::Gold,
+            }],
+            hybrid_equity_ratio: None,
+        };
+        assert_eq!(config.classify("Gold ETF FoF"), AssetCategory::Gold);
+        assert_eq!(config.classify("Liquid"), AssetCategory::Cash);
+        assert_eq!(config.classify("REIT"), AssetCategory::RealEstate);
+        assert_eq!(config.classify("Solution Oriented"), AssetCategory::Other);
+    }
+
+    #[test]
+    fn test_split_hybrid() {
+        let config = AssetClassConfig::default();
+        assert_eq!(
+            config.split(AssetCategory::Hybrid),
+            vec![(AssetCategory::Hybrid, 1.0)]
+        );
+
+        let config = AssetClassConfig {
+            rules: Vec::new(),
+            hybrid_equity_ratio: Some(65.0),
+        };
+        let split = config.split(AssetCategory::Hybrid);
+        assert_eq!(split[0].0, AssetCategory::Equity);
+        assert!((split[0].1 - 0.65).abs() < 1e-9);
+        assert_eq!(split[1].0, AssetCategory::Debt);
+        assert!((split[1].1 - 0.35).abs() < 1e-9);
+        assert_eq!(
+            config.split(AssetCategory::Gold),
+            vec![(AssetCategory::Gold, 1.0)]
+        );
+        assert!(
+            AssetClassConfig {
+                rules: Vec::new(),
+                hybrid_equity_ratio: Some(120.0),
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_targets() {
+        assert!(validate_targets(&targets(&[("Equity", 70.0), ("Debt", 30.0)])).is_ok());
+        assert!(validate_targets(&targets(&[("Equity", 70.0), ("Debt", 20.0)])).is_err());
+        assert!(validate_targets(&targets(&[("Equity", 120.0), ("Debt", -20.0)])).is_err());
+    }
+
+    #[test]
+    fn test_calculate_drift() {
+        let values = HashMap::from([("Equity".to_string(), 800.0), ("Gold".to_string(), 200.0)]);
+        let drifts = calculate_drift(&values, &targets(&[("equity", 70.0), ("Debt", 30.0)]));
+
+        let drift = |category: &str| {
+            drifts
+                .iter()
+                .find(|d| d.category == category)
+                .unwrap()
+                .drift()
+        };
+        assert_eq!(drifts.len(), 3);
+        assert!((drift("Equity") - 10.0).abs() < 1e-9);
+        assert!((drift("Gold") - 20.0).abs() < 1e-9);
+        assert!((drift("Debt") + 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_plan_rebalance_within_tolerance() {
+        let holdings = [
+            holding("Equity", "AAPL", 720.0),
+            holding("Debt", "FD", 280.0),
+        ];
+        let trades = plan_rebalance(
+            &holdings,
+            &targets(&[("Equity", 70.0), ("Debt", 30.0)]),
+            5.0,
+            None,
+        );
+        assert!(trades.is_empty());
+    }
+
+    #[test]
+    fn test_plan_rebalance_outside_tolerance() {
+        let holdings = [
+            holding("Equity", "AAPL", 600.0),
+            holding("Equity", "MSFT", 200.0),
+            holding("Debt", "FD", 200.0),
+        ];
+        let trades = plan_rebalance(
+            &holdings,
+            &targets(&[("Equity", 60.0), ("Debt", 40.0)]),
+            5.0,
+            None,
+        );
+
+        let amount = |identifier: &str| {
+            trades
+                .iter()
+                .find(|t| t.identifier.as_deref() == Some(identifier))
+                .unwrap()
+                .amount
+        };
+        assert!((amount("AAPL") + 150.0).abs() < 1e-9);
+        assert!((amount("MSFT") + 50.0).abs() < 1e-9);
+        assert!((amount("FD") - 200.0).abs() < 1e-9);
+        assert!(trades.iter().map(|t| t.amount).sum::<f64>().abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_plan_rebalance_new_money_only() {
+        let holdings = [
+            holding("Equity", "AAPL", 800.0),
+            holding("Debt", "FD", 200.0),
+        ];
+        let trades = plan_rebalance(
+            &holdings,
+            &targets(&[("Equity", 50.0), ("Debt", 40.0), ("Gold", 10.0)]),
+            5.0,
+            Some(500.0),
+        );
+
+        // New total 1500: Debt is 400 short and Gold 150 short, Equity is over target
+        assert!(
+            trades
+                .iter()
+                .all(|t| t.amount > 0.0 && t.category != "Equity")
+        );
+        assert!((trades.iter().map(|t| t.amount).sum::<f64>() - 500.0).abs() < 1e-9);
+        let debt = trades.iter().find(|t| t.category == "Debt").unwrap();
+        assert!((debt.amount - 500.0 * 400.0 / 550.0).abs() < 1e-9);
+        let gold = trades.iter().find(|t| t.category == "Gold").unwrap();
+        assert_eq!(gold.identifier, None);
+        assert!((gold.amount - 500.0 * 150.0 / 550.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_plan_rebalance_never_sells_fixed_deposits() {
+        let holdings = [
+            holding("Equity", "AAPL", 200.0),
+            AllocationHolding {
+                tradable: false,
+                ..holding("Debt", "FD", 600.0)
+            },
+            holding("Debt", "Liquid Fund", 100.0),
+            holding("Gold", "GOLDBEES", 100.0),
+        ];
+        let trades = plan_rebalance(
+            &holdings,
+            &targets(&[("Equity", 60.0), ("Debt", 30.0), ("Gold", 10.0)]),
+            5.0,
+            None,
+        );
+
+        // Debt is 400 over target but only the liquid fund can be sold, and
+        // the proceeds all go to Equity, the only category below target
+        assert!(trades.iter().all(|t| t.identifier.as_deref() != Some("FD")));
+        let amount = |identifier: &str| {
+            trades
+                .iter()
+                .find(|t| t.identifier.as_deref() == Some(identifier))
+                .unwrap()
+                .amount
+        };
+        assert!((amount("Liquid Fund") + 100.0).abs() < 1e-9);
+        assert!((amount("AAPL") - 100.0).abs() < 1e-9);
+        assert_eq!(trades.len(), 2);
+
+        // New money is only invested in tradable holdings
+        let trades = plan_rebalance(
+            &[
+                holding("Equity", "AAPL", 500.0),
+                AllocationHolding {
+                    tradable: false,
+                    ..holding("Debt", "FD", 100.0)
+                },
+            ],
+            &targets(&[("Equity", 50.0), ("Debt", 50.0)]),
+            5.0,
+            Some(400.0),
+        );
+        assert_eq!(
+            trades,
+            vec![Trade {
+                category: "Debt".to_string(),
+                identifier: None,
+                amount: 400.0,
+            }]
+        );
+    }
+}
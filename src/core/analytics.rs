@@ -1,9 +1,13 @@
 //! Provides functions for performing financial calculations on portfolios.
 use crate::core::config::{Investment, Portfolio};
 use crate::core::currency::CurrencyRateProvider;
-use crate::core::price::{HistoricalPeriod, PriceResult};
-use anyhow::{Result, anyhow};
-use std::collections::HashMap;
+use crate::core::price::{HistoricalPeriod, PriceResult, ProviderError, ProviderResult};
+use anyhow::{Result, bail};
+use chrono::{Datelike, Local, NaiveDate};
+use rust_decimal::{Decimal, prelude::*};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use tracing::debug;
 
 /// Represents the calculated value and weight of a single investment holding.
@@ -17,7 +21,7 @@ pub struct InvestmentValue {
     pub value_currency: Option<String>,
     pub converted_value: Option<f64>,
     pub weight: Option<f64>,
-    pub error: Option<String>,
+    pub error: Option<ProviderError>,
 }
 
 /// Represents a summary of a portfolio's holdings, with all values
@@ -30,6 +34,15 @@ pub struct PortfolioValue {
     pub target_currency: String,
 }
 
+impl PortfolioValue {
+    /// Returns the investments that could not be valued along with the error.
+    pub fn errors(&self) -> impl Iterator<Item = (&str, &ProviderError)> {
+        self.investments
+            .iter()
+            .filter_map(|i| i.error.as_ref().map(|e| (i.identifier.as_str(), e)))
+    }
+}
+
 /// Calculates the market value and weight of each investment in a portfolio.
 ///
 /// This function normalizes all investment values into a single `target_currency`
@@ -37,7 +50,7 @@ pub struct PortfolioValue {
 /// calculation function. Progress updates can be reported via the `update_callback`.
 pub async fn calculate_portfolio_value(
     portfolio: &Portfolio,
-    price_results: &HashMap<String, Result<PriceResult>>,
+    price_results: &HashMap<String, ProviderResult<PriceResult>>,
     currency_provider: &(dyn CurrencyRateProvider + Send + Sync),
     target_currency: &str,
     update_callback: &dyn Fn(),
@@ -52,6 +65,7 @@ pub async fn calculate_portfolio_value(
     let mut all_valid = true;
 
     for investment in &portfolio.investments {
+        let mut quote_error = None;
         let (identifier, units, needs_fetch, value_currency, value) = match investment {
             Investment::FixedDeposit(fd) => (
                 fd.name.clone(),
@@ -60,8 +74,39 @@ pub async fn calculate_portfolio_value(
                 fd.currency
                     .clone()
                     .or_else(|| Some(target_currency.to_string())),
-                Some(fd.value),
+                Some(fd.value_on(Local::now().date_naive())),
+            ),
+            Investment::SmallSavings(s) => (
+                s.name.clone(),
+                None,
+                false,
+                s.currency
+                    .clone()
+                    .or_else(|| Some(target_currency.to_string())),
+                Some(s.plan.value_on(Local::now().date_naive())),
             ),
+            Investment::Bond(b) => {
+                let quote = b.symbol.as_ref().and_then(|s| price_results.get(s));
+                let (clean_price, currency) = match quote {
+                    Some(Ok(price_data)) => (price_data.price, Some(price_data.currency.clone())),
+                    Some(Err(e)) => {
+                        quote_error = Some(e.clone());
+                        (b.manual_price(), b.currency.clone())
+                    }
+                    // Bonds without a symbol are valued at their manual price
+                    None => (b.manual_price(), b.currency.clone()),
+                };
+                let dirty_price = b
+                    .terms()
+                    .dirty_price(clean_price, Local::now().date_naive());
+                (
+                    b.name.clone(),
+                    None,
+                    false,
+                    currency.or_else(|| Some(target_currency.to_string())),
+                    Some(b.units * dirty_price),
+                )
+            }
             Investment::Stock(s) => (s.symbol.clone(), Some(s.units), true, None, None),
             Investment::MutualFund(mf) => (mf.isin.clone(), Some(mf.units), true, None, None),
         };
@@ -78,6 +123,12 @@ pub async fn calculate_portfolio_value(
             error: None,
         };
 
+        if let Some(e) = quote_error {
+            all_valid = false;
+            debug!("Price fetch error for {}: {}", identifier, e);
+            holding.error = Some(e);
+        }
+
         if needs_fetch {
             match price_results.get(&identifier) {
                 Some(Ok(price_data)) => {
@@ -89,12 +140,14 @@ pub async fn calculate_portfolio_value(
                 }
                 Some(Err(e)) => {
                     all_valid = false;
-                    holding.error = Some(e.to_string());
+                    holding.error = Some(e.clone());
                     debug!("Price fetch error for {}: {}", identifier, e);
                 }
                 None => {
                     all_valid = false;
-                    holding.error = Some(format!("Price data not available for {identifier}"));
+                    holding.error = Some(ProviderError::Other(format!(
+                        "Price data not available for {identifier}"
+                    )));
                     debug!(
                         "Price data not found for {} in pre-fetched results map",
                         identifier
@@ -121,7 +174,7 @@ pub async fn calculate_portfolio_value(
                 }
                 Err(e) => {
                     all_valid = false;
-                    holding.error = Some(e.to_string());
+                    holding.error = Some(e);
                 }
             }
         }
@@ -150,7 +203,7 @@ async fn convert_currency(
     current_value: &f64,
     current_currency: &str,
     target_currency: &str,
-) -> Result<f64> {
+) -> ProviderResult<f64> {
     if current_currency == target_currency {
         debug!(
             "No currency conversion needed for {identifier} ({current_currency} -> {target_currency})",
@@ -174,54 +227,127 @@ async fn convert_currency(
         }
         Err(e) => {
             debug!("Currency conversion error for {}: {}", identifier, e);
-            Err(anyhow!(format!(
-                "Currency conversion failed from {current_currency} to {target_currency}: {e}",
+            Err(e.context(format!(
+                "Currency conversion failed from {current_currency} to {target_currency}"
             )))
         }
     }
 }
 
+/// Average calendar days per year, used to annualize returns.
+pub const DAYS_PER_YEAR: f64 = 365.25;
+
+/// Default boundaries in percent of the rolling return distribution buckets.
+pub const DEFAULT_ROLLING_BUCKETS: [f64; 4] = [0.0, 5.0, 10.0, 20.0];
+
+fn default_rolling_buckets() -> Vec<f64> {
+    DEFAULT_ROLLING_BUCKETS.to_vec()
+}
+
+/// Default first month of the fiscal year, April as in India.
+pub const DEFAULT_FISCAL_YEAR_START: u32 = 4;
+
+fn default_fiscal_year_start() -> u32 {
+    DEFAULT_FISCAL_YEAR_START
+}
+
+/// Settings for `xmf returns`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReturnsConfig {
+    /// Ascending boundaries in percent of the rolling return distribution buckets
+    #[serde(default = "default_rolling_buckets")]
+    pub rolling_buckets: Vec<f64>,
+    /// First month (1-12) of the fiscal year used by `--fiscal`
+    #[serde(default = "default_fiscal_year_start")]
+    pub fiscal_year_start: u32,
+}
+
+impl Default for ReturnsConfig {
+    fn default() -> Self {
+        ReturnsConfig {
+            rolling_buckets: default_rolling_buckets(),
+            fiscal_year_start: DEFAULT_FISCAL_YEAR_START,
+        }
+    }
+}
+
+impl ReturnsConfig {
+    /// Checks that there is at least one bucket boundary, that they ascend and
+    /// that the fiscal year starts on a valid month.
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=12).contains(&self.fiscal_year_start) {
+            bail!(
+                "fiscal_year_start must be a month from 1 to 12, got {}",
+                self.fiscal_year_start
+            );
+        }
+        if self.rolling_buckets.is_empty() {
+            bail!("rolling_buckets must have at least one boundary");
+        }
+        if self.rolling_buckets.windows(2).any(|w| w[0] >= w[1]) {
+            bail!(
+                "rolling_buckets must be in ascending order, got {:?}",
+                self.rolling_buckets
+            );
+        }
+        Ok(())
+    }
+}
+
 /// Represents the statistics of rolling returns for a specific period.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct RollingReturnStats {
     pub average: f64,
     pub min: f64,
     pub max: f64,
     pub std_dev: f64,
-    pub distribution: [f64; 5],
+    /// Share of returns in percent below the first bucket boundary, between
+    /// each pair of boundaries, and from the last boundary up
+    pub distribution: Vec<f64>,
 }
 
 /// Calculates rolling returns for a given set of historical prices.
 pub fn calculate_rolling_returns(
     price_data: &PriceResult,
     period: HistoricalPeriod,
+    buckets: &[f64],
 ) -> Result<Option<RollingReturnStats>> {
-    if price_data.daily_prices.is_empty() {
-        return Ok(None);
-    }
+    calculate_series_rolling_returns(&price_data.daily_prices, period, buckets)
+}
 
-    let trading_days = period.to_trading_days() as usize;
-    if price_data.daily_prices.len() < trading_days {
-        return Ok(None);
+/// Calculates rolling returns for a daily value series.
+///
+/// Each date is compared with the last value on or before the start of the
+/// period ending on it, and the change is annualized over the calendar days
+/// in between. Dates whose period starts before the series are skipped.
+pub fn calculate_series_rolling_returns(
+    daily_prices: &[(NaiveDate, f64)],
+    period: HistoricalPeriod,
+    buckets: &[f64],
+) -> Result<Option<RollingReturnStats>> {
+    if !period.is_trailing() {
+        bail!("Rolling returns need a fixed-length period such as 1y, got {period}");
     }
 
     // Sort by date to ensure chronological order
-    let mut sorted_daily = price_data.daily_prices.clone();
+    let mut sorted_daily = daily_prices.to_vec();
     sorted_daily.sort_by_key(|(date, _)| *date);
-
-    // Convert to price vector only
-    let prices: Vec<f64> = sorted_daily.iter().map(|(_, price)| *price).collect();
-
-    if prices.len() < trading_days {
+    let Some(&(first_date, _)) = sorted_daily.first() else {
         return Ok(None);
-    }
+    };
 
     let mut returns = Vec::new();
-    for window in prices.windows(trading_days) {
-        let start_price = window[0];
-        let end_price = window[trading_days - 1];
+    for &(date, end_price) in &sorted_daily {
+        let Some(start) = period.start_date(date) else {
+            continue;
+        };
+        if start < first_date {
+            continue;
+        }
+        let start_index = sorted_daily.partition_point(|(d, _)| *d <= start) - 1;
+        let start_price = sorted_daily[start_index].1;
         if start_price > 0.0 {
-            let years = (trading_days as f64) / 252.0; // 252 trading days per year
+            let years = (date - start).num_days() as f64 / DAYS_PER_YEAR;
             let cagr = ((end_price / start_price).powf(1.0 / years) - 1.0) * 100.0;
             returns.push(cagr);
         }
@@ -242,19 +368,13 @@ pub fn calculate_rolling_returns(
     let min = returns.iter().cloned().fold(f64::MAX, f64::min);
     let max = returns.iter().cloned().fold(f64::MIN, f64::max);
 
-    let mut distribution = [0.0; 5];
+    let mut distribution = vec![0.0; buckets.len() + 1];
     for &ret in &returns {
-        if ret < 0.0 {
-            distribution[0] += 1.0;
-        } else if ret < 5.0 {
-            distribution[1] += 1.0;
-        } else if ret < 10.0 {
-            distribution[2] += 1.0;
-        } else if ret < 20.0 {
-            distribution[3] += 1.0;
-        } else {
-            distribution[4] += 1.0;
-        }
+        let bucket = buckets
+            .iter()
+            .position(|boundary| ret < *boundary)
+            .unwrap_or(buckets.len());
+        distribution[bucket] += 1.0;
     }
 
     for val in &mut distribution {
@@ -270,13 +390,116 @@ pub fn calculate_rolling_returns(
     }))
 }
 
+/// Returns the year a date falls in when years start on `start_month`,
+/// named by the calendar year they start in.
+pub fn year_of(date: NaiveDate, start_month: u32) -> i32 {
+    if date.month() >= start_month {
+        date.year()
+    } else {
+        date.year() - 1
+    }
+}
+
+/// Calculates the percent change of a value series over each year starting on
+/// `start_month`, keyed by [`year_of`].
+///
+/// A year's change runs from the last value before it to its last value, so
+/// the first year of the series, which has no earlier value, is skipped. The
+/// last year runs to the latest value.
+pub fn yearly_returns(series: &[(NaiveDate, f64)], start_month: u32) -> BTreeMap<i32, f64> {
+    let mut sorted = series.to_vec();
+    sorted.sort_by_key(|(date, _)| *date);
+
+    let mut year_ends: Vec<(i32, f64)> = Vec::new();
+    for (date, value) in sorted {
+        let year = year_of(date, start_month);
+        match year_ends.last_mut() {
+            Some(last) if last.0 == year => last.1 = value,
+            _ => year_ends.push((year, value)),
+        }
+    }
+
+    year_ends
+        .windows(2)
+        .filter(|w| w[0].1 > 0.0)
+        .map(|w| (w[1].0, (w[1].1 / w[0].1 - 1.0) * 100.0))
+        .collect()
+}
+
+/// Calculates the annualized internal rate of return, in percent, of cash
+/// flows by date, with deposits negative and withdrawals or the current
+/// value positive.
+///
+/// Returns `None` without both kinds of flows or when the rate does not converge.
+pub fn xirr(cashflows: &[(NaiveDate, f64)]) -> Option<f64> {
+    let mut flows = cashflows.to_vec();
+    flows.sort_by_key(|(date, _)| *date);
+    if !flows.iter().any(|(_, v)| *v < 0.0) || !flows.iter().any(|(_, v)| *v > 0.0) {
+        return None;
+    }
+    let flow_table = flows
+        .iter()
+        .map(|(date, value)| Some((Decimal::from_f64(*value)?, date.num_days_from_ce())))
+        .collect::<Option<Vec<_>>>()?;
+    let rate = rust_finprim::rate::xirr(&flow_table, None, None).ok()?;
+    (rate * Decimal::from(100)).to_f64()
+}
+
+/// Builds a daily value series for a portfolio from its holdings.
+///
+/// Each position is a daily price series with the current value of the holding;
+/// its prices are scaled so the last one equals that value. Series are aligned
+/// on their dates from the latest first date on, carrying the last known price
+/// forward over days a series has no price, e.g. market holidays.
+pub fn portfolio_value_series(positions: &[(&[(NaiveDate, f64)], f64)]) -> Vec<(NaiveDate, f64)> {
+    let mut series: Vec<(Vec<(NaiveDate, f64)>, f64)> = Vec::new();
+    for (prices, value) in positions {
+        let mut sorted = prices.to_vec();
+        sorted.sort_by_key(|(date, _)| *date);
+        if let Some((_, last)) = sorted.last()
+            && *last > 0.0
+        {
+            let scale = value / last;
+            series.push((sorted, scale));
+        }
+    }
+    let Some(start) = series.iter().map(|(prices, _)| prices[0].0).max() else {
+        return Vec::new();
+    };
+
+    let dates: BTreeSet<NaiveDate> = series
+        .iter()
+        .flat_map(|(prices, _)| prices.iter().map(|(date, _)| *date))
+        .filter(|date| *date >= start)
+        .collect();
+    let mut cursors = vec![0usize; series.len()];
+    dates
+        .into_iter()
+        .map(|date| {
+            let value = series
+                .iter()
+                .zip(cursors.iter_mut())
+                .map(|((prices, scale), cursor)| {
+                    while *cursor + 1 < prices.len() && prices[*cursor + 1].0 <= date {
+                        *cursor += 1;
+                    }
+                    prices[*cursor].1 * scale
+                })
+                .sum();
+            (date, value)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::config::{FixedDepositInvestment, Investment, Portfolio, StockInvestment};
+    use crate::core::config::{
+        BondInvestment, FixedDepositInvestment, Investment, Portfolio, StockInvestment,
+    };
     use crate::core::currency::CurrencyRateProvider;
+    use crate::core::deposit::Compounding;
     use crate::core::price::PriceResult;
-    use anyhow::Result;
     use async_trait::async_trait;
 
     // MockCurrencyProvider for CurrencyRateProvider
@@ -299,12 +522,11 @@ mod tests {
 
     #[async_trait]
     impl CurrencyRateProvider for MockCurrencyProvider {
-        async fn get_rate(&self, from: &str, to: &str) -> Result<f64> {
+        async fn get_rate(&self, from: &str, to: &str) -> ProviderResult<f64> {
             let key = format!("{from}:{to}");
-            self.rates
-                .get(&key)
-                .cloned()
-                .ok_or_else(|| anyhow!("Rate not found for {} to {}", from, to))
+            self.rates.get(&key).cloned().ok_or_else(|| {
+                ProviderError::NotFound(format!("Rate not found for {from} to {to}"))
+            })
         }
     }
 
@@ -329,7 +551,13 @@ mod tests {
                 symbol: "AAPL".to_string(),
                 units: 10.0,
                 category: None,
+                tags: Vec::new(),
+                benchmark: None,
+                transactions: Vec::new(),
             })],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
         };
         let holdings = calculate_portfolio_value(
             &portfolio,
@@ -354,6 +582,50 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_bond_keeps_currency_when_quote_fails() {
+        let currency_provider = MockCurrencyProvider::new();
+        let price_results = HashMap::from([(
+            "UST".to_string(),
+            Err(ProviderError::Network("API unavailable".to_string())),
+        )]);
+        let today = Local::now().date_naive();
+        let portfolio = Portfolio {
+            name: "Bonds".to_string(),
+            investments: vec![Investment::Bond(BondInvestment {
+                name: "Treasury".to_string(),
+                units: 1.0,
+                face_value: 1000.0,
+                coupon_rate: 0.0,
+                frequency: Compounding::HalfYearly,
+                issue_date: today - chrono::Months::new(12),
+                maturity_date: today + chrono::Months::new(12),
+                price: None,
+                symbol: Some("UST".to_string()),
+                currency: Some("USD".to_string()),
+                category: None,
+                tags: Vec::new(),
+            })],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
+        };
+        let holdings = calculate_portfolio_value(
+            &portfolio,
+            &price_results,
+            &currency_provider,
+            "INR",
+            &|| (),
+        )
+        .await;
+
+        // Valued at the manual price, still in dollars
+        let bond = &holdings.investments[0];
+        assert_eq!(bond.value_currency.as_deref(), Some("USD"));
+        assert_eq!(bond.value, Some(1000.0));
+        assert!(matches!(bond.error, Some(ProviderError::Network(_))));
+    }
+
     #[tokio::test]
     async fn test_error_handling_price_fetch() {
         let currency_provider = MockCurrencyProvider::new();
@@ -368,7 +640,10 @@ mod tests {
                 short_name: Some("Apple Inc.".to_string()),
             }),
         );
-        price_results.insert("MSFT".to_string(), Err(anyhow!("API unavailable")));
+        price_results.insert(
+            "MSFT".to_string(),
+            Err(ProviderError::Network("API unavailable".to_string())),
+        );
 
         let portfolio = Portfolio {
             name: "Tech".to_string(),
@@ -377,13 +652,22 @@ mod tests {
                     symbol: "AAPL".to_string(),
                     units: 10.0,
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
                 Investment::Stock(StockInvestment {
                     symbol: "MSFT".to_string(),
                     units: 5.0,
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
             ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
         };
 
         let holdings = calculate_portfolio_value(
@@ -398,8 +682,8 @@ mod tests {
         assert!(holdings.total_converted_value.is_none());
         assert_eq!(holdings.investments[0].error, None);
         assert_eq!(
-            holdings.investments[1].error.as_deref(),
-            Some("API unavailable")
+            holdings.investments[1].error,
+            Some(ProviderError::Network("API unavailable".to_string()))
         );
         assert!(holdings.investments[0].converted_value.is_some());
         assert!(holdings.investments[1].converted_value.is_none());
@@ -437,13 +721,22 @@ mod tests {
                     symbol: "AAPL".to_string(),
                     units: 10.0,
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
                 Investment::Stock(StockInvestment {
                     symbol: "RY".to_string(),
                     units: 10.0,
                     category: None,
+                    tags: Vec::new(),
+                    benchmark: None,
+                    transactions: Vec::new(),
                 }),
             ],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
         };
 
         let holdings = calculate_portfolio_value(
@@ -473,17 +766,22 @@ mod tests {
 
     #[tokio::test]
     async fn test_fixed_deposit_investment() {
-        let price_results: HashMap<String, Result<PriceResult>> = HashMap::new();
+        let price_results: HashMap<String, ProviderResult<PriceResult>> = HashMap::new();
         let currency_provider = MockCurrencyProvider::new();
 
         let portfolio = Portfolio {
             name: "Bank".to_string(),
             investments: vec![Investment::FixedDeposit(FixedDepositInvestment {
                 name: "My FD".to_string(),
-                value: 5000.0,
+                value: Some(5000.0),
                 currency: Some("INR".to_string()),
                 category: None,
+                tags: Vec::new(),
+                ..Default::default()
             })],
+            target_allocation: None,
+            tags: Vec::new(),
+            benchmark: None,
         };
 
         let holdings = calculate_portfolio_value(
@@ -501,4 +799,106 @@ mod tests {
         assert_eq!(holdings.investments[0].converted_value, Some(5000.0));
         assert_eq!(holdings.investments[0].weight, Some(100.0));
     }
+
+    #[test]
+    fn test_portfolio_value_series_aligns_dates() {
+        let date = |d: u32| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let stock = vec![(date(1), 10.0), (date(2), 11.0), (date(4), 12.0)];
+        let fund = vec![(date(2), 50.0), (date(3), 55.0), (date(4), 60.0)];
+
+        let series = portfolio_value_series(&[(&stock, 1200.0), (&fund, 600.0)]);
+
+        // Starts when both have prices, holiday prices are carried forward
+        assert_eq!(
+            series,
+            vec![
+                (date(2), 1100.0 + 500.0),
+                (date(3), 1100.0 + 550.0),
+                (date(4), 1200.0 + 600.0),
+            ]
+        );
+        assert!(portfolio_value_series(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_rolling_returns_with_custom_buckets() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        // Prices rise 1% a day, so every 1-day rolling return is far above 20%
+        let series: Vec<(NaiveDate, f64)> = (0..10)
+            .map(|i| (start + chrono::Duration::days(i), 1.01f64.powi(i as i32)))
+            .collect();
+
+        let stats = calculate_series_rolling_returns(&series, HistoricalPeriod::FiveDays, &[0.0])
+            .unwrap()
+            .unwrap();
+        assert_eq!(stats.distribution, vec![0.0, 100.0]);
+
+        let stats = calculate_series_rolling_returns(
+            &series,
+            HistoricalPeriod::FiveDays,
+            &DEFAULT_ROLLING_BUCKETS,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(stats.distribution.len(), 5);
+        assert_eq!(stats.distribution[4], 100.0);
+
+        assert!(ReturnsConfig::default().validate().is_ok());
+        let unsorted = ReturnsConfig {
+            rolling_buckets: vec![10.0, 5.0],
+            ..Default::default()
+        };
+        assert!(unsorted.validate().is_err());
+    }
+
+    #[test]
+    fn test_yearly_returns() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let series = vec![
+            (date(2022, 6, 1), 80.0),
+            (date(2022, 12, 30), 100.0),
+            (date(2023, 3, 31), 90.0),
+            (date(2023, 12, 29), 120.0),
+            (date(2024, 2, 15), 126.0),
+        ];
+
+        let calendar = yearly_returns(&series, 1);
+        assert_eq!(
+            calendar.keys().copied().collect::<Vec<_>>(),
+            vec![2023, 2024]
+        );
+        assert!((calendar[&2023] - 20.0).abs() < 1e-9);
+        assert!((calendar[&2024] - 5.0).abs() < 1e-9);
+
+        // April fiscal years: FY2022 ends at 90, FY2023 runs to the latest value
+        let fiscal = yearly_returns(&series, 4);
+        assert_eq!(fiscal.keys().copied().collect::<Vec<_>>(), vec![2023]);
+        assert!((fiscal[&2023] - 40.0).abs() < 1e-9);
+        assert_eq!(year_of(date(2024, 3, 31), 4), 2023);
+        assert_eq!(year_of(date(2024, 4, 1), 4), 2024);
+
+        let invalid = ReturnsConfig {
+            fiscal_year_start: 13,
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_xirr() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let one_year = [(date(2023, 1, 1), -1000.0), (date(2024, 1, 1), 1100.0)];
+        assert!((xirr(&one_year).unwrap() - 10.0).abs() < 0.01);
+
+        // Deposits at different times, in any order
+        let flows = [
+            (date(2024, 1, 1), 2100.0),
+            (date(2023, 1, 1), -1000.0),
+            (date(2023, 7, 2), -1000.0),
+        ];
+        let rate = xirr(&flows).unwrap();
+        assert!(rate > 5.0 && rate < 7.0);
+
+        assert_eq!(xirr(&[(date(2023, 1, 1), -1000.0)]), None);
+    }
 }
@@ -0,0 +1,224 @@
+//! Comparison of value series against a benchmark index.
+
+use crate::core::risk::{TRADING_DAYS_PER_YEAR, daily_returns};
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Benchmarks used for fund categories without a configured rule.
+const DEFAULT_RULES: &[(&str, &str)] = &[
+    ("large & mid cap", "^CRSLDX"),
+    ("large cap", "^NSEI"),
+    ("mid cap", "^NSEMDCP50"),
+    ("flexi cap", "^CRSLDX"),
+    ("multi cap", "^CRSLDX"),
+    ("elss", "^CRSLDX"),
+];
+
+/// Maps fund categories containing `pattern`, ignoring case, to a benchmark.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BenchmarkRule {
+    pub pattern: String,
+    /// Symbol or ISIN of the benchmark
+    pub benchmark: String,
+}
+
+/// How benchmarks are chosen for funds without one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct BenchmarkConfig {
+    /// Rules tried in order before the built-in ones
+    #[serde(default)]
+    pub rules: Vec<BenchmarkRule>,
+}
+
+impl BenchmarkConfig {
+    /// Returns the default benchmark for a fund category.
+    pub fn for_category(&self, category: &str) -> Option<String> {
+        let lower = category.to_lowercase();
+        self.rules
+            .iter()
+            .map(|rule| (rule.pattern.as_str(), rule.benchmark.as_str()))
+            .chain(DEFAULT_RULES.iter().copied())
+            .find(|(pattern, _)| lower.contains(&pattern.to_lowercase()))
+            .map(|(_, benchmark)| benchmark.to_string())
+    }
+}
+
+/// Returns true for identifiers shaped like an ISIN, e.g. `INF179KB1HU9`.
+pub fn is_isin(identifier: &str) -> bool {
+    let bytes = identifier.as_bytes();
+    bytes.len() == 12
+        && bytes[..2].iter().all(u8::is_ascii_uppercase)
+        && bytes[2..11]
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        && bytes[11].is_ascii_digit()
+}
+
+/// Performance relative to a benchmark, with returns in percent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchmarkComparison {
+    /// Annualized return above the benchmark
+    pub excess_return: f64,
+    /// Annualized standard deviation of the daily return difference
+    pub tracking_error: f64,
+    pub beta: Option<f64>,
+    /// Annualized return above what the beta and risk-free rate explain
+    pub alpha: Option<f64>,
+    /// Average return on days the benchmark rose, relative to the benchmark's
+    pub up_capture: Option<f64>,
+    /// Average return on days the benchmark fell, relative to the benchmark's
+    pub down_capture: Option<f64>,
+}
+
+/// Daily values by date.
+type Series = Vec<(NaiveDate, f64)>;
+
+/// Keeps the values of two series on the dates both have.
+pub fn align_series(
+    series: &[(NaiveDate, f64)],
+    benchmark: &[(NaiveDate, f64)],
+) -> (Series, Series) {
+    let benchmark: HashMap<NaiveDate, f64> = benchmark.iter().copied().collect();
+    let mut sorted = series.to_vec();
+    sorted.sort_by_key(|(date, _)| *date);
+    sorted
+        .into_iter()
+        .filter_map(|(date, value)| benchmark.get(&date).map(|b| ((date, value), (date, *b))))
+        .unzip()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Compares a series against a benchmark on their common dates.
+///
+/// `risk_free_rate` is an annual rate in percent. Returns `None` when there are
+/// fewer than three common dates.
+pub fn compare(
+    series: &[(NaiveDate, f64)],
+    benchmark: &[(NaiveDate, f64)],
+    risk_free_rate: f64,
+) -> Option<BenchmarkComparison> {
+    let (series, benchmark) = align_series(series, benchmark);
+    let returns = daily_returns(&series);
+    let benchmark_returns = daily_returns(&benchmark);
+    if returns.len() < 2 || returns.len() != benchmark_returns.len() {
+        return None;
+    }
+
+    let count = returns.len() as f64;
+    let mean_return = mean(&returns);
+    let mean_benchmark = mean(&benchmark_returns);
+    let differences: Vec<f64> = returns
+        .iter()
+        .zip(&benchmark_returns)
+        .map(|(r, b)| r - b)
+        .collect();
+    let mean_difference = mean(&differences);
+    let tracking_variance = differences
+        .iter()
+        .map(|d| (d - mean_difference).powi(2))
+        .sum::<f64>()
+        / (count - 1.0);
+    let covariance = returns
+        .iter()
+        .zip(&benchmark_returns)
+        .map(|(r, b)| (r - mean_return) * (b - mean_benchmark))
+        .sum::<f64>()
+        / (count - 1.0);
+    let benchmark_variance = benchmark_returns
+        .iter()
+        .map(|b| (b - mean_benchmark).powi(2))
+        .sum::<f64>()
+        / (count - 1.0);
+
+    let annual_return = mean_return * TRADING_DAYS_PER_YEAR * 100.0;
+    let annual_benchmark = mean_benchmark * TRADING_DAYS_PER_YEAR * 100.0;
+    let beta = (benchmark_variance > 0.0).then(|| covariance / benchmark_variance);
+    let capture = |rising: bool| {
+        let (asset, index): (Vec<f64>, Vec<f64>) = returns
+            .iter()
+            .zip(&benchmark_returns)
+            .filter(|(_, b)| if rising { **b > 0.0 } else { **b < 0.0 })
+            .unzip();
+        (!index.is_empty()).then(|| mean(&asset) / mean(&index) * 100.0)
+    };
+
+    Some(BenchmarkComparison {
+        excess_return: annual_return - annual_benchmark,
+        tracking_error: tracking_variance.sqrt() * TRADING_DAYS_PER_YEAR.sqrt() * 100.0,
+        beta,
+        alpha: beta.map(|beta| {
+            annual_return - (risk_free_rate + beta * (annual_benchmark - risk_free_rate))
+        }),
+        up_capture: capture(true),
+        down_capture: capture(false),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(values: &[f64]) -> Vec<(NaiveDate, f64)> {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (start + chrono::Duration::days(i as i64), *v))
+            .collect()
+    }
+
+    #[test]
+    fn test_compare_leveraged_series() {
+        // Daily returns exactly twice the benchmark's
+        let benchmark = series(&[100.0, 110.0, 99.0, 108.9]);
+        let fund = series(&[100.0, 120.0, 96.0, 115.2]);
+        let comparison = compare(&fund, &benchmark, 0.0).unwrap();
+
+        assert!((comparison.beta.unwrap() - 2.0).abs() < 1e-9);
+        assert!(comparison.alpha.unwrap().abs() < 1e-6);
+        assert!((comparison.up_capture.unwrap() - 200.0).abs() < 1e-9);
+        assert!((comparison.down_capture.unwrap() - 200.0).abs() < 1e-9);
+        assert!(comparison.tracking_error > 0.0);
+        let annual_benchmark = (0.1 - 0.1 + 0.1) / 3.0 * 252.0 * 100.0;
+        assert!((comparison.excess_return - annual_benchmark).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_compare_uses_common_dates() {
+        let benchmark = series(&[100.0, 101.0, 102.0, 103.0]);
+        let fund = vec![benchmark[0], benchmark[2], benchmark[3]];
+        let comparison = compare(&fund, &benchmark, 0.0).unwrap();
+        assert!(comparison.tracking_error.abs() < 1e-9);
+        assert!(comparison.excess_return.abs() < 1e-9);
+        assert_eq!(comparison.down_capture, None);
+        assert!(compare(&fund[..2], &benchmark, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_default_benchmarks() {
+        let config = BenchmarkConfig {
+            rules: vec![BenchmarkRule {
+                pattern: "Large Cap".to_string(),
+                benchmark: "^BSESN".to_string(),
+            }],
+        };
+        assert_eq!(
+            config.for_category("Large Cap Fund").as_deref(),
+            Some("^BSESN")
+        );
+        assert_eq!(
+            config.for_category("Mid Cap Fund").as_deref(),
+            Some("^NSEMDCP50")
+        );
+        assert_eq!(config.for_category("Liquid Fund"), None);
+
+        assert!(is_isin("INF179KB1HU9"));
+        assert!(!is_isin("^NSEI"));
+        assert!(!is_isin("AAPL"));
+    }
+}
@@ -0,0 +1,197 @@
+//! Coupon schedules, accrued interest, yield and duration of bonds.
+
+use crate::core::deposit::Compounding;
+use chrono::{Months, NaiveDate};
+
+/// Bounds of the annual yield searched for, as a fraction.
+const YIELD_BOUNDS: (f64, f64) = (-0.5, 2.0);
+const YIELD_TOLERANCE: f64 = 1e-10;
+
+/// Terms of a bond paying a fixed coupon each period until maturity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BondTerms {
+    pub face_value: f64,
+    /// Annual coupon rate in percent of the face value
+    pub coupon_rate: f64,
+    pub frequency: Compounding,
+    pub issue: NaiveDate,
+    pub maturity: NaiveDate,
+}
+
+impl BondTerms {
+    /// Returns the coupon paid each period.
+    pub fn coupon(&self) -> f64 {
+        self.face_value * self.coupon_rate / 100.0 * f64::from(self.frequency.months()) / 12.0
+    }
+
+    fn periods_per_year(&self) -> f64 {
+        12.0 / f64::from(self.frequency.months())
+    }
+
+    /// Returns the coupon date `periods` periods before maturity.
+    fn coupon_date_before_maturity(&self, periods: u32) -> Option<NaiveDate> {
+        self.maturity
+            .checked_sub_months(Months::new(self.frequency.months() * periods))
+    }
+
+    /// Returns the coupon dates after `date` through maturity, counted back
+    /// from the maturity date.
+    pub fn coupon_dates_after(&self, date: NaiveDate) -> Vec<NaiveDate> {
+        let mut dates: Vec<NaiveDate> = (0..)
+            .map_while(|i| self.coupon_date_before_maturity(i))
+            .take_while(|d| *d > date && *d > self.issue)
+            .collect();
+        dates.reverse();
+        dates
+    }
+
+    /// Returns the start and end of the coupon period containing `date`,
+    /// before maturity.
+    fn coupon_period(&self, date: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        let remaining = self.coupon_dates_after(date).len() as u32;
+        if remaining == 0 {
+            return None;
+        }
+        Some((
+            self.coupon_date_before_maturity(remaining)?,
+            self.coupon_date_before_maturity(remaining - 1)?,
+        ))
+    }
+
+    /// Returns the coupon earned since the last coupon date, which a buyer
+    /// pays the seller on top of the clean price.
+    pub fn accrued_interest(&self, date: NaiveDate) -> f64 {
+        if date <= self.issue {
+            return 0.0;
+        }
+        let Some((start, end)) = self.coupon_period(date) else {
+            return 0.0;
+        };
+        let accrual_start = start.max(self.issue);
+        self.coupon() * (date - accrual_start).num_days() as f64 / (end - start).num_days() as f64
+    }
+
+    /// Returns the price including accrued interest, from the clean price.
+    pub fn dirty_price(&self, clean_price: f64, date: NaiveDate) -> f64 {
+        clean_price + self.accrued_interest(date)
+    }
+
+    /// Returns the coupons and redemption due after `date`.
+    pub fn cashflows_after(&self, date: NaiveDate) -> Vec<(NaiveDate, f64)> {
+        let mut cashflows: Vec<(NaiveDate, f64)> = self
+            .coupon_dates_after(date)
+            .into_iter()
+            .map(|d| (d, self.coupon()))
+            .collect();
+        if let Some(last) = cashflows.last_mut() {
+            last.1 += self.face_value;
+        }
+        cashflows
+    }
+
+    /// Discounts the remaining cash flows at an annual yield, returning the
+    /// dirty price and the Macaulay duration in years.
+    fn price_at_yield(&self, annual_yield: f64, date: NaiveDate) -> Option<(f64, f64)> {
+        let (start, end) = self.coupon_period(date)?;
+        let f = self.periods_per_year();
+        // Fraction of a period until the next coupon
+        let first = (end - date).num_days() as f64 / (end - start).num_days() as f64;
+        let (price, weighted) = self.cashflows_after(date).iter().enumerate().fold(
+            (0.0, 0.0),
+            |(price, weighted), (i, (_, amount))| {
+                let periods = first + i as f64;
+                let pv = amount / (1.0 + annual_yield / f).powf(periods);
+                (price + pv, weighted + pv * periods / f)
+            },
+        );
+        (price > 0.0).then(|| (price, weighted / price))
+    }
+
+    /// Returns the annual yield to maturity in percent at a dirty price,
+    /// compounded at the coupon frequency.
+    pub fn yield_to_maturity(&self, dirty_price: f64, date: NaiveDate) -> Option<f64> {
+        let price = |y: f64| self.price_at_yield(y, date).map(|(p, _)| p);
+        let (mut low, mut high) = YIELD_BOUNDS;
+        // Prices fall as yields rise
+        if dirty_price > price(low)? || dirty_price < price(high)? {
+            return None;
+        }
+        while high - low > YIELD_TOLERANCE {
+            let mid = (low + high) / 2.0;
+            if price(mid)? > dirty_price {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Some((low + high) / 2.0 * 100.0)
+    }
+
+    /// Returns the modified duration in years at a yield to maturity in
+    /// percent: the percentage change in price for a 1% change in yield.
+    pub fn modified_duration(&self, ytm: f64, date: NaiveDate) -> Option<f64> {
+        let annual_yield = ytm / 100.0;
+        let (_, macaulay) = self.price_at_yield(annual_yield, date)?;
+        Some(macaulay / (1.0 + annual_yield / self.periods_per_year()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn terms(coupon_rate: f64) -> BondTerms {
+        BondTerms {
+            face_value: 100.0,
+            coupon_rate,
+            frequency: Compounding::HalfYearly,
+            issue: date(2020, 3, 15),
+            maturity: date(2030, 3, 15),
+        }
+    }
+
+    #[test]
+    fn test_coupon_schedule_and_accrued_interest() {
+        let bond = terms(7.0);
+        assert_eq!(bond.coupon(), 3.5);
+        let dates = bond.coupon_dates_after(date(2029, 1, 1));
+        assert_eq!(
+            dates,
+            vec![date(2029, 3, 15), date(2029, 9, 15), date(2030, 3, 15)]
+        );
+        let cashflows = bond.cashflows_after(date(2029, 1, 1));
+        assert_eq!(cashflows.last(), Some(&(date(2030, 3, 15), 103.5)));
+
+        assert_eq!(bond.accrued_interest(date(2025, 3, 15)), 0.0);
+        // 92 of the 184 days from March 15 to September 15
+        let accrued = bond.accrued_interest(date(2025, 6, 15));
+        assert!((accrued - 1.75).abs() < 1e-9);
+        assert!((bond.dirty_price(99.0, date(2025, 6, 15)) - 100.75).abs() < 1e-9);
+        assert_eq!(bond.accrued_interest(date(2031, 1, 1)), 0.0);
+    }
+
+    #[test]
+    fn test_yield_and_duration() {
+        let bond = terms(7.0);
+        let on_coupon = date(2025, 3, 15);
+        // At par on a coupon date the yield is the coupon rate
+        let ytm = bond.yield_to_maturity(100.0, on_coupon).unwrap();
+        assert!((ytm - 7.0).abs() < 1e-6);
+        let discounted = bond.yield_to_maturity(95.0, on_coupon).unwrap();
+        assert!(discounted > 7.0);
+
+        // A zero coupon bond's Macaulay duration is its remaining life
+        let zero = terms(0.0);
+        let ytm = zero.yield_to_maturity(70.0, on_coupon).unwrap();
+        let duration = zero.modified_duration(ytm, on_coupon).unwrap();
+        assert!((duration - 5.0 / (1.0 + ytm / 200.0)).abs() < 1e-6);
+        // Coupons shorten the duration
+        assert!(bond.modified_duration(7.0, on_coupon).unwrap() < duration);
+
+        assert_eq!(bond.yield_to_maturity(100.0, date(2031, 1, 1)), None);
+    }
+}
@@ -1,7 +1,12 @@
 use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::Duration;
 
+/// Number of writes to a collection after which expired entries are swept.
+pub const SWEEP_EVERY_N_PUTS: usize = 64;
+
 /// Trait representing a cache store with collection management.
 pub trait Store {
     /// Retrieves a collection by name, optionally creating it if missing.
@@ -17,6 +22,41 @@ pub trait Store {
     fn remove_collection(&self, name: &str) -> bool;
 }
 
+/// Size limits for a collection. Least recently used entries are evicted once
+/// either limit is exceeded. `None` means unbounded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CollectionLimits {
+    pub max_entries: Option<usize>,
+    /// Maximum size of keys and values in bytes
+    pub max_bytes: Option<u64>,
+}
+
+impl CollectionLimits {
+    /// Returns true if any limit is configured.
+    pub fn is_bounded(&self) -> bool {
+        self.max_entries.is_some() || self.max_bytes.is_some()
+    }
+
+    /// Returns true if a collection with given entries and size is within limits.
+    pub fn allows(&self, entries: usize, bytes: u64) -> bool {
+        self.max_entries.is_none_or(|max| entries <= max)
+            && self.max_bytes.is_none_or(|max| bytes <= max)
+    }
+}
+
+/// Point-in-time statistics for a collection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CollectionStats {
+    pub entries: usize,
+    pub bytes: u64,
+    /// Entries removed since the collection was opened because their TTL elapsed
+    pub expired: u64,
+    /// Entries removed since the collection was opened to respect size limits
+    pub evictions: u64,
+    /// Reads served after the entry's TTL elapsed, when stale entries are kept
+    pub stale_hits: u64,
+}
+
 /// Trait representing a cache with key-based access and TTL support.
 #[async_trait]
 pub trait KeyValueCollection: Send + Sync {
@@ -31,4 +71,10 @@ pub trait KeyValueCollection: Send + Sync {
 
     /// Clears all entries from the cache.
     async fn clear(&self);
+
+    /// Removes all expired entries. Returns the number of entries removed.
+    async fn sweep(&self) -> usize;
+
+    /// Returns entry count, size and eviction statistics for the cache.
+    async fn stats(&self) -> CollectionStats;
 }
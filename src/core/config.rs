@@ -1,59 +1,768 @@
-use anyhow::{Context, Result};
+use crate::core::allocation::{AssetClassConfig, TargetAllocation};
+use crate::core::analytics::ReturnsConfig;
+use crate::core::benchmark::BenchmarkConfig;
+use crate::core::bond::BondTerms;
+use crate::core::cache::CollectionLimits;
+use crate::core::correlation::CorrelationConfig;
+use crate::core::deposit::{Compounding, DepositTerms};
+use crate::core::include;
+use crate::core::migrate;
+use crate::core::risk::RiskConfig;
+use crate::core::savings::SavingsPlan;
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
 use directories::ProjectDirs;
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use serde_json::json;
+use std::borrow::Cow;
+use std::{collections::HashMap, path::PathBuf};
 use tracing::debug;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone)]
 pub struct StockInvestment {
+    /// Yahoo Finance symbol
+    #[schemars(example = &"AAPL")]
     pub symbol: String,
+    /// Number of shares held
     pub units: f64,
+    /// Category for allocation, e.g. `Equity`, `Debt` or `Gold`
+    #[schemars(example = &"Equity")]
     pub category: Option<String>,
+    /// Labels for selecting the investment with `--tag`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Symbol or ISIN of the index to compare the holding against
+    #[schemars(example = &"^GSPC")]
+    pub benchmark: Option<String>,
+    /// Purchases and sales, recorded with `xmf add-txn`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transactions: Vec<Transaction>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// A purchase of a stock or fund, or a sale with negative units.
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone, PartialEq)]
+pub struct Transaction {
+    pub date: NaiveDate,
+    /// Units bought, or sold if negative
+    pub units: f64,
+    /// Price per unit, in the currency the investment is quoted in
+    pub price: f64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone)]
 pub struct MutualFundInvestment {
+    /// ISIN of the fund
+    #[schemars(example = &"INF179KB1HU9")]
     pub isin: String,
+    /// Number of fund units held
     pub units: f64,
+    /// Category for allocation, e.g. `Equity`, `Debt` or `Gold`
+    #[schemars(example = &"Equity")]
     pub category: Option<String>,
+    /// Labels for selecting the investment with `--tag`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Symbol or ISIN of the index to compare the holding against
+    #[schemars(example = &"^NSEI")]
+    pub benchmark: Option<String>,
+    /// Purchases and sales, recorded with `xmf add-txn`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transactions: Vec<Transaction>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone, Default)]
 pub struct FixedDepositInvestment {
+    /// Name of the deposit, shown in reports
+    pub name: String,
+    /// Current value, for deposits without a principal
+    pub value: Option<f64>,
+    /// Currency the value is in, if not the global currency
+    #[schemars(example = &"INR")]
+    pub currency: Option<String>,
+    /// Category for allocation, e.g. `Equity`, `Debt` or `Gold`
+    #[schemars(example = &"Debt")]
+    pub category: Option<String>,
+    /// Labels for selecting the investment with `--tag`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Amount deposited on `start_date`, which then accrues interest
+    pub principal: Option<f64>,
+    /// Annual interest rate in percent
+    pub rate: Option<f64>,
+    /// Date the principal was deposited
+    pub start_date: Option<NaiveDate>,
+    /// Date the deposit matures, after which it stops accruing interest
+    pub maturity_date: Option<NaiveDate>,
+    /// How often interest is compounded, or paid out
+    #[serde(default)]
+    pub compounding: Compounding,
+    /// Interest is paid out each period instead of reinvested
+    #[serde(default)]
+    pub payout: bool,
+}
+
+impl FixedDepositInvestment {
+    /// Returns the interest terms of a deposit with a principal.
+    pub fn terms(&self) -> Option<DepositTerms> {
+        Some(DepositTerms {
+            principal: self.principal?,
+            rate: self.rate?,
+            start: self.start_date?,
+            maturity: self.maturity_date,
+            compounding: self.compounding,
+            payout: self.payout,
+        })
+    }
+
+    /// Returns the accrued value on `date`, or the configured value.
+    pub fn value_on(&self, date: NaiveDate) -> f64 {
+        match self.terms() {
+            Some(terms) => terms.value_on(date),
+            None => self.value.unwrap_or_default(),
+        }
+    }
+
+    /// Checks that the deposit has a value or complete interest terms.
+    pub fn validate(&self) -> Result<()> {
+        match (self.value, self.principal) {
+            (None, None) => bail!("Fixed deposit '{}' needs a value or a principal", self.name),
+            (_, Some(_)) if self.rate.is_none() || self.start_date.is_none() => bail!(
+                "Fixed deposit '{}' needs a rate and start_date with its principal",
+                self.name
+            ),
+            _ => {}
+        }
+        if let (Some(start), Some(maturity)) = (self.start_date, self.maturity_date)
+            && maturity <= start
+        {
+            bail!(
+                "Fixed deposit '{}' matures on {maturity}, before it starts on {start}",
+                self.name
+            );
+        }
+        if self.rate.is_some_and(|rate| rate < 0.0) {
+            bail!("Fixed deposit '{}' has a negative rate", self.name);
+        }
+        Ok(())
+    }
+}
+
+/// Bonds paying a fixed coupon, valued at their clean price plus accrued interest.
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone)]
+pub struct BondInvestment {
+    /// Name of the bond, shown in reports
+    pub name: String,
+    /// Number of bonds held
+    pub units: f64,
+    /// Amount repaid per bond at maturity
+    pub face_value: f64,
+    /// Annual coupon rate in percent of the face value
+    pub coupon_rate: f64,
+    /// How often coupons are paid
+    #[serde(default = "default_coupon_frequency")]
+    pub frequency: Compounding,
+    pub issue_date: NaiveDate,
+    /// Date the face value is repaid
+    pub maturity_date: NaiveDate,
+    /// Clean price per bond, used when there is no `symbol`. Defaults to the face value
+    pub price: Option<f64>,
+    /// Yahoo Finance symbol to fetch the clean price per bond from
+    pub symbol: Option<String>,
+    /// Currency the value is in, if not the global currency
+    #[schemars(example = &"INR")]
+    pub currency: Option<String>,
+    /// Category for allocation, e.g. `Equity`, `Debt` or `Gold`
+    #[schemars(example = &"Debt")]
+    pub category: Option<String>,
+    /// Labels for selecting the investment with `--tag`
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_coupon_frequency() -> Compounding {
+    Compounding::HalfYearly
+}
+
+impl BondInvestment {
+    pub fn terms(&self) -> BondTerms {
+        BondTerms {
+            face_value: self.face_value,
+            coupon_rate: self.coupon_rate,
+            frequency: self.frequency,
+            issue: self.issue_date,
+            maturity: self.maturity_date,
+        }
+    }
+
+    /// Returns the manual clean price per bond, or the face value.
+    pub fn manual_price(&self) -> f64 {
+        self.price.unwrap_or(self.face_value)
+    }
+
+    /// Checks that the bond matures after it is issued and has positive amounts.
+    pub fn validate(&self) -> Result<()> {
+        if self.maturity_date <= self.issue_date {
+            bail!(
+                "Bond '{}' matures on {}, before it is issued on {}",
+                self.name,
+                self.maturity_date,
+                self.issue_date
+            );
+        }
+        if self.face_value <= 0.0 || self.units < 0.0 || self.coupon_rate < 0.0 {
+            bail!(
+                "Bond '{}' needs a positive face value, units and coupon rate",
+                self.name
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A PPF, EPF, NSC, SSY or recurring deposit account, valued from its
+/// contributions and interest rates.
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone)]
+pub struct SmallSavingsInvestment {
+    /// Name of the account, shown in reports
     pub name: String,
-    pub value: f64,
+    /// Currency the value is in, if not the global currency
+    #[schemars(example = &"INR")]
     pub currency: Option<String>,
+    /// Category for allocation, e.g. `Equity`, `Debt` or `Gold`
+    #[schemars(example = &"Debt")]
     pub category: Option<String>,
+    /// Labels for selecting the investment with `--tag`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(flatten)]
+    pub plan: SavingsPlan,
+}
+
+impl SmallSavingsInvestment {
+    /// Returns the deposits made so far as outflows, and the value on `date` as an inflow.
+    pub fn cashflows(&self, date: NaiveDate) -> Vec<(NaiveDate, f64)> {
+        let mut cashflows: Vec<(NaiveDate, f64)> = self
+            .plan
+            .contributions_until(date)
+            .iter()
+            .map(|c| (c.date, -c.amount))
+            .collect();
+        cashflows.push((date, self.plan.value_on(date)));
+        cashflows
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-#[serde(untagged)]
+/// An investment, parsed by its `type` field or, without one, by the fields
+/// it has.
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Investment {
     Stock(StockInvestment),
     MutualFund(MutualFundInvestment),
     FixedDeposit(FixedDepositInvestment),
+    SmallSavings(SmallSavingsInvestment),
+    Bond(BondInvestment),
+}
+
+/// Values of the `type` field of an investment.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum InvestmentType {
+    Stock,
+    MutualFund,
+    FixedDeposit,
+    SmallSavings,
+    Bond,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+impl InvestmentType {
+    const EXPECTED: &str = "stock, mutual_fund, fixed_deposit, small_savings or bond";
+
+    /// Infers the type of an investment without a `type` field from the field
+    /// only that type has.
+    fn infer(fields: &serde_yaml::Mapping) -> Option<Self> {
+        [
+            ("face_value", InvestmentType::Bond),
+            ("scheme", InvestmentType::SmallSavings),
+            ("isin", InvestmentType::MutualFund),
+            ("symbol", InvestmentType::Stock),
+            ("name", InvestmentType::FixedDeposit),
+        ]
+        .into_iter()
+        .find(|(field, _)| fields.contains_key(*field))
+        .map(|(_, investment_type)| investment_type)
+    }
+
+    fn parse(self, value: serde_yaml::Value) -> serde_yaml::Result<Investment> {
+        Ok(match self {
+            InvestmentType::Stock => Investment::Stock(serde_yaml::from_value(value)?),
+            InvestmentType::MutualFund => Investment::MutualFund(serde_yaml::from_value(value)?),
+            InvestmentType::FixedDeposit => {
+                Investment::FixedDeposit(serde_yaml::from_value(value)?)
+            }
+            InvestmentType::SmallSavings => {
+                Investment::SmallSavings(serde_yaml::from_value(value)?)
+            }
+            InvestmentType::Bond => Investment::Bond(serde_yaml::from_value(value)?),
+        })
+    }
+}
+
+impl Investment {
+    /// Parses an investment, naming the field at fault when it is invalid.
+    pub fn from_value(value: serde_yaml::Value) -> Result<Investment> {
+        let serde_yaml::Value::Mapping(mut fields) = value else {
+            bail!("expected a mapping of investment fields");
+        };
+        let investment_type = match fields.remove("type") {
+            Some(tag) => serde_yaml::from_value::<InvestmentType>(tag.clone()).map_err(|_| {
+                anyhow::anyhow!(
+                    "unknown type {}, expected {}",
+                    serde_yaml::to_string(&tag).unwrap_or_default().trim(),
+                    InvestmentType::EXPECTED
+                )
+            })?,
+            None => InvestmentType::infer(&fields).with_context(|| {
+                format!(
+                    "cannot tell the type of investment, set `type` to one of {}",
+                    InvestmentType::EXPECTED
+                )
+            })?,
+        };
+
+        let investment = match investment_type.parse(fields.clone().into()) {
+            Ok(investment) => investment,
+            Err(e) => {
+                let message = e.to_string();
+                if message.starts_with("missing field") || message.starts_with("duplicate field") {
+                    bail!("{message}");
+                }
+                // Errors about a field's value do not name it. Removing the
+                // field at fault changes the error, since values are checked
+                // before missing fields.
+                let field = fields.keys().find(|key| {
+                    let mut without = fields.clone();
+                    without.remove(*key);
+                    investment_type
+                        .parse(without.into())
+                        .map_or_else(|other| other.to_string() != message, |_| true)
+                });
+                match field.and_then(|key| key.as_str()) {
+                    Some(key) => bail!("invalid field `{key}`: {message}"),
+                    None => bail!("{message}"),
+                }
+            }
+        };
+
+        // Reject misspelt fields, which would otherwise be ignored
+        let known = match serde_yaml::to_value(&investment)? {
+            serde_yaml::Value::Mapping(known) => known,
+            _ => serde_yaml::Mapping::new(),
+        };
+        if let Some(unknown) = fields
+            .keys()
+            .filter_map(|key| key.as_str())
+            .find(|key| !known.contains_key(*key))
+        {
+            let mut expected: Vec<&str> = known
+                .keys()
+                .filter_map(|key| key.as_str())
+                .filter(|key| *key != "type")
+                .collect();
+            expected.sort_unstable();
+            bail!(
+                "unknown field `{unknown}`, expected one of {}",
+                expected.join(", ")
+            );
+        }
+        Ok(investment)
+    }
+}
+
+impl<'de> Deserialize<'de> for Investment {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+        Investment::from_value(value).map_err(serde::de::Error::custom)
+    }
+}
+
+impl JsonSchema for Investment {
+    fn schema_name() -> Cow<'static, str> {
+        "Investment".into()
+    }
+
+    /// Any of the investment types, each with an optional `type` and no
+    /// fields of its own beyond those of the type.
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let variant = |investment_type: &str, mut schema: Schema| {
+            if let Some(properties) = schema
+                .get_mut("properties")
+                .and_then(serde_json::Value::as_object_mut)
+            {
+                properties.insert("type".into(), json!({ "const": investment_type }));
+            }
+            schema.insert("additionalProperties".into(), false.into());
+            schema
+        };
+        let variants = vec![
+            variant("stock", StockInvestment::json_schema(generator)),
+            variant("mutual_fund", MutualFundInvestment::json_schema(generator)),
+            variant(
+                "fixed_deposit",
+                FixedDepositInvestment::json_schema(generator),
+            ),
+            variant(
+                "small_savings",
+                SmallSavingsInvestment::json_schema(generator),
+            ),
+            variant("bond", BondInvestment::json_schema(generator)),
+        ];
+        json_schema!({
+            "description": "An investment, parsed by its `type` or, without one, by the fields it has",
+            "anyOf": variants,
+        })
+    }
+}
+
+/// A portfolio as written in the config, before its investments are parsed.
+#[derive(Deserialize, JsonSchema)]
+struct RawPortfolio {
+    /// Name shown in reports and matched by `--portfolio`
+    name: String,
+    #[schemars(with = "Vec<Investment>")]
+    investments: Vec<serde_yaml::Value>,
+    /// Target weights in percent by category, overriding the global target
+    target_allocation: Option<TargetAllocation>,
+    /// Labels for selecting the portfolio with `--tag`, inherited by its investments
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Symbol or ISIN of the index to compare the portfolio against
+    benchmark: Option<String>,
+}
+
+impl TryFrom<RawPortfolio> for Portfolio {
+    type Error = String;
+
+    fn try_from(raw: RawPortfolio) -> Result<Self, Self::Error> {
+        let investments = raw
+            .investments
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| {
+                Investment::from_value(value)
+                    .map_err(|e| format!("Portfolio '{}', investments[{index}]: {e}", raw.name))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Portfolio {
+            name: raw.name,
+            investments,
+            target_allocation: raw.target_allocation,
+            tags: raw.tags,
+            benchmark: raw.benchmark,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone)]
+#[serde(try_from = "RawPortfolio")]
 pub struct Portfolio {
     pub name: String,
     pub investments: Vec<Investment>,
+    /// Target weights in percent by category, overriding the global target
+    pub target_allocation: Option<TargetAllocation>,
+    /// Labels for selecting the portfolio with `--tag`, inherited by its investments
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Symbol or ISIN of the index to compare the portfolio against
+    pub benchmark: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Difference in units below which transactions account for a holding
+const UNITS_EPSILON: f64 = 1e-6;
+
+impl Investment {
+    /// Returns the cash flows of a stock or fund from its transactions, with
+    /// purchases negative and its current `value` on `date` last. `None` unless
+    /// the transactions account for all units held.
+    pub fn transaction_cashflows(
+        &self,
+        value: f64,
+        date: NaiveDate,
+    ) -> Option<Vec<(NaiveDate, f64)>> {
+        let (transactions, units) = match self {
+            Investment::Stock(s) => (&s.transactions, s.units),
+            Investment::MutualFund(mf) => (&mf.transactions, mf.units),
+            _ => return None,
+        };
+        let recorded: f64 = transactions.iter().map(|t| t.units).sum();
+        if transactions.is_empty() || (recorded - units).abs() > UNITS_EPSILON {
+            return None;
+        }
+        let mut cashflows: Vec<(NaiveDate, f64)> = transactions
+            .iter()
+            .map(|t| (t.date, -t.units * t.price))
+            .collect();
+        cashflows.push((date, value));
+        Some(cashflows)
+    }
+
+    /// Returns the symbol, ISIN or name that identifies the holding.
+    pub fn identifier(&self) -> &str {
+        match self {
+            Investment::Stock(s) => &s.symbol,
+            Investment::MutualFund(mf) => &mf.isin,
+            Investment::Bond(b) => &b.name,
+            Investment::SmallSavings(s) => &s.name,
+            Investment::FixedDeposit(fd) => &fd.name,
+        }
+    }
+
+    /// Returns whether the holding can be bought or sold on demand, unlike
+    /// deposits, small savings schemes and bonds held to maturity.
+    pub fn is_tradable(&self) -> bool {
+        matches!(self, Investment::Stock(_) | Investment::MutualFund(_))
+    }
+
+    /// Returns the name of a holding identified by name rather than by a
+    /// symbol or ISIN.
+    fn name_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Investment::Stock(_) | Investment::MutualFund(_) => None,
+            Investment::Bond(b) => Some(&mut b.name),
+            Investment::SmallSavings(s) => Some(&mut s.name),
+            Investment::FixedDeposit(fd) => Some(&mut fd.name),
+        }
+    }
+
+    /// Returns the labels set on the investment itself.
+    pub fn tags(&self) -> &[String] {
+        match self {
+            Investment::Stock(s) => &s.tags,
+            Investment::MutualFund(mf) => &mf.tags,
+            Investment::Bond(b) => &b.tags,
+            Investment::SmallSavings(s) => &s.tags,
+            Investment::FixedDeposit(fd) => &fd.tags,
+        }
+    }
+}
+
+impl Portfolio {
+    /// Merges the holdings of all portfolios into one, summing the units or
+    /// value of investments with the same identifier.
+    ///
+    /// The first category set for a holding is kept. Fixed deposits are only
+    /// merged when their currency matches and neither has interest terms, bonds
+    /// when their terms and currency match, and small savings accounts never.
+    /// A holding kept apart under a name already taken gets its portfolio's
+    /// name appended.
+    ///
+    /// Target allocations, with `global` for portfolios without one, and
+    /// benchmarks are kept when the portfolios agree on them. Differing ones
+    /// are dropped from the merged portfolio, and a warning for each is
+    /// returned alongside it.
+    pub fn consolidate(
+        portfolios: &[Portfolio],
+        name: &str,
+        global: Option<&TargetAllocation>,
+    ) -> (Portfolio, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut target_allocation = portfolios.first().and_then(|p| p.target_allocation(global));
+        if let Some(other) = portfolios
+            .iter()
+            .find(|p| p.target_allocation(global) != target_allocation)
+        {
+            warnings.push(format!(
+                "Portfolios '{}' and '{}' have different target allocations, the consolidated view has none",
+                portfolios[0].name, other.name
+            ));
+            target_allocation = None;
+        }
+        let mut benchmarks = portfolios
+            .iter()
+            .filter_map(|p| p.benchmark.as_ref().map(|benchmark| (p, benchmark)));
+        let mut benchmark = benchmarks.next();
+        if let Some((first, first_benchmark)) = benchmark
+            && let Some((other, _)) = benchmarks.find(|(_, b)| *b != first_benchmark)
+        {
+            warnings.push(format!(
+                "Portfolios '{}' and '{}' have different benchmarks, the consolidated view has none",
+                first.name, other.name
+            ));
+            benchmark = None;
+        }
+
+        let mut investments: Vec<Investment> = Vec::new();
+        for portfolio in portfolios {
+            for investment in &portfolio.investments {
+                let existing = investments.iter_mut().find(|i| match (&**i, investment) {
+                    (Investment::Stock(a), Investment::Stock(b)) => a.symbol == b.symbol,
+                    (Investment::MutualFund(a), Investment::MutualFund(b)) => a.isin == b.isin,
+                    (Investment::FixedDeposit(a), Investment::FixedDeposit(b)) => {
+                        a.name == b.name
+                            && a.currency == b.currency
+                            && a.principal.is_none()
+                            && b.principal.is_none()
+                    }
+                    (Investment::Bond(a), Investment::Bond(b)) => {
+                        a.name == b.name && a.currency == b.currency && a.terms() == b.terms()
+                    }
+                    _ => false,
+                });
+                match (existing, investment) {
+                    (Some(Investment::Stock(a)), Investment::Stock(b)) => {
+                        a.units += b.units;
+                        a.category = a.category.take().or_else(|| b.category.clone());
+                        a.transactions.extend(b.transactions.iter().cloned());
+                    }
+                    (Some(Investment::MutualFund(a)), Investment::MutualFund(b)) => {
+                        a.units += b.units;
+                        a.category = a.category.take().or_else(|| b.category.clone());
+                        a.transactions.extend(b.transactions.iter().cloned());
+                    }
+                    (Some(Investment::FixedDeposit(a)), Investment::FixedDeposit(b)) => {
+                        a.value = Some(a.value.unwrap_or_default() + b.value.unwrap_or_default());
+                        a.category = a.category.take().or_else(|| b.category.clone());
+                    }
+                    (Some(Investment::Bond(a)), Investment::Bond(b)) => {
+                        a.units += b.units;
+                        a.category = a.category.take().or_else(|| b.category.clone());
+                    }
+                    _ => {
+                        let mut investment = investment.clone();
+                        let taken = |name: &str| investments.iter().any(|i| i.identifier() == name);
+                        if taken(investment.identifier())
+                            && let Some(name) = investment.name_mut()
+                        {
+                            *name = format!("{name} ({})", portfolio.name);
+                        }
+                        investments.push(investment);
+                    }
+                }
+            }
+        }
+        let merged = Portfolio {
+            name: name.to_string(),
+            investments,
+            target_allocation: target_allocation.cloned(),
+            tags: Vec::new(),
+            benchmark: benchmark.map(|(_, benchmark)| benchmark.clone()),
+        };
+        (merged, warnings)
+    }
+
+    /// Returns the portfolio's target allocation, or `global` if it has none.
+    pub fn target_allocation<'a>(
+        &'a self,
+        global: Option<&'a TargetAllocation>,
+    ) -> Option<&'a TargetAllocation> {
+        self.target_allocation.as_ref().or(global)
+    }
+}
+
+/// Selects portfolios by name and investments by tag.
+#[derive(Debug, Clone, Default)]
+pub struct PortfolioFilter {
+    /// Portfolio names or glob patterns such as `retirement-*`, matched ignoring case
+    pub names: Vec<String>,
+    /// Tags of which an investment, or its portfolio, must have at least one
+    pub tags: Vec<String>,
+}
+
+impl PortfolioFilter {
+    /// Returns the matching portfolios, keeping only investments with a matching tag.
+    ///
+    /// Portfolios left without investments by the tag filter are dropped.
+    pub fn apply(&self, portfolios: &[Portfolio]) -> Result<Vec<Portfolio>> {
+        let patterns = self
+            .names
+            .iter()
+            .map(|name| {
+                glob::Pattern::new(name)
+                    .with_context(|| format!("Invalid portfolio pattern '{name}'"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let options = glob::MatchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+        let has_tag = |tags: &[String]| {
+            tags.iter()
+                .any(|tag| self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+        };
+
+        let selected: Vec<Portfolio> = portfolios
+            .iter()
+            .filter(|p| {
+                patterns.is_empty()
+                    || patterns
+                        .iter()
+                        .any(|pattern| pattern.matches_with(&p.name, options))
+            })
+            .filter_map(|p| {
+                if self.tags.is_empty() || has_tag(&p.tags) {
+                    return Some(p.clone());
+                }
+                let investments: Vec<Investment> = p
+                    .investments
+                    .iter()
+                    .filter(|i| has_tag(i.tags()))
+                    .cloned()
+                    .collect();
+                (!investments.is_empty()).then(|| Portfolio {
+                    investments,
+                    ..p.clone()
+                })
+            })
+            .collect();
+
+        if selected.is_empty() && !(self.names.is_empty() && self.tags.is_empty()) {
+            bail!(
+                "No portfolios match the filters (portfolio: {:?}, tag: {:?})",
+                self.names,
+                self.tags
+            );
+        }
+        Ok(selected)
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone)]
 pub struct YahooProviderConfig {
+    /// Base URL of the provider's API
     pub base_url: String,
+    /// Maximum in-flight requests to this provider
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+    /// Per-request timeout in seconds
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone)]
 pub struct AmfiProviderConfig {
+    /// Base URL of the provider's API
     pub base_url: String,
+    /// Maximum in-flight requests to this provider
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+    /// Per-request timeout in seconds
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone)]
 pub struct ProvidersConfig {
+    /// Yahoo Finance, for stock prices and currency rates
     pub yahoo: Option<YahooProviderConfig>,
+    /// AMFI, for Indian mutual fund prices and metadata
     pub amfi: Option<AmfiProviderConfig>,
+    /// Maximum in-flight requests across all providers
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+    /// Default per-request timeout in seconds, unless overridden per provider
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
 }
 
 impl Default for ProvidersConfig {
@@ -61,21 +770,98 @@ impl Default for ProvidersConfig {
         ProvidersConfig {
             yahoo: Some(YahooProviderConfig {
                 base_url: "https://query1.finance.yahoo.com".to_string(),
+                max_concurrency: None,
+                timeout_secs: None,
             }),
             amfi: Some(AmfiProviderConfig {
                 base_url: "https://mf.captnemo.in".to_string(),
+                max_concurrency: None,
+                timeout_secs: None,
             }),
+            max_concurrency: None,
+            timeout_secs: None,
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Size limits for cached provider data. Top-level limits apply to every
+/// collection unless overridden by provider name (e.g. `yahoo`) under `collections`.
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone, Default)]
+pub struct CacheConfig {
+    #[serde(flatten)]
+    pub limits: CollectionLimits,
+    #[serde(default)]
+    pub collections: HashMap<String, CollectionLimits>,
+}
+
+impl CacheConfig {
+    pub fn limits_for(&self, collection: &str) -> CollectionLimits {
+        // Provider collections are namespaced as `<provider>#<source hash>`
+        let name = collection.split('#').next().unwrap_or(collection);
+        self.collections.get(name).copied().unwrap_or(self.limits)
+    }
+}
+
+/// Network settings applied to every provider's HTTP client.
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone, Default, PartialEq)]
+pub struct NetworkConfig {
+    /// Proxy URL for all requests, e.g. `http://proxy.corp:3128`
+    pub proxy: Option<String>,
+    /// Hosts or domains that bypass the proxy
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+    /// PEM bundle with extra root certificates, trusted in addition to the system roots
+    pub ca_bundle: Option<PathBuf>,
+    /// User agent sent with each request (defaults to `xmf/1.0`)
+    pub user_agent: Option<String>,
+    /// Headers sent with each request
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+/// Portfolios to track and settings for reporting on them.
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Clone)]
 pub struct AppConfig {
+    /// Format version of the config, upgraded with `xmf config migrate`
+    #[serde(default = "legacy_version")]
+    #[schemars(example = &migrate::CURRENT_VERSION)]
+    pub version: u32,
     pub portfolios: Vec<Portfolio>,
+    /// Price, currency and metadata providers
     #[serde(default)]
     pub providers: ProvidersConfig,
+    /// Currency all values are reported in
+    #[schemars(example = &"USD")]
     pub currency: String,
+    /// Directory for cached provider data, instead of the platform default
     pub data_path: Option<String>,
+    /// Size limits for cached provider data
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// Proxy, certificate and header settings for all requests
+    #[serde(default)]
+    pub network: NetworkConfig,
+    /// Target weights in percent by category for all portfolios
+    pub target_allocation: Option<TargetAllocation>,
+    /// Grouping of raw categories into asset classes
+    #[serde(default)]
+    pub asset_classes: AssetClassConfig,
+    /// Settings for `xmf returns`
+    #[serde(default)]
+    pub returns: ReturnsConfig,
+    /// Settings for `xmf risk`
+    #[serde(default)]
+    pub risk: RiskConfig,
+    /// Default benchmarks for funds by category
+    #[serde(default)]
+    pub benchmarks: BenchmarkConfig,
+    /// Settings for `xmf correlation`
+    #[serde(default)]
+    pub correlation: CorrelationConfig,
+}
+
+fn legacy_version() -> u32 {
+    migrate::LEGACY_VERSION
 }
 
 impl AppConfig {
@@ -91,6 +877,44 @@ impl AppConfig {
         Ok(proj_dirs.config_dir().join("config.yaml"))
     }
 
+    /// Finds a named config, `<name>.yaml` or `<name>.yml`, in the default
+    /// config directory or the directories the default config includes from.
+    pub fn named_config_path(name: &str) -> Result<PathBuf> {
+        let default_path = Self::default_config_path()?;
+        let mut dirs: Vec<PathBuf> = default_path
+            .parent()
+            .map(PathBuf::from)
+            .into_iter()
+            .collect();
+        if default_path.exists() {
+            for file in include::files(&default_path)? {
+                if let Some(dir) = file.parent()
+                    && !dirs.iter().any(|d| d == dir)
+                {
+                    dirs.push(dir.to_path_buf());
+                }
+            }
+        }
+
+        let extensions = ["yaml", "yml"];
+        dirs.iter()
+            .flat_map(|dir| {
+                extensions
+                    .iter()
+                    .map(move |ext| dir.join(format!("{name}.{ext}")))
+            })
+            .find(|path| path.exists())
+            .with_context(|| {
+                format!(
+                    "No config file found for name '{name}' with extensions {extensions:?} in {}",
+                    dirs.iter()
+                        .map(|d| d.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+
     pub fn default_data_path(&self) -> Result<PathBuf> {
         if let Some(custom_path) = &self.data_path {
             return Ok(PathBuf::from(custom_path));
@@ -101,11 +925,31 @@ impl AppConfig {
     }
 
     pub fn load_from_path<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
-        let config_str = fs::read_to_string(path.as_ref())
-            .with_context(|| format!("Failed to read config file: {}", path.as_ref().display()))?;
-
-        let config: Self = serde_yaml::from_str(&config_str)
-            .with_context(|| format!("Failed to parse config file: {}", path.as_ref().display()))?;
+        let value = include::load(path.as_ref())?;
+        migrate::version(&value)
+            .with_context(|| format!("Invalid config file: {}", path.as_ref().display()))?;
+        let config: Self = serde_yaml::from_value(value).with_context(|| {
+            format!(
+                "Failed to parse config file: {} (run `xmf config validate` for line numbers)",
+                path.as_ref().display()
+            )
+        })?;
+        for portfolio in &config.portfolios {
+            for investment in &portfolio.investments {
+                match investment {
+                    Investment::FixedDeposit(fd) => fd
+                        .validate()
+                        .with_context(|| format!("Invalid portfolio '{}'", portfolio.name))?,
+                    Investment::Bond(b) => b
+                        .validate()
+                        .with_context(|| format!("Invalid portfolio '{}'", portfolio.name))?,
+                    Investment::SmallSavings(s) => s.plan.validate().with_context(|| {
+                        format!("Invalid portfolio '{}': '{}'", portfolio.name, s.name)
+                    })?,
+                    _ => {}
+                }
+            }
+        }
         debug!("Successfully loaded config");
         Ok(config)
     }
@@ -114,6 +958,7 @@ impl AppConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::savings::Scheme;
 
     #[test]
     fn test_config_deserialization() {
@@ -181,7 +1026,7 @@ currency: "USD"
         assert_eq!(config.portfolios[2].investments.len(), 3);
         if let Investment::FixedDeposit(fd) = &config.portfolios[2].investments[0] {
             assert_eq!(fd.name, "FD with Bank of Rust");
-            assert_eq!(fd.value, 50000.0);
+            assert_eq!(fd.value, Some(50000.0));
             assert_eq!(fd.currency.as_deref(), Some("INR"));
         } else {
             panic!("Expected a fixed deposit investment");
@@ -194,7 +1039,7 @@ currency: "USD"
         }
         if let Investment::FixedDeposit(fd) = &config.portfolios[2].investments[2] {
             assert_eq!(fd.name, "FD without Currency");
-            assert_eq!(fd.value, 30000.0);
+            assert_eq!(fd.value, Some(30000.0));
             assert!(fd.currency.is_none());
         } else {
             panic!("Expected a fixed deposit investment");
@@ -234,4 +1079,513 @@ currency: "EUR"
         );
         assert_eq!(config_with_providers.currency, "EUR");
     }
+
+    #[test]
+    fn test_cache_config_deserialization() {
+        let yaml_str = r#"
+portfolios: []
+currency: "USD"
+cache:
+  max_entries: 1000
+  collections:
+    yahoo:
+      max_bytes: 1048576
+"#;
+        let config: AppConfig = serde_yaml::from_str(yaml_str).unwrap();
+        assert_eq!(config.cache.limits_for("amfi").max_entries, Some(1000));
+        assert_eq!(config.cache.limits_for("amfi").max_bytes, None);
+        assert_eq!(config.cache.limits_for("yahoo").max_entries, None);
+        assert_eq!(config.cache.limits_for("yahoo").max_bytes, Some(1048576));
+        assert_eq!(
+            config.cache.limits_for("yahoo#0123456789abcdef").max_bytes,
+            Some(1048576)
+        );
+    }
+
+    #[test]
+    fn test_network_config_deserialization() {
+        let yaml_str = r#"
+portfolios: []
+currency: "USD"
+network:
+  proxy: "http://proxy.corp:3128"
+  no_proxy: ["localhost", ".internal"]
+  ca_bundle: "/etc/ssl/corp.pem"
+  user_agent: "corp-agent"
+  headers:
+    X-Api-Key: "secret"
+"#;
+        let config: AppConfig = serde_yaml::from_str(yaml_str).unwrap();
+        let network = config.network;
+        assert_eq!(network.proxy.as_deref(), Some("http://proxy.corp:3128"));
+        assert_eq!(network.no_proxy, vec!["localhost", ".internal"]);
+        assert_eq!(network.ca_bundle, Some(PathBuf::from("/etc/ssl/corp.pem")));
+        assert_eq!(network.user_agent.as_deref(), Some("corp-agent"));
+        assert_eq!(network.headers["X-Api-Key"], "secret");
+
+        let config: AppConfig = serde_yaml::from_str("portfolios: []\ncurrency: USD").unwrap();
+        assert_eq!(config.network, NetworkConfig::default());
+    }
+
+    #[test]
+    fn test_asset_classes_deserialization() {
+        use crate::core::allocation::AssetCategory;
+
+        let yaml_str = r#"
+portfolios: []
+currency: "INR"
+asset_classes:
+  hybrid_equity_ratio: 65
+  rules:
+    - pattern: "gold"
+      asset_class: Gold
+    - pattern: "international"
+      asset_class: Equity
+"#;
+        let config: AppConfig = serde_yaml::from_str(yaml_str).unwrap();
+        let asset_classes = config.asset_classes;
+        assert_eq!(asset_classes.hybrid_equity_ratio, Some(65.0));
+        assert_eq!(asset_classes.rules.len(), 2);
+        assert_eq!(asset_classes.classify("Gold FoF"), AssetCategory::Gold);
+
+        let config: AppConfig = serde_yaml::from_str("portfolios: []\ncurrency: USD").unwrap();
+        assert!(config.asset_classes.rules.is_empty());
+        assert_eq!(config.asset_classes.hybrid_equity_ratio, None);
+    }
+
+    #[test]
+    fn test_fixed_deposit_terms() {
+        let yaml_str = r#"
+portfolios:
+  - name: "Deposits"
+    investments:
+      - name: "SBI FD"
+        principal: 100000
+        rate: 7.1
+        start_date: 2024-01-15
+        maturity_date: 2026-01-15
+        compounding: monthly
+      - name: "Old FD"
+        value: 5000
+currency: "INR"
+"#;
+        let config: AppConfig = serde_yaml::from_str(yaml_str).unwrap();
+        let Investment::FixedDeposit(fd) = &config.portfolios[0].investments[0] else {
+            panic!("Expected a fixed deposit investment");
+        };
+        assert!(fd.validate().is_ok());
+        let terms = fd.terms().unwrap();
+        assert_eq!(terms.compounding, Compounding::Monthly);
+        assert!(!terms.payout);
+        let start = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        assert_eq!(fd.value_on(start), 100000.0);
+        assert!(fd.value_on(NaiveDate::from_ymd_opt(2025, 1, 15).unwrap()) > 107000.0);
+
+        let Investment::FixedDeposit(manual) = &config.portfolios[0].investments[1] else {
+            panic!("Expected a fixed deposit investment");
+        };
+        assert!(manual.terms().is_none());
+        assert_eq!(manual.value_on(start), 5000.0);
+
+        let missing_rate = FixedDepositInvestment {
+            name: "FD".to_string(),
+            principal: Some(1000.0),
+            start_date: Some(start),
+            ..Default::default()
+        };
+        assert!(missing_rate.validate().is_err());
+        let empty = FixedDepositInvestment {
+            name: "FD".to_string(),
+            ..Default::default()
+        };
+        assert!(empty.validate().is_err());
+        let backwards = FixedDepositInvestment {
+            rate: Some(7.0),
+            maturity_date: NaiveDate::from_ymd_opt(2023, 1, 1),
+            ..missing_rate
+        };
+        assert!(backwards.validate().is_err());
+    }
+
+    #[test]
+    fn test_investment_type_tag() {
+        let yaml_str = r#"
+portfolios:
+  - name: "Tagged"
+    investments:
+      - type: mutual_fund
+        isin: "INF123"
+        units: 10
+      - type: fixed_deposit
+        name: "FD"
+        value: 1000
+      - symbol: "AAPL"
+        units: 5
+currency: "USD"
+"#;
+        let config: AppConfig = serde_yaml::from_str(yaml_str).unwrap();
+        let investments = &config.portfolios[0].investments;
+        assert!(matches!(investments[0], Investment::MutualFund(_)));
+        assert!(matches!(investments[1], Investment::FixedDeposit(_)));
+        assert!(matches!(investments[2], Investment::Stock(_)));
+
+        let serialized = serde_yaml::to_string(&investments[0]).unwrap();
+        assert!(serialized.contains("type: mutual_fund"));
+    }
+
+    #[test]
+    fn test_investment_errors_name_portfolio_and_field() {
+        let error = |investment: &str| {
+            let yaml_str = format!(
+                "portfolios:\n  - name: \"Mine\"\n    investments:\n      - symbol: \"AAPL\"\n        units: 1\n      - {investment}\ncurrency: \"USD\"\n"
+            );
+            serde_yaml::from_str::<AppConfig>(&yaml_str)
+                .unwrap_err()
+                .to_string()
+        };
+
+        let typo = error("{ isn: \"INF123\", units: 10 }");
+        assert!(typo.contains("Portfolio 'Mine', investments[1]"), "{typo}");
+        assert!(typo.contains("cannot tell the type"), "{typo}");
+
+        let missing = error("{ isin: \"INF123\" }");
+        assert!(missing.contains("missing field `units`"), "{missing}");
+
+        let invalid = error("{ symbol: \"MSFT\", units: \"ten\" }");
+        assert!(invalid.contains("invalid field `units`"), "{invalid}");
+
+        let unknown = error("{ type: stock, symbol: \"MSFT\", units: 1, categry: \"x\" }");
+        assert!(unknown.contains("unknown field `categry`"), "{unknown}");
+
+        let bad_type = error("{ type: crypto, name: \"BTC\" }");
+        assert!(bad_type.contains("unknown type crypto"), "{bad_type}");
+    }
+
+    #[test]
+    fn test_bond_investment() {
+        let yaml_str = r#"
+portfolios:
+  - name: "Bonds"
+    investments:
+      - name: "GS 2033"
+        symbol: "GS2033.NS"
+        units: 10
+        face_value: 100
+        coupon_rate: 7.26
+        issue_date: 2023-02-06
+        maturity_date: 2033-02-06
+      - symbol: "AAPL"
+        units: 5
+currency: "INR"
+"#;
+        let config: AppConfig = serde_yaml::from_str(yaml_str).unwrap();
+        let Investment::Bond(bond) = &config.portfolios[0].investments[0] else {
+            panic!("Expected a bond investment");
+        };
+        assert_eq!(bond.frequency, Compounding::HalfYearly);
+        assert_eq!(bond.manual_price(), 100.0);
+        assert!(bond.validate().is_ok());
+        assert!(matches!(
+            config.portfolios[0].investments[1],
+            Investment::Stock(_)
+        ));
+
+        let backwards = BondInvestment {
+            maturity_date: bond.issue_date,
+            ..bond.clone()
+        };
+        assert!(backwards.validate().is_err());
+    }
+
+    #[test]
+    fn test_small_savings_investment() {
+        let yaml_str = r#"
+portfolios:
+  - name: "Savings"
+    investments:
+      - name: "PPF"
+        scheme: ppf
+        start_date: 2023-04-01
+        contributions:
+          - date: 2023-04-05
+            amount: 150000
+        rates:
+          - from: 2023-04-01
+            rate: 7.1
+      - name: "RD"
+        scheme: rd
+        start_date: 2023-04-01
+        maturity_date: 2028-04-01
+        recurring:
+          amount: 5000
+        rates:
+          - from: 2023-04-01
+            rate: 6.7
+currency: "INR"
+"#;
+        let config: AppConfig = serde_yaml::from_str(yaml_str).unwrap();
+        let Investment::SmallSavings(ppf) = &config.portfolios[0].investments[0] else {
+            panic!("Expected a small savings investment");
+        };
+        assert_eq!(ppf.plan.scheme, Scheme::Ppf);
+        assert!(ppf.plan.validate().is_ok());
+        let year_end = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        assert!((ppf.plan.value_on(year_end) - 160650.0).abs() < 1e-6);
+        let cashflows = ppf.cashflows(year_end);
+        assert_eq!(cashflows.len(), 2);
+        assert_eq!(cashflows[0].1, -150000.0);
+
+        let Investment::SmallSavings(rd) = &config.portfolios[0].investments[1] else {
+            panic!("Expected a small savings investment");
+        };
+        let recurring = rd.plan.recurring.unwrap();
+        assert_eq!(recurring.frequency, Compounding::Monthly);
+        assert_eq!(rd.plan.contributions_until(year_end).len(), 12);
+
+        let invalid = yaml_str.replace("rate: 6.7", "rate: -1");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(&path, invalid).unwrap();
+        let err = AppConfig::load_from_path(&path).unwrap_err();
+        assert!(format!("{err:#}").contains("'RD'"));
+    }
+
+    #[test]
+    fn test_consolidate_portfolios() {
+        let yaml_str = r#"
+portfolios:
+  - name: "Mine"
+    benchmark: "^NSEI"
+    investments:
+      - symbol: "AAPL"
+        units: 10
+      - isin: "INF123"
+        units: 5.5
+        category: "Equity"
+      - name: "FD"
+        value: 1000
+        currency: "INR"
+      - type: bond
+        name: "GS 2033"
+        units: 100
+        face_value: 100.0
+        coupon_rate: 7.26
+        issue_date: 2023-02-06
+        maturity_date: 2033-02-06
+      - type: small_savings
+        name: "PPF"
+        scheme: ppf
+        start_date: 2020-04-01
+        rates:
+          - from: 2020-04-01
+            rate: 7.1
+  - name: "Spouse"
+    investments:
+      - symbol: "AAPL"
+        units: 2
+        category: "Tech"
+      - isin: "INF123"
+        units: 4.5
+      - name: "FD"
+        value: 500
+        currency: "USD"
+      - type: bond
+        name: "GS 2033"
+        units: 50
+        face_value: 100.0
+        coupon_rate: 7.26
+        issue_date: 2023-02-06
+        maturity_date: 2033-02-06
+        category: "Gilt"
+      - type: small_savings
+        name: "PPF"
+        scheme: ppf
+        start_date: 2021-04-01
+        rates:
+          - from: 2021-04-01
+            rate: 7.1
+currency: "INR"
+"#;
+        let config: AppConfig = serde_yaml::from_str(yaml_str).unwrap();
+        let (merged, warnings) = Portfolio::consolidate(&config.portfolios, "Household", None);
+        assert!(warnings.is_empty());
+
+        assert_eq!(merged.name, "Household");
+        assert_eq!(merged.benchmark.as_deref(), Some("^NSEI"));
+        assert_eq!(merged.target_allocation, None);
+        let identifiers: Vec<&str> = merged.investments.iter().map(|i| i.identifier()).collect();
+        assert_eq!(
+            identifiers,
+            [
+                "AAPL",
+                "INF123",
+                "FD",
+                "GS 2033",
+                "PPF",
+                "FD (Spouse)",
+                "PPF (Spouse)"
+            ]
+        );
+        match &merged.investments[0] {
+            Investment::Stock(s) => {
+                assert_eq!(s.units, 12.0);
+                assert_eq!(s.category.as_deref(), Some("Tech"));
+            }
+            other => panic!("Expected stock, got {other:?}"),
+        }
+        match &merged.investments[1] {
+            Investment::MutualFund(mf) => {
+                assert_eq!(mf.units, 10.0);
+                assert_eq!(mf.category.as_deref(), Some("Equity"));
+            }
+            other => panic!("Expected mutual fund, got {other:?}"),
+        }
+        match &merged.investments[3] {
+            Investment::Bond(b) => {
+                assert_eq!(b.units, 150.0);
+                assert_eq!(b.category.as_deref(), Some("Gilt"));
+            }
+            other => panic!("Expected bond, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_consolidate_drops_conflicting_targets() {
+        let yaml_str = r#"
+target_allocation:
+  Equity: 60
+  Debt: 40
+portfolios:
+  - name: "Mine"
+    benchmark: "^NSEI"
+    investments:
+      - symbol: "AAPL"
+        units: 10
+  - name: "Spouse"
+    target_allocation:
+      Equity: 60
+      Debt: 40
+    investments:
+      - symbol: "MSFT"
+        units: 2
+currency: "INR"
+"#;
+        let mut config: AppConfig = serde_yaml::from_str(yaml_str).unwrap();
+        let global = config.target_allocation.clone();
+        let (merged, warnings) =
+            Portfolio::consolidate(&config.portfolios, "Household", global.as_ref());
+        assert_eq!(merged.target_allocation, global);
+        assert_eq!(merged.benchmark.as_deref(), Some("^NSEI"));
+        assert!(warnings.is_empty());
+
+        // The first portfolio falls back to a global target it no longer matches
+        let (merged, warnings) = Portfolio::consolidate(&config.portfolios, "Household", None);
+        assert_eq!(merged.target_allocation, None);
+        assert_eq!(merged.benchmark.as_deref(), Some("^NSEI"));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("different target allocations"));
+
+        config.portfolios[1].benchmark = Some("^BSESN".to_string());
+        let (merged, warnings) =
+            Portfolio::consolidate(&config.portfolios, "Household", global.as_ref());
+        assert_eq!(merged.target_allocation, global);
+        assert_eq!(merged.benchmark, None);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("different benchmarks"));
+    }
+
+    #[test]
+    fn test_transaction_cashflows() {
+        let yaml_str = r#"
+isin: "INF179KB1HU9"
+units: 15
+transactions:
+  - date: 2023-01-01
+    units: 10
+    price: 100
+  - date: 2023-07-01
+    units: 5
+    price: 120
+"#;
+        let mut investment =
+            Investment::from_value(serde_yaml::from_str(yaml_str).unwrap()).unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        assert_eq!(
+            investment.transaction_cashflows(2000.0, today),
+            Some(vec![
+                (NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(), -1000.0),
+                (NaiveDate::from_ymd_opt(2023, 7, 1).unwrap(), -600.0),
+                (today, 2000.0),
+            ])
+        );
+
+        // Units held before the first recorded transaction have no cost
+        if let Investment::MutualFund(mf) = &mut investment {
+            mf.units = 20.0;
+        }
+        assert_eq!(investment.transaction_cashflows(2000.0, today), None);
+    }
+
+    #[test]
+    fn test_portfolio_filter() {
+        let yaml_str = r#"
+portfolios:
+  - name: "Retirement-US"
+    tags: ["long-term"]
+    investments:
+      - symbol: "VTI"
+        units: 10
+  - name: "Retirement-IN"
+    investments:
+      - isin: "INF123"
+        units: 5
+        tags: ["Long-Term"]
+      - isin: "INF456"
+        units: 5
+  - name: "Trading"
+    investments:
+      - symbol: "TSLA"
+        units: 1
+currency: "USD"
+"#;
+        let config: AppConfig = serde_yaml::from_str(yaml_str).unwrap();
+        let names = |filter: &PortfolioFilter| -> Vec<String> {
+            filter
+                .apply(&config.portfolios)
+                .unwrap()
+                .into_iter()
+                .map(|p| p.name)
+                .collect()
+        };
+
+        let all = PortfolioFilter::default();
+        assert_eq!(names(&all).len(), 3);
+
+        let by_name = PortfolioFilter {
+            names: vec!["retirement-*".to_string()],
+            tags: Vec::new(),
+        };
+        assert_eq!(names(&by_name), vec!["Retirement-US", "Retirement-IN"]);
+
+        let by_tag = PortfolioFilter {
+            names: Vec::new(),
+            tags: vec!["long-term".to_string()],
+        };
+        let selected = by_tag.apply(&config.portfolios).unwrap();
+        assert_eq!(selected.len(), 2);
+        assert_eq!(selected[0].investments.len(), 1);
+        assert_eq!(selected[1].investments.len(), 1);
+        assert_eq!(selected[1].investments[0].identifier(), "INF123");
+
+        let none = PortfolioFilter {
+            names: vec!["Trading".to_string()],
+            tags: vec!["long-term".to_string()],
+        };
+        assert!(none.apply(&config.portfolios).is_err());
+        let invalid = PortfolioFilter {
+            names: vec!["[".to_string()],
+            tags: Vec::new(),
+        };
+        assert!(invalid.apply(&config.portfolios).is_err());
+    }
 }
@@ -0,0 +1,120 @@
+//! Correlation of returns between holdings.
+
+use crate::core::benchmark::align_series;
+use crate::core::risk::daily_returns;
+use chrono::{Datelike, NaiveDate};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Correlation above which a pair of holdings is flagged as concentration risk.
+pub const DEFAULT_THRESHOLD: f64 = 0.8;
+
+/// Settings for `xmf correlation`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub struct CorrelationConfig {
+    /// Correlation above which pairs are highlighted
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+}
+
+fn default_threshold() -> f64 {
+    DEFAULT_THRESHOLD
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        CorrelationConfig {
+            threshold: DEFAULT_THRESHOLD,
+        }
+    }
+}
+
+/// Keeps the last value of each ISO week of a date-sorted series.
+pub fn weekly_series(series: &[(NaiveDate, f64)]) -> Vec<(NaiveDate, f64)> {
+    let mut weekly: Vec<(NaiveDate, f64)> = Vec::new();
+    for &(date, value) in series {
+        match weekly.last_mut() {
+            Some(last) if last.0.iso_week() == date.iso_week() => *last = (date, value),
+            _ => weekly.push((date, value)),
+        }
+    }
+    weekly
+}
+
+/// Pearson correlation of the returns of two series on their common dates.
+///
+/// Returns `None` with fewer than three common returns or when either series is flat.
+pub fn correlation(a: &[(NaiveDate, f64)], b: &[(NaiveDate, f64)]) -> Option<f64> {
+    let (a, b) = align_series(a, b);
+    let returns_a = daily_returns(&a);
+    let returns_b = daily_returns(&b);
+    if returns_a.len() < 3 || returns_a.len() != returns_b.len() {
+        return None;
+    }
+
+    let count = returns_a.len() as f64;
+    let mean_a = returns_a.iter().sum::<f64>() / count;
+    let mean_b = returns_b.iter().sum::<f64>() / count;
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (ra, rb) in returns_a.iter().zip(&returns_b) {
+        covariance += (ra - mean_a) * (rb - mean_b);
+        variance_a += (ra - mean_a).powi(2);
+        variance_b += (rb - mean_b).powi(2);
+    }
+    if variance_a <= 0.0 || variance_b <= 0.0 {
+        return None;
+    }
+    Some((covariance / (variance_a * variance_b).sqrt()).clamp(-1.0, 1.0))
+}
+
+/// Correlations between every pair of series, each pair aligned on its common dates.
+pub fn correlation_matrix(series: &[Vec<(NaiveDate, f64)>]) -> Vec<Vec<Option<f64>>> {
+    let n = series.len();
+    let mut matrix = vec![vec![None; n]; n];
+    for i in 0..n {
+        matrix[i][i] = Some(1.0);
+        for j in (i + 1)..n {
+            let value = correlation(&series[i], &series[j]);
+            matrix[i][j] = value;
+            matrix[j][i] = value;
+        }
+    }
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(values: &[f64]) -> Vec<(NaiveDate, f64)> {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (start + chrono::Duration::days(i as i64), *v))
+            .collect()
+    }
+
+    #[test]
+    fn test_correlation_matrix() {
+        let a = series(&[100.0, 110.0, 99.0, 108.9, 120.0]);
+        let doubled = series(&[100.0, 120.0, 96.0, 115.2, 139.0]);
+        let inverse = series(&[100.0, 90.0, 99.0, 89.1, 80.0]);
+        let flat = series(&[100.0; 5]);
+
+        let matrix = correlation_matrix(&[a, doubled, inverse, flat]);
+        assert_eq!(matrix[0][0], Some(1.0));
+        assert!(matrix[0][1].unwrap() > 0.99);
+        assert_eq!(matrix[0][1], matrix[1][0]);
+        assert!(matrix[0][2].unwrap() < -0.99);
+        assert_eq!(matrix[0][3], None);
+    }
+
+    #[test]
+    fn test_weekly_series() {
+        // 2024-01-01 is a Monday
+        let daily = series(&(1..=10).map(f64::from).collect::<Vec<_>>());
+        let weekly = weekly_series(&daily);
+        assert_eq!(weekly, vec![daily[6], daily[9]]);
+    }
+}
@@ -1,9 +1,9 @@
 //! Currency conversion abstractions
 
-use anyhow::Result;
+use crate::core::price::ProviderResult;
 use async_trait::async_trait;
 
 #[async_trait]
 pub trait CurrencyRateProvider: Send + Sync {
-    async fn get_rate(&self, from: &str, to: &str) -> Result<f64>;
+    async fn get_rate(&self, from: &str, to: &str) -> ProviderResult<f64>;
 }
@@ -0,0 +1,151 @@
+//! Interest accrual on fixed deposits.
+
+use chrono::{Months, NaiveDate};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Days per year used for interest on broken periods, as Indian banks do.
+const INTEREST_DAYS_PER_YEAR: f64 = 365.0;
+
+/// How often interest is compounded, or paid out for payout deposits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Compounding {
+    Monthly,
+    #[default]
+    Quarterly,
+    HalfYearly,
+    Annual,
+}
+
+impl Compounding {
+    /// Returns the number of months in each interest period.
+    pub fn months(self) -> u32 {
+        match self {
+            Compounding::Monthly => 1,
+            Compounding::Quarterly => 3,
+            Compounding::HalfYearly => 6,
+            Compounding::Annual => 12,
+        }
+    }
+}
+
+/// Terms of a deposit earning interest from its start date.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepositTerms {
+    pub principal: f64,
+    /// Annual interest rate in percent
+    pub rate: f64,
+    pub start: NaiveDate,
+    /// Interest stops accruing on this date
+    pub maturity: Option<NaiveDate>,
+    pub compounding: Compounding,
+    /// Interest is paid out each period instead of reinvested
+    pub payout: bool,
+}
+
+impl DepositTerms {
+    /// Returns the value on `date`: the principal plus interest not yet paid out.
+    ///
+    /// Interest compounds at the end of each full period from the start date,
+    /// with simple interest for the days since. Payout deposits only hold the
+    /// interest accrued since the last payout.
+    pub fn value_on(&self, date: NaiveDate) -> f64 {
+        let date = self.maturity.map_or(date, |maturity| date.min(maturity));
+        if date <= self.start {
+            return self.principal;
+        }
+
+        let months = self.compounding.months();
+        let period_end =
+            |periods: u32| self.start.checked_add_months(Months::new(months * periods));
+        let mut periods = 0;
+        while period_end(periods + 1).is_some_and(|end| end <= date) {
+            periods += 1;
+        }
+        let last_period_end = period_end(periods).unwrap_or(self.start);
+
+        let rate = self.rate / 100.0;
+        let balance = if self.payout {
+            self.principal
+        } else {
+            self.principal * (1.0 + rate * f64::from(months) / 12.0).powi(periods as i32)
+        };
+        let broken_days = (date - last_period_end).num_days() as f64;
+        balance * (1.0 + rate * broken_days / INTEREST_DAYS_PER_YEAR)
+    }
+
+    /// Returns the value at maturity, if the deposit has a maturity date.
+    pub fn maturity_value(&self) -> Option<f64> {
+        self.maturity.map(|maturity| self.value_on(maturity))
+    }
+
+    /// Returns the value on each day from the start date through `end`.
+    pub fn value_series(&self, end: NaiveDate) -> Vec<(NaiveDate, f64)> {
+        self.start
+            .iter_days()
+            .take_while(|date| *date <= end)
+            .map(|date| (date, self.value_on(date)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn terms(compounding: Compounding, payout: bool) -> DepositTerms {
+        DepositTerms {
+            principal: 100_000.0,
+            rate: 8.0,
+            start: date(2023, 1, 15),
+            maturity: Some(date(2025, 1, 15)),
+            compounding,
+            payout,
+        }
+    }
+
+    #[test]
+    fn test_cumulative_deposit_compounds_per_period() {
+        let deposit = terms(Compounding::Quarterly, false);
+        assert_eq!(deposit.value_on(date(2023, 1, 1)), 100_000.0);
+        assert!((deposit.value_on(date(2023, 4, 15)) - 102_000.0).abs() < 1e-6);
+        // One full year is four quarters of 2%
+        let year = 100_000.0 * 1.02f64.powi(4);
+        assert!((deposit.value_on(date(2024, 1, 15)) - year).abs() < 1e-6);
+        // Simple interest for the 31 days after the last quarter
+        let broken = year * (1.0 + 0.08 * 31.0 / 365.0);
+        assert!((deposit.value_on(date(2024, 2, 15)) - broken).abs() < 1e-6);
+
+        let maturity = 100_000.0 * 1.02f64.powi(8);
+        assert!((deposit.maturity_value().unwrap() - maturity).abs() < 1e-6);
+        assert_eq!(
+            deposit.value_on(date(2030, 1, 1)),
+            deposit.maturity_value().unwrap()
+        );
+
+        let annual = terms(Compounding::Annual, false);
+        assert!((annual.maturity_value().unwrap() - 116_640.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_payout_deposit_keeps_principal() {
+        let deposit = terms(Compounding::Monthly, true);
+        assert_eq!(deposit.value_on(date(2024, 3, 15)), 100_000.0);
+        let accrued = 100_000.0 * (1.0 + 0.08 * 10.0 / 365.0);
+        assert!((deposit.value_on(date(2024, 3, 25)) - accrued).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_value_series() {
+        let deposit = terms(Compounding::Quarterly, false);
+        let series = deposit.value_series(date(2023, 1, 24));
+        assert_eq!(series.len(), 10);
+        assert_eq!(series[0], (date(2023, 1, 15), 100_000.0));
+        assert!(series.windows(2).all(|w| w[1].1 > w[0].1));
+    }
+}
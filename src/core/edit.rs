@@ -0,0 +1,554 @@
+//! Edits the investments of a portfolio in a config file in place, keeping
+//! its comments and formatting.
+
+use crate::core::config::Investment;
+use crate::core::include;
+use crate::core::validate::{Path, locate};
+use anyhow::{Context, Result, bail};
+use serde_yaml::Value;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// Identifies an investment by the field it is keyed on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvestmentId {
+    Isin(String),
+    Symbol(String),
+    Name(String),
+}
+
+impl InvestmentId {
+    pub fn field(&self) -> &'static str {
+        match self {
+            InvestmentId::Isin(_) => "isin",
+            InvestmentId::Symbol(_) => "symbol",
+            InvestmentId::Name(_) => "name",
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match self {
+            InvestmentId::Isin(v) | InvestmentId::Symbol(v) | InvestmentId::Name(v) => v,
+        }
+    }
+
+    /// Returns the `type` of the investments this identifies, unless it is
+    /// ambiguous.
+    pub fn investment_type(&self) -> Option<&'static str> {
+        match self {
+            InvestmentId::Isin(_) => Some("mutual_fund"),
+            InvestmentId::Symbol(_) => Some("stock"),
+            InvestmentId::Name(_) => None,
+        }
+    }
+
+    /// Returns whether this identifies a parsed investment.
+    pub fn identifies(&self, investment: &Investment) -> bool {
+        let value = match (self, investment) {
+            (InvestmentId::Isin(_), Investment::MutualFund(mf)) => &mf.isin,
+            (InvestmentId::Symbol(_), Investment::Stock(s)) => &s.symbol,
+            (InvestmentId::Name(_), Investment::FixedDeposit(fd)) => &fd.name,
+            (InvestmentId::Name(_), Investment::SmallSavings(s)) => &s.name,
+            (InvestmentId::Name(_), Investment::Bond(b)) => &b.name,
+            _ => return false,
+        };
+        value.eq_ignore_ascii_case(self.value())
+    }
+
+    fn matches(&self, investment: &Value) -> bool {
+        investment
+            .get(self.field())
+            .and_then(Value::as_str)
+            .is_some_and(|v| v.eq_ignore_ascii_case(self.value()))
+    }
+}
+
+impl fmt::Display for InvestmentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} '{}'", self.field(), self.value())
+    }
+}
+
+/// A portfolio in one of the files of a config, and that file's text.
+#[derive(Debug)]
+pub struct PortfolioFile {
+    pub file: PathBuf,
+    pub text: String,
+    name: String,
+    path: Path,
+    investments: Vec<Value>,
+}
+
+/// Finds the file defining a portfolio, ignoring case, among the config file
+/// and the files it includes.
+pub fn find_portfolio(config_path: &std::path::Path, name: &str) -> Result<PortfolioFile> {
+    search(config_path, name)?.with_context(|| {
+        format!(
+            "No portfolio named '{name}' in {} or the files it includes",
+            config_path.display()
+        )
+    })
+}
+
+/// Finds a portfolio like `find_portfolio`, or adds an empty one to the end
+/// of the config file's portfolios. Returns whether it was added, in which
+/// case the file itself is left unchanged.
+pub fn find_or_add_portfolio(
+    config_path: &std::path::Path,
+    name: &str,
+) -> Result<(PortfolioFile, bool)> {
+    if let Some(portfolio) = search(config_path, name)? {
+        return Ok((portfolio, false));
+    }
+    let text = fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read config file: {}", config_path.display()))?;
+    let text = add_portfolio(&text, name)?;
+    let portfolio = PortfolioFile::parse(config_path.to_path_buf(), text, name)?
+        .context("Portfolio not found after adding it")?;
+    Ok((portfolio, true))
+}
+
+fn search(config_path: &std::path::Path, name: &str) -> Result<Option<PortfolioFile>> {
+    for file in include::files(config_path)? {
+        let text = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read config file: {}", file.display()))?;
+        if let Some(portfolio) = PortfolioFile::parse(file, text, name)? {
+            return Ok(Some(portfolio));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the text of a config file with an empty portfolio added to the
+/// end of its portfolios.
+pub fn add_portfolio(text: &str, name: &str) -> Result<String> {
+    let root: Value = serde_yaml::from_str(text).context("Failed to parse config file")?;
+    let mut lines = split_lines(text);
+    let portfolio = |prefix: &str| {
+        let indent = " ".repeat(prefix.len());
+        [
+            format!("{prefix}name: {}", quote(name)),
+            format!("{indent}investments: []"),
+        ]
+    };
+    let key = Path::default().key("portfolios");
+    match root.get("portfolios").and_then(Value::as_sequence) {
+        Some(portfolios) if !portfolios.is_empty() => {
+            let item = item_lines(text, &key.index(portfolios.len() - 1))?;
+            let prefix = lines[item.start][..item.key_column].to_string();
+            lines.splice(item.end..item.end, portfolio(&prefix));
+        }
+        Some(_) => {
+            // Turn `portfolios: []` into a block list
+            let location = locate(text, &key).context("Config has no portfolios")?;
+            let line = &lines[location.line - 1];
+            let indent = line[..location.column - 1].to_string();
+            let value = line[location.column - 1..]
+                .trim_start_matches("portfolios:")
+                .trim();
+            if !value.starts_with("[]") {
+                bail!("Cannot edit the portfolios list at line {}", location.line);
+            }
+            lines[location.line - 1] = format!("{indent}portfolios:");
+            let new_lines = portfolio(&format!("{indent}  - "));
+            lines.splice(location.line..location.line, new_lines);
+        }
+        None => {
+            lines.push("portfolios:".to_string());
+            lines.extend(portfolio("  - "));
+        }
+    }
+    Ok(join_lines(lines, text))
+}
+
+impl PortfolioFile {
+    /// Finds a portfolio in the text of one of the files of a config.
+    fn parse(file: PathBuf, text: String, name: &str) -> Result<Option<PortfolioFile>> {
+        let root: Value = serde_yaml::from_str(&text)
+            .with_context(|| format!("Failed to parse config file: {}", file.display()))?;
+        let has_name = |p: &Value| {
+            p.get("name")
+                .and_then(Value::as_str)
+                .is_some_and(|n| n.eq_ignore_ascii_case(name))
+        };
+        let found = if root.get("investments").is_some() && has_name(&root) {
+            Some((Path::default(), &root))
+        } else {
+            root.get("portfolios")
+                .and_then(Value::as_sequence)
+                .and_then(|portfolios| portfolios.iter().enumerate().find(|(_, p)| has_name(p)))
+                .map(|(i, p)| (Path::default().key("portfolios").index(i), p))
+        };
+        let Some((path, portfolio)) = found else {
+            return Ok(None);
+        };
+        let investments = portfolio
+            .get("investments")
+            .and_then(Value::as_sequence)
+            .cloned()
+            .unwrap_or_default();
+        Ok(Some(PortfolioFile {
+            file,
+            text,
+            name: name.to_string(),
+            path,
+            investments,
+        }))
+    }
+
+    /// Returns the portfolio as it is in an edited text of its file.
+    pub fn with_text(&self, text: String) -> Result<PortfolioFile> {
+        PortfolioFile::parse(self.file.clone(), text, &self.name)?
+            .context("The edited config no longer has the portfolio")
+    }
+
+    /// Returns the index of an investment in the portfolio.
+    pub fn find(&self, id: &InvestmentId) -> Option<usize> {
+        self.investments.iter().position(|i| id.matches(i))
+    }
+
+    /// Returns the units held of an investment.
+    pub fn units(&self, index: usize) -> Option<f64> {
+        self.investments.get(index)?.get("units")?.as_f64()
+    }
+
+    /// Returns the text with an investment appended to the portfolio, written
+    /// in the style of its last investment.
+    pub fn add_investment(&self, fields: &[(&str, String)]) -> Result<String> {
+        append_item(
+            &self.text,
+            &self.path.key("investments"),
+            self.investments.len(),
+            fields,
+        )
+    }
+
+    /// Returns the text with an item appended to a list field of an
+    /// investment, adding the field at the end of the investment if missing.
+    pub fn add_to_list(
+        &self,
+        index: usize,
+        field: &str,
+        fields: &[(&str, String)],
+    ) -> Result<String> {
+        let investment = self.path.key("investments").index(index);
+        match self.investments.get(index).and_then(|i| i.get(field)) {
+            Some(Value::Sequence(items)) => {
+                append_item(&self.text, &investment.key(field), items.len(), fields)
+            }
+            Some(_) => bail!("Investment has a `{field}` that is not a list"),
+            None => {
+                let mut lines = split_lines(&self.text);
+                let item = item_lines(&self.text, &investment)?;
+                let indent = " ".repeat(item.key_column);
+                let mut new_lines = vec![format!("{indent}{field}:")];
+                new_lines.extend(list_item(&format!("{indent}  - "), fields));
+                lines.splice(item.end..item.end, new_lines);
+                Ok(join_lines(lines, &self.text))
+            }
+        }
+    }
+
+    /// Returns the text without an investment, along with its comments.
+    pub fn remove_investment(&self, index: usize) -> Result<String> {
+        let mut lines = split_lines(&self.text);
+        let item = item_lines(&self.text, &self.path.key("investments").index(index))?;
+        lines.drain(item.first..item.end);
+        if self.investments.len() == 1 {
+            let key = self.path.key("investments");
+            let location = locate(&self.text, &key).context("Portfolio has no investments")?;
+            let indent = &lines[location.line - 1][..location.column - 1];
+            lines[location.line - 1] = format!("{indent}investments: []");
+        }
+        Ok(join_lines(lines, &self.text))
+    }
+
+    /// Returns the text with the value of an investment's field replaced,
+    /// keeping any comment after it.
+    pub fn set_field(&self, index: usize, field: &str, value: &str) -> Result<String> {
+        let mut lines = split_lines(&self.text);
+        let path = self.path.key("investments").index(index).key(field);
+        let location =
+            locate(&self.text, &path).with_context(|| format!("Investment has no `{field}`"))?;
+        let line = &lines[location.line - 1];
+        let key_end = location.column - 1 + field.len();
+        let rest = line[key_end..]
+            .strip_prefix(':')
+            .with_context(|| format!("Cannot edit `{field}` at line {}", location.line))?;
+        let (old, comment) = rest.split_at(rest.find(" #").unwrap_or(rest.len()));
+        let padding = &old[old.trim_end().len()..];
+        lines[location.line - 1] = format!("{}: {value}{padding}{comment}", &line[..key_end]);
+        Ok(join_lines(lines, &self.text))
+    }
+}
+
+/// Returns the text with an item appended to the list at `path`, which has
+/// `count` items, written in the style of its last item.
+fn append_item(text: &str, path: &Path, count: usize, fields: &[(&str, String)]) -> Result<String> {
+    let mut lines = split_lines(text);
+    let (at, prefix) = match count {
+        0 => {
+            // Turn `field: []` into a block list
+            let location = locate(text, path).context("List not found in the file")?;
+            let line = &lines[location.line - 1];
+            let indent = &line[..location.column - 1];
+            let (key, value) = line[location.column - 1..]
+                .split_once(':')
+                .with_context(|| format!("Cannot edit the list at line {}", location.line))?;
+            if !value.trim().starts_with("[]") {
+                bail!("Cannot edit the list at line {}", location.line);
+            }
+            let prefix = format!("{indent}  - ");
+            lines[location.line - 1] = format!("{indent}{key}:");
+            (location.line, prefix)
+        }
+        count => {
+            let item = item_lines(text, &path.index(count - 1))?;
+            let prefix = lines[item.start][..item.key_column].to_string();
+            (item.end, prefix)
+        }
+    };
+    lines.splice(at..at, list_item(&prefix, fields));
+    Ok(join_lines(lines, text))
+}
+
+/// Lines of a block list item, the first starting with `prefix` and the
+/// rest aligned with it.
+fn list_item(prefix: &str, fields: &[(&str, String)]) -> Vec<String> {
+    let indent = " ".repeat(prefix.len());
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, (key, value))| {
+            let lead = if i == 0 { prefix } else { &indent };
+            format!("{lead}{key}: {value}")
+        })
+        .collect()
+}
+
+/// Line range of a list item, with the column its first key starts at.
+struct ItemLines {
+    /// First line, including comments above the item
+    first: usize,
+    /// Line of the item's `-`
+    start: usize,
+    end: usize,
+    key_column: usize,
+}
+
+/// Finds the lines of a block list item: from its `-`, or the comments
+/// above it, up to the next line indented no further than it, leaving out
+/// trailing blank lines.
+fn item_lines(text: &str, path: &Path) -> Result<ItemLines> {
+    let location = locate(text, path).context("List item not found in the file")?;
+    let lines = split_lines(text);
+    let start = location.line - 1;
+    let key_column = location.column - 1;
+    let dash = lines[start][..key_column].trim_end();
+    if !dash.ends_with('-') {
+        bail!(
+            "Only items of block lists (`- symbol: ...`) can be edited, see line {}",
+            location.line
+        );
+    }
+    let dash_column = dash.len() - 1;
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let mut end = start + 1;
+    while end < lines.len() && (lines[end].trim().is_empty() || indent(&lines[end]) > dash_column) {
+        end += 1;
+    }
+    while end > start + 1 && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    // Comments right above the item belong to it
+    let mut first = start;
+    while first > 0
+        && lines[first - 1].trim_start().starts_with('#')
+        && indent(&lines[first - 1]) == dash_column
+    {
+        first -= 1;
+    }
+    Ok(ItemLines {
+        first,
+        start,
+        end,
+        key_column,
+    })
+}
+
+/// Splits a file's text into lines.
+pub fn split_lines(text: &str) -> Vec<String> {
+    text.lines().map(str::to_string).collect()
+}
+
+/// Joins edited lines, ending with a newline if the original text did.
+pub fn join_lines(lines: Vec<String>, original: &str) -> String {
+    let mut text = lines.join("\n");
+    if original.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
+/// Formats a string as a double-quoted YAML scalar.
+pub fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| format!("\"{value}\""))
+}
+
+/// Writes a config file, first copying the current one to a timestamped
+/// backup next to it. Returns the backup's path.
+pub fn write_with_backup(file: &std::path::Path, text: &str) -> Result<PathBuf> {
+    let timestamp = chrono::Local::now().format("%Y%m%d%H%M%S");
+    // Keep earlier backups taken within the same second
+    let backup = (0..)
+        .map(|n| {
+            let suffix = if n == 0 {
+                String::new()
+            } else {
+                format!("-{n}")
+            };
+            let mut backup = file.as_os_str().to_owned();
+            backup.push(format!(".{timestamp}{suffix}.bak"));
+            PathBuf::from(backup)
+        })
+        .find(|backup| !backup.exists())
+        .expect("unbounded range");
+    fs::copy(file, &backup)
+        .with_context(|| format!("Failed to back up config file to {}", backup.display()))?;
+    fs::write(file, text)
+        .with_context(|| format!("Failed to write config file: {}", file.display()))?;
+    Ok(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"# Household config
+currency: INR
+portfolios:
+  - name: "Retirement"
+    investments:
+      # Index fund
+      - isin: "INF179KB1HU9"
+        units: 100 # bought in 2020
+        category: Equity
+
+      - symbol: "AAPL"
+        units: 5
+  - name: Empty
+    investments: []
+"#;
+
+    fn portfolio(name: &str) -> (tempfile::TempDir, PortfolioFile) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, CONFIG).unwrap();
+        let portfolio = find_portfolio(&path, name).unwrap();
+        (dir, portfolio)
+    }
+
+    #[test]
+    fn test_set_field_keeps_comments() {
+        let (_dir, retirement) = portfolio("retirement");
+        let index = retirement
+            .find(&InvestmentId::Isin("INF179KB1HU9".to_string()))
+            .unwrap();
+        assert_eq!(retirement.units(index), Some(100.0));
+        let text = retirement.set_field(index, "units", "112.5").unwrap();
+        assert_eq!(
+            text,
+            CONFIG.replace("units: 100 # bought", "units: 112.5 # bought")
+        );
+    }
+
+    #[test]
+    fn test_add_and_remove_investments() {
+        let (_dir, retirement) = portfolio("Retirement");
+        let fields = [("symbol", quote("MSFT")), ("units", "2".to_string())];
+        let text = retirement.add_investment(&fields).unwrap();
+        assert_eq!(
+            text,
+            CONFIG.replace(
+                "        units: 5\n",
+                "        units: 5\n      - symbol: \"MSFT\"\n        units: 2\n"
+            )
+        );
+
+        let index = retirement
+            .find(&InvestmentId::Symbol("aapl".to_string()))
+            .unwrap();
+        let text = retirement.remove_investment(index).unwrap();
+        assert_eq!(
+            text,
+            CONFIG.replace("      - symbol: \"AAPL\"\n        units: 5\n", "")
+        );
+        // The comment above the removed fund goes with it
+        let text = retirement.remove_investment(0).unwrap();
+        assert!(!text.contains("Index fund"));
+        assert!(text.contains("      - symbol: \"AAPL\""));
+
+        let (_dir, empty) = portfolio("Empty");
+        let text = empty.add_investment(&fields).unwrap();
+        assert!(text.ends_with("    investments:\n      - symbol: \"MSFT\"\n        units: 2\n"));
+        let config: Value = serde_yaml::from_str(&text).unwrap();
+        assert_eq!(config["portfolios"][1]["investments"][0]["units"], 2);
+    }
+
+    #[test]
+    fn test_add_to_list() {
+        let (_dir, retirement) = portfolio("Retirement");
+        let transaction = |date: &str| [("date", date.to_string()), ("units", "5".to_string())];
+        let text = retirement
+            .add_to_list(0, "transactions", &transaction("2024-01-15"))
+            .unwrap();
+        assert_eq!(
+            text,
+            CONFIG.replace(
+                "        category: Equity\n",
+                "        category: Equity\n        transactions:\n          - date: 2024-01-15\n            units: 5\n"
+            )
+        );
+
+        let retirement = retirement.with_text(text).unwrap();
+        let text = retirement
+            .add_to_list(0, "transactions", &transaction("2024-02-15"))
+            .unwrap();
+        let config: Value = serde_yaml::from_str(&text).unwrap();
+        let transactions = &config["portfolios"][0]["investments"][0]["transactions"];
+        assert_eq!(transactions.as_sequence().unwrap().len(), 2);
+        assert_eq!(transactions[1]["date"], "2024-02-15");
+        assert!(text.contains("units: 100 # bought in 2020"));
+    }
+
+    #[test]
+    fn test_add_portfolio() {
+        let text = add_portfolio(CONFIG, "Kids").unwrap();
+        assert_eq!(
+            text,
+            format!("{CONFIG}  - name: \"Kids\"\n    investments: []\n")
+        );
+        let text = add_portfolio("currency: INR\nportfolios: []\n", "Kids").unwrap();
+        assert_eq!(
+            text,
+            "currency: INR\nportfolios:\n  - name: \"Kids\"\n    investments: []\n"
+        );
+        let text = add_portfolio("include: people/\n", "Kids").unwrap();
+        let config: Value = serde_yaml::from_str(&text).unwrap();
+        assert_eq!(config["portfolios"][0]["name"], "Kids");
+    }
+
+    #[test]
+    fn test_write_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, "old").unwrap();
+        let backup = write_with_backup(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "old");
+        let second = write_with_backup(&path, "newer").unwrap();
+        assert_ne!(second, backup);
+        assert_eq!(fs::read_to_string(&second).unwrap(), "new");
+    }
+}
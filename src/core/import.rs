@@ -0,0 +1,448 @@
+//! Reads holdings from the CSV exports of Indian brokers and registrars.
+
+use crate::core::benchmark::is_isin;
+use crate::core::edit::InvestmentId;
+use anyhow::{Context, Result, bail};
+use std::str::FromStr;
+use tracing::debug;
+
+/// Decimal places kept when summing units
+const UNITS_SCALE: f64 = 1e6;
+
+/// Prefix of the ISINs of Indian mutual fund schemes
+const FUND_ISIN_PREFIX: &str = "INF";
+
+/// Yahoo Finance suffix of stocks listed on the NSE
+const NSE_SUFFIX: &str = ".NS";
+
+/// Words in a transaction's type that mark units going out
+const OUTFLOWS: &[&str] = &[
+    "redemption",
+    "redeem",
+    "switch out",
+    "switch-out",
+    "stp out",
+    "swp",
+    "transfer out",
+    "sell",
+];
+
+/// Layouts of the CSV files holdings can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    /// Zerodha Console or Kite holdings
+    Zerodha,
+    /// Groww stock or mutual fund holdings
+    Groww,
+    /// CAMS mutual fund transactions
+    Cams,
+    /// KFintech mutual fund transactions
+    Kfintech,
+    /// Any CSV, with its columns named by a `ColumnMap`
+    Generic,
+}
+
+impl ImportFormat {
+    pub const EXPECTED: &str = "zerodha, groww, cams, kfintech or generic";
+
+    /// Header names, in lower case, each field is read from.
+    fn columns(self) -> Columns {
+        let isin = &["isin", "scheme isin", "isin no", "isin no."][..];
+        match self {
+            ImportFormat::Zerodha => Columns {
+                symbol: &["symbol", "instrument", "tradingsymbol"],
+                isin,
+                name: &[],
+                units: &["quantity available", "qty.", "qty", "quantity"],
+                kind: &[],
+            },
+            ImportFormat::Groww => Columns {
+                symbol: &["symbol", "nse symbol"],
+                isin,
+                name: &["stock name", "scheme name", "name"],
+                units: &["quantity", "units", "qty"],
+                kind: &[],
+            },
+            ImportFormat::Cams | ImportFormat::Kfintech => Columns {
+                symbol: &[],
+                isin,
+                name: &["scheme_name", "scheme name", "scheme", "fund description"],
+                units: &["units"],
+                kind: &[
+                    "transaction_type",
+                    "transaction type",
+                    "transaction description",
+                    "description",
+                    "type",
+                ],
+            },
+            ImportFormat::Generic => Columns::default(),
+        }
+    }
+
+    /// Whether rows are transactions, summed up into holdings.
+    fn is_ledger(self) -> bool {
+        matches!(self, ImportFormat::Cams | ImportFormat::Kfintech)
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.trim().to_lowercase().as_str() {
+            "zerodha" => ImportFormat::Zerodha,
+            "groww" => ImportFormat::Groww,
+            "cams" => ImportFormat::Cams,
+            "kfintech" | "kfin" => ImportFormat::Kfintech,
+            "generic" => ImportFormat::Generic,
+            _ => bail!("Unknown format '{s}', expected {}", Self::EXPECTED),
+        })
+    }
+}
+
+/// CSV columns to read the fields of a holding from, for the generic format.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnMap {
+    pub symbol: Option<String>,
+    pub isin: Option<String>,
+    pub units: Option<String>,
+    pub category: Option<String>,
+}
+
+impl FromStr for ColumnMap {
+    type Err = anyhow::Error;
+
+    /// Parses `field=Column` pairs separated by commas, e.g.
+    /// `symbol=Ticker,units=Qty`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut map = ColumnMap::default();
+        for pair in s.split(',').filter(|p| !p.trim().is_empty()) {
+            let Some((field, column)) = pair.split_once('=') else {
+                bail!("Invalid mapping '{pair}', expected field=Column");
+            };
+            let column = Some(column.trim().to_string());
+            match field.trim().to_lowercase().as_str() {
+                "symbol" => map.symbol = column,
+                "isin" => map.isin = column,
+                "units" => map.units = column,
+                "category" => map.category = column,
+                other => bail!("Unknown field '{other}', expected symbol, isin, units or category"),
+            }
+        }
+        if map.units.is_none() || (map.symbol.is_none() && map.isin.is_none()) {
+            bail!("Map the units column and a symbol or isin column, e.g. symbol=Ticker,units=Qty");
+        }
+        Ok(map)
+    }
+}
+
+/// Header names a format reads each field from.
+#[derive(Debug, Default)]
+struct Columns {
+    symbol: &'static [&'static str],
+    isin: &'static [&'static str],
+    name: &'static [&'static str],
+    units: &'static [&'static str],
+    kind: &'static [&'static str],
+}
+
+/// Positions of the fields in the rows of a file.
+#[derive(Debug)]
+struct Layout {
+    symbol: Option<usize>,
+    isin: Option<usize>,
+    name: Option<usize>,
+    units: usize,
+    kind: Option<usize>,
+    category: Option<usize>,
+}
+
+/// An investment and the units held of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holding {
+    pub id: InvestmentId,
+    pub units: f64,
+    pub category: Option<String>,
+}
+
+/// A row that could not be imported, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skipped {
+    pub line: usize,
+    pub reason: String,
+}
+
+/// Holdings read from a file, in the order they first appear.
+#[derive(Debug, Default)]
+pub struct Import {
+    pub holdings: Vec<Holding>,
+    pub skipped: Vec<Skipped>,
+}
+
+/// Reads the holdings in a CSV export. Rows for the same investment are
+/// added up, and transactions are summed into the units left.
+///
+/// Funds are identified by ISIN and stocks by symbol, with the `.NS` suffix
+/// Yahoo Finance uses for NSE listings added to broker symbols.
+pub fn read(text: &str, format: ImportFormat, map: Option<&ColumnMap>) -> Result<Import> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(text.trim_start_matches('\u{feff}').as_bytes());
+    let mut records = reader.records();
+
+    // Exports may have a title or account details above the header
+    let layout = loop {
+        let Some(record) = records.next() else {
+            bail!("No header row with the columns of a {format:?} export found");
+        };
+        let record = record?;
+        let header: Vec<String> = record.iter().map(str::to_lowercase).collect();
+        if let Some(layout) = find_layout(&header, format, map)? {
+            debug!(
+                "Found {format:?} header at line {:?}",
+                record.position().map(|p| p.line())
+            );
+            break layout;
+        }
+    };
+
+    let mut import = Import::default();
+    // Line of the last row of each holding
+    let mut last_lines = Vec::new();
+    for record in records {
+        let record = record?;
+        let line = record.position().map_or(0, |p| p.line() as usize);
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .filter(|value| !value.is_empty())
+        };
+        let (symbol, isin) = (
+            field(layout.symbol),
+            field(layout.isin).map(str::to_uppercase),
+        );
+        let Some(units) = field(Some(layout.units)) else {
+            // Blank rows, totals and charges such as stamp duty
+            if !format.is_ledger() && (symbol.is_some() || isin.is_some()) {
+                let reason = "no units".to_string();
+                import.skipped.push(Skipped { line, reason });
+            }
+            continue;
+        };
+        let Ok(mut units) = units.replace(',', "").parse::<f64>() else {
+            let reason = format!("invalid units '{units}'");
+            import.skipped.push(Skipped { line, reason });
+            continue;
+        };
+        let kind = field(layout.kind).unwrap_or_default().to_lowercase();
+        if units > 0.0 && OUTFLOWS.iter().any(|word| kind.contains(word)) {
+            units = -units;
+        }
+
+        let fund_isin = isin
+            .clone()
+            .filter(|isin| format == ImportFormat::Generic || isin.starts_with(FUND_ISIN_PREFIX));
+        let id = match (fund_isin, symbol) {
+            (Some(isin), symbol) if format != ImportFormat::Generic || symbol.is_none() => {
+                if !is_isin(&isin) {
+                    let reason = format!("'{isin}' is not an ISIN");
+                    import.skipped.push(Skipped { line, reason });
+                    continue;
+                }
+                InvestmentId::Isin(isin)
+            }
+            (_, Some(symbol)) if format == ImportFormat::Generic || symbol.contains('.') => {
+                InvestmentId::Symbol(symbol.to_string())
+            }
+            (_, Some(symbol)) => InvestmentId::Symbol(format!("{symbol}{NSE_SUFFIX}")),
+            (_, None) => {
+                let what = field(layout.name).or(isin.as_deref()).unwrap_or("row");
+                let reason = format!(
+                    "no symbol for {what}, add it with `xmf add --symbol` or import with --from generic"
+                );
+                import.skipped.push(Skipped { line, reason });
+                continue;
+            }
+        };
+
+        let category = field(layout.category).map(str::to_string);
+        match import.holdings.iter().position(|h| h.id == id) {
+            Some(i) => {
+                import.holdings[i].units += units;
+                last_lines[i] = line;
+            }
+            None => {
+                import.holdings.push(Holding {
+                    id,
+                    units,
+                    category,
+                });
+                last_lines.push(line);
+            }
+        }
+    }
+
+    let mut holdings = Vec::new();
+    for (mut holding, line) in import.holdings.into_iter().zip(last_lines) {
+        holding.units = (holding.units * UNITS_SCALE).round() / UNITS_SCALE;
+        if holding.units < 0.0 {
+            let reason = format!(
+                "{} has {} more units sold than bought, check the file has every transaction",
+                holding.id, -holding.units
+            );
+            import.skipped.push(Skipped { line, reason });
+        } else if holding.units > 0.0 {
+            holdings.push(holding);
+        }
+    }
+    import.holdings = holdings;
+    import.skipped.sort_by_key(|s| s.line);
+    Ok(import)
+}
+
+/// Finds the columns of a format in a header row, or `None` if the row is
+/// not the header.
+fn find_layout(
+    header: &[String],
+    format: ImportFormat,
+    map: Option<&ColumnMap>,
+) -> Result<Option<Layout>> {
+    let find = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| header.iter().position(|column| column == name))
+    };
+    if format == ImportFormat::Generic {
+        let map = map.context("The generic format needs --map, e.g. symbol=Ticker,units=Qty")?;
+        // Mapped columns, `Some(None)` when missing from the row
+        let columns = [&map.symbol, &map.isin, &map.units, &map.category]
+            .map(|column| column.as_ref().map(|c| find(&[c.to_lowercase().as_str()])));
+        if columns.iter().any(|column| matches!(column, Some(None))) {
+            return Ok(None);
+        }
+        let [symbol, isin, units, category] = columns.map(Option::flatten);
+        return Ok(units.map(|units| Layout {
+            symbol,
+            isin,
+            name: None,
+            units,
+            kind: None,
+            category,
+        }));
+    }
+
+    let columns = format.columns();
+    let (symbol, isin) = (find(columns.symbol), find(columns.isin));
+    let Some(units) = find(columns.units) else {
+        return Ok(None);
+    };
+    if symbol.is_none() && isin.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(Layout {
+        symbol,
+        isin,
+        name: find(columns.name),
+        units,
+        kind: find(columns.kind),
+        category: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(import: &Import) -> Vec<(String, f64)> {
+        import
+            .holdings
+            .iter()
+            .map(|h| (h.id.to_string(), h.units))
+            .collect()
+    }
+
+    #[test]
+    fn test_read_zerodha_holdings() {
+        let text = "\u{feff}Client ID,AB1234\n\nSymbol,ISIN,Sector,Quantity Available,Quantity Discrepant,Average Price\nINFY,INE009A01021,IT,10,0,1500.5\nAXIS LONG TERM EQUITY FUND,INF846K01131,,\"1,234.567\",0,50\nTATAMOTORS,INE155A01022,Auto,5,0,400\nINFY,INE009A01021,IT,2,0,1600\nFOO,,,,0,\n";
+        let import = read(text, ImportFormat::Zerodha, None).unwrap();
+        assert_eq!(
+            ids(&import),
+            vec![
+                ("symbol 'INFY.NS'".to_string(), 12.0),
+                ("isin 'INF846K01131'".to_string(), 1234.567),
+                ("symbol 'TATAMOTORS.NS'".to_string(), 5.0),
+            ]
+        );
+        assert_eq!(import.holdings[1].id.investment_type(), Some("mutual_fund"));
+        assert_eq!(
+            import.skipped,
+            vec![Skipped {
+                line: 8,
+                reason: "no units".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_read_groww_holdings_without_symbols() {
+        let text = "Stock Name,ISIN,Quantity,Average buy price\nReliance Industries,INE002A01018,4,2400\nParag Parikh Flexi Cap,INF879O01027,25.5,60\n";
+        let import = read(text, ImportFormat::Groww, None).unwrap();
+        assert_eq!(
+            ids(&import),
+            vec![("isin 'INF879O01027'".to_string(), 25.5)]
+        );
+        assert_eq!(import.skipped.len(), 1);
+        assert_eq!(import.skipped[0].line, 2);
+        assert!(
+            import.skipped[0]
+                .reason
+                .starts_with("no symbol for Reliance Industries")
+        );
+    }
+
+    #[test]
+    fn test_read_cams_transactions() {
+        let text = "FOLIO_NUMBER,SCHEME_NAME,ISIN,TRADE_DATE,TRANSACTION_TYPE,AMOUNT,UNITS\n\
+            123,Fund A,INF179KB1HU9,01-Jan-2023,Purchase,1000,10.5\n\
+            123,Fund A,INF179KB1HU9,01-Jan-2023,*** Stamp Duty ***,0.05,\n\
+            123,Fund A,INF179KB1HU9,01-Feb-2023,Redemption,500,4.25\n\
+            456,Fund B,INF200K01RJ1,01-Mar-2023,Purchase,100,1\n\
+            456,Fund B,INF200K01RJ1,01-Apr-2023,Switch Out,-100,-1\n\
+            789,Fund C,INF090I01239,01-May-2023,Redemption,100,2\n";
+        let import = read(text, ImportFormat::Cams, None).unwrap();
+        assert_eq!(
+            ids(&import),
+            vec![("isin 'INF179KB1HU9'".to_string(), 6.25)]
+        );
+        // Fund B is fully redeemed, and Fund C misses its purchases
+        assert_eq!(import.skipped.len(), 1);
+        assert_eq!(import.skipped[0].line, 7);
+    }
+
+    #[test]
+    fn test_read_generic_csv() {
+        let map: ColumnMap = "symbol=Ticker, units=Qty, category=Class".parse().unwrap();
+        let text = "Ticker,Qty,Class\nAAPL,3,Equity\nVWRL.L,2.5,\n";
+        let import = read(text, ImportFormat::Generic, Some(&map)).unwrap();
+        assert_eq!(
+            ids(&import),
+            vec![
+                ("symbol 'AAPL'".to_string(), 3.0),
+                ("symbol 'VWRL.L'".to_string(), 2.5),
+            ]
+        );
+        assert_eq!(import.holdings[0].category.as_deref(), Some("Equity"));
+
+        let missing = read("Name,Qty\nAAPL,3\n", ImportFormat::Generic, Some(&map));
+        assert!(missing.unwrap_err().to_string().contains("No header row"));
+        assert!("symbol=Ticker".parse::<ColumnMap>().is_err());
+        assert!(
+            "units=Qty,price=Close,symbol=T"
+                .parse::<ColumnMap>()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Resolves `include:` of other config files and `${VAR}` references to
+//! environment variables.
+
+use anyhow::{Context, Result, bail};
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Key listing the files, directories or glob patterns a config includes
+pub const INCLUDE_KEY: &str = "include";
+
+/// Key whose lists are concatenated across included files rather than replaced
+const PORTFOLIOS_KEY: &str = "portfolios";
+
+/// Loads a config file merged with the files it includes, with environment
+/// variables expanded.
+///
+/// Settings in the including file take precedence over included ones, and
+/// earlier includes over later ones, so `providers` and other sections are
+/// merged key by key. Portfolios of all files are kept, in order. An included
+/// file with `investments` at the top is read as a single portfolio.
+pub fn load(path: &Path) -> Result<Value> {
+    load_with(path, &mut Vec::new(), &mut Vec::new())
+}
+
+/// Returns the config file and the files it includes, directly or not, in
+/// the order they are merged.
+pub fn files(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    load_with(path, &mut Vec::new(), &mut files)?;
+    Ok(files)
+}
+
+fn load_with(path: &Path, stack: &mut Vec<PathBuf>, files: &mut Vec<PathBuf>) -> Result<Value> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if stack.contains(&canonical) {
+        bail!("Config file {} includes itself", path.display());
+    }
+    files.push(path.to_path_buf());
+
+    let mut value: Value = serde_yaml::from_str(&text)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+    expand_env_vars(&mut value)
+        .with_context(|| format!("Invalid config file: {}", path.display()))?;
+    let Value::Mapping(mut config) = value else {
+        return Ok(value);
+    };
+    let includes = match config.remove(INCLUDE_KEY) {
+        Some(includes) => include_paths(path, includes)
+            .with_context(|| format!("Invalid include in config file: {}", path.display()))?,
+        None => Vec::new(),
+    };
+
+    stack.push(canonical);
+    for include in includes {
+        let included = match load_with(&include, stack, files)? {
+            Value::Mapping(included) if included.contains_key("investments") => {
+                Mapping::from_iter([(
+                    PORTFOLIOS_KEY.into(),
+                    Value::Sequence(vec![included.into()]),
+                )])
+            }
+            Value::Mapping(included) => included,
+            _ => bail!(
+                "Included config file {} must be a mapping of settings or a portfolio",
+                include.display()
+            ),
+        };
+        merge(&mut config, included);
+    }
+    stack.pop();
+    Ok(config.into())
+}
+
+/// Resolves included files relative to the including file. Directories
+/// include their YAML files, and glob patterns every file matching them.
+fn include_paths(path: &Path, includes: Value) -> Result<Vec<PathBuf>> {
+    let includes: Vec<String> = match includes {
+        Value::String(include) => vec![include],
+        includes => {
+            serde_yaml::from_value(includes).context("include must be a path or a list of paths")?
+        }
+    };
+    let base = path.parent().unwrap_or(Path::new(""));
+    let mut paths = Vec::new();
+    for include in includes {
+        let full = base.join(&include);
+        let pattern = if full.is_dir() {
+            full.join("*.y*ml")
+        } else if include.contains(['*', '?', '[']) {
+            full
+        } else {
+            paths.push(full);
+            continue;
+        };
+        let pattern = pattern.to_string_lossy();
+        let mut matched = glob::glob(&pattern)
+            .with_context(|| format!("Invalid include pattern '{include}'"))?
+            .collect::<Result<Vec<_>, _>>()?;
+        matched.retain(|p| p.is_file());
+        matched.sort();
+        paths.extend(matched);
+    }
+    Ok(paths)
+}
+
+/// Adds the settings of an included file missing from `config`, recursing
+/// into sections both have and appending the included portfolios.
+fn merge(config: &mut Mapping, included: Mapping) {
+    for (key, value) in included {
+        match (config.get_mut(&key), value) {
+            (Some(Value::Sequence(portfolios)), Value::Sequence(included))
+                if key.as_str() == Some(PORTFOLIOS_KEY) =>
+            {
+                portfolios.extend(included);
+            }
+            (Some(Value::Mapping(section)), Value::Mapping(included)) => {
+                merge(section, included);
+            }
+            (Some(_), _) => {}
+            (None, value) => {
+                config.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Replaces `${VAR}` in every string value with the environment variable,
+/// or `${VAR:-default}` with the default when the variable is unset.
+pub fn expand_env_vars(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(s) if s.contains("${") => *s = expand(s)?,
+        Value::Sequence(items) => items.iter_mut().try_for_each(expand_env_vars)?,
+        Value::Mapping(fields) => fields.values_mut().try_for_each(expand_env_vars)?,
+        Value::Tagged(tagged) => expand_env_vars(&mut tagged.value)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn expand(s: &str) -> Result<String> {
+    let mut expanded = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            bail!("Unterminated '${{' in '{s}'");
+        };
+        let reference = &rest[start + 2..start + end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        match (std::env::var(name), default) {
+            (Ok(value), _) => expanded.push_str(&value),
+            (Err(_), Some(default)) => expanded.push_str(default),
+            (Err(_), None) => bail!("Environment variable '{name}' in '{s}' is not set"),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_env_vars() {
+        // SAFETY: the variable is only used by this test
+        unsafe { std::env::set_var("XMF_TEST_DATA_DIR", "/srv/xmf") };
+        assert_eq!(
+            expand("${XMF_TEST_DATA_DIR}/data").unwrap(),
+            "/srv/xmf/data"
+        );
+        assert_eq!(
+            expand("${XMF_TEST_UNSET:-http://localhost}/api").unwrap(),
+            "http://localhost/api"
+        );
+        assert_eq!(expand("plain").unwrap(), "plain");
+        assert!(expand("${XMF_TEST_UNSET}").is_err());
+        assert!(expand("${XMF_TEST_DATA_DIR").is_err());
+    }
+
+    #[test]
+    fn test_load_merges_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, text: &str| {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, text).unwrap();
+            path
+        };
+        let main = write(
+            "config.yaml",
+            "include: [shared.yaml, people]\ncurrency: INR\nproviders:\n  yahoo:\n    base_url: http://main\nportfolios:\n  - name: Main\n    investments: []\n",
+        );
+        write(
+            "shared.yaml",
+            "currency: USD\nproviders:\n  yahoo:\n    base_url: http://shared\n  amfi:\n    base_url: http://amfi\n",
+        );
+        write("people/b.yaml", "name: B\ninvestments: []\n");
+        write("people/a.yml", "name: A\ninvestments: []\n");
+
+        let value = load(&main).unwrap();
+        assert_eq!(value["currency"], "INR");
+        assert_eq!(value["providers"]["yahoo"]["base_url"], "http://main");
+        assert_eq!(value["providers"]["amfi"]["base_url"], "http://amfi");
+        let names: Vec<&str> = value["portfolios"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Main", "A", "B"]);
+        assert_eq!(files(&main).unwrap().len(), 4);
+
+        write("loop.yaml", "include: config.yaml\n");
+        let looping = write("start.yaml", "include: loop.yaml\n");
+        write("config.yaml", "include: start.yaml\n");
+        let err = load(&looping).unwrap_err();
+        assert!(err.to_string().contains("includes itself"), "{err:#}");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::core::price::ProviderResult;
 use async_trait::async_trait;
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
@@ -17,5 +18,5 @@ pub struct FundMetadata {
 
 #[async_trait]
 pub trait MetadataProvider: Send + Sync {
-    async fn fetch_metadata(&self, identifier: &str) -> anyhow::Result<FundMetadata>;
+    async fn fetch_metadata(&self, identifier: &str) -> ProviderResult<FundMetadata>;
 }
@@ -0,0 +1,329 @@
+//! Upgrades config files written in older formats to the current one.
+//!
+//! Version 1, any config without a `version`, leaves the type of each
+//! investment to be inferred from its fields. Version 2 names the `type` of
+//! each.
+
+use crate::core::config::Investment;
+use crate::core::edit::{join_lines, split_lines};
+use crate::core::include;
+use crate::core::validate::{Path, locate};
+use anyhow::{Context, Result, bail};
+use serde_yaml::Value;
+use std::cmp::Reverse;
+
+/// Key holding the format version of a config
+pub const VERSION_KEY: &str = "version";
+
+/// Version of configs without a `version`
+pub const LEGACY_VERSION: u32 = 1;
+
+/// Version of the format this release writes
+pub const CURRENT_VERSION: u32 = 2;
+
+/// Lines of unchanged context around each change in a diff
+const DIFF_CONTEXT: usize = 3;
+
+/// Returns the format version of a config, failing when it is newer than
+/// this release can read.
+pub fn version(root: &Value) -> Result<u32> {
+    let Some(value) = root.get(VERSION_KEY) else {
+        return Ok(LEGACY_VERSION);
+    };
+    let version = value
+        .as_u64()
+        .and_then(|v| u32::try_from(v).ok())
+        .filter(|v| *v >= LEGACY_VERSION)
+        .with_context(|| {
+            format!(
+                "`{VERSION_KEY}` must be a whole number from {LEGACY_VERSION} to {CURRENT_VERSION}"
+            )
+        })?;
+    if version > CURRENT_VERSION {
+        bail!(
+            "Config version {version} is newer than this xmf supports ({CURRENT_VERSION}), upgrade xmf to read it"
+        );
+    }
+    Ok(version)
+}
+
+/// Describes how to upgrade a config in a deprecated format.
+pub fn deprecation(version: u32) -> String {
+    format!(
+        "config version {version} is deprecated, run `xmf config migrate` to upgrade it to version {CURRENT_VERSION}"
+    )
+}
+
+/// Rewrites the text of a config file in the current format, keeping its
+/// comments. Files included by the main one hold no version of their own.
+pub fn migrate_text(text: &str, main: bool) -> Result<String> {
+    if main {
+        let root: Value = serde_yaml::from_str(text)?;
+        version(&root)?;
+    }
+    let text = add_types(text)?;
+    if !main {
+        return Ok(text);
+    }
+    set_version(&text)
+}
+
+/// Adds a `type` to every investment without one, as the first field.
+fn add_types(text: &str) -> Result<String> {
+    let root: Value = serde_yaml::from_str(text)?;
+    let root_path = Path::default();
+    let mut lists = Vec::new();
+    // An included file may be a portfolio of its own
+    if let Some(Value::Sequence(investments)) = root.get("investments") {
+        lists.push((root_path.key("investments"), investments));
+    }
+    if let Some(Value::Sequence(portfolios)) = root.get("portfolios") {
+        for (i, portfolio) in portfolios.iter().enumerate() {
+            if let Some(Value::Sequence(investments)) = portfolio.get("investments") {
+                let path = root_path.key("portfolios").index(i).key("investments");
+                lists.push((path, investments));
+            }
+        }
+    }
+
+    let mut insertions = Vec::new();
+    for (path, investments) in lists {
+        for (j, investment) in investments.iter().enumerate() {
+            if investment.get("type").is_some() {
+                continue;
+            }
+            let location =
+                locate(text, &path.index(j)).context("Investment not found in the file")?;
+            let mut fields = investment.clone();
+            include::expand_env_vars(&mut fields)?;
+            let investment = Investment::from_value(fields)
+                .with_context(|| format!("Invalid investment at line {}", location.line))?;
+            let tag = serde_yaml::to_value(&investment)?
+                .get("type")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .context("Investment has no type")?;
+            insertions.push((location, tag));
+        }
+    }
+
+    // From the end, so the locations of earlier investments stay put
+    insertions.sort_by_key(|(location, _)| Reverse(*location));
+    let mut lines = split_lines(text);
+    for (location, tag) in insertions {
+        let index = location.line - 1;
+        let (before, after) = lines[index].split_at(location.column - 1);
+        let (before, after) = (before.to_string(), after.to_string());
+        if let Some(fields) = after.strip_prefix('{') {
+            // A flow mapping, `{ symbol: ... }`
+            let fields = fields.trim_start();
+            let space = &after[1..after.len() - fields.len()];
+            lines[index] = format!("{before}{{{space}type: {tag}, {fields}");
+        } else {
+            let indent = " ".repeat(before.len());
+            lines[index] = format!("{before}type: {tag}");
+            lines.insert(index + 1, format!("{indent}{after}"));
+        }
+    }
+    Ok(join_lines(lines, text))
+}
+
+/// Sets `version` to the current one, adding it above the first setting.
+fn set_version(text: &str) -> Result<String> {
+    let root: Value = serde_yaml::from_str(text)?;
+    let version = format!("{VERSION_KEY}: {CURRENT_VERSION}");
+    let mut lines = split_lines(text);
+    if root.get(VERSION_KEY).is_some() {
+        let key = Path::default().key(VERSION_KEY);
+        let location = locate(text, &key).context("Version not found in the file")?;
+        let line = &lines[location.line - 1];
+        let comment = line.find(" #").map_or("", |i| &line[i..]);
+        lines[location.line - 1] = format!("{version}{comment}");
+    } else {
+        let is_setting = |line: &String| {
+            !(line.trim().is_empty() || line.starts_with(['#', '%']) || line.starts_with("---"))
+        };
+        let mut at = lines.iter().position(is_setting).unwrap_or(lines.len());
+        // Keep the comments right above the first setting with it
+        while at > 0 && lines[at - 1].starts_with('#') {
+            at -= 1;
+        }
+        lines.splice(at..at, [version, String::new()]);
+    }
+    Ok(join_lines(lines, text))
+}
+
+/// Returns the changed lines between two texts as unified diff hunks, each
+/// line prefixed with `-`, `+` or a space.
+pub fn diff(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // Length of the longest common subsequence of the lines from i and j on
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    // Each line with its marker and the line numbers reached before it
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push((' ', old[i], i, j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            ops.push(('-', old[i], i, j));
+            i += 1;
+        } else {
+            ops.push(('+', new[j], i, j));
+            j += 1;
+        }
+    }
+
+    let changes: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0 != ' ').collect();
+    let mut output = Vec::new();
+    let mut k = 0;
+    while k < changes.len() {
+        let first = changes[k];
+        let mut last = first;
+        while k + 1 < changes.len() && changes[k + 1] - last <= 2 * DIFF_CONTEXT {
+            k += 1;
+            last = changes[k];
+        }
+        k += 1;
+        let start = first.saturating_sub(DIFF_CONTEXT);
+        let end = (last + DIFF_CONTEXT + 1).min(ops.len());
+        let hunk = &ops[start..end];
+        let removed = hunk.iter().filter(|op| op.0 != '+').count();
+        let added = hunk.iter().filter(|op| op.0 != '-').count();
+        let (_, _, old_start, new_start) = hunk[0];
+        output.push(format!(
+            "@@ -{},{removed} +{},{added} @@",
+            old_start + 1,
+            new_start + 1
+        ));
+        output.extend(
+            hunk.iter()
+                .map(|(marker, line, _, _)| format!("{marker}{line}")),
+        );
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::AppConfig;
+
+    const LEGACY: &str = r#"# My holdings
+currency: INR
+
+# Everything I own
+portfolios:
+  - name: Main
+    investments:
+      # Index fund
+      - isin: "INF179KB1HU9"
+        units: 100 # bought in 2020
+      - { symbol: "AAPL", units: 5 }
+      - name: Bank FD
+        value: 50000
+
+# Reporting
+target_allocation:
+  Equity: 100
+"#;
+
+    const MIGRATED: &str = r#"version: 2
+
+# My holdings
+currency: INR
+
+# Everything I own
+portfolios:
+  - name: Main
+    investments:
+      # Index fund
+      - type: mutual_fund
+        isin: "INF179KB1HU9"
+        units: 100 # bought in 2020
+      - { type: stock, symbol: "AAPL", units: 5 }
+      - type: fixed_deposit
+        name: Bank FD
+        value: 50000
+
+# Reporting
+target_allocation:
+  Equity: 100
+"#;
+
+    #[test]
+    fn test_version() {
+        let config = |text: &str| serde_yaml::from_str::<Value>(text).unwrap();
+        assert_eq!(version(&config("currency: INR")).unwrap(), LEGACY_VERSION);
+        assert_eq!(version(&config("version: 2")).unwrap(), 2);
+        let err = version(&config("version: 3")).unwrap_err();
+        assert!(err.to_string().contains("newer"), "{err:#}");
+        assert!(version(&config("version: two")).is_err());
+    }
+
+    #[test]
+    fn test_migrate_legacy_config() {
+        let migrated = migrate_text(LEGACY, true).unwrap();
+        assert_eq!(migrated, MIGRATED);
+        // Migrating again changes nothing
+        assert_eq!(migrate_text(&migrated, true).unwrap(), MIGRATED);
+
+        let config: AppConfig = serde_yaml::from_str(&migrated).unwrap();
+        assert_eq!(config.version, CURRENT_VERSION);
+        assert_eq!(config.portfolios[0].investments.len(), 3);
+
+        // Included files only gain types
+        let part = "name: Kids\ninvestments:\n  - symbol: MSFT\n    units: 1\n";
+        assert_eq!(
+            migrate_text(part, false).unwrap(),
+            "name: Kids\ninvestments:\n  - type: stock\n    symbol: MSFT\n    units: 1\n"
+        );
+    }
+
+    #[test]
+    fn test_legacy_config_loads() {
+        let config: AppConfig = serde_yaml::from_str(LEGACY).unwrap();
+        assert_eq!(config.version, LEGACY_VERSION);
+        assert!(matches!(
+            config.portfolios[0].investments[2],
+            Investment::FixedDeposit(_)
+        ));
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        assert_eq!(
+            diff(old, new),
+            vec![
+                "@@ -1,5 +1,5 @@",
+                " a",
+                "-b",
+                "+B",
+                " c",
+                " d",
+                " e",
+                "@@ -8,3 +8,4 @@",
+                " h",
+                " i",
+                " j",
+                "+k",
+            ]
+        );
+        assert!(diff(old, old).is_empty());
+    }
+}
@@ -2,14 +2,29 @@
 
 pub mod allocation;
 pub mod analytics;
+pub mod benchmark;
+pub mod bond;
 pub mod cache;
 pub mod config;
+pub mod correlation;
 pub mod currency;
+pub mod deposit;
+pub mod edit;
+pub mod import;
+pub mod include;
 pub mod log;
 pub mod metadata;
+pub mod migrate;
 pub mod price;
+pub mod risk;
+pub mod savings;
+pub mod schema;
+pub mod validate;
 
 // Re-export main types for cleaner imports
 pub use currency::CurrencyRateProvider;
 pub use metadata::{FundMetadata, MetadataProvider};
-pub use price::{HistoricalPeriod, PriceProvider, PriceResult};
+pub use price::{
+    DEFAULT_PERIODS, HistoricalPeriod, PERIOD_FORMATS, PeriodPrices, PriceProvider, PriceResult,
+    ProviderError, ProviderResult,
+};
@@ -2,12 +2,16 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
-use chrono::{Duration, NaiveDate};
+use chrono::{Datelike, Duration, Local, Months, NaiveDate};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::str::FromStr;
 
+/// Span of time ending on a reference date, usually the latest price.
+///
+/// The named variants are the periods providers precompute prices for;
+/// parsing maps e.g. `1y` to [`HistoricalPeriod::OneYear`] so they are found.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum HistoricalPeriod {
     OneDay,
@@ -17,53 +21,146 @@ pub enum HistoricalPeriod {
     ThreeYears,
     FiveYears,
     TenYears,
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+    Years(u32),
+    /// Since the last close of the previous year
+    YearToDate,
+    /// Since the last close of the previous quarter
+    QuarterToDate,
+    /// Since the last close of the previous month
+    MonthToDate,
+    /// All available history
+    Inception,
+    /// From the first date to the second
+    Range(NaiveDate, NaiveDate),
 }
 
+/// Periods shown when none are requested.
+pub const DEFAULT_PERIODS: [HistoricalPeriod; 7] = [
+    HistoricalPeriod::OneDay,
+    HistoricalPeriod::FiveDays,
+    HistoricalPeriod::OneMonth,
+    HistoricalPeriod::OneYear,
+    HistoricalPeriod::ThreeYears,
+    HistoricalPeriod::FiveYears,
+    HistoricalPeriod::TenYears,
+];
+
+/// Accepted period formats, for error messages and help text.
+pub const PERIOD_FORMATS: &str =
+    "Nd, Nw, Nm or Ny (e.g. 5d, 6m, 2y), YTD, QTD, MTD, inception or YYYY-MM-DD..YYYY-MM-DD";
+
 impl Display for HistoricalPeriod {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                HistoricalPeriod::OneDay => "1D",
-                HistoricalPeriod::FiveDays => "5D",
-                HistoricalPeriod::OneMonth => "1M",
-                HistoricalPeriod::OneYear => "1Y",
-                HistoricalPeriod::ThreeYears => "3Y",
-                HistoricalPeriod::FiveYears => "5Y",
-                HistoricalPeriod::TenYears => "10Y",
-            }
-        )
+        match self {
+            HistoricalPeriod::OneDay => write!(f, "1D"),
+            HistoricalPeriod::FiveDays => write!(f, "5D"),
+            HistoricalPeriod::OneMonth => write!(f, "1M"),
+            HistoricalPeriod::OneYear => write!(f, "1Y"),
+            HistoricalPeriod::ThreeYears => write!(f, "3Y"),
+            HistoricalPeriod::FiveYears => write!(f, "5Y"),
+            HistoricalPeriod::TenYears => write!(f, "10Y"),
+            HistoricalPeriod::Days(n) => write!(f, "{n}D"),
+            HistoricalPeriod::Weeks(n) => write!(f, "{n}W"),
+            HistoricalPeriod::Months(n) => write!(f, "{n}M"),
+            HistoricalPeriod::Years(n) => write!(f, "{n}Y"),
+            HistoricalPeriod::YearToDate => write!(f, "YTD"),
+            HistoricalPeriod::QuarterToDate => write!(f, "QTD"),
+            HistoricalPeriod::MonthToDate => write!(f, "MTD"),
+            HistoricalPeriod::Inception => write!(f, "Inception"),
+            HistoricalPeriod::Range(from, to) => write!(f, "{from}..{to}"),
+        }
     }
 }
 
 impl HistoricalPeriod {
-    pub fn to_duration(&self) -> Duration {
+    /// Returns true for periods of a fixed length, which can be rolled over a series.
+    pub fn is_trailing(&self) -> bool {
+        !matches!(
+            self,
+            HistoricalPeriod::YearToDate
+                | HistoricalPeriod::QuarterToDate
+                | HistoricalPeriod::MonthToDate
+                | HistoricalPeriod::Inception
+                | HistoricalPeriod::Range(..)
+        )
+    }
+
+    /// Returns the last date of the period, given the latest available date.
+    pub fn end_date(&self, latest: NaiveDate) -> NaiveDate {
         match self {
-            HistoricalPeriod::OneDay => Duration::days(1),
-            HistoricalPeriod::FiveDays => Duration::days(5),
-            HistoricalPeriod::OneMonth => Duration::days(30),
-            HistoricalPeriod::OneYear => Duration::days(365),
-            HistoricalPeriod::ThreeYears => Duration::days(365 * 3),
-            HistoricalPeriod::FiveYears => Duration::days(365 * 5),
-            HistoricalPeriod::TenYears => Duration::days(365 * 10),
+            HistoricalPeriod::Range(_, to) => *to,
+            _ => latest,
         }
     }
 
-    pub fn to_trading_days(&self) -> u32 {
+    /// Returns the date the period starts on when it ends on `end`, or `None`
+    /// for [`HistoricalPeriod::Inception`].
+    ///
+    /// Months and years are calendar months, clamped to the end of shorter
+    /// months, e.g. one month before March 31 is February 28 or 29. Periods
+    /// reaching back past the earliest representable date also give `None`.
+    pub fn start_date(&self, end: NaiveDate) -> Option<NaiveDate> {
+        let days_back = |days: u32| end.checked_sub_signed(Duration::days(i64::from(days)));
+        let months_back = |months: u32| end.checked_sub_months(Months::new(months));
         match self {
-            HistoricalPeriod::OneDay => 1,
-            HistoricalPeriod::FiveDays => 5,
-            HistoricalPeriod::OneMonth => 21,
-            HistoricalPeriod::OneYear => 252,
-            HistoricalPeriod::ThreeYears => 756,
-            HistoricalPeriod::FiveYears => 1260,
-            HistoricalPeriod::TenYears => 2520,
+            HistoricalPeriod::OneDay => days_back(1),
+            HistoricalPeriod::FiveDays => days_back(5),
+            HistoricalPeriod::Days(n) => days_back(*n),
+            HistoricalPeriod::Weeks(n) => days_back(n.saturating_mul(7)),
+            HistoricalPeriod::OneMonth => months_back(1),
+            HistoricalPeriod::Months(n) => months_back(*n),
+            HistoricalPeriod::OneYear => months_back(12),
+            HistoricalPeriod::ThreeYears => months_back(36),
+            HistoricalPeriod::FiveYears => months_back(60),
+            HistoricalPeriod::TenYears => months_back(120),
+            HistoricalPeriod::Years(n) => months_back(n.saturating_mul(12)),
+            HistoricalPeriod::YearToDate => NaiveDate::from_ymd_opt(end.year(), 1, 1)?.pred_opt(),
+            HistoricalPeriod::QuarterToDate => {
+                let quarter_month = (end.month() - 1) / 3 * 3 + 1;
+                NaiveDate::from_ymd_opt(end.year(), quarter_month, 1)?.pred_opt()
+            }
+            HistoricalPeriod::MonthToDate => end.with_day(1)?.pred_opt(),
+            HistoricalPeriod::Inception => None,
+            HistoricalPeriod::Range(from, _) => Some(*from),
+        }
+    }
+
+    /// Returns the calendar length of the period ending on `end`, or `None` for
+    /// [`HistoricalPeriod::Inception`].
+    pub fn to_duration(&self, end: NaiveDate) -> Option<Duration> {
+        let end = self.end_date(end);
+        self.start_date(end).map(|start| end - start)
+    }
+
+    /// Parses each of `values`, or returns `default` when there are none.
+    pub fn parse_list(values: &[String], default: &[HistoricalPeriod]) -> Result<Vec<Self>> {
+        if values.is_empty() {
+            return Ok(default.to_vec());
         }
+        values.iter().map(|value| value.parse()).collect()
     }
 
-    pub fn variants() -> [&'static str; 7] {
-        ["1D", "5D", "1M", "1Y", "3Y", "5Y", "10Y"]
+    /// Maps a count and unit to a period, preferring the named variants.
+    fn from_count(count: u32, unit: char) -> Option<Self> {
+        let period = match (count, unit) {
+            (0, _) => return None,
+            (1, 'D') => HistoricalPeriod::OneDay,
+            (5, 'D') => HistoricalPeriod::FiveDays,
+            (1, 'M') => HistoricalPeriod::OneMonth,
+            (1, 'Y') => HistoricalPeriod::OneYear,
+            (3, 'Y') => HistoricalPeriod::ThreeYears,
+            (5, 'Y') => HistoricalPeriod::FiveYears,
+            (10, 'Y') => HistoricalPeriod::TenYears,
+            (n, 'D') => HistoricalPeriod::Days(n),
+            (n, 'W') => HistoricalPeriod::Weeks(n),
+            (n, 'M') => HistoricalPeriod::Months(n),
+            (n, 'Y') => HistoricalPeriod::Years(n),
+            _ => return None,
+        };
+        Some(period)
     }
 }
 
@@ -71,24 +168,49 @@ impl FromStr for HistoricalPeriod {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s_upper = s.to_uppercase();
+        let s_upper = s.trim().to_uppercase();
+        let invalid = || anyhow::anyhow!("Invalid period: '{}'. Use {}", s, PERIOD_FORMATS);
         match s_upper.as_str() {
-            "1D" => Ok(HistoricalPeriod::OneDay),
-            "5D" => Ok(HistoricalPeriod::FiveDays),
-            "1M" => Ok(HistoricalPeriod::OneMonth),
-            "1Y" => Ok(HistoricalPeriod::OneYear),
-            "3Y" => Ok(HistoricalPeriod::ThreeYears),
-            "5Y" => Ok(HistoricalPeriod::FiveYears),
-            "10Y" => Ok(HistoricalPeriod::TenYears),
-            _ => {
-                let valid_periods = Self::variants().join(", ");
-                Err(anyhow::anyhow!(
-                    "Invalid period: '{}'. Valid periods are: {}",
-                    s,
-                    valid_periods
-                ))
+            "YTD" => return Ok(HistoricalPeriod::YearToDate),
+            "QTD" => return Ok(HistoricalPeriod::QuarterToDate),
+            "MTD" => return Ok(HistoricalPeriod::MonthToDate),
+            "INCEPTION" | "MAX" => return Ok(HistoricalPeriod::Inception),
+            _ => {}
+        }
+
+        if let Some((from, to)) = s_upper.split_once("..") {
+            let parse = |date: &str| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d");
+            let (from, to) = (
+                parse(from).map_err(|_| invalid())?,
+                parse(to).map_err(|_| invalid())?,
+            );
+            if from >= to {
+                anyhow::bail!("Invalid period: '{}'. The start must be before the end", s);
             }
+            return Ok(HistoricalPeriod::Range(from, to));
         }
+
+        let unit = s_upper.chars().last().ok_or_else(invalid)?;
+        let count = s_upper[..s_upper.len() - unit.len_utf8()]
+            .parse()
+            .map_err(|_| invalid())?;
+        Self::from_count(count, unit).ok_or_else(invalid)
+    }
+}
+
+/// Prices at the start and end of a period.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeriodPrices {
+    pub start: f64,
+    pub end: f64,
+    /// Calendar days between the two prices
+    pub days: i64,
+}
+
+impl PeriodPrices {
+    /// Returns the change over the period in percent.
+    pub fn change(&self) -> f64 {
+        (self.end - self.start) / self.start * 100.0
     }
 }
 
@@ -101,7 +223,240 @@ pub struct PriceResult {
     pub short_name: Option<String>,
 }
 
+/// Failure reported by a price, currency or metadata provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProviderError {
+    /// The symbol, ISIN or currency pair is unknown to the provider
+    NotFound(String),
+    /// The provider is throttling requests
+    RateLimited(String),
+    /// The provider could not be reached, e.g. connection failure or timeout
+    Network(String),
+    /// The provider responded with data that could not be parsed
+    InvalidResponse(String),
+    /// Any other provider failure, e.g. an unexpected HTTP status
+    Other(String),
+}
+
+impl ProviderError {
+    /// Returns the error message without the error class.
+    pub fn message(&self) -> &str {
+        match self {
+            ProviderError::NotFound(msg)
+            | ProviderError::RateLimited(msg)
+            | ProviderError::Network(msg)
+            | ProviderError::InvalidResponse(msg)
+            | ProviderError::Other(msg) => msg,
+        }
+    }
+
+    /// Prefixes the message with `context`, keeping the error class.
+    pub fn context(self, context: impl Display) -> Self {
+        let wrap = |msg: String| format!("{context}: {msg}");
+        match self {
+            ProviderError::NotFound(msg) => ProviderError::NotFound(wrap(msg)),
+            ProviderError::RateLimited(msg) => ProviderError::RateLimited(wrap(msg)),
+            ProviderError::Network(msg) => ProviderError::Network(wrap(msg)),
+            ProviderError::InvalidResponse(msg) => ProviderError::InvalidResponse(wrap(msg)),
+            ProviderError::Other(msg) => ProviderError::Other(wrap(msg)),
+        }
+    }
+}
+
+impl Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let class = match self {
+            ProviderError::NotFound(_) => "Not found",
+            ProviderError::RateLimited(_) => "Rate limited",
+            ProviderError::Network(_) => "Network error",
+            ProviderError::InvalidResponse(_) => "Invalid response",
+            ProviderError::Other(_) => "Provider error",
+        };
+        write!(f, "{class}: {}", self.message())
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+/// Result type returned by providers.
+pub type ProviderResult<T> = std::result::Result<T, ProviderError>;
+
+impl PriceResult {
+    /// Returns the prices at the start and end of `period`.
+    ///
+    /// Uses the provider's historical price when it has one, else the last daily
+    /// price on or before the start date. Returns `None` when the daily prices
+    /// don't reach back to the start of the period.
+    pub fn period_prices(&self, period: HistoricalPeriod) -> Option<PeriodPrices> {
+        let latest = self
+            .daily_prices
+            .iter()
+            .map(|(date, _)| *date)
+            .max()
+            .unwrap_or_else(|| Local::now().date_naive());
+
+        if let Some(start) = self.historical_prices.get(&period) {
+            let days = period.to_duration(latest)?.num_days();
+            return (*start > 0.0).then_some(PeriodPrices {
+                start: *start,
+                end: self.price,
+                days,
+            });
+        }
+
+        let price_on = |date: NaiveDate| {
+            self.daily_prices
+                .iter()
+                .filter(|(d, _)| *d <= date)
+                .max_by_key(|(d, _)| *d)
+                .copied()
+        };
+        let end_date = period.end_date(latest);
+        let end = if end_date < latest {
+            price_on(end_date)?.1
+        } else {
+            self.price
+        };
+        let (start_date, start) = match period.start_date(end_date) {
+            Some(start_date) => price_on(start_date).map(|(_, price)| (start_date, price))?,
+            None => self.daily_prices.iter().min_by_key(|(d, _)| *d).copied()?,
+        };
+        (start > 0.0 && start_date < end_date).then_some(PeriodPrices {
+            start,
+            end,
+            days: (end_date - start_date).num_days(),
+        })
+    }
+}
+
 #[async_trait]
 pub trait PriceProvider: Send + Sync {
-    async fn fetch_price(&self, symbol: &str) -> Result<PriceResult>;
+    async fn fetch_price(&self, symbol: &str) -> ProviderResult<PriceResult>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_error_context_keeps_class() {
+        let err = ProviderError::NotFound("No data for AAPLX".to_string()).context("Yahoo");
+        assert_eq!(
+            err,
+            ProviderError::NotFound("Yahoo: No data for AAPLX".to_string())
+        );
+        assert_eq!(err.message(), "Yahoo: No data for AAPLX");
+        assert_eq!(err.to_string(), "Not found: Yahoo: No data for AAPLX");
+    }
+
+    #[test]
+    fn test_parse_periods() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let parse = |s: &str| s.parse::<HistoricalPeriod>().unwrap();
+
+        assert_eq!(parse("1y"), HistoricalPeriod::OneYear);
+        assert_eq!(parse("5D"), HistoricalPeriod::FiveDays);
+        assert_eq!(parse("2y"), HistoricalPeriod::Years(2));
+        assert_eq!(parse("6m"), HistoricalPeriod::Months(6));
+        assert_eq!(parse("3w"), HistoricalPeriod::Weeks(3));
+        assert_eq!(parse("ytd"), HistoricalPeriod::YearToDate);
+        assert_eq!(parse("Inception"), HistoricalPeriod::Inception);
+        assert_eq!(
+            parse("2022-01-01..2022-12-31"),
+            HistoricalPeriod::Range(date(2022, 1, 1), date(2022, 12, 31))
+        );
+        for invalid in [
+            "",
+            "0y",
+            "2x",
+            "y",
+            "2022-12-31..2022-01-01",
+            "2022-01-01..",
+        ] {
+            assert!(invalid.parse::<HistoricalPeriod>().is_err(), "{invalid}");
+        }
+        assert_eq!(parse("6m").to_string(), "6M");
+    }
+
+    #[test]
+    fn test_period_start_dates_are_calendar_accurate() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let end = date(2024, 3, 31);
+
+        assert_eq!(
+            HistoricalPeriod::OneMonth.start_date(end),
+            Some(date(2024, 2, 29))
+        );
+        assert_eq!(
+            HistoricalPeriod::OneYear.start_date(date(2024, 2, 29)),
+            Some(date(2023, 2, 28))
+        );
+        assert_eq!(
+            HistoricalPeriod::Years(2).start_date(end),
+            Some(date(2022, 3, 31))
+        );
+        assert_eq!(
+            HistoricalPeriod::Weeks(2).start_date(end),
+            Some(date(2024, 3, 17))
+        );
+        assert_eq!(
+            HistoricalPeriod::YearToDate.start_date(end),
+            Some(date(2023, 12, 31))
+        );
+        assert_eq!(
+            HistoricalPeriod::QuarterToDate.start_date(date(2024, 5, 15)),
+            Some(date(2024, 3, 31))
+        );
+        assert_eq!(
+            HistoricalPeriod::MonthToDate.start_date(end),
+            Some(date(2024, 2, 29))
+        );
+        assert_eq!(HistoricalPeriod::Inception.start_date(end), None);
+        // Too far back for a date, rather than a panic
+        for period in ["99999999d", "99999999w", "99999999m", "99999999y"] {
+            let period: HistoricalPeriod = period.parse().unwrap();
+            assert_eq!(period.start_date(end), None, "{period}");
+            assert_eq!(period.to_duration(end), None, "{period}");
+        }
+        assert_eq!(
+            HistoricalPeriod::OneYear
+                .to_duration(end)
+                .unwrap()
+                .num_days(),
+            366
+        );
+    }
+
+    #[test]
+    fn test_period_prices_from_daily_prices() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let result = PriceResult {
+            price: 120.0,
+            currency: "USD".to_string(),
+            historical_prices: HashMap::from([(HistoricalPeriod::OneYear, 60.0)]),
+            daily_prices: vec![
+                (date(2023, 1, 2), 80.0),
+                (date(2023, 12, 29), 100.0),
+                (date(2024, 3, 28), 110.0),
+                (date(2024, 6, 28), 120.0),
+            ],
+            short_name: None,
+        };
+
+        let ytd = result.period_prices(HistoricalPeriod::YearToDate).unwrap();
+        assert_eq!((ytd.start, ytd.end), (100.0, 120.0));
+        assert!((ytd.change() - 20.0).abs() < 1e-9);
+
+        let inception = result.period_prices(HistoricalPeriod::Inception).unwrap();
+        assert_eq!((inception.start, inception.end), (80.0, 120.0));
+
+        let range = HistoricalPeriod::Range(date(2023, 12, 31), date(2024, 3, 31));
+        let range = result.period_prices(range).unwrap();
+        assert_eq!((range.start, range.end, range.days), (100.0, 110.0, 91));
+
+        // Provider prices take precedence over daily prices
+        let year = result.period_prices(HistoricalPeriod::OneYear).unwrap();
+        assert_eq!(year.start, 60.0);
+        assert!(result.period_prices(HistoricalPeriod::Years(2)).is_none());
+    }
 }
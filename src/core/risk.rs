@@ -0,0 +1,202 @@
+//! Risk metrics computed from daily value series.
+
+use crate::core::price::HistoricalPeriod;
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Trading days used to annualize daily figures.
+pub const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+/// Settings for risk-adjusted return ratios.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RiskConfig {
+    /// Annual risk-free rate in percent, used for Sharpe and Sortino ratios
+    #[serde(default)]
+    pub risk_free_rate: f64,
+}
+
+/// Largest peak-to-trough decline of a series.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drawdown {
+    /// Decline from the peak in percent, as a negative number
+    pub depth: f64,
+    pub peak: NaiveDate,
+    pub trough: NaiveDate,
+    /// First date the series is back at the peak, if it recovered
+    pub recovery: Option<NaiveDate>,
+}
+
+/// Annualized return and risk of a series, in percent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RiskMetrics {
+    /// Mean daily return, annualized
+    pub annual_return: f64,
+    /// Standard deviation of daily returns, annualized
+    pub volatility: f64,
+    pub max_drawdown: Option<Drawdown>,
+    pub sharpe: Option<f64>,
+    pub sortino: Option<f64>,
+}
+
+/// Keeps the part of a date-sorted series within `period`, ending on its last date.
+pub fn trim_to_period(
+    series: &[(NaiveDate, f64)],
+    period: HistoricalPeriod,
+) -> &[(NaiveDate, f64)] {
+    let Some((last, _)) = series.last() else {
+        return series;
+    };
+    let end = period.end_date(*last);
+    let last = series.partition_point(|(date, _)| *date <= end);
+    let first = period
+        .start_date(end)
+        .map_or(0, |start| series.partition_point(|(date, _)| *date < start));
+    &series[first..last.max(first)]
+}
+
+/// Returns the change between consecutive values, as fractions.
+pub fn daily_returns(series: &[(NaiveDate, f64)]) -> Vec<f64> {
+    series
+        .windows(2)
+        .filter(|w| w[0].1 > 0.0)
+        .map(|w| w[1].1 / w[0].1 - 1.0)
+        .collect()
+}
+
+/// Finds the largest decline from a previous peak, or `None` if the series never declines.
+pub fn max_drawdown(series: &[(NaiveDate, f64)]) -> Option<Drawdown> {
+    let (mut peak_date, mut peak_value) = *series.first()?;
+    let mut worst: Option<(Drawdown, f64)> = None;
+    for &(date, value) in series {
+        if value > peak_value {
+            peak_date = date;
+            peak_value = value;
+            continue;
+        }
+        let depth = (value / peak_value - 1.0) * 100.0;
+        if depth < 0.0 && worst.is_none_or(|(d, _)| depth < d.depth) {
+            worst = Some((
+                Drawdown {
+                    depth,
+                    peak: peak_date,
+                    trough: date,
+                    recovery: None,
+                },
+                peak_value,
+            ));
+        }
+    }
+
+    let (mut drawdown, peak_value) = worst?;
+    drawdown.recovery = series
+        .iter()
+        .find(|(date, value)| *date > drawdown.trough && *value >= peak_value)
+        .map(|(date, _)| *date);
+    Some(drawdown)
+}
+
+/// Calculates annualized return, volatility, drawdown and risk-adjusted ratios.
+///
+/// `risk_free_rate` is an annual rate in percent. Returns `None` for series
+/// with fewer than three values. Ratios are `None` when there is no volatility
+/// (Sharpe) or no returns below the risk-free rate (Sortino).
+pub fn calculate_risk(series: &[(NaiveDate, f64)], risk_free_rate: f64) -> Option<RiskMetrics> {
+    let returns = daily_returns(series);
+    if returns.len() < 2 {
+        return None;
+    }
+
+    let count = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / count;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (count - 1.0);
+    let daily_risk_free = risk_free_rate / 100.0 / TRADING_DAYS_PER_YEAR;
+    let downside = (returns
+        .iter()
+        .map(|r| (r - daily_risk_free).min(0.0).powi(2))
+        .sum::<f64>()
+        / count)
+        .sqrt();
+
+    let annualize = |daily: f64| daily * TRADING_DAYS_PER_YEAR.sqrt() * 100.0;
+    let annual_return = mean * TRADING_DAYS_PER_YEAR * 100.0;
+    let volatility = annualize(variance.sqrt());
+    let downside_volatility = annualize(downside);
+    let excess = annual_return - risk_free_rate;
+
+    Some(RiskMetrics {
+        annual_return,
+        volatility,
+        max_drawdown: max_drawdown(series),
+        sharpe: (volatility > 0.0).then(|| excess / volatility),
+        sortino: (downside_volatility > 0.0).then(|| excess / downside_volatility),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(values: &[f64]) -> Vec<(NaiveDate, f64)> {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (start + chrono::Duration::days(i as i64), *v))
+            .collect()
+    }
+
+    #[test]
+    fn test_max_drawdown_with_recovery() {
+        let data = series(&[100.0, 120.0, 90.0, 60.0, 100.0, 130.0, 110.0]);
+        let drawdown = max_drawdown(&data).unwrap();
+
+        assert!((drawdown.depth + 50.0).abs() < 1e-9);
+        assert_eq!(drawdown.peak, data[1].0);
+        assert_eq!(drawdown.trough, data[3].0);
+        assert_eq!(drawdown.recovery, Some(data[5].0));
+
+        let unrecovered = series(&[100.0, 80.0, 90.0]);
+        assert_eq!(max_drawdown(&unrecovered).unwrap().recovery, None);
+        assert_eq!(max_drawdown(&series(&[1.0, 2.0, 3.0])), None);
+    }
+
+    #[test]
+    fn test_calculate_risk() {
+        let data = series(&[100.0, 101.0, 100.0, 102.0, 101.0, 103.0]);
+        let metrics = calculate_risk(&data, 0.0).unwrap();
+
+        let returns = daily_returns(&data);
+        let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+        assert!((metrics.annual_return - mean * 252.0 * 100.0).abs() < 1e-9);
+        assert!(metrics.volatility > 0.0);
+        let sharpe = metrics.sharpe.unwrap();
+        assert!((sharpe - metrics.annual_return / metrics.volatility).abs() < 1e-9);
+        // Only downside moves count for Sortino, so it exceeds Sharpe here
+        assert!(metrics.sortino.unwrap() > sharpe);
+
+        // A higher risk-free rate lowers both ratios
+        let with_rate = calculate_risk(&data, 5.0).unwrap();
+        assert!(with_rate.sharpe.unwrap() < sharpe);
+
+        let flat = series(&[100.0, 100.0, 100.0]);
+        let metrics = calculate_risk(&flat, 0.0).unwrap();
+        assert_eq!(metrics.volatility, 0.0);
+        assert_eq!(metrics.sharpe, None);
+        assert_eq!(metrics.sortino, None);
+        assert!(calculate_risk(&series(&[100.0, 101.0]), 0.0).is_none());
+    }
+
+    #[test]
+    fn test_trim_to_period() {
+        let data = series(&[1.0; 40]);
+        // January 1 to February 9, so one month back is January 9
+        let trimmed = trim_to_period(&data, HistoricalPeriod::OneMonth);
+        assert_eq!(trimmed.len(), 32);
+        assert_eq!(trimmed.last(), data.last());
+
+        let range = HistoricalPeriod::Range(data[5].0, data[9].0);
+        assert_eq!(trim_to_period(&data, range), &data[5..10]);
+        assert_eq!(trim_to_period(&data, HistoricalPeriod::Inception).len(), 40);
+    }
+}
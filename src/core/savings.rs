@@ -0,0 +1,362 @@
+//! Balances of small savings schemes with contributions over time.
+
+use crate::core::deposit::{Compounding, DepositTerms};
+use anyhow::{Result, bail};
+use chrono::{Datelike, Months, NaiveDate};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Term of a National Savings Certificate.
+const NSC_TERM_MONTHS: u32 = 60;
+
+/// Small savings scheme, which sets how interest is credited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    /// Public Provident Fund
+    Ppf,
+    /// Employees' Provident Fund
+    Epf,
+    /// National Savings Certificate
+    Nsc,
+    /// Sukanya Samriddhi Yojana
+    Ssy,
+    /// Recurring deposit
+    Rd,
+}
+
+impl Scheme {
+    /// Returns the last day of the month on which a deposit still counts
+    /// towards that month's balance, for schemes crediting interest yearly.
+    fn monthly_cutoff_day(self) -> Option<u32> {
+        match self {
+            Scheme::Ppf => Some(5),
+            Scheme::Ssy => Some(10),
+            // Contributions earn interest from the month after they are made
+            Scheme::Epf => Some(0),
+            Scheme::Nsc | Scheme::Rd => None,
+        }
+    }
+}
+
+/// A deposit into the scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Contribution {
+    pub date: NaiveDate,
+    pub amount: f64,
+}
+
+/// An interest rate in percent, applying from `from` until the next change.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RateChange {
+    pub from: NaiveDate,
+    pub rate: f64,
+}
+
+/// A deposit repeated every period from the start date.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RecurringContribution {
+    pub amount: f64,
+    #[serde(default = "default_frequency")]
+    pub frequency: Compounding,
+    /// Date of the last deposit, defaults to the period before maturity
+    pub until: Option<NaiveDate>,
+}
+
+fn default_frequency() -> Compounding {
+    Compounding::Monthly
+}
+
+/// Contributions and interest rates of a small savings account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SavingsPlan {
+    pub scheme: Scheme,
+    pub start_date: NaiveDate,
+    /// Interest stops accruing on this date
+    pub maturity_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub contributions: Vec<Contribution>,
+    pub recurring: Option<RecurringContribution>,
+    /// Interest rates by date, in ascending order
+    pub rates: Vec<RateChange>,
+}
+
+impl SavingsPlan {
+    /// Checks that the plan has deposits and a rate for each of them.
+    pub fn validate(&self) -> Result<()> {
+        if self.contributions.is_empty() && self.recurring.is_none() {
+            bail!("needs contributions or a recurring contribution");
+        }
+        if let Some(maturity) = self.maturity_date
+            && maturity <= self.start_date
+        {
+            bail!(
+                "matures on {maturity}, before it starts on {}",
+                self.start_date
+            );
+        }
+        match self.rates.first() {
+            None => bail!("needs at least one rate"),
+            Some(first) if first.from > self.start_date => bail!(
+                "has no rate from its start on {}, the first rate is from {}",
+                self.start_date,
+                first.from
+            ),
+            _ => {}
+        }
+        if self.rates.windows(2).any(|w| w[1].from <= w[0].from) {
+            bail!("rates must be in ascending order of date");
+        }
+        if self.rates.iter().any(|r| r.rate < 0.0) {
+            bail!("has a negative rate");
+        }
+        if let Some(c) = self.contributions.iter().find(|c| c.date < self.start_date) {
+            bail!(
+                "has a contribution on {}, before it starts on {}",
+                c.date,
+                self.start_date
+            );
+        }
+        let recurring_amount = self.recurring.map(|r| r.amount);
+        let amounts = self.contributions.iter().map(|c| c.amount);
+        if amounts.chain(recurring_amount).any(|amount| amount <= 0.0) {
+            bail!("contribution amounts must be positive");
+        }
+        Ok(())
+    }
+
+    /// Returns the interest rate in percent applying on `date`.
+    pub fn rate_on(&self, date: NaiveDate) -> f64 {
+        self.rates
+            .iter()
+            .rev()
+            .find(|r| r.from <= date)
+            .or(self.rates.first())
+            .map_or(0.0, |r| r.rate)
+    }
+
+    /// Returns all deposits made on or before `date`, oldest first.
+    pub fn contributions_until(&self, date: NaiveDate) -> Vec<Contribution> {
+        let mut contributions: Vec<Contribution> = self
+            .contributions
+            .iter()
+            .filter(|c| c.date <= date)
+            .copied()
+            .collect();
+        if let Some(recurring) = &self.recurring {
+            let last = recurring.until.map_or(date, |until| date.min(until));
+            let months = recurring.frequency.months();
+            contributions.extend(
+                (0..)
+                    .map_while(|i| self.start_date.checked_add_months(Months::new(months * i)))
+                    // No deposit is due on the maturity date itself
+                    .take_while(|d| *d <= last && self.maturity_date.is_none_or(|m| *d < m))
+                    .map(|date| Contribution {
+                        date,
+                        amount: recurring.amount,
+                    }),
+            );
+        }
+        contributions.sort_by_key(|c| c.date);
+        contributions
+    }
+
+    /// Returns the balance on `date`, including interest accrued but not yet credited.
+    pub fn value_on(&self, date: NaiveDate) -> f64 {
+        let end = self
+            .maturity_date
+            .map_or(date, |maturity| date.min(maturity));
+        let contributions = self.contributions_until(end);
+        match self.scheme {
+            Scheme::Nsc => contributions
+                .iter()
+                .map(|c| self.certificate(c, self.rate_on(c.date)).value_on(end))
+                .sum(),
+            // The rate is fixed for the life of the deposit when it is opened
+            Scheme::Rd => {
+                let rate = self.rate_on(self.start_date);
+                contributions
+                    .iter()
+                    .map(|c| self.certificate(c, rate).value_on(end))
+                    .sum()
+            }
+            Scheme::Ppf | Scheme::Ssy | Scheme::Epf => {
+                let cutoff = self.scheme.monthly_cutoff_day().unwrap_or_default();
+                yearly_credited_balance(&contributions, cutoff, end, |month| self.rate_on(month))
+            }
+        }
+    }
+
+    /// Deposit terms of a single contribution earning its own interest.
+    fn certificate(&self, contribution: &Contribution, rate: f64) -> DepositTerms {
+        let (compounding, maturity) = match self.scheme {
+            Scheme::Nsc => (
+                Compounding::Annual,
+                contribution
+                    .date
+                    .checked_add_months(Months::new(NSC_TERM_MONTHS)),
+            ),
+            _ => (Compounding::Quarterly, self.maturity_date),
+        };
+        DepositTerms {
+            principal: contribution.amount,
+            rate,
+            start: contribution.date,
+            maturity,
+            compounding,
+            payout: false,
+        }
+    }
+}
+
+/// Balance of an account earning interest each month on its lowest balance
+/// after `cutoff_day`, credited at the end of each fiscal year in March.
+fn yearly_credited_balance(
+    contributions: &[Contribution],
+    cutoff_day: u32,
+    end: NaiveDate,
+    rate_on: impl Fn(NaiveDate) -> f64,
+) -> f64 {
+    let Some(first) = contributions.first() else {
+        return 0.0;
+    };
+    let mut balance = 0.0;
+    let mut accrued = 0.0;
+    let mut month = first.date.with_day(1).unwrap_or(first.date);
+    while let Some(next) = month.checked_add_months(Months::new(1)) {
+        let in_month = contributions
+            .iter()
+            .filter(|c| c.date >= month && c.date < next);
+        let deposited: f64 = in_month.clone().map(|c| c.amount).sum();
+        if next.pred_opt().is_none_or(|month_end| month_end > end) {
+            // Interest for the current month is only known at its end
+            balance += deposited;
+            break;
+        }
+
+        let early: f64 = in_month
+            .filter(|c| c.date.day() <= cutoff_day)
+            .map(|c| c.amount)
+            .sum();
+        accrued += (balance + early) * rate_on(month) / 1200.0;
+        balance += deposited;
+        if month.month() == 3 {
+            balance += accrued;
+            accrued = 0.0;
+        }
+        month = next;
+    }
+    balance + accrued
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn plan(scheme: Scheme, contributions: Vec<Contribution>) -> SavingsPlan {
+        SavingsPlan {
+            scheme,
+            start_date: date(2023, 4, 1),
+            maturity_date: None,
+            contributions,
+            recurring: None,
+            rates: vec![
+                RateChange {
+                    from: date(2023, 4, 1),
+                    rate: 7.2,
+                },
+                RateChange {
+                    from: date(2023, 10, 1),
+                    rate: 8.4,
+                },
+            ],
+        }
+    }
+
+    fn deposit(date: NaiveDate, amount: f64) -> Contribution {
+        Contribution { date, amount }
+    }
+
+    #[test]
+    fn test_ppf_interest_on_minimum_balance_credited_yearly() {
+        let on_time = plan(Scheme::Ppf, vec![deposit(date(2023, 4, 5), 120_000.0)]);
+        let late = plan(Scheme::Ppf, vec![deposit(date(2023, 4, 6), 120_000.0)]);
+
+        // 6 months at 0.6% and 6 months at 0.7% a month, credited on March 31
+        let year = 120_000.0 * (1.0 + 6.0 * 0.006 + 6.0 * 0.007);
+        assert!((on_time.value_on(date(2024, 3, 31)) - year).abs() < 1e-6);
+        // A deposit after the 5th misses the interest for April
+        let missed = year - 120_000.0 * 0.006;
+        assert!((late.value_on(date(2024, 3, 31)) - missed).abs() < 1e-6);
+
+        // Accrued interest is included before it is credited
+        let half = 120_000.0 * (1.0 + 6.0 * 0.006);
+        assert!((on_time.value_on(date(2023, 10, 15)) - half).abs() < 1e-6);
+        // Credited interest earns interest the next year
+        let april = year * (1.0 + 0.007);
+        assert!((on_time.value_on(date(2024, 4, 30)) - april).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_epf_contributions_earn_from_next_month() {
+        let mut epf = plan(Scheme::Epf, Vec::new());
+        epf.recurring = Some(RecurringContribution {
+            amount: 1000.0,
+            frequency: Compounding::Monthly,
+            until: None,
+        });
+        // Three deposits; April's earns for May and June, May's for June
+        let value = 3000.0 + 3.0 * 1000.0 * 0.006;
+        assert!((epf.value_on(date(2023, 6, 30)) - value).abs() < 1e-6);
+        // June's deposit is made but May's interest on it is not yet known
+        assert!((epf.value_on(date(2023, 6, 1)) - value + 2000.0 * 0.006).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_nsc_and_rd_compound_each_deposit() {
+        let nsc = plan(
+            Scheme::Nsc,
+            vec![
+                deposit(date(2023, 4, 1), 1000.0),
+                deposit(date(2023, 10, 1), 1000.0),
+            ],
+        );
+        // Each certificate keeps the rate on its purchase date
+        let value = 1000.0 * 1.072f64.powi(5) + 1000.0 * 1.084f64.powi(5);
+        assert!((nsc.value_on(date(2030, 1, 1)) - value).abs() < 1e-6);
+
+        let mut rd = plan(Scheme::Rd, Vec::new());
+        rd.maturity_date = Some(date(2024, 4, 1));
+        rd.recurring = Some(RecurringContribution {
+            amount: 1000.0,
+            frequency: Compounding::Quarterly,
+            until: None,
+        });
+        // Four deposits, no deposit on the maturity date, all at the opening rate
+        let value: f64 = (1..=4).map(|q| 1000.0 * 1.018f64.powi(q)).sum();
+        assert!((rd.value_on(date(2025, 1, 1)) - value).abs() < 1e-6);
+        assert_eq!(rd.contributions_until(date(2025, 1, 1)).len(), 4);
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = plan(Scheme::Ppf, vec![deposit(date(2023, 4, 5), 1000.0)]);
+        assert!(valid.validate().is_ok());
+        assert_eq!(valid.rate_on(date(2024, 1, 1)), 8.4);
+
+        let empty = plan(Scheme::Ppf, Vec::new());
+        assert!(empty.validate().is_err());
+        let early = plan(Scheme::Ppf, vec![deposit(date(2023, 3, 1), 1000.0)]);
+        assert!(early.validate().is_err());
+        let mut unordered = valid.clone();
+        unordered.rates.reverse();
+        assert!(unordered.validate().is_err());
+        let mut no_rates = valid;
+        no_rates.rates.clear();
+        assert!(no_rates.validate().is_err());
+    }
+}
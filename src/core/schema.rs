@@ -0,0 +1,82 @@
+//! JSON Schema of the config file, for editors that validate YAML against it.
+
+use crate::core::config::AppConfig;
+use crate::core::include::INCLUDE_KEY;
+use serde_json::json;
+
+/// Returns the JSON Schema of the config file, generated from its types.
+pub fn config_schema() -> serde_json::Value {
+    let mut schema = schemars::schema_for!(AppConfig);
+    // Includes are resolved before the config is parsed, so are not a field
+    if let Some(properties) = schema
+        .get_mut("properties")
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        properties.insert(
+            INCLUDE_KEY.into(),
+            json!({
+                "description": "Files, directories or glob patterns merged into this config",
+                "anyOf": [
+                    { "type": "string" },
+                    { "type": "array", "items": { "type": "string" } }
+                ],
+                "examples": [["shared.yaml", "portfolios/"]]
+            }),
+        );
+    }
+    schema.to_value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(config: &str) -> Vec<String> {
+        let validator = jsonschema::validator_for(&config_schema()).unwrap();
+        let instance: serde_json::Value = serde_yaml::from_str(config).unwrap();
+        validator
+            .iter_errors(&instance)
+            .map(|e| format!("{}: {e}", e.instance_path()))
+            .collect()
+    }
+
+    #[test]
+    fn test_example_config_matches_schema() {
+        let errors = errors(include_str!("../../docs/example_config.yaml"));
+        assert!(errors.is_empty(), "{errors:#?}");
+    }
+
+    #[test]
+    fn test_schema_rejects_invalid_config() {
+        let config = r#"
+currency: USD
+include: shared.yaml
+portfolios:
+  - name: Mine
+    investments:
+      - symbol: AAPL
+        units: ten
+      - isin: INF179KB1HU9
+        units: 1
+        colour: blue
+"#;
+        let errors = errors(config);
+        assert!(!errors.is_empty());
+        assert!(
+            errors
+                .iter()
+                .all(|e| e.starts_with("/portfolios/0/investments/")),
+            "{errors:#?}"
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.starts_with("/portfolios/0/investments/0"))
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.starts_with("/portfolios/0/investments/1"))
+        );
+    }
+}
@@ -0,0 +1,826 @@
+//! Checks a config file for problems, reporting each at its line and column.
+
+use crate::core::allocation::{self, AssetCategory, AssetClassConfig, TargetAllocation};
+use crate::core::analytics::ReturnsConfig;
+use crate::core::benchmark::{BenchmarkConfig, is_isin};
+use crate::core::config::{
+    AppConfig, CacheConfig, Investment, NetworkConfig, Portfolio, ProvidersConfig,
+};
+use crate::core::correlation::CorrelationConfig;
+use crate::core::include;
+use crate::core::migrate::{self, CURRENT_VERSION, VERSION_KEY};
+use crate::core::risk::RiskConfig;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor,
+};
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Active ISO 4217 currency codes, plus gold and silver.
+const CURRENCY_CODES: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD", "CAD",
+    "CDF", "CHF", "CLP", "CNY", "COP", "CRC", "CUP", "CVE", "CZK", "DJF", "DKK", "DOP", "DZD",
+    "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP", "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ",
+    "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS", "INR", "IQD", "IRR", "ISK", "JMD", "JOD",
+    "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW", "KWD", "KYD", "KZT", "LAK", "LBP", "LKR",
+    "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD", "MMK", "MNT", "MOP", "MRU", "MUR", "MVR",
+    "MWK", "MXN", "MYR", "MZN", "NAD", "NGN", "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN",
+    "PGK", "PHP", "PKR", "PLN", "PYG", "QAR", "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR",
+    "SDG", "SEK", "SGD", "SHP", "SLE", "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB",
+    "TJS", "TMT", "TND", "TOP", "TRY", "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "UYU", "UZS",
+    "VES", "VND", "VUV", "WST", "XAF", "XAG", "XAU", "XCD", "XOF", "XPF", "YER", "ZAR", "ZMW",
+    "ZWL",
+];
+
+/// Provider cache collections whose limits can be overridden under `cache.collections`.
+const CACHE_COLLECTIONS: &[&str] = &["yahoo", "amfi", "currency", "metadata"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Line and column of a node in the config file, both starting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl From<serde_yaml::Location> for Location {
+    fn from(location: serde_yaml::Location) -> Self {
+        Location {
+            line: location.line(),
+            column: location.column(),
+        }
+    }
+}
+
+/// A problem found in the config, at the node it concerns when known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub location: Option<Location>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic at the node found by following `path` in `text`.
+    pub fn at(text: &str, path: &Path, severity: Severity, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            location: locate(text, path),
+            message: message.into(),
+        }
+    }
+
+    fn from_yaml_error(error: &serde_yaml::Error) -> Self {
+        let location = error.location().map(Location::from);
+        let message = error.to_string();
+        // The location is reported separately
+        let message = match location {
+            Some(l) => message
+                .strip_suffix(&format!(" at line {} column {}", l.line, l.column))
+                .map(str::to_string)
+                .unwrap_or(message),
+            None => message,
+        };
+        Diagnostic {
+            severity: Severity::Error,
+            location,
+            message,
+        }
+    }
+}
+
+/// A step into a YAML document.
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Path from the root of a YAML document to one of its nodes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path(Vec<Segment>);
+
+impl Path {
+    /// Returns the path to the value of `key` in the mapping at this path.
+    pub fn key(&self, key: &str) -> Path {
+        self.with(Segment::Key(key.to_string()))
+    }
+
+    /// Returns the path to an item of the sequence at this path.
+    pub fn index(&self, index: usize) -> Path {
+        self.with(Segment::Index(index))
+    }
+
+    fn with(&self, segment: Segment) -> Path {
+        let mut segments = self.0.clone();
+        segments.push(segment);
+        Path(segments)
+    }
+}
+
+/// Finds the line and column of the node at `path`, or of its key when it is
+/// a mapping value, by deserializing the document up to it and failing there
+/// so the parser reports its position.
+pub fn locate(text: &str, path: &Path) -> Option<Location> {
+    match Locate(&path.0).deserialize(serde_yaml::Deserializer::from_str(text)) {
+        Ok(()) => None,
+        Err(e) => e.location().map(Location::from),
+    }
+}
+
+struct Locate<'a>(&'a [Segment]);
+
+impl<'de> DeserializeSeed<'de> for Locate<'_> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        match self.0.split_first() {
+            None => deserializer.deserialize_any(Target),
+            Some((Segment::Key(key), rest)) => deserializer.deserialize_map(FindKey { key, rest }),
+            Some((Segment::Index(index), rest)) => deserializer.deserialize_seq(FindIndex {
+                index: *index,
+                rest,
+            }),
+        }
+    }
+}
+
+/// Rejects any node, failing at the node located.
+struct Target;
+
+impl<'de> Visitor<'de> for Target {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the located node")
+    }
+}
+
+struct FindKey<'a> {
+    key: &'a str,
+    rest: &'a [Segment],
+}
+
+impl<'de> Visitor<'de> for FindKey<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a mapping")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let seed = || MatchKey {
+            key: self.key,
+            last: self.rest.is_empty(),
+        };
+        while let Some(found) = map.next_key_seed(seed())? {
+            if found {
+                map.next_value_seed(Locate(self.rest))?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares a mapping key with the one looked for, failing at it if it is
+/// the last step of the path.
+struct MatchKey<'a> {
+    key: &'a str,
+    last: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for MatchKey<'_> {
+    type Value = bool;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<bool, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for MatchKey<'_> {
+    type Value = bool;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a mapping key")
+    }
+
+    fn visit_str<E: de::Error>(self, key: &str) -> Result<bool, E> {
+        match key == self.key {
+            true if self.last => Err(E::custom("located")),
+            found => Ok(found),
+        }
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<bool, E> {
+        Ok(false)
+    }
+}
+
+struct FindIndex<'a> {
+    index: usize,
+    rest: &'a [Segment],
+}
+
+impl<'de> Visitor<'de> for FindIndex<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        for _ in 0..self.index {
+            if seq.next_element::<IgnoredAny>()?.is_none() {
+                return Ok(());
+            }
+        }
+        seq.next_element_seed(Locate(self.rest))?;
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(())
+    }
+}
+
+/// Returns true if the ISIN's check digit matches the Luhn checksum of its
+/// other characters, with letters counted as 10 to 35.
+pub fn has_valid_isin_checksum(isin: &str) -> bool {
+    let mut digits = Vec::new();
+    for c in isin.chars() {
+        let Some(value) = c.to_digit(36) else {
+            return false;
+        };
+        if value >= 10 {
+            digits.push(value / 10);
+        }
+        digits.push(value % 10);
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (1, doubled) if doubled > 9 => doubled - 9,
+            (1, doubled) => doubled,
+            _ => d,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Checks a config for every problem it has, rather than stopping at the first.
+pub fn validate(text: &str) -> Vec<Diagnostic> {
+    validate_with(text, true)
+}
+
+/// Checks one file of a config split up with `include`, which may leave
+/// required settings to other files or hold a single portfolio.
+pub fn validate_part(text: &str) -> Vec<Diagnostic> {
+    validate_with(text, false)
+}
+
+fn validate_with(text: &str, complete: bool) -> Vec<Diagnostic> {
+    let mut root: Value = match serde_yaml::from_str(text) {
+        Ok(root) => root,
+        Err(e) => return vec![Diagnostic::from_yaml_error(&e)],
+    };
+    let mut validator = Validator {
+        text,
+        complete,
+        diagnostics: Vec::new(),
+    };
+    if let Err(e) = include::expand_env_vars(&mut root) {
+        validator.error(&Path::default(), format!("{e:#}"));
+        return validator.diagnostics;
+    }
+    validator.check(&root);
+    if complete
+        && !validator.has_errors()
+        && let Err(e) = serde_yaml::from_str::<AppConfig>(text)
+    {
+        // Anything the checks above missed
+        validator.diagnostics.push(Diagnostic::from_yaml_error(&e));
+    }
+    validator.diagnostics
+}
+
+struct Validator<'a> {
+    text: &'a str,
+    /// Whether the file holds the whole config rather than part of it
+    complete: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn error(&mut self, path: &Path, message: impl Into<String>) {
+        let diagnostic = Diagnostic::at(self.text, path, Severity::Error, message);
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn warning(&mut self, path: &Path, message: impl Into<String>) {
+        let diagnostic = Diagnostic::at(self.text, path, Severity::Warning, message);
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+
+    /// Parses an optional top-level section, reporting it if invalid.
+    fn section<T: DeserializeOwned + Default>(&mut self, root: &Value, key: &str) -> T {
+        let Some(value) = root.get(key) else {
+            return T::default();
+        };
+        serde_yaml::from_value(value.clone()).unwrap_or_else(|e| {
+            self.error(&Path::default().key(key), format!("invalid {key}: {e}"));
+            T::default()
+        })
+    }
+
+    fn check(&mut self, root: &Value) {
+        let root_path = Path::default();
+        if !root.is_mapping() {
+            self.error(&root_path, "expected a mapping of config settings");
+            return;
+        }
+        if !self.complete && root.get("investments").is_some() {
+            self.check_portfolio(&root_path, root, &AssetClassConfig::default());
+            return;
+        }
+
+        let version = match migrate::version(root) {
+            Ok(version) => version,
+            Err(e) => {
+                self.error(&root_path.key(VERSION_KEY), e.to_string());
+                CURRENT_VERSION
+            }
+        };
+        if self.complete && version < CURRENT_VERSION {
+            let message = migrate::deprecation(version);
+            self.warning(&root_path.key(VERSION_KEY), message);
+        }
+
+        match root.get("currency") {
+            Some(Value::String(code)) => self.check_currency(&root_path.key("currency"), code),
+            Some(_) => self.error(&root_path.key("currency"), "currency must be a string"),
+            None if self.complete => self.error(&root_path, "missing field `currency`"),
+            None => {}
+        }
+
+        self.section::<ProvidersConfig>(root, "providers");
+        self.section::<NetworkConfig>(root, "network");
+        self.section::<RiskConfig>(root, "risk");
+        self.section::<CorrelationConfig>(root, "correlation");
+        let cache: CacheConfig = self.section(root, "cache");
+        for name in cache.collections.keys() {
+            if !CACHE_COLLECTIONS.contains(&name.as_str()) {
+                self.error(
+                    &root_path.key("cache").key("collections").key(name),
+                    format!(
+                        "unknown cache collection '{name}', expected one of {}",
+                        CACHE_COLLECTIONS.join(", ")
+                    ),
+                );
+            }
+        }
+        let asset_classes: AssetClassConfig = self.section(root, "asset_classes");
+        if let Err(e) = asset_classes.validate() {
+            self.error(&root_path.key("asset_classes"), e.to_string());
+        }
+        let returns: ReturnsConfig = self.section(root, "returns");
+        if let Err(e) = returns.validate() {
+            self.error(&root_path.key("returns"), e.to_string());
+        }
+        let benchmarks: BenchmarkConfig = self.section(root, "benchmarks");
+        for (i, rule) in benchmarks.rules.iter().enumerate() {
+            let path = root_path
+                .key("benchmarks")
+                .key("rules")
+                .index(i)
+                .key("benchmark");
+            self.check_benchmark(&path, &rule.benchmark);
+        }
+
+        let portfolios = match root.get("portfolios") {
+            Some(Value::Sequence(portfolios)) => portfolios.as_slice(),
+            Some(_) => {
+                self.error(&root_path.key("portfolios"), "portfolios must be a list");
+                &[]
+            }
+            None => {
+                if self.complete {
+                    self.error(&root_path, "missing field `portfolios`");
+                }
+                &[]
+            }
+        };
+        let mut names: HashMap<String, usize> = HashMap::new();
+        let mut all_investments = Vec::new();
+        for (i, portfolio) in portfolios.iter().enumerate() {
+            let path = root_path.key("portfolios").index(i);
+            let investments = self.check_portfolio(&path, portfolio, &asset_classes);
+            if let Some(name) = portfolio.get("name").and_then(Value::as_str) {
+                match names.get(&name.to_lowercase()) {
+                    Some(first) => {
+                        let first_name = root_path.key("portfolios").index(*first).key("name");
+                        let line = locate(self.text, &first_name)
+                            .map(|l| format!(" on line {}", l.line))
+                            .unwrap_or_default();
+                        self.error(
+                            &path.key("name"),
+                            format!("duplicate portfolio name '{name}', first used{line}"),
+                        );
+                    }
+                    None => {
+                        names.insert(name.to_lowercase(), i);
+                    }
+                }
+            }
+            all_investments.extend(investments);
+        }
+
+        if let Some(value) = root.get("target_allocation") {
+            let path = root_path.key("target_allocation");
+            self.check_targets(&path, value, &all_investments, &asset_classes);
+        }
+    }
+
+    /// Checks a portfolio and returns its valid investments.
+    fn check_portfolio(
+        &mut self,
+        path: &Path,
+        portfolio: &Value,
+        asset_classes: &AssetClassConfig,
+    ) -> Vec<Investment> {
+        let Some(fields) = portfolio.as_mapping() else {
+            self.error(
+                path,
+                "expected a mapping with the portfolio's name and investments",
+            );
+            return Vec::new();
+        };
+        // Check the portfolio's own fields apart from its investments
+        let mut own_fields = fields.clone();
+        let investments = own_fields.insert("investments".into(), Value::Sequence(Vec::new()));
+        if let Err(e) = serde_yaml::from_value::<Portfolio>(own_fields.into()) {
+            self.error(path, format!("invalid portfolio: {e}"));
+        }
+        let name = fields
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if let Some(benchmark) = fields.get("benchmark").and_then(Value::as_str) {
+            self.check_benchmark(&path.key("benchmark"), benchmark);
+        }
+
+        let mut valid = Vec::new();
+        if let Some(Value::Sequence(investments)) = &investments {
+            for (j, value) in investments.iter().enumerate() {
+                let path = path.key("investments").index(j);
+                match Investment::from_value(value.clone()) {
+                    Ok(investment) => {
+                        self.check_investment(&path, &investment, asset_classes);
+                        valid.push(investment);
+                    }
+                    Err(e) => {
+                        let message = e.to_string();
+                        let field = ["invalid field `", "unknown field `"]
+                            .iter()
+                            .find_map(|prefix| message.strip_prefix(prefix))
+                            .and_then(|rest| rest.split('`').next());
+                        let path = field.map_or(path.clone(), |field| path.key(field));
+                        self.error(
+                            &path,
+                            format!("Portfolio '{name}', investments[{j}]: {message}"),
+                        );
+                    }
+                }
+            }
+        }
+
+        if let Some(value) = fields.get("target_allocation") {
+            self.check_targets(&path.key("target_allocation"), value, &valid, asset_classes);
+        }
+        valid
+    }
+
+    fn check_investment(
+        &mut self,
+        path: &Path,
+        investment: &Investment,
+        asset_classes: &AssetClassConfig,
+    ) {
+        let (units, currency, category, benchmark) = match investment {
+            Investment::Stock(s) => (Some(s.units), None, &s.category, &s.benchmark),
+            Investment::MutualFund(mf) => {
+                if !is_isin(&mf.isin) {
+                    self.error(&path.key("isin"), format!("'{}' is not an ISIN", mf.isin));
+                } else if !has_valid_isin_checksum(&mf.isin) {
+                    self.error(
+                        &path.key("isin"),
+                        format!("ISIN '{}' has an invalid check digit", mf.isin),
+                    );
+                }
+                (Some(mf.units), None, &mf.category, &mf.benchmark)
+            }
+            Investment::FixedDeposit(fd) => {
+                if let Err(e) = fd.validate() {
+                    self.error(path, e.to_string());
+                }
+                (None, fd.currency.as_ref(), &fd.category, &None)
+            }
+            Investment::SmallSavings(s) => {
+                if let Err(e) = s.plan.validate() {
+                    self.error(path, format!("'{}': {e}", s.name));
+                }
+                (None, s.currency.as_ref(), &s.category, &None)
+            }
+            Investment::Bond(b) => {
+                if let Err(e) = b.validate() {
+                    self.error(path, e.to_string());
+                }
+                (Some(b.units), b.currency.as_ref(), &b.category, &None)
+            }
+        };
+        if let Some(units) = units.filter(|units| *units < 0.0) {
+            self.error(
+                &path.key("units"),
+                format!("units must not be negative, got {units}"),
+            );
+        }
+        if let Some(code) = currency {
+            self.check_currency(&path.key("currency"), code);
+        }
+        if let Some(benchmark) = benchmark {
+            self.check_benchmark(&path.key("benchmark"), benchmark);
+        }
+        if let Some(category) = category
+            && asset_classes.classify(category) == AssetCategory::Other
+            && !category.eq_ignore_ascii_case("other")
+        {
+            self.warning(
+                &path.key("category"),
+                format!(
+                    "category '{category}' is not a known asset class and is counted as Other, \
+                     add a rule under asset_classes to classify it"
+                ),
+            );
+        }
+    }
+
+    fn check_currency(&mut self, path: &Path, code: &str) {
+        if !CURRENCY_CODES.contains(&code) {
+            self.error(path, format!("unknown currency code '{code}'"));
+        }
+    }
+
+    /// Checks the check digit of benchmarks given as an ISIN.
+    fn check_benchmark(&mut self, path: &Path, benchmark: &str) {
+        if is_isin(benchmark) && !has_valid_isin_checksum(benchmark) {
+            self.error(
+                path,
+                format!("benchmark ISIN '{benchmark}' has an invalid check digit"),
+            );
+        }
+    }
+
+    /// Checks target weights, and warns about categories no investment has.
+    fn check_targets(
+        &mut self,
+        path: &Path,
+        value: &Value,
+        investments: &[Investment],
+        asset_classes: &AssetClassConfig,
+    ) {
+        let targets: TargetAllocation = match serde_yaml::from_value(value.clone()) {
+            Ok(targets) => targets,
+            Err(e) => {
+                self.error(path, format!("invalid target allocation: {e}"));
+                return;
+            }
+        };
+        if let Err(e) = allocation::validate_targets(&targets) {
+            self.error(path, e.to_string());
+        }
+        // Categories of funds without one are only known once fetched
+        let mut categories = Vec::new();
+        for investment in investments {
+            match investment {
+                Investment::Stock(s) => categories.push(s.category.as_deref()),
+                Investment::MutualFund(mf) if mf.category.is_none() => return,
+                Investment::MutualFund(mf) => categories.push(mf.category.as_deref()),
+                Investment::FixedDeposit(fd) => categories.push(fd.category.as_deref()),
+                Investment::SmallSavings(s) => categories.push(s.category.as_deref()),
+                Investment::Bond(b) => categories.push(b.category.as_deref()),
+            }
+        }
+        let mut unknown: Vec<&String> = targets
+            .keys()
+            .filter(|target| {
+                asset_classes.classify(target) == AssetCategory::Other
+                    && !target.eq_ignore_ascii_case("other")
+                    && !categories
+                        .iter()
+                        .flatten()
+                        .any(|category| category.eq_ignore_ascii_case(target))
+            })
+            .collect();
+        unknown.sort();
+        for target in unknown {
+            self.warning(
+                &path.key(target),
+                format!("target category '{target}' matches no investment or asset class"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<(Severity, Option<usize>, &str)> {
+        diagnostics
+            .iter()
+            .map(|d| (d.severity, d.location.map(|l| l.line), d.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_isin_checksum() {
+        assert!(has_valid_isin_checksum("US0378331005"));
+        assert!(has_valid_isin_checksum("INF179KB1HU9"));
+        assert!(!has_valid_isin_checksum("US0378331006"));
+        assert!(!has_valid_isin_checksum("INF179KB1HU-"));
+    }
+
+    #[test]
+    fn test_locate() {
+        let text = "currency: USD\nportfolios:\n  - name: A\n    investments:\n      - symbol: X\n        units: 1\n";
+        let units = Path::default()
+            .key("portfolios")
+            .index(0)
+            .key("investments")
+            .index(0)
+            .key("units");
+        assert_eq!(locate(text, &units).map(|l| l.line), Some(6));
+        let currency = locate(text, &Path::default().key("currency")).unwrap();
+        assert_eq!((currency.line, currency.column), (1, 1));
+        assert_eq!(locate(text, &Path::default().key("missing")), None);
+    }
+
+    #[test]
+    fn test_validate_reports_all_problems() {
+        let text = r#"currency: USDX
+portfolios:
+  - name: Mine
+    investments:
+      - symbol: AAPL
+        units: -2
+      - isin: US0378331006
+        units: 1
+      - name: Deposit
+        value: 100
+        currency: inr
+      - symbol: MSFT
+        units: ten
+      - symbol: GOOG
+        units: 1
+        category: Moonshots
+  - name: mine
+    target_allocation:
+      Equity: 60
+      Crypto: 40
+    investments: []
+cache:
+  collections:
+    yahooo:
+      max_entries: 10
+version: 2
+"#;
+        let diagnostics = validate(text);
+        assert_eq!(
+            messages(&diagnostics),
+            vec![
+                (Severity::Error, Some(1), "unknown currency code 'USDX'"),
+                (
+                    Severity::Error,
+                    Some(24),
+                    "unknown cache collection 'yahooo', expected one of yahoo, amfi, currency, metadata"
+                ),
+                (
+                    Severity::Error,
+                    Some(6),
+                    "units must not be negative, got -2"
+                ),
+                (
+                    Severity::Error,
+                    Some(7),
+                    "ISIN 'US0378331006' has an invalid check digit"
+                ),
+                (Severity::Error, Some(11), "unknown currency code 'inr'"),
+                (
+                    Severity::Error,
+                    Some(13),
+                    "Portfolio 'Mine', investments[3]: invalid field `units`: invalid type: string \"ten\", expected f64"
+                ),
+                (
+                    Severity::Warning,
+                    Some(16),
+                    "category 'Moonshots' is not a known asset class and is counted as Other, add a rule under asset_classes to classify it"
+                ),
+                (
+                    Severity::Warning,
+                    Some(20),
+                    "target category 'Crypto' matches no investment or asset class"
+                ),
+                (
+                    Severity::Error,
+                    Some(17),
+                    "duplicate portfolio name 'mine', first used on line 3"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_syntax_error_parts_and_valid_config() {
+        let diagnostics = validate("currency: USD\nportfolios: [\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert!(diagnostics[0].location.is_some());
+
+        let portfolio = "name: A\ninvestments:\n  - symbol: X\n    units: -1\n";
+        let diagnostics = validate_part(portfolio);
+        assert_eq!(
+            messages(&diagnostics),
+            vec![(
+                Severity::Error,
+                Some(4),
+                "units must not be negative, got -1"
+            )]
+        );
+        assert_eq!(validate_part("include: people/\n"), vec![]);
+
+        let valid = "version: 2\ncurrency: INR\nportfolios:\n  - name: A\n    investments:\n      - isin: INF179KB1HU9\n        units: 5\n";
+        assert_eq!(validate(valid), vec![]);
+    }
+
+    #[test]
+    fn test_validate_legacy_config() {
+        let legacy = "currency: INR\nportfolios:\n  - name: A\n    investments:\n      - symbol: X\n        units: -1\n";
+        assert_eq!(
+            messages(&validate(legacy)),
+            vec![
+                (
+                    Severity::Warning,
+                    None,
+                    "config version 1 is deprecated, run `xmf config migrate` to upgrade it to version 2"
+                ),
+                (
+                    Severity::Error,
+                    Some(6),
+                    "units must not be negative, got -1"
+                ),
+            ]
+        );
+
+        let diagnostics = validate("version: 3\ncurrency: INR\nportfolios: []\n");
+        assert_eq!(diagnostics[0].location.map(|l| l.line), Some(1));
+        assert!(diagnostics[0].message.contains("newer"));
+    }
+}
@@ -3,26 +3,130 @@ pub mod core;
 pub mod providers;
 pub mod store;
 
+use crate::core::ProviderError;
+use crate::providers::http::{HttpClient, HttpOptions};
 use crate::store::KeyValueStore;
 use anyhow::Result;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
 use tracing::{debug, info};
 
 /// Commands that require full provider setup, or setup command
 pub enum AppCommand {
     Summary,
-    Change,
-    Returns { rolling_period: Option<String> },
+    Change {
+        periods: Vec<String>,
+    },
+    Returns {
+        periods: Vec<String>,
+        rolling_period: Option<String>,
+        calendar: bool,
+        fiscal: bool,
+    },
     Fees,
+    Risk {
+        period: Option<String>,
+        risk_free_rate: Option<f64>,
+    },
+    Correlation {
+        period: String,
+        weekly: bool,
+        threshold: Option<f64>,
+        csv: Option<std::path::PathBuf>,
+    },
     Alloc,
+    Bonds {
+        within_days: u32,
+    },
+    Maturities {
+        within_days: u32,
+    },
+    Rebalance {
+        tolerance: f64,
+        new_money: Option<f64>,
+    },
+    Cache(CacheAction),
+    Config(ConfigAction),
+    Edit(EditAction),
     Setup,
 }
 
+/// Maintenance actions for the provider data cache
+pub enum CacheAction {
+    List,
+    Sweep,
+}
+
+/// Actions on the config file itself, run without loading it
+pub enum ConfigAction {
+    Validate { check_urls: bool },
+    Schema,
+    Migrate { dry_run: bool },
+}
+
+/// Changes to the investments of the portfolio selected with `--portfolio`
+pub enum EditAction {
+    Add {
+        investment: core::edit::InvestmentId,
+        units: f64,
+        category: Option<String>,
+        verify: bool,
+    },
+    Remove {
+        investment: core::edit::InvestmentId,
+    },
+    SetUnits {
+        investment: core::edit::InvestmentId,
+        units: f64,
+    },
+    AddTxn {
+        investment: core::edit::InvestmentId,
+        units: f64,
+        price: f64,
+        date: chrono::NaiveDate,
+    },
+    Import {
+        format: core::import::ImportFormat,
+        file: std::path::PathBuf,
+        map: Option<core::import::ColumnMap>,
+        dry_run: bool,
+        force: bool,
+    },
+}
+
+/// Which portfolios a command reports on, and how they are combined
+#[derive(Debug, Clone, Default)]
+pub struct PortfolioView {
+    /// Portfolios and investments to report on
+    pub filter: core::config::PortfolioFilter,
+    /// Merge holdings across portfolios into a single view
+    pub consolidated: bool,
+}
+
+/// Name of the merged portfolio shown in consolidated mode
+pub const CONSOLIDATED_PORTFOLIO_NAME: &str = "Consolidated";
+
+/// Process exit code for a failed command, by provider error class.
+///
+/// Errors not caused by a provider exit with 1.
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    match error.downcast_ref::<ProviderError>() {
+        Some(ProviderError::NotFound(_)) => 3,
+        Some(ProviderError::Network(_)) => 4,
+        Some(ProviderError::RateLimited(_)) => 5,
+        Some(ProviderError::InvalidResponse(_) | ProviderError::Other(_)) => 6,
+        None => 1,
+    }
+}
+
 /// Common command execution entry point
 pub async fn run_command(
     command: AppCommand,
     config_path: Option<&std::path::Path>,
     force_refresh: bool,
+    offline: bool,
+    view: &PortfolioView,
 ) -> Result<()> {
     match command {
         AppCommand::Setup => {
@@ -33,6 +137,20 @@ pub async fn run_command(
                 cli::setup::setup()
             }
         }
+        AppCommand::Config(ConfigAction::Schema) => cli::config::schema(),
+        AppCommand::Config(action) => {
+            let path = match config_path {
+                Some(path) => path.to_path_buf(),
+                None => core::config::AppConfig::default_config_path()?,
+            };
+            match action {
+                ConfigAction::Validate { check_urls } => {
+                    cli::config::validate(&path, check_urls).await
+                }
+                ConfigAction::Migrate { dry_run } => cli::config::migrate(&path, dry_run),
+                ConfigAction::Schema => unreachable!(), // Handled above
+            }
+        }
         _ => {
             // All other commands follow the normal flow
             let config = match config_path {
@@ -40,119 +158,389 @@ pub async fn run_command(
                 None => core::config::AppConfig::load()?,
             };
             debug!("Loaded config: {config:#?}");
+            cli::config::warn_if_outdated(&config);
 
             // Create shared caches
             let data_path = config
                 .default_data_path()
                 .expect("Failed to get default data path");
-            let store = Arc::new(KeyValueStore::new(data_path.as_path()));
+            // Offline, expired data is better than none
+            let store = Arc::new(
+                KeyValueStore::with_config(data_path.as_path(), config.cache.clone())
+                    .serving_stale(offline),
+            );
 
             if force_refresh {
                 info!("--refresh: clearing persistent cache");
                 store.clear_persistent_cache()?;
             }
 
+            if let AppCommand::Cache(action) = &command {
+                return match action {
+                    CacheAction::List => cli::cache::list(&store).await,
+                    CacheAction::Sweep => cli::cache::sweep(&store).await,
+                };
+            }
+
+            if let AppCommand::Edit(action) = &command {
+                let path = match config_path {
+                    Some(path) => path.to_path_buf(),
+                    None => core::config::AppConfig::default_config_path()?,
+                };
+                let [portfolio] = view.filter.names.as_slice() else {
+                    anyhow::bail!("Select the portfolio to edit with a single --portfolio <NAME>");
+                };
+                return match action {
+                    EditAction::Add {
+                        investment,
+                        units,
+                        category,
+                        verify,
+                    } => {
+                        let (symbol_provider, isin_provider, _, _) =
+                            setup_providers(&config, &store, offline)?;
+                        cli::edit::add(
+                            &path,
+                            portfolio,
+                            investment,
+                            *units,
+                            category.as_deref(),
+                            *verify,
+                            &*symbol_provider,
+                            &*isin_provider,
+                        )
+                        .await
+                    }
+                    EditAction::Remove { investment } => {
+                        cli::edit::remove(&path, portfolio, investment)
+                    }
+                    EditAction::SetUnits { investment, units } => {
+                        cli::edit::set_units(&path, portfolio, investment, *units)
+                    }
+                    EditAction::AddTxn {
+                        investment,
+                        units,
+                        price,
+                        date,
+                    } => cli::edit::add_txn(&path, portfolio, investment, *units, *price, *date),
+                    EditAction::Import {
+                        format,
+                        file,
+                        map,
+                        dry_run,
+                        force,
+                    } => cli::import::import(
+                        &config,
+                        &path,
+                        portfolio,
+                        file,
+                        *format,
+                        map.as_ref(),
+                        *dry_run,
+                        *force,
+                    ),
+                };
+            }
+
+            // Filter before setting up providers so unselected holdings are never fetched
+            let selected = view.filter.apply(&config.portfolios)?;
+
             // Initialize providers
             let (symbol_provider, isin_provider, currency_provider, metadata_provider) =
-                setup_providers(&config, &store);
+                setup_providers(&config, &store, offline)?;
+
+            let merged = view.consolidated.then(|| {
+                let (merged, warnings) = core::config::Portfolio::consolidate(
+                    &selected,
+                    CONSOLIDATED_PORTFOLIO_NAME,
+                    config.target_allocation.as_ref(),
+                );
+                for warning in &warnings {
+                    cli::ui::print_warning(warning);
+                }
+                merged
+            });
+            // The merged portfolio already resolved the global target, and has
+            // none when the portfolios disagree
+            let global_target = match &merged {
+                Some(_) => None,
+                None => config.target_allocation.as_ref(),
+            };
+            let portfolios = match &merged {
+                Some(merged) => vec![merged.clone()],
+                None => selected.clone(),
+            };
+            // Consolidated views are followed by the value of each portfolio
+            let breakdown = async |separate: bool| {
+                if view.consolidated {
+                    if separate {
+                        cli::ui::print_separator();
+                    }
+                    cli::summary::print_breakdown(
+                        &selected,
+                        &*symbol_provider,
+                        &*isin_provider,
+                        &*currency_provider,
+                        &config.currency,
+                    )
+                    .await;
+                }
+            };
 
-            match command {
+            let result = match command {
                 AppCommand::Summary => {
                     cli::summary::run(
-                        &config.portfolios,
+                        &selected,
                         &*symbol_provider,
                         &*isin_provider,
                         &*currency_provider,
                         &config.currency,
+                        merged.as_ref(),
                     )
                     .await
                 }
-                AppCommand::Change => {
+                AppCommand::Change { periods } => {
                     cli::change::run(
-                        &config.portfolios,
+                        &portfolios,
                         &*symbol_provider,
                         &*isin_provider,
                         &*currency_provider,
                         &config.currency,
+                        &periods,
                     )
                     .await
                 }
-                AppCommand::Returns { rolling_period } => {
-                    cli::returns::run(
-                        &config.portfolios,
+                AppCommand::Returns {
+                    periods,
+                    rolling_period,
+                    calendar,
+                    fiscal,
+                } => {
+                    let mode = if fiscal {
+                        cli::returns::ReturnsMode::Yearly {
+                            start_month: config.returns.fiscal_year_start,
+                        }
+                    } else if calendar {
+                        cli::returns::ReturnsMode::Yearly { start_month: 1 }
+                    } else if let Some(period) = &rolling_period {
+                        cli::returns::ReturnsMode::Rolling(period)
+                    } else {
+                        cli::returns::ReturnsMode::Trailing(&periods)
+                    };
+                    let options = cli::returns::ReturnsOptions {
+                        mode,
+                        returns_config: &config.returns,
+                        benchmark_config: &config.benchmarks,
+                        risk_free_rate: config.risk.risk_free_rate,
+                    };
+                    let result = cli::returns::run(
+                        &portfolios,
                         &*symbol_provider,
                         &*isin_provider,
                         &*currency_provider,
+                        &*metadata_provider,
                         &config.currency,
-                        rolling_period.as_deref(),
+                        &options,
                     )
-                    .await
+                    .await;
+                    breakdown(true).await;
+                    result
                 }
                 AppCommand::Fees => {
-                    cli::fees::run(
-                        &config.portfolios,
+                    let result = cli::fees::run(
+                        &portfolios,
                         &*symbol_provider,
                         &*isin_provider,
                         &*currency_provider,
                         &*metadata_provider,
                         &config.currency,
                     )
+                    .await;
+                    breakdown(true).await;
+                    result
+                }
+                AppCommand::Risk {
+                    period,
+                    risk_free_rate,
+                } => {
+                    cli::risk::run(
+                        &portfolios,
+                        &*symbol_provider,
+                        &*isin_provider,
+                        &*currency_provider,
+                        &config.currency,
+                        period.as_deref(),
+                        risk_free_rate.unwrap_or(config.risk.risk_free_rate),
+                    )
+                    .await
+                }
+                AppCommand::Correlation {
+                    period,
+                    weekly,
+                    threshold,
+                    csv,
+                } => {
+                    cli::correlation::run(
+                        &portfolios,
+                        &*symbol_provider,
+                        &*isin_provider,
+                        &*currency_provider,
+                        &config.currency,
+                        &period,
+                        weekly,
+                        threshold.unwrap_or(config.correlation.threshold),
+                        csv.as_deref(),
+                    )
                     .await
                 }
                 AppCommand::Alloc => {
-                    cli::alloc::run(
-                        &config.portfolios,
+                    let result = cli::alloc::run(
+                        &portfolios,
                         &*symbol_provider,
                         &*isin_provider,
                         &*currency_provider,
                         &*metadata_provider,
                         &config.currency,
+                        global_target,
+                        &config.asset_classes,
+                    )
+                    .await;
+                    breakdown(false).await;
+                    result
+                }
+                AppCommand::Bonds { within_days } => {
+                    cli::bonds::run(
+                        &portfolios,
+                        &*symbol_provider,
+                        &*currency_provider,
+                        &config.currency,
+                        within_days,
                     )
                     .await
                 }
-                AppCommand::Setup => unreachable!(), // Handled above
+                AppCommand::Maturities { within_days } => {
+                    cli::maturities::run(&portfolios, &config.currency, within_days)
+                }
+                AppCommand::Rebalance {
+                    tolerance,
+                    new_money,
+                } => {
+                    if merged
+                        .as_ref()
+                        .is_some_and(|m| m.target_allocation.is_none())
+                        && selected.iter().any(|p| {
+                            p.target_allocation(config.target_allocation.as_ref())
+                                .is_some()
+                        })
+                    {
+                        anyhow::bail!(
+                            "The selected portfolios have different target allocations, rebalance them without --consolidated"
+                        );
+                    }
+                    cli::rebalance::run(
+                        &portfolios,
+                        &*symbol_provider,
+                        &*isin_provider,
+                        &*currency_provider,
+                        &*metadata_provider,
+                        &config.currency,
+                        global_target,
+                        tolerance,
+                        new_money,
+                    )
+                    .await
+                }
+                AppCommand::Setup
+                | AppCommand::Cache(_)
+                | AppCommand::Config(_)
+                | AppCommand::Edit(_) => {
+                    unreachable!()
+                } // Handled above
+            };
+
+            let stale_hits = store.stale_hits().await;
+            if stale_hits > 0 {
+                cli::ui::print_warning(&format!(
+                    "{stale_hits} cached lookup(s) were past their expiry, results may be out of date"
+                ));
             }
+            result
         }
     }
 }
 
-fn setup_providers(
-    config: &core::config::AppConfig,
-    store: &Arc<KeyValueStore>,
-) -> (
+type Providers = (
     Arc<providers::yahoo_finance::YahooFinanceProvider>,
     Arc<providers::amfi_provider::AmfiProvider>,
     Arc<providers::yahoo_finance::YahooCurrencyProvider>,
     Arc<providers::kuvera_provider::KuveraProvider>,
-) {
-    let yahoo_base = config
-        .providers
-        .yahoo
-        .as_ref()
-        .map_or("https://query1.finance.yahoo.com", |p| &p.base_url);
-
-    let amfi_base = config
-        .providers
-        .amfi
-        .as_ref()
-        .map_or("https://mf.captnemo.in", |p| &p.base_url);
-
-    (
-        Arc::new(providers::yahoo_finance::YahooFinanceProvider::new(
-            yahoo_base,
-            Arc::clone(store),
-        )),
-        Arc::new(providers::amfi_provider::AmfiProvider::new(
-            amfi_base,
-            Arc::clone(store),
-        )),
-        Arc::new(providers::yahoo_finance::YahooCurrencyProvider::new(
-            yahoo_base,
-            Arc::clone(store),
-        )),
-        Arc::new(providers::kuvera_provider::KuveraProvider::new(
+);
+
+fn setup_providers(
+    config: &core::config::AppConfig,
+    store: &Arc<KeyValueStore>,
+    offline: bool,
+) -> Result<Providers> {
+    let providers_config = &config.providers;
+    let yahoo = providers_config.yahoo.as_ref();
+    let amfi = providers_config.amfi.as_ref();
+    let yahoo_base = yahoo.map_or("https://query1.finance.yahoo.com", |p| &p.base_url);
+    let amfi_base = amfi.map_or("https://mf.captnemo.in", |p| &p.base_url);
+
+    let global_limit = Arc::new(Semaphore::new(
+        providers_config
+            .max_concurrency
+            .unwrap_or(providers::http::DEFAULT_GLOBAL_CONCURRENCY)
+            .max(1),
+    ));
+    let http_client = |max_concurrency: Option<usize>, timeout_secs: Option<u64>| {
+        let defaults = HttpOptions::default();
+        let options = HttpOptions {
+            timeout: timeout_secs
+                .or(providers_config.timeout_secs)
+                .map_or(defaults.timeout, Duration::from_secs),
+            max_concurrency: max_concurrency.unwrap_or(defaults.max_concurrency),
+            network: config.network.clone(),
+            offline,
+            ..defaults
+        };
+        HttpClient::new(&options, Some(Arc::clone(&global_limit))).map(Arc::new)
+    };
+    let yahoo_http = http_client(
+        yahoo.and_then(|p| p.max_concurrency),
+        yahoo.and_then(|p| p.timeout_secs),
+    )?;
+    let amfi_http = http_client(
+        amfi.and_then(|p| p.max_concurrency),
+        amfi.and_then(|p| p.timeout_secs),
+    )?;
+
+    Ok((
+        Arc::new(
+            providers::yahoo_finance::YahooFinanceProvider::with_http_client(
+                yahoo_base,
+                Arc::clone(store),
+                Arc::clone(&yahoo_http),
+            ),
+        ),
+        Arc::new(providers::amfi_provider::AmfiProvider::with_http_client(
             amfi_base,
             Arc::clone(store),
+            Arc::clone(&amfi_http),
         )),
-    )
+        Arc::new(
+            providers::yahoo_finance::YahooCurrencyProvider::with_http_client(
+                yahoo_base,
+                Arc::clone(store),
+                yahoo_http,
+            ),
+        ),
+        Arc::new(
+            providers::kuvera_provider::KuveraProvider::with_http_client(
+                amfi_base,
+                Arc::clone(store),
+                amfi_http,
+            ),
+        ),
+    ))
 }
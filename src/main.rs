@@ -1,6 +1,7 @@
 use anyhow::Result;
-use clap::{CommandFactory, Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand};
 use std::path::PathBuf;
+use std::process::ExitCode;
 use xmf::core::log::init_logging;
 
 #[derive(Parser)]
@@ -11,9 +12,30 @@ struct Cli {
     verbose: bool,
 
     /// Refresh cached data (force fresh quotes)
-    #[arg(short = 'r', long = "refresh", global = true)]
+    #[arg(
+        short = 'r',
+        long = "refresh",
+        global = true,
+        conflicts_with = "offline"
+    )]
     refresh: bool,
 
+    /// Use cached data only, even if expired, without network requests
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Only include portfolios whose name matches (glob patterns allowed, repeatable)
+    #[arg(short, long = "portfolio", global = true, value_name = "NAME")]
+    portfolios: Vec<String>,
+
+    /// Only include investments with this tag, or in a portfolio with it (repeatable)
+    #[arg(short, long = "tag", global = true, value_name = "TAG")]
+    tags: Vec<String>,
+
+    /// Merge holdings across portfolios into a single view
+    #[arg(long, global = true)]
+    consolidated: bool,
+
     /// Path to custom configuration file (overrides default config search)
     #[arg(
         short,
@@ -42,12 +64,106 @@ impl From<Commands> for xmf::AppCommand {
     fn from(cmd: Commands) -> xmf::AppCommand {
         match cmd {
             Commands::Summary => xmf::AppCommand::Summary,
-            Commands::Change => xmf::AppCommand::Change,
-            Commands::Returns { rolling } => xmf::AppCommand::Returns {
+            Commands::Change { periods } => xmf::AppCommand::Change { periods },
+            Commands::Returns {
+                periods,
+                rolling,
+                calendar,
+                fiscal,
+            } => xmf::AppCommand::Returns {
+                periods,
                 rolling_period: rolling,
+                calendar,
+                fiscal,
             },
             Commands::Fees => xmf::AppCommand::Fees,
+            Commands::Risk {
+                period,
+                risk_free_rate,
+            } => xmf::AppCommand::Risk {
+                period,
+                risk_free_rate,
+            },
+            Commands::Correlation {
+                period,
+                weekly,
+                threshold,
+                csv,
+            } => xmf::AppCommand::Correlation {
+                period,
+                weekly,
+                threshold,
+                csv,
+            },
             Commands::Alloc => xmf::AppCommand::Alloc,
+            Commands::Bonds { within } => xmf::AppCommand::Bonds {
+                within_days: within,
+            },
+            Commands::Maturities { within } => xmf::AppCommand::Maturities {
+                within_days: within,
+            },
+            Commands::Rebalance {
+                tolerance,
+                new_money,
+            } => xmf::AppCommand::Rebalance {
+                tolerance,
+                new_money,
+            },
+            Commands::Cache { action } => xmf::AppCommand::Cache(match action {
+                CacheCommands::Ls => xmf::CacheAction::List,
+                CacheCommands::Sweep => xmf::CacheAction::Sweep,
+            }),
+            Commands::Config { action } => xmf::AppCommand::Config(match action {
+                ConfigCommands::Validate { check_urls } => {
+                    xmf::ConfigAction::Validate { check_urls }
+                }
+                ConfigCommands::Schema => xmf::ConfigAction::Schema,
+                ConfigCommands::Migrate { dry_run } => xmf::ConfigAction::Migrate { dry_run },
+            }),
+            Commands::Add {
+                investment,
+                units,
+                category,
+                no_verify,
+            } => xmf::AppCommand::Edit(xmf::EditAction::Add {
+                investment: investment.into(),
+                units,
+                category,
+                verify: !no_verify,
+            }),
+            Commands::Remove { investment } => xmf::AppCommand::Edit(xmf::EditAction::Remove {
+                investment: investment.into(),
+            }),
+            Commands::SetUnits { investment, units } => {
+                xmf::AppCommand::Edit(xmf::EditAction::SetUnits {
+                    investment: investment.into(),
+                    units,
+                })
+            }
+            Commands::AddTxn {
+                investment,
+                units,
+                price,
+                date,
+            } => xmf::AppCommand::Edit(xmf::EditAction::AddTxn {
+                investment: investment.into(),
+                units,
+                price,
+                date: date.unwrap_or_else(|| chrono::Local::now().date_naive()),
+            }),
+            Commands::Import {
+                from,
+                file,
+                map,
+                dry_run,
+                force,
+            } => xmf::AppCommand::Edit(xmf::EditAction::Import {
+                format: from,
+                file,
+                map,
+                dry_run,
+                force,
+            }),
             Commands::Setup => xmf::AppCommand::Setup,
         }
     }
@@ -60,60 +176,248 @@ enum Commands {
     /// Display portfolio summary
     Summary,
     /// Display price change summary
-    Change,
+    Change {
+        /// Comma-separated periods to show (e.g. 1d,6m,ytd,2023-01-01..2023-12-31)
+        #[arg(long, value_name = "PERIODS", value_delimiter = ',')]
+        periods: Vec<String>,
+    },
     /// Display CAGR return calculations
     Returns {
-        /// Calculate rolling returns for a specified period (e.g. 1y, 3y, 5y)
-        #[arg(long, value_name = "PERIOD")]
+        /// Comma-separated periods to show (e.g. 6m,2y,inception), defaults to 1y,3y,5y,10y
+        #[arg(
+            long,
+            value_name = "PERIODS",
+            value_delimiter = ',',
+            conflicts_with_all = ["rolling", "calendar", "fiscal"]
+        )]
+        periods: Vec<String>,
+        /// Calculate rolling returns for a specified period (e.g. 1y, 2y, 6m)
+        #[arg(long, value_name = "PERIOD", conflicts_with_all = ["calendar", "fiscal"])]
         rolling: Option<String>,
+        /// Show year-by-year returns for each calendar year
+        #[arg(long, conflicts_with = "fiscal")]
+        calendar: bool,
+        /// Show year-by-year returns for each fiscal year, see `returns.fiscal_year_start`
+        #[arg(long)]
+        fiscal: bool,
     },
     /// Display expense ratios and fees
     Fees,
+    /// Display volatility, drawdown and risk-adjusted returns
+    Risk {
+        /// Only use prices from this period (e.g. 1y, 3y, 5y), defaults to all available
+        #[arg(long, value_name = "PERIOD")]
+        period: Option<String>,
+        /// Annual risk-free rate in percent, overrides `risk.risk_free_rate` in the config
+        #[arg(long, value_name = "PERCENT")]
+        risk_free_rate: Option<f64>,
+    },
+    /// Display correlations of returns between holdings
+    Correlation {
+        /// Period of prices to use (e.g. 1y, 3y, 5y)
+        #[arg(long, value_name = "PERIOD", default_value = xmf::cli::correlation::DEFAULT_PERIOD)]
+        period: String,
+        /// Use weekly instead of daily returns
+        #[arg(long)]
+        weekly: bool,
+        /// Highlight pairs correlated above this value, overrides `correlation.threshold`
+        #[arg(long, value_name = "VALUE")]
+        threshold: Option<f64>,
+        /// Also write the correlations to a CSV file
+        #[arg(long, value_name = "FILE")]
+        csv: Option<PathBuf>,
+    },
     /// Display asset allocation breakdown
     Alloc,
+    /// Show bond prices, yields and durations, and upcoming coupons
+    Bonds {
+        /// Number of days ahead to list coupons for
+        #[arg(long, value_name = "DAYS", default_value_t = xmf::cli::bonds::DEFAULT_COUPON_WINDOW_DAYS)]
+        within: u32,
+    },
+    /// List fixed deposits maturing soon
+    Maturities {
+        /// Number of days ahead to look
+        #[arg(long, value_name = "DAYS", default_value_t = xmf::cli::maturities::DEFAULT_WINDOW_DAYS)]
+        within: u32,
+    },
+    /// Propose trades that restore the target asset allocation
+    Rebalance {
+        /// Allowed drift from target in percentage points before rebalancing
+        #[arg(long, value_name = "PERCENT", default_value_t = xmf::cli::rebalance::DEFAULT_TOLERANCE)]
+        tolerance: f64,
+        /// Only invest this new contribution, without selling
+        #[arg(long, value_name = "AMOUNT")]
+        new_money: Option<f64>,
+    },
+    /// Inspect and maintain cached provider data
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommands,
+    },
+    /// Add a stock or fund to the portfolio selected with --portfolio
+    Add {
+        #[command(flatten)]
+        investment: InvestmentArgs,
+        /// Units held
+        #[arg(long)]
+        units: f64,
+        /// Category of the investment
+        #[arg(long)]
+        category: Option<String>,
+        /// Add without checking that a quote can be fetched for it
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// Remove an investment from the portfolio selected with --portfolio
+    Remove {
+        #[command(flatten)]
+        investment: InvestmentArgs,
+    },
+    /// Set the units held of an investment in the portfolio selected with --portfolio
+    SetUnits {
+        #[command(flatten)]
+        investment: InvestmentArgs,
+        /// Units held
+        #[arg(long)]
+        units: f64,
+    },
+    /// Record a purchase, or a sale with negative units, of a stock or fund in
+    /// the portfolio selected with --portfolio, adjusting its units
+    AddTxn {
+        #[command(flatten)]
+        investment: InvestmentArgs,
+        /// Units bought, or sold if negative
+        #[arg(long, allow_negative_numbers = true)]
+        units: f64,
+        /// Price per unit
+        #[arg(long)]
+        price: f64,
+        /// Date of the transaction, e.g. 2024-01-31. Defaults to today
+        #[arg(long)]
+        date: Option<chrono::NaiveDate>,
+    },
+    /// Import holdings from a broker or registrar CSV export into the
+    /// portfolio selected with --portfolio, adding the portfolio if needed
+    Import {
+        /// Export format: zerodha, groww, cams, kfintech or generic
+        #[arg(long)]
+        from: xmf::core::import::ImportFormat,
+        /// CSV file to import
+        file: PathBuf,
+        /// Columns of a generic CSV, e.g. symbol=Ticker,units=Qty
+        #[arg(long)]
+        map: Option<xmf::core::import::ColumnMap>,
+        /// Only show the changes, without writing them
+        #[arg(long)]
+        dry_run: bool,
+        /// Also import holdings other portfolios already have
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check, describe or upgrade the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+}
+
+/// The investment a config editing command applies to
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct InvestmentArgs {
+    /// ISIN of a mutual fund
+    #[arg(long)]
+    isin: Option<String>,
+    /// Symbol of a stock
+    #[arg(long)]
+    symbol: Option<String>,
+    /// Name of a fixed deposit, small savings account or bond
+    #[arg(long)]
+    name: Option<String>,
+}
+
+impl From<InvestmentArgs> for xmf::core::edit::InvestmentId {
+    fn from(args: InvestmentArgs) -> Self {
+        use xmf::core::edit::InvestmentId;
+        match (args.isin, args.symbol, args.name) {
+            (Some(isin), _, _) => InvestmentId::Isin(isin),
+            (_, Some(symbol), _) => InvestmentId::Symbol(symbol),
+            (_, _, name) => InvestmentId::Name(name.unwrap_or_default()),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// List cached collections with size and eviction statistics
+    Ls,
+    /// Remove expired entries from the cache
+    Sweep,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Report every problem in the config with its line and column, failing on errors
+    Validate {
+        /// Also check that provider URLs can be reached
+        #[arg(long)]
+        check_urls: bool,
+    },
+    /// Print the JSON Schema of the config file, for editor validation and completion
+    Schema,
+    /// Upgrade the config to the current format, showing the changes as a diff
+    Migrate {
+        /// Only show the changes, without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
 
     init_logging(cli.verbose);
 
-    let config_arg =
-        if let Some(name) = &cli.config_name {
-            let mut base_path = xmf::core::config::AppConfig::default_config_path()?;
-            // Pop the default config file name to get the config directory
-            base_path.pop();
-
-            // Check for both yaml and yml extensions
-            let extensions = ["yaml", "yml"];
-            let path = extensions.iter()
-            .map(|ext| {
-                let mut path = base_path.clone();
-                path.push(format!("{name}.{ext}"));
-                path
-            })
-            .find(|path| path.exists())
-            .ok_or_else(|| anyhow::anyhow!(
-                "No config file found for name '{}' with extensions {:?} in config directory", 
-                name, extensions
-            ))?;
-
-            Some(path)
-        } else {
-            cli.config_path
-        };
-
-    let result = match cli.command {
-        Some(cmd) => xmf::run_command(cmd.into(), config_arg.as_deref(), cli.refresh).await,
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            tracing::error!(error = %e, "Application failed");
+            eprintln!("Error: {e:?}");
+            ExitCode::from(xmf::exit_code(&e))
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let config_arg = match &cli.config_name {
+        Some(name) => Some(xmf::core::config::AppConfig::named_config_path(name)?),
+        None => cli.config_path,
+    };
+
+    let view = xmf::PortfolioView {
+        filter: xmf::core::config::PortfolioFilter {
+            names: cli.portfolios,
+            tags: cli.tags,
+        },
+        consolidated: cli.consolidated,
+    };
+
+    match cli.command {
+        Some(cmd) => {
+            xmf::run_command(
+                cmd.into(),
+                config_arg.as_deref(),
+                cli.refresh,
+                cli.offline,
+                &view,
+            )
+            .await
+        }
         None => {
             Cli::command().print_help()?;
             Ok(())
         }
-    };
-
-    if let Err(e) = &result {
-        tracing::error!(error = %e, "Application failed");
     }
-    result
 }
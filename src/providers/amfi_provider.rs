@@ -1,8 +1,9 @@
-use crate::core::cache::{KeyValueCollection, Store};
-use crate::core::{HistoricalPeriod, PriceProvider, PriceResult};
-use crate::providers::util::{seconds_until, with_retry};
+use crate::core::cache::KeyValueCollection;
+use crate::core::{DEFAULT_PERIODS, PriceProvider, PriceResult, ProviderError, ProviderResult};
+use crate::providers::http::HttpClient;
+use crate::providers::util::{parse_json, provider_collection, seconds_until};
 use crate::store::KeyValueStore;
-use anyhow::{Context, Result, anyhow};
+use anyhow::Result;
 use async_trait::async_trait;
 use chrono;
 use serde::Deserialize;
@@ -14,14 +15,25 @@ use tracing::{debug, warn};
 pub struct AmfiProvider {
     base_url: String,
     cache: Arc<dyn KeyValueCollection>,
+    http: Arc<HttpClient>,
 }
 
 impl AmfiProvider {
     pub fn new(base_url: &str, cache: Arc<KeyValueStore>) -> Self {
-        let collection = cache.get_collection("amfi", true, true).unwrap();
+        Self::with_http_client(base_url, cache, Arc::new(HttpClient::default()))
+    }
+
+    /// Creates a provider that sends requests through a shared HTTP client.
+    pub fn with_http_client(
+        base_url: &str,
+        cache: Arc<KeyValueStore>,
+        http: Arc<HttpClient>,
+    ) -> Self {
+        let collection = provider_collection(&cache, "amfi", base_url);
         AmfiProvider {
             base_url: base_url.to_string(),
             cache: collection,
+            http,
         }
     }
 
@@ -30,6 +42,7 @@ impl AmfiProvider {
         Self {
             base_url: base_url.to_string(),
             cache,
+            http: Arc::new(HttpClient::default()),
         }
     }
 }
@@ -45,35 +58,32 @@ struct AmfiResponse {
 
 #[async_trait]
 impl PriceProvider for AmfiProvider {
-    async fn fetch_price(&self, identifier: &str) -> Result<PriceResult> {
+    async fn fetch_price(&self, identifier: &str) -> ProviderResult<PriceResult> {
         if let Some(cached) = self.cache.get(identifier.as_bytes()).await {
-            return Ok(serde_json::from_slice(&cached)?);
+            return parse_json(&cached, "cached NAV");
         }
 
         let url = format!("{}/nav/{}", self.base_url, identifier);
         debug!("Requesting price data from {}", url);
 
-        let client = reqwest::Client::builder().user_agent("xmf/1.0").build()?;
-        let response = with_retry(|| async { client.get(&url).send().await }, 3, 500)
-            .await
-            .with_context(|| format!("Failed to send request for ISIN: {identifier}"))?;
-
-        let response_text = response
-            .text()
-            .await
-            .with_context(|| format!("Failed to get response text for ISIN: {identifier}"))?;
+        let response_text = self.http.get_text(&url).await.map_err(|e| match e {
+            ProviderError::NotFound(_) => {
+                ProviderError::NotFound(format!("Unknown ISIN: {identifier}"))
+            }
+            e => e.context(format!("NAV request for ISIN {identifier}")),
+        })?;
 
         // Check for empty or non-JSON responses before parsing
         if response_text.trim().is_empty() {
-            return Err(anyhow!("Received empty response for ISIN: {}", identifier));
+            return Err(ProviderError::NotFound(format!(
+                "Received empty response for ISIN: {identifier}"
+            )));
         }
 
-        let amfi_response: AmfiResponse =
-            serde_json::from_str(&response_text).with_context(|| {
-                format!(
-                    "Failed to parse AMFI response for ISIN: {identifier}. Response: '{response_text}'",
-                )
-            })?;
+        let amfi_response: AmfiResponse = parse_json(
+            response_text.as_bytes(),
+            &format!("AMFI response for ISIN: {identifier}. Response: '{response_text}'"),
+        )?;
 
         debug!(
             "Successfully fetched price for ISIN {}: {:?}",
@@ -107,16 +117,10 @@ impl PriceProvider for AmfiProvider {
                             );
                             chrono::Utc::now().date_naive()
                         });
-                for period in [
-                    HistoricalPeriod::OneDay,
-                    HistoricalPeriod::FiveDays,
-                    HistoricalPeriod::OneMonth,
-                    HistoricalPeriod::OneYear,
-                    HistoricalPeriod::ThreeYears,
-                    HistoricalPeriod::FiveYears,
-                    HistoricalPeriod::TenYears,
-                ] {
-                    let period_start_date = current_nav_date - period.to_duration();
+                for period in DEFAULT_PERIODS {
+                    let Some(period_start_date) = period.start_date(current_nav_date) else {
+                        continue;
+                    };
 
                     if let Some((_date, price)) = prices
                         .iter()
@@ -185,6 +189,7 @@ impl PriceProvider for AmfiProvider {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::HistoricalPeriod;
     use crate::store::memory::MemoryCollection;
     use wiremock::matchers::{method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
@@ -397,9 +402,12 @@ mod tests {
         let provider = AmfiProvider::new_with_collection(&mock_server.uri(), cache);
         let result = provider.fetch_price(isin).await;
 
-        assert!(result.is_err());
-        let error_msg = result.unwrap_err().to_string();
-        assert!(error_msg.starts_with(&format!("Failed to parse AMFI response for ISIN: {isin}")),);
+        match result.unwrap_err() {
+            ProviderError::Other(msg) => {
+                assert!(msg.starts_with(&format!("NAV request for ISIN {isin}: HTTP 500")))
+            }
+            e => panic!("Unexpected error: {e}"),
+        }
     }
 
     #[tokio::test]
@@ -429,10 +437,9 @@ mod tests {
         let provider = AmfiProvider::new_with_collection(&mock_server.uri(), cache);
         let result = provider.fetch_price(isin).await;
 
-        assert!(result.is_err());
         assert_eq!(
-            result.unwrap_err().to_string(),
-            format!("Received empty response for ISIN: {isin}")
+            result.unwrap_err(),
+            ProviderError::NotFound(format!("Received empty response for ISIN: {isin}"))
         );
     }
 }
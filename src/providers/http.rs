@@ -0,0 +1,449 @@
+//! Shared HTTP client with connection pooling, timeouts and concurrency limits
+use crate::core::config::NetworkConfig;
+use crate::core::price::{ProviderError, ProviderResult};
+use crate::providers::util::with_retry;
+use anyhow::{Context, Result};
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Default number of in-flight requests per provider
+pub const DEFAULT_MAX_CONCURRENCY: usize = 4;
+
+/// Default number of in-flight requests across all providers
+pub const DEFAULT_GLOBAL_CONCURRENCY: usize = 8;
+
+/// Default per-request timeout
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// User agent sent unless overridden in the network config
+pub const DEFAULT_USER_AGENT: &str = "xmf/1.0";
+
+/// Base delay before the first retry, doubled on each subsequent attempt
+const RETRY_DELAY_MS: u64 = 500;
+
+/// Options for a provider's HTTP client.
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    pub timeout: Duration,
+    pub max_concurrency: usize,
+    /// Retries for failed requests, in addition to the first attempt
+    pub retries: usize,
+    pub network: NetworkConfig,
+    /// Fail requests without sending them, so only cached data is used
+    pub offline: bool,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            retries: 3,
+            network: NetworkConfig::default(),
+            offline: false,
+        }
+    }
+}
+
+/// HTTP client shared by all requests of a provider.
+///
+/// Requests are retried with backoff and limited both per provider and,
+/// optionally, across all providers sharing `global_limit`.
+pub struct HttpClient {
+    client: reqwest::Client,
+    limit: Semaphore,
+    global_limit: Option<Arc<Semaphore>>,
+    retries: usize,
+    offline: bool,
+}
+
+impl HttpClient {
+    pub fn new(options: &HttpOptions, global_limit: Option<Arc<Semaphore>>) -> Result<Self> {
+        let network = &options.network;
+        let mut builder = reqwest::Client::builder()
+            .user_agent(network.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT))
+            .default_headers(default_headers(network)?)
+            .timeout(options.timeout);
+        if let Some(proxy_url) = &network.proxy {
+            let proxy = reqwest::Proxy::all(proxy_url)
+                .with_context(|| format!("Invalid proxy URL: {proxy_url}"))?
+                .no_proxy(reqwest::NoProxy::from_string(&network.no_proxy.join(",")));
+            builder = builder.proxy(proxy);
+        }
+        if let Some(path) = &network.ca_bundle {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("Failed to read CA bundle: {}", path.display()))?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("Invalid CA bundle: {}", path.display()))?;
+            builder = builder.tls_certs_merge(certs);
+        }
+        let client = builder.build().context("Failed to build HTTP client")?;
+        Ok(Self {
+            client,
+            limit: Semaphore::new(options.max_concurrency.max(1)),
+            global_limit,
+            retries: options.retries,
+            offline: options.offline,
+        })
+    }
+
+    /// Sends a GET request, waiting for a free slot under the concurrency limits.
+    ///
+    /// Non-success statuses are mapped to the matching [`ProviderError`].
+    pub async fn get(&self, url: &str) -> ProviderResult<reqwest::Response> {
+        if self.offline {
+            return Err(ProviderError::Network(format!(
+                "Offline mode, no cached data for {url}"
+            )));
+        }
+        let response = with_retry(
+            || async {
+                // Held for this attempt only, so slots are free while retries wait
+                let _permits = self.acquire_permits().await;
+                self.client.get(url).send().await
+            },
+            self.retries,
+            RETRY_DELAY_MS,
+        )
+        .await
+        .map_err(|e| ProviderError::Network(format!("Request to {url} failed: {e:#}")))?;
+
+        let status = response.status();
+        match status {
+            _ if status.is_success() => Ok(response),
+            StatusCode::NOT_FOUND => Err(ProviderError::NotFound(format!("No data at {url}"))),
+            StatusCode::TOO_MANY_REQUESTS => Err(ProviderError::RateLimited(format!(
+                "Too many requests to {url}"
+            ))),
+            _ => Err(ProviderError::Other(format!("HTTP {status} from {url}"))),
+        }
+    }
+
+    /// Waits for a slot under the global and then the provider's limit. The
+    /// semaphores are never closed, so acquiring them cannot fail.
+    async fn acquire_permits(&self) -> Vec<SemaphorePermit<'_>> {
+        let mut permits = Vec::with_capacity(2);
+        if let Some(limit) = &self.global_limit {
+            permits.extend(limit.acquire().await.ok());
+        }
+        permits.extend(self.limit.acquire().await.ok());
+        permits
+    }
+
+    /// Sends a GET request and returns the response body as text.
+    pub async fn get_text(&self, url: &str) -> ProviderResult<String> {
+        self.get(url)
+            .await?
+            .text()
+            .await
+            .map_err(|e| ProviderError::Network(format!("Failed to read response from {url}: {e}")))
+    }
+}
+
+fn default_headers(network: &NetworkConfig) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &network.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .with_context(|| format!("Invalid header name: {name}"))?;
+        let value = HeaderValue::from_str(value)
+            .with_context(|| format!("Invalid value for header: {name}"))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new(&HttpOptions::default(), None).expect("Failed to build HTTP client")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::time::Instant;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn create_slow_server(delay: Duration) -> MockServer {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(delay))
+            .mount(&mock_server)
+            .await;
+        mock_server
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let mock_server = create_slow_server(Duration::from_millis(100)).await;
+        let options = HttpOptions {
+            max_concurrency: 2,
+            ..Default::default()
+        };
+        let client = HttpClient::new(&options, None).unwrap();
+        let url = format!("{}/quote", mock_server.uri());
+
+        // 6 requests, 2 at a time, take at least 3 rounds
+        let start = Instant::now();
+        let requests = (0..6).map(|_| client.get(&url));
+        for response in futures::future::join_all(requests).await {
+            assert!(response.unwrap().status().is_success());
+        }
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn test_global_concurrency_limit() {
+        let mock_server = create_slow_server(Duration::from_millis(100)).await;
+        let global = Arc::new(Semaphore::new(1));
+        let first = HttpClient::new(&HttpOptions::default(), Some(Arc::clone(&global))).unwrap();
+        let second = HttpClient::new(&HttpOptions::default(), Some(global)).unwrap();
+        let url = format!("{}/quote", mock_server.uri());
+
+        // Both clients share a single global slot
+        let start = Instant::now();
+        let requests = (0..2).flat_map(|_| [first.get(&url), second.get(&url)]);
+        for response in futures::future::join_all(requests).await {
+            assert!(response.unwrap().status().is_success());
+        }
+        assert!(start.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_retry_delay_releases_global_slot() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/throttled"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "1"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/quote"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        let global = Arc::new(Semaphore::new(1));
+        let options = HttpOptions {
+            retries: 1,
+            ..Default::default()
+        };
+        let throttled = HttpClient::new(&options, Some(Arc::clone(&global))).unwrap();
+        let other = HttpClient::new(&options, Some(global)).unwrap();
+        let (throttled_url, url) = (
+            format!("{}/throttled", mock_server.uri()),
+            format!("{}/quote", mock_server.uri()),
+        );
+
+        // The other provider goes ahead while the throttled request waits to retry
+        let start = Instant::now();
+        let (throttled_response, other_elapsed) =
+            tokio::join!(throttled.get(&throttled_url), async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                other.get(&url).await.unwrap();
+                start.elapsed()
+            });
+        assert!(matches!(
+            throttled_response,
+            Err(ProviderError::RateLimited(_))
+        ));
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert!(other_elapsed < Duration::from_millis(800));
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let mock_server = create_slow_server(Duration::from_secs(5)).await;
+        let options = HttpOptions {
+            timeout: Duration::from_millis(50),
+            retries: 0,
+            ..Default::default()
+        };
+        let client = HttpClient::new(&options, None).unwrap();
+        let result = client.get(&format!("{}/slow", mock_server.uri())).await;
+        assert!(matches!(result, Err(ProviderError::Network(_))));
+    }
+
+    #[tokio::test]
+    async fn test_status_maps_to_provider_error() {
+        let mock_server = MockServer::start().await;
+        for (route, status) in [("/missing", 404), ("/throttled", 429), ("/teapot", 418)] {
+            Mock::given(method("GET"))
+                .and(path(route))
+                .respond_with(ResponseTemplate::new(status))
+                .mount(&mock_server)
+                .await;
+        }
+        let options = HttpOptions {
+            retries: 0,
+            ..Default::default()
+        };
+        let client = HttpClient::new(&options, None).unwrap();
+        let uri = mock_server.uri();
+
+        let result = client.get(&format!("{uri}/missing")).await;
+        assert!(matches!(result, Err(ProviderError::NotFound(_))));
+        let result = client.get(&format!("{uri}/throttled")).await;
+        assert!(matches!(result, Err(ProviderError::RateLimited(_))));
+        let result = client.get(&format!("{uri}/teapot")).await;
+        assert!(matches!(result, Err(ProviderError::Other(_))));
+    }
+
+    #[tokio::test]
+    async fn test_offline_skips_requests() {
+        let mock_server = create_slow_server(Duration::ZERO).await;
+        let options = HttpOptions {
+            offline: true,
+            ..Default::default()
+        };
+        let client = HttpClient::new(&options, None).unwrap();
+        let result = client.get(&mock_server.uri()).await;
+        assert!(matches!(result, Err(ProviderError::Network(_))));
+        assert!(mock_server.received_requests().await.unwrap().is_empty());
+    }
+
+    const TEST_CA_PEM: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBgjCCASmgAwIBAgIUcQSPylBoy9iZAfWI9v4u3NHKx3YwCgYIKoZIzj0EAwIw
+FjEUMBIGA1UEAwwLeG1mIHRlc3QgQ0EwIBcNMjYxMDE4MTM0NTEzWhgPMjEyNjA5
+MjQxMzQ1MTNaMBYxFDASBgNVBAMMC3htZiB0ZXN0IENBMFkwEwYHKoZIzj0CAQYI
+KoZIzj0DAQcDQgAEfctat9QY0bvcJ5RkHv51uv4opmfaj/KS/GGYFu4aGOquXesF
+/KC6RD6i7SiHJRFRpCIHhZBNYMDewyjgRnaLbKNTMFEwHQYDVR0OBBYEFDlX1ydx
+E5P7wFw5pmdBxThth1BlMB8GA1UdIwQYMBaAFDlX1ydxE5P7wFw5pmdBxThth1Bl
+MA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDRwAwRAIgBeNn1DNl0Qbw4lTK
+eYUj5dmvC6Mt4dxB7OkhxgavuEICIG0/q+jIB38571uYBFDor3WPnt8cQ7APCOdn
+MERjYFI1
+-----END CERTIFICATE-----
+";
+
+    fn client_with_network(network: NetworkConfig) -> Result<HttpClient> {
+        let options = HttpOptions {
+            retries: 0,
+            network,
+            ..Default::default()
+        };
+        HttpClient::new(&options, None)
+    }
+
+    #[tokio::test]
+    async fn test_user_agent_and_default_headers() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("user-agent", "corp-agent/2.0"))
+            .and(header("x-api-key", "secret"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = client_with_network(NetworkConfig {
+            user_agent: Some("corp-agent/2.0".to_string()),
+            headers: HashMap::from([("X-Api-Key".to_string(), "secret".to_string())]),
+            ..Default::default()
+        })
+        .unwrap();
+        let response = client.get(&mock_server.uri()).await.unwrap();
+        assert!(response.status().is_success());
+    }
+
+    #[tokio::test]
+    async fn test_default_user_agent() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("user-agent", DEFAULT_USER_AGENT))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = client_with_network(NetworkConfig::default()).unwrap();
+        let response = client.get(&mock_server.uri()).await.unwrap();
+        assert!(response.status().is_success());
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_through_proxy() {
+        // The mock server acts as the proxy; plain HTTP requests are forwarded
+        // to it with the target in absolute form.
+        let proxy = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v8/finance/chart/AAPL"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&proxy)
+            .await;
+
+        let client = client_with_network(NetworkConfig {
+            proxy: Some(proxy.uri()),
+            ..Default::default()
+        })
+        .unwrap();
+        let response = client
+            .get("http://finance.example.invalid/v8/finance/chart/AAPL")
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+    }
+
+    #[tokio::test]
+    async fn test_no_proxy_bypasses_proxy() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Nothing listens on the discard port, so proxied requests would fail
+        let client = client_with_network(NetworkConfig {
+            proxy: Some("http://127.0.0.1:9".to_string()),
+            no_proxy: vec!["127.0.0.1".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        let response = client.get(&mock_server.uri()).await.unwrap();
+        assert!(response.status().is_success());
+    }
+
+    #[test]
+    fn test_ca_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("ca.pem");
+        std::fs::write(&bundle, TEST_CA_PEM).unwrap();
+        assert!(
+            client_with_network(NetworkConfig {
+                ca_bundle: Some(bundle),
+                ..Default::default()
+            })
+            .is_ok()
+        );
+
+        let missing = dir.path().join("missing.pem");
+        let err = client_with_network(NetworkConfig {
+            ca_bundle: Some(missing),
+            ..Default::default()
+        })
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("Failed to read CA bundle"));
+    }
+
+    #[test]
+    fn test_invalid_network_config() {
+        let invalid_proxy = NetworkConfig {
+            proxy: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        assert!(client_with_network(invalid_proxy).is_err());
+
+        let invalid_header = NetworkConfig {
+            headers: HashMap::from([("bad header".to_string(), "value".to_string())]),
+            ..Default::default()
+        };
+        assert!(client_with_network(invalid_header).is_err());
+    }
+}
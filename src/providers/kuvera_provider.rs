@@ -1,12 +1,13 @@
-use super::util::with_retry;
+use super::http::HttpClient;
+use super::util::{parse_json, provider_collection};
 use crate::{
     core::{
-        cache::{KeyValueCollection, Store},
+        cache::KeyValueCollection,
         metadata::{FundMetadata, MetadataProvider},
+        price::{ProviderError, ProviderResult},
     },
     store::KeyValueStore,
 };
-use anyhow::{Context, anyhow};
 use async_trait::async_trait;
 use chrono::NaiveDate;
 use serde::Deserialize;
@@ -31,16 +32,25 @@ struct KuveraResponse {
 pub struct KuveraProvider {
     base_url: String,
     cache: Arc<dyn KeyValueCollection>,
+    http: Arc<HttpClient>,
 }
 
 impl KuveraProvider {
     pub fn new(base_url: &str, cache: Arc<KeyValueStore>) -> Self {
-        let collection = cache
-            .get_collection("metadata", true /* persist */, true /* create */)
-            .unwrap();
+        Self::with_http_client(base_url, cache, Arc::new(HttpClient::default()))
+    }
+
+    /// Creates a provider that sends requests through a shared HTTP client.
+    pub fn with_http_client(
+        base_url: &str,
+        cache: Arc<KeyValueStore>,
+        http: Arc<HttpClient>,
+    ) -> Self {
+        let collection = provider_collection(&cache, "metadata", base_url);
         Self {
             base_url: base_url.to_string(),
             cache: collection,
+            http,
         }
     }
 
@@ -49,54 +59,58 @@ impl KuveraProvider {
         Self {
             base_url: base_url.to_string(),
             cache,
+            http: Arc::new(HttpClient::default()),
         }
     }
 
-    fn parse_api_date(date_str: &str) -> anyhow::Result<NaiveDate> {
-        NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
-            .with_context(|| format!("Failed to parse date: {date_str}"))
+    fn parse_api_date(date_str: &str) -> ProviderResult<NaiveDate> {
+        NaiveDate::parse_from_str(date_str, "%Y-%m-%d").map_err(|e| {
+            ProviderError::InvalidResponse(format!("Failed to parse date: {date_str}: {e}"))
+        })
     }
 }
 
 #[async_trait]
 impl MetadataProvider for KuveraProvider {
-    async fn fetch_metadata(&self, identifier: &str) -> anyhow::Result<FundMetadata> {
+    async fn fetch_metadata(&self, identifier: &str) -> ProviderResult<FundMetadata> {
         if let Some(cached) = self.cache.get(identifier.as_bytes()).await {
-            return Ok(serde_json::from_slice(&cached)?);
+            return parse_json(&cached, "cached metadata");
         }
 
         let url = format!("{}/kuvera/{}", self.base_url, identifier);
-        let response = with_retry(|| async { reqwest::get(&url).await }, 3, 500)
-            .await
-            .context("Metadata request failed")?;
-
-        let response_text = response
-            .text()
+        let response_text = self
+            .http
+            .get_text(&url)
             .await
-            .context("Failed to get response text")?;
-
-        let funds: Vec<KuveraResponse> = match serde_json::from_str(&response_text) {
-            Ok(data) => data,
-            Err(e) => {
-                error!(
-                    error = ?e,
-                    response = %response_text,
-                    "Failed to parse metadata response"
-                );
-                return Err(e).context("Failed to parse metadata response");
-            }
-        };
-
-        let fund = funds.first().ok_or_else(|| anyhow!("Empty funds array"))?;
+            .map_err(|e| e.context("Metadata request failed"))?;
+
+        let funds: Vec<KuveraResponse> =
+            match parse_json(response_text.as_bytes(), "metadata response") {
+                Ok(data) => data,
+                Err(e) => {
+                    error!(
+                        error = %e,
+                        response = %response_text,
+                        "Failed to parse metadata response"
+                    );
+                    return Err(e);
+                }
+            };
+
+        let fund = funds
+            .first()
+            .ok_or_else(|| ProviderError::NotFound(format!("No metadata for {identifier}")))?;
 
         let metadata = FundMetadata {
             isin: fund.isin.clone(),
             fund_type: fund.fund_type.clone(),
             fund_category: fund.fund_category.clone(),
-            expense_ratio: fund
-                .expense_ratio
-                .parse()
-                .context("Invalid expense_ratio")?,
+            expense_ratio: fund.expense_ratio.parse().map_err(|_| {
+                ProviderError::InvalidResponse(format!(
+                    "Invalid expense_ratio: {}",
+                    fund.expense_ratio
+                ))
+            })?,
             expense_ratio_date: Self::parse_api_date(&fund.expense_ratio_date)?,
             aum: fund.aum,
             fund_rating: fund.fund_rating,
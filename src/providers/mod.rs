@@ -1,4 +1,5 @@
 pub mod amfi_provider;
+pub mod http;
 pub mod kuvera_provider;
 pub mod util;
 pub mod yahoo_finance;
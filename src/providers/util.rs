@@ -1,45 +1,140 @@
+use crate::core::cache::{KeyValueCollection, Store};
+use crate::core::price::{ProviderError, ProviderResult};
+use crate::store::KeyValueStore;
 use anyhow::{Error, Result, anyhow};
+use serde::de::DeserializeOwned;
 use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::debug;
 
-/// Retries an async operation with configurable attempts and delays
+/// Returns a stable identity for a provider and its endpoint, formatted as
+/// `<name>#<hash of base URL>`.
+pub fn provider_identity(name: &str, base_url: &str) -> String {
+    // FNV-1a, stable across Rust releases unlike `DefaultHasher`
+    let hash = base_url
+        .trim_end_matches('/')
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    format!("{name}#{hash:016x}")
+}
+
+/// Opens the persistent cache collection for a provider. Collections are
+/// namespaced by base URL so switching endpoints never serves stale data
+/// fetched from a different source.
+pub fn provider_collection(
+    store: &KeyValueStore,
+    name: &str,
+    base_url: &str,
+) -> Arc<dyn KeyValueCollection> {
+    // Drop the collection named after the provider alone by older versions,
+    // whose data could have come from any endpoint
+    if store.remove_persisted(name) {
+        debug!("Removed legacy cache collection {}", name);
+    }
+    let collection = provider_identity(name, base_url);
+    store.set_source(&collection, base_url);
+    store
+        .get_collection(&collection, true /* persist */, true /* create */)
+        .unwrap()
+}
+
+/// Deserializes a JSON provider response or cache entry.
+pub fn parse_json<T: DeserializeOwned>(data: &[u8], context: &str) -> ProviderResult<T> {
+    serde_json::from_slice(data)
+        .map_err(|e| ProviderError::InvalidResponse(format!("Failed to parse {context}: {e}")))
+}
+
+/// Upper bound for a single wait between retries, including `Retry-After`.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Retries an HTTP request with exponential backoff and jitter
+///
+/// Only transient failures are retried: connection errors, timeouts and
+/// retryable statuses (408, 429, 5xx). A `Retry-After` header on the response
+/// takes precedence over the computed backoff.
 ///
 /// # Parameters
 /// - `operation`: Closure returning a future
 /// - `retries`: Number of retry attempts (total runs = 1 initial + retries)
-/// - `delay_ms`: Milliseconds between retry attempts
+/// - `delay_ms`: Base delay before the first retry, doubled on each attempt
 ///
 /// # Returns
-/// Either the successful result or the error after all attempts
-pub async fn with_retry<F, Fut, T>(
+/// The last response, which may have a non-success status, or the error after all attempts
+pub async fn with_retry<F, Fut>(
     mut operation: F,
     retries: usize,
     delay_ms: u64,
-) -> Result<T, Error>
+) -> Result<reqwest::Response, Error>
 where
     F: FnMut() -> Fut,
-    Fut: Future<Output = Result<T, reqwest::Error>>,
+    Fut: Future<Output = Result<reqwest::Response, reqwest::Error>>,
 {
     let mut attempt = 1;
     loop {
-        match operation().await.map_err(anyhow::Error::from) {
-            Ok(val) => return Ok(val),
+        let delay = match operation().await {
+            Ok(response) => {
+                if !is_retryable_status(response.status()) || attempt > retries {
+                    return Ok(response);
+                }
+                debug!(
+                    "Attempt {}/{} returned {}. Retrying...",
+                    attempt,
+                    retries,
+                    response.status()
+                );
+                retry_after(&response).unwrap_or_else(|| backoff_delay(attempt, delay_ms))
+            }
             Err(err) => {
-                if attempt > retries {
-                    return Err(err);
+                let transient = err.is_timeout() || err.is_connect() || err.is_request();
+                if !transient || attempt > retries {
+                    return Err(anyhow::Error::from(err));
                 }
                 debug!(
                     "Attempt {}/{} failed: {}. Retrying...",
                     attempt, retries, err
                 );
-                attempt += 1;
-                tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                backoff_delay(attempt, delay_ms)
             }
-        }
+        };
+        attempt += 1;
+        tokio::time::sleep(delay.min(MAX_RETRY_DELAY)).await;
     }
 }
 
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status.is_server_error()
+}
+
+/// Exponential backoff with "equal jitter": a random delay between half and
+/// all of `delay_ms * 2^(attempt - 1)`.
+fn backoff_delay(attempt: usize, delay_ms: u64) -> Duration {
+    let exponent = (attempt.saturating_sub(1)).min(16) as u32;
+    let ceiling = delay_ms.saturating_mul(1 << exponent);
+    let half = ceiling / 2;
+    Duration::from_millis(half + fastrand::u64(0..=ceiling - half))
+}
+
+/// Parses a `Retry-After` header given either in seconds or as an HTTP date.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let seconds = (date.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_seconds();
+    Some(Duration::from_secs(seconds.max(0) as u64))
+}
+
 /// Calculates seconds until target UTC time (hour 0-23, minute 0-59).
 pub fn seconds_until(target_hour: u32, target_minute: u32) -> anyhow::Result<u64> {
     seconds_until_with_now(target_hour, target_minute, chrono::Utc::now())
@@ -75,6 +170,91 @@ fn seconds_until_with_now(
 mod tests {
     use super::*;
     use chrono::{TimeZone, Utc};
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn send_with_retry(server: &MockServer, retries: usize) -> reqwest::Response {
+        let client = reqwest::Client::new();
+        let url = server.uri();
+        with_retry(|| client.get(&url).send(), retries, 1)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_retries_retryable_status() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        let response = send_with_retry(&mock_server, 3).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(mock_server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_skips_non_retryable_status() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+
+        let response = send_with_retry(&mock_server, 3).await;
+        assert_eq!(response.status(), 404);
+        assert_eq!(mock_server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_gives_up_after_retries() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .mount(&mock_server)
+            .await;
+
+        let response = send_with_retry(&mock_server, 2).await;
+        assert_eq!(response.status(), 429);
+        assert_eq!(mock_server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_honors_retry_after() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "1"))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        let start = std::time::Instant::now();
+        let response = send_with_retry(&mock_server, 1).await;
+        assert_eq!(response.status(), 200);
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_backoff_delay_grows_with_jitter() {
+        for attempt in 1..=4 {
+            let ceiling = 100 * (1 << (attempt - 1));
+            let delay = backoff_delay(attempt, 100).as_millis() as u64;
+            assert!(
+                delay >= ceiling / 2 && delay <= ceiling,
+                "{attempt}: {delay}"
+            );
+        }
+    }
 
     #[test]
     fn test_seconds_until_future_time() {
@@ -104,6 +284,42 @@ mod tests {
         assert_eq!(result, 1);
     }
 
+    #[test]
+    fn test_provider_identity() {
+        let id = provider_identity("yahoo", "https://query1.finance.yahoo.com");
+        assert!(id.starts_with("yahoo#"));
+        assert_eq!(id.len(), "yahoo#".len() + 16);
+
+        // Trailing slashes don't change the identity, different hosts do
+        assert_eq!(
+            id,
+            provider_identity("yahoo", "https://query1.finance.yahoo.com/")
+        );
+        assert_ne!(id, provider_identity("yahoo", "http://localhost:8080"));
+        assert_ne!(
+            id,
+            provider_identity("currency", "https://query1.finance.yahoo.com")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_provider_collection_removes_legacy_collection() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = KeyValueStore::new(dir.path());
+        store.get_collection("yahoo", true, true).unwrap();
+        store.get_collection("currency", true, true).unwrap();
+
+        provider_collection(&store, "yahoo", "http://localhost:8080");
+        let names: Vec<String> = store.stats().await.into_iter().map(|(n, _)| n).collect();
+        assert_eq!(
+            names,
+            vec![
+                "currency".to_string(),
+                provider_identity("yahoo", "http://localhost:8080")
+            ]
+        );
+    }
+
     #[test]
     fn test_invalid_time() {
         assert!(seconds_until(24, 0).is_err());
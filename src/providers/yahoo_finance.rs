@@ -1,6 +1,6 @@
-use crate::providers::util::with_retry;
-use crate::{core::cache::Store, store::KeyValueStore};
-use anyhow::{Result, anyhow};
+use crate::providers::http::HttpClient;
+use crate::providers::util::{parse_json, provider_collection};
+use crate::store::KeyValueStore;
 use async_trait::async_trait;
 use chrono::{TimeZone, Utc};
 use serde::Deserialize;
@@ -9,7 +9,10 @@ use std::sync::Arc;
 use tracing::{debug, instrument};
 
 use crate::core::cache::KeyValueCollection;
-use crate::core::{CurrencyRateProvider, HistoricalPeriod, PriceProvider, PriceResult};
+use crate::core::{
+    CurrencyRateProvider, DEFAULT_PERIODS, HistoricalPeriod, PriceProvider, PriceResult,
+    ProviderError, ProviderResult,
+};
 use std::time::Duration;
 
 fn find_closest_price(target_ts: i64, timestamps: &[i64], prices: &[Option<f64>]) -> Option<f64> {
@@ -63,18 +66,16 @@ fn extract_historical_prices(chart_item: &PriceChartItem) -> HashMap<HistoricalP
         }
 
         // Calculate other periods using historical data
-        for period in [
-            HistoricalPeriod::FiveDays,
-            HistoricalPeriod::OneMonth,
-            HistoricalPeriod::OneYear,
-            HistoricalPeriod::ThreeYears,
-            HistoricalPeriod::FiveYears,
-            HistoricalPeriod::TenYears,
-        ] {
-            // Logic is not perfect since we're not excluding weekends and other holidays.
-            // Use approximation to avoid multiple API calls to the providers.
-            let target_date = reference_date - period.to_duration();
-            if let Some(price) = find_closest_price(target_date.timestamp(), timestamps, closes)
+        for period in DEFAULT_PERIODS
+            .into_iter()
+            .filter(|p| *p != HistoricalPeriod::OneDay)
+        {
+            // The closest price stands in when the start date is a weekend or holiday
+            let Some(target_date) = period.start_date(reference_date.date_naive()) else {
+                continue;
+            };
+            let target_time = target_date.and_time(reference_date.time()).and_utc();
+            if let Some(price) = find_closest_price(target_time.timestamp(), timestamps, closes)
                 && price > 0.0
             {
                 historical_prices.insert(period, price);
@@ -89,16 +90,25 @@ fn extract_historical_prices(chart_item: &PriceChartItem) -> HashMap<HistoricalP
 pub struct YahooFinanceProvider {
     base_url: String,
     cache: Arc<dyn KeyValueCollection>,
+    http: Arc<HttpClient>,
 }
 
 impl YahooFinanceProvider {
     pub fn new(base_url: &str, cache: Arc<KeyValueStore>) -> Self {
-        let collection = cache
-            .get_collection("yahoo", true /* persist */, true /* create */)
-            .unwrap();
+        Self::with_http_client(base_url, cache, Arc::new(HttpClient::default()))
+    }
+
+    /// Creates a provider that sends requests through a shared HTTP client.
+    pub fn with_http_client(
+        base_url: &str,
+        cache: Arc<KeyValueStore>,
+        http: Arc<HttpClient>,
+    ) -> Self {
+        let collection = provider_collection(&cache, "yahoo", base_url);
         YahooFinanceProvider {
             base_url: base_url.to_string(),
             cache: collection,
+            http,
         }
     }
 
@@ -107,6 +117,7 @@ impl YahooFinanceProvider {
         Self {
             base_url: base_url.to_string(),
             cache,
+            http: Arc::new(HttpClient::default()),
         }
     }
 }
@@ -154,9 +165,9 @@ impl PriceProvider for YahooFinanceProvider {
         skip(self),
         fields(symbol = %symbol)
     )]
-    async fn fetch_price(&self, symbol: &str) -> Result<PriceResult> {
+    async fn fetch_price(&self, symbol: &str) -> ProviderResult<PriceResult> {
         if let Some(cached) = self.cache.get(symbol.as_bytes()).await {
-            return Ok(serde_json::from_slice(&cached)?);
+            return parse_json(&cached, "cached price");
         }
 
         let url = format!(
@@ -165,19 +176,20 @@ impl PriceProvider for YahooFinanceProvider {
         );
         debug!("Requesting price data from {}", url);
 
-        let client = reqwest::Client::builder().user_agent("xmf/1.0").build()?;
-        let response = with_retry(|| async { client.get(&url).send().await }, 3, 500)
-            .await
-            .map_err(|e| anyhow!("Request error: {} for symbol: {} URL: {}", e, symbol, url))?;
+        let response = self.http.get_text(&url).await.map_err(|e| match e {
+            ProviderError::NotFound(_) => {
+                ProviderError::NotFound(format!("Unknown symbol: {symbol}"))
+            }
+            e => e.context(format!("Price request for {symbol}")),
+        })?;
 
-        debug!(response = ?response, "Received Yahoo response");
+        debug!(response = %response, "Received Yahoo response");
 
-        let data = response.json::<YahooPriceResponse>().await?;
-        let item = data
-            .chart
-            .result
-            .first()
-            .ok_or_else(|| anyhow!("No price data found for symbol: {}", symbol))?;
+        let data: YahooPriceResponse =
+            parse_json(response.as_bytes(), &format!("price data for {symbol}"))?;
+        let item = data.chart.result.first().ok_or_else(|| {
+            ProviderError::NotFound(format!("No price data found for symbol: {symbol}"))
+        })?;
 
         let mut current_price = item.meta.regular_market_price;
         let mut currency = item.meta.currency.clone();
@@ -241,16 +253,25 @@ impl PriceProvider for YahooFinanceProvider {
 pub struct YahooCurrencyProvider {
     base_url: String,
     cache: Arc<dyn KeyValueCollection>,
+    http: Arc<HttpClient>,
 }
 
 impl YahooCurrencyProvider {
     pub fn new(base_url: &str, cache: Arc<KeyValueStore>) -> Self {
-        let collection = cache
-            .get_collection("currency", true /* persist */, true /* create */)
-            .unwrap();
+        Self::with_http_client(base_url, cache, Arc::new(HttpClient::default()))
+    }
+
+    /// Creates a provider that sends requests through a shared HTTP client.
+    pub fn with_http_client(
+        base_url: &str,
+        cache: Arc<KeyValueStore>,
+        http: Arc<HttpClient>,
+    ) -> Self {
+        let collection = provider_collection(&cache, "currency", base_url);
         YahooCurrencyProvider {
             base_url: base_url.to_string(),
             cache: collection,
+            http,
         }
     }
 
@@ -259,6 +280,7 @@ impl YahooCurrencyProvider {
         Self {
             base_url: base_url.to_string(),
             cache,
+            http: Arc::new(HttpClient::default()),
         }
     }
 }
@@ -286,44 +308,28 @@ struct CurrencyChartMeta {
 
 #[async_trait]
 impl CurrencyRateProvider for YahooCurrencyProvider {
-    async fn get_rate(&self, from: &str, to: &str) -> Result<f64> {
+    async fn get_rate(&self, from: &str, to: &str) -> ProviderResult<f64> {
         let symbol = format!("{from}{to}=X");
         if let Some(cached) = self.cache.get(symbol.as_bytes()).await {
-            return Ok(serde_json::from_slice(&cached)?);
+            return parse_json(&cached, "cached currency rate");
         }
 
         let endpoint = format!("/v8/finance/chart/{symbol}");
         let url = format!("{}{}", self.base_url, endpoint);
         debug!("Requesting currency rate from {}", url);
 
-        let client = reqwest::Client::builder().user_agent("xmf/1.0").build()?;
-
-        let response = with_retry(|| async { client.get(&url).send().await }, 3, 500)
+        let text = self
+            .http
+            .get_text(&url)
             .await
-            .map_err(|e| anyhow!("Request error: {} for currency pair: {}", e, symbol))?;
-
-        if !response.status().is_success() {
-            return Err(anyhow!(
-                "HTTP error: {} for currency pair: {}",
-                response.status(),
-                symbol
-            ));
-        }
+            .map_err(|e| e.context(format!("Currency pair {symbol}")))?;
 
-        let text = response
-            .text()
-            .await
-            .map_err(|e| anyhow!("Failed to read response text: {}", e))?;
+        let data: YahooCurrencyResponse =
+            parse_json(text.as_bytes(), &format!("currency rate for {symbol}"))?;
 
-        let data: YahooCurrencyResponse = serde_json::from_str(&text)
-            .map_err(|e| anyhow!("Failed to parse JSON response for {}: {}", symbol, e))?;
-
-        let item = data
-            .chart
-            .result
-            .into_iter()
-            .next()
-            .ok_or_else(|| anyhow!("No rate data found for currency pair: {}", symbol))?;
+        let item = data.chart.result.into_iter().next().ok_or_else(|| {
+            ProviderError::NotFound(format!("No rate data found for currency pair: {symbol}"))
+        })?;
 
         let rate = item.meta.regular_market_price;
         self.cache
@@ -563,10 +569,9 @@ mod tests {
 
         let provider = YahooFinanceProvider::new_with_collection(&mock_server.uri(), cache);
         let result = provider.fetch_price("INVALID").await;
-        assert!(result.is_err());
         assert_eq!(
-            result.unwrap_err().to_string(),
-            "No price data found for symbol: INVALID"
+            result.unwrap_err(),
+            ProviderError::NotFound("No price data found for symbol: INVALID".to_string())
         );
     }
 
@@ -628,6 +633,30 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_price_cache_is_namespaced_by_base_url() {
+        let response = |price: f64| {
+            format!(
+                r#"{{"chart": {{"result": [{{"meta": {{"regularMarketPrice": {price}, "currency": "USD"}}}}]}}}}"#
+            )
+        };
+        let first_server = create_mock_server("AAPL", &response(100.0)).await;
+        let second_server = create_mock_server("AAPL", &response(200.0)).await;
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(KeyValueStore::new(dir.path()));
+
+        let first = YahooFinanceProvider::new(&first_server.uri(), Arc::clone(&store));
+        assert_eq!(first.fetch_price("AAPL").await.unwrap().price, 100.0);
+
+        // Same store, different endpoint: must not be served from the first cache
+        let second = YahooFinanceProvider::new(&second_server.uri(), Arc::clone(&store));
+        assert_eq!(second.fetch_price("AAPL").await.unwrap().price, 200.0);
+
+        let sources = store.sources();
+        assert_eq!(sources.len(), 2);
+        assert!(sources.values().any(|s| *s == first_server.uri()));
+    }
+
     // Tests for YahooCurrencyProvider (CurrencyRateProvider)
     #[tokio::test]
     async fn test_successful_rate_fetch() {
@@ -681,10 +710,9 @@ mod tests {
             .await;
 
         let result = provider.get_rate("USD", "EUR").await;
-        assert!(result.is_err());
         assert_eq!(
-            result.unwrap_err().to_string(),
-            "No rate data found for currency pair: USDEUR=X"
+            result.unwrap_err(),
+            ProviderError::NotFound("No rate data found for currency pair: USDEUR=X".to_string())
         );
     }
 
@@ -702,11 +730,10 @@ mod tests {
             .await;
 
         let result = provider.get_rate("USD", "EUR").await;
-        assert!(result.is_err());
-        assert_eq!(
-            result.unwrap_err().to_string(),
-            "HTTP error: 500 Internal Server Error for currency pair: USDEUR=X"
-        );
+        assert!(matches!(
+            result.unwrap_err(),
+            ProviderError::Other(msg) if msg.starts_with("Currency pair USDEUR=X: HTTP 500")
+        ));
     }
 
     #[tokio::test]
@@ -729,12 +756,9 @@ mod tests {
             .await;
 
         let result = provider.get_rate("USD", "EUR").await;
-        assert!(result.is_err());
-        assert!(
-            result
-                .unwrap_err()
-                .to_string()
-                .contains("Failed to parse JSON response for USDEUR=X")
-        );
+        assert!(matches!(
+            result.unwrap_err(),
+            ProviderError::InvalidResponse(msg) if msg.contains("Failed to parse currency rate for USDEUR=X")
+        ));
     }
 }
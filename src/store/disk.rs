@@ -1,35 +1,130 @@
-use crate::core::cache::KeyValueCollection;
+use super::lru::LruIndex;
+use crate::core::cache::{
+    CollectionLimits, CollectionStats, KeyValueCollection, SWEEP_EVERY_N_PUTS,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use fjall::{Config, Keyspace, PartitionCreateOptions, PartitionHandle, PersistMode};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 use tracing::debug;
 
+/// Reserved partition mapping collection names to the source of their data.
+const SOURCES_PARTITION: &str = "$sources";
+
+/// Age after which a read rewrites an entry's access time, which only later
+/// runs need since the current one tracks accesses in memory
+const ACCESS_PERSIST_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 #[derive(Serialize, Deserialize)]
 struct CacheEntry {
     value: Vec<u8>,
     expires_at: Option<SystemTime>,
+    /// Last read or write of the entry, used for LRU eviction. Older caches
+    /// don't have this field and are treated as least recently used.
+    #[serde(default)]
+    accessed_at: Option<SystemTime>,
+}
+
+impl CacheEntry {
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| now > expires_at)
+    }
 }
 
 pub struct DiskStore {
     keyspace: Arc<Keyspace>,
+    /// Keep expired entries and serve them, for offline use
+    serve_stale: bool,
 }
 
 impl DiskStore {
     pub fn new(path: &std::path::Path) -> Result<Self> {
         let keyspace = Arc::new(Config::new(path).open()?);
-        Ok(Self { keyspace })
+        Ok(Self {
+            keyspace,
+            serve_stale: false,
+        })
+    }
+
+    /// Makes collections opened from now on keep expired entries and serve
+    /// them instead of removing them.
+    pub fn set_serve_stale(&mut self, serve_stale: bool) {
+        self.serve_stale = serve_stale;
     }
 
     pub fn get_collection(&self, name: &str) -> Result<DiskCollection> {
-        Ok(DiskCollection::new(
+        self.get_collection_with_limits(name, CollectionLimits::default())
+    }
+
+    pub fn get_collection_with_limits(
+        &self,
+        name: &str,
+        limits: CollectionLimits,
+    ) -> Result<DiskCollection> {
+        Ok(DiskCollection::open(
             self.keyspace
                 .open_partition(name, PartitionCreateOptions::default())?,
+            limits,
+            self.serve_stale,
         ))
     }
 
+    /// Returns the names of all collections persisted on disk.
+    pub fn list_collections(&self) -> Vec<String> {
+        self.keyspace
+            .list_partitions()
+            .into_iter()
+            .map(|name| name.to_string())
+            .filter(|name| name != SOURCES_PARTITION)
+            .collect()
+    }
+
+    /// Records where the data in `collection` comes from, e.g. a provider URL.
+    pub fn set_source(&self, collection: &str, source: &str) -> Result<()> {
+        let partition = self
+            .keyspace
+            .open_partition(SOURCES_PARTITION, PartitionCreateOptions::default())?;
+        partition.insert(collection, source)?;
+        Ok(())
+    }
+
+    /// Returns the recorded source for each collection.
+    pub fn sources(&self) -> Result<HashMap<String, String>> {
+        let partition = self
+            .keyspace
+            .open_partition(SOURCES_PARTITION, PartitionCreateOptions::default())?;
+        partition
+            .iter()
+            .map(|item| {
+                let (key, value) = item?;
+                Ok((
+                    String::from_utf8_lossy(&key).to_string(),
+                    String::from_utf8_lossy(&value).to_string(),
+                ))
+            })
+            .collect()
+    }
+
+    /// Deletes a collection and its recorded source. Returns false if there
+    /// is no such collection.
+    pub fn delete_collection(&self, name: &str) -> Result<bool> {
+        if !self.list_collections().iter().any(|c| c == name) {
+            return Ok(false);
+        }
+        let partition = self
+            .keyspace
+            .open_partition(name, PartitionCreateOptions::default())?;
+        self.keyspace.delete_partition(partition)?;
+        self.keyspace
+            .open_partition(SOURCES_PARTITION, PartitionCreateOptions::default())?
+            .remove(name)?;
+        Ok(true)
+    }
+
     pub fn persist(&self) -> Result<()> {
         self.keyspace.persist(PersistMode::SyncAll)?;
         Ok(())
@@ -48,31 +143,164 @@ impl DiskStore {
 
 pub struct DiskCollection {
     partition: PartitionHandle,
+    limits: CollectionLimits,
+    /// Size and recency of the entries, loaded on open
+    usage: Mutex<LruIndex>,
+    /// Serve expired entries instead of removing them, except when swept explicitly
+    serve_stale: bool,
+    puts_since_sweep: AtomicUsize,
+    expired: AtomicU64,
+    evictions: AtomicU64,
+    stale_hits: AtomicU64,
 }
 
 impl DiskCollection {
     pub fn new(partition: PartitionHandle) -> Self {
-        Self { partition }
+        Self::with_limits(partition, CollectionLimits::default())
+    }
+
+    /// Creates a collection bounded by `limits`. Expired entries left behind
+    /// by earlier runs are swept on open.
+    pub fn with_limits(partition: PartitionHandle, limits: CollectionLimits) -> Self {
+        Self::open(partition, limits, false)
+    }
+
+    /// Creates a collection bounded by `limits`, which keeps and serves
+    /// expired entries if `serve_stale` is set and sweeps them on open otherwise.
+    pub fn open(partition: PartitionHandle, limits: CollectionLimits, serve_stale: bool) -> Self {
+        let collection = Self {
+            partition,
+            limits,
+            usage: Mutex::new(LruIndex::default()),
+            serve_stale,
+            puts_since_sweep: AtomicUsize::new(0),
+            expired: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            stale_hits: AtomicU64::new(0),
+        };
+        if let Err(e) = collection.load() {
+            debug!("DiskCollection load error: {}", e);
+        }
+        collection
+    }
+
+    /// Sweeps the collection and indexes the remaining entries by their
+    /// persisted access time.
+    fn load(&self) -> Result<()> {
+        let now = SystemTime::now();
+        let mut expired_keys = Vec::new();
+        let mut entries = Vec::new();
+        for item in self.partition.iter() {
+            let (key, value) = item?;
+            match serde_json::from_slice::<CacheEntry>(&value) {
+                Ok(entry) if self.serve_stale || !entry.is_expired(now) => {
+                    let size = entry_size(&key, &value);
+                    entries.push((entry.accessed_at, key, size));
+                }
+                _ => expired_keys.push(key),
+            }
+        }
+        for key in &expired_keys {
+            self.partition.remove(&**key)?;
+        }
+        self.expired
+            .fetch_add(expired_keys.len() as u64, Ordering::Relaxed);
+
+        entries.sort_by_key(|(accessed_at, _, _)| *accessed_at);
+        let mut usage = self.usage.lock().unwrap();
+        for (_, key, size) in entries {
+            usage.insert(&key, size);
+        }
+        Ok(())
+    }
+
+    /// Removes expired and unreadable entries.
+    fn sweep_entries(&self) -> Result<usize> {
+        let now = SystemTime::now();
+        let mut expired_keys = Vec::new();
+        for item in self.partition.iter() {
+            let (key, value) = item?;
+            let expired = serde_json::from_slice::<CacheEntry>(&value)
+                .map_or(true, |entry| entry.is_expired(now));
+            if expired {
+                expired_keys.push(key);
+            }
+        }
+        let removed = expired_keys.len();
+        let mut usage = self.usage.lock().unwrap();
+        for key in expired_keys {
+            self.partition.remove(&*key)?;
+            usage.remove(&key);
+        }
+        self.expired.fetch_add(removed as u64, Ordering::Relaxed);
+        Ok(removed)
+    }
+
+    /// Writes an entry and indexes it as the most recently used.
+    fn write(&self, key: &[u8], entry: &CacheEntry) -> Result<()> {
+        let value = serde_json::to_vec(entry)?;
+        self.partition.insert(key, &value)?;
+        self.usage
+            .lock()
+            .unwrap()
+            .insert(key, entry_size(key, &value));
+        Ok(())
+    }
+
+    /// Evicts least recently used entries until the collection is within limits.
+    fn evict_entries(&self) -> Result<()> {
+        let evicted = self.usage.lock().unwrap().evict(&self.limits);
+        for key in &evicted {
+            self.partition.remove(key)?;
+        }
+        self.evictions
+            .fetch_add(evicted.len() as u64, Ordering::Relaxed);
+        Ok(())
     }
 }
 
+fn entry_size(key: &[u8], value: &[u8]) -> u64 {
+    (key.len() + value.len()) as u64
+}
+
 #[async_trait]
 impl KeyValueCollection for DiskCollection {
     async fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
         let res: Result<Option<Vec<u8>>> = (|| {
             if let Some(value) = self.partition.get(key)? {
-                let entry: CacheEntry = serde_json::from_slice(&value)?;
-                if let Some(expires_at) = entry.expires_at
-                    && SystemTime::now() > expires_at
-                {
+                let mut entry: CacheEntry = serde_json::from_slice(&value)?;
+                let now = SystemTime::now();
+                if entry.is_expired(now) && self.serve_stale {
+                    debug!(
+                        "Serving stale cache entry for key: {:?}",
+                        String::from_utf8_lossy(key)
+                    );
+                    self.usage.lock().unwrap().touch(key);
+                    self.stale_hits.fetch_add(1, Ordering::Relaxed);
+                    return Ok(Some(entry.value));
+                }
+                if entry.is_expired(now) {
                     debug!(
                         "Cache entry expired for key: {:?}",
                         String::from_utf8_lossy(key)
                     );
                     self.partition.remove(key)?;
+                    self.usage.lock().unwrap().remove(key);
+                    self.expired.fetch_add(1, Ordering::Relaxed);
                     return Ok(None);
                 }
                 debug!("Cache HIT for key: {:?}", String::from_utf8_lossy(key));
+                self.usage.lock().unwrap().touch(key);
+                // Persist the access for later runs only now and then, so
+                // reads rarely write
+                let stale = entry.accessed_at.is_none_or(|accessed_at| {
+                    now.duration_since(accessed_at)
+                        .is_ok_and(|age| age >= ACCESS_PERSIST_INTERVAL)
+                });
+                if self.limits.is_bounded() && stale {
+                    entry.accessed_at = Some(now);
+                    self.write(key, &entry)?;
+                }
                 return Ok(Some(entry.value));
             }
             debug!("Cache MISS for key: {:?}", String::from_utf8_lossy(key));
@@ -90,13 +318,25 @@ impl KeyValueCollection for DiskCollection {
 
     async fn put(&self, key: &[u8], value: &[u8], ttl: Option<Duration>) {
         let res: Result<()> = (|| {
-            let expires_at = ttl.map(|d| SystemTime::now() + d);
+            let now = SystemTime::now();
+            let expires_at = ttl.map(|d| now + d);
             let entry = CacheEntry {
                 value: value.to_vec(),
                 expires_at,
+                accessed_at: Some(now),
             };
-            self.partition.insert(key, serde_json::to_vec(&entry)?)?;
+            self.write(key, &entry)?;
             debug!("Cache PUT for key: {:?}", String::from_utf8_lossy(key));
+
+            if !self.serve_stale
+                && self.puts_since_sweep.fetch_add(1, Ordering::Relaxed) + 1 >= SWEEP_EVERY_N_PUTS
+            {
+                self.puts_since_sweep.store(0, Ordering::Relaxed);
+                self.sweep_entries()?;
+            }
+            if self.limits.is_bounded() {
+                self.evict_entries()?;
+            }
             Ok(())
         })();
         if let Err(e) = res {
@@ -108,6 +348,7 @@ impl KeyValueCollection for DiskCollection {
         if let Err(e) = self.partition.remove(key) {
             debug!("DiskCollection remove error: {}", e);
         }
+        self.usage.lock().unwrap().remove(key);
     }
 
     async fn clear(&self) {
@@ -125,6 +366,25 @@ impl KeyValueCollection for DiskCollection {
         if let Err(e) = res {
             debug!("DiskCollection clear error: {}", e);
         }
+        self.usage.lock().unwrap().clear();
+    }
+
+    async fn sweep(&self) -> usize {
+        self.sweep_entries().unwrap_or_else(|e| {
+            debug!("DiskCollection sweep error: {}", e);
+            0
+        })
+    }
+
+    async fn stats(&self) -> CollectionStats {
+        let usage = self.usage.lock().unwrap();
+        CollectionStats {
+            entries: usage.count(),
+            bytes: usage.bytes(),
+            expired: self.expired.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            stale_hits: self.stale_hits.load(Ordering::Relaxed),
+        }
     }
 }
 
@@ -254,4 +514,162 @@ mod tests {
         store.clear().unwrap();
         assert_eq!(store.keyspace.list_partitions().len(), 0);
     }
+
+    #[test]
+    fn test_disk_store_sources() {
+        let dir = tempdir().unwrap();
+        let store = DiskStore::new(dir.path()).unwrap();
+
+        store.get_collection("test1").unwrap();
+        store.set_source("test1", "http://localhost:8080").unwrap();
+
+        assert_eq!(store.list_collections(), vec!["test1".to_string()]);
+        assert_eq!(
+            store.sources().unwrap().get("test1").map(String::as_str),
+            Some("http://localhost:8080")
+        );
+    }
+
+    #[test]
+    fn test_disk_store_delete_collection() {
+        let dir = tempdir().unwrap();
+        let store = DiskStore::new(dir.path()).unwrap();
+
+        store.get_collection("test1").unwrap();
+        store.get_collection("test2").unwrap();
+        store.set_source("test1", "http://localhost:8080").unwrap();
+
+        assert!(store.delete_collection("test1").unwrap());
+        assert!(!store.delete_collection("test1").unwrap());
+        assert_eq!(store.list_collections(), vec!["test2".to_string()]);
+        assert!(store.sources().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_sweep_on_open() {
+        let dir = tempdir().unwrap();
+        let store = DiskStore::new(dir.path()).unwrap();
+
+        {
+            let cache = store.get_collection("test").unwrap();
+            cache
+                .put(b"key1", b"value1", Some(Duration::from_millis(10)))
+                .await;
+            cache.put(b"key2", b"value2", None).await;
+        }
+        sleep(Duration::from_millis(20)).await;
+
+        let cache = store.get_collection("test").unwrap();
+        let stats = cache.stats().await;
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.expired, 1);
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_serves_stale_entries() {
+        let dir = tempdir().unwrap();
+        let mut store = DiskStore::new(dir.path()).unwrap();
+
+        {
+            let cache = store.get_collection("test").unwrap();
+            cache
+                .put(b"key1", b"value1", Some(Duration::from_millis(10)))
+                .await;
+        }
+        sleep(Duration::from_millis(20)).await;
+
+        store.set_serve_stale(true);
+        let cache = store.get_collection("test").unwrap();
+        assert_eq!(cache.get(b"key1").await, Some(b"value1".to_vec()));
+        let stats = cache.stats().await;
+        assert_eq!((stats.entries, stats.expired, stats.stale_hits), (1, 0, 1));
+
+        // An explicit sweep still removes the entry
+        assert_eq!(cache.sweep().await, 1);
+        assert_eq!(cache.get(b"key1").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_sweep() {
+        let (cache, _dir) = create_test_collection();
+
+        cache
+            .put(b"key1", b"value1", Some(Duration::from_millis(10)))
+            .await;
+        cache.put(b"key2", b"value2", None).await;
+        sleep(Duration::from_millis(20)).await;
+
+        assert_eq!(cache.sweep().await, 1);
+        assert_eq!(cache.stats().await.entries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_stats_track_writes() {
+        let (cache, _dir) = create_test_collection();
+
+        cache.put(b"key1", b"value1", None).await;
+        cache.put(b"key2", b"value2", None).await;
+        let bytes = cache.stats().await.bytes;
+        cache.put(b"key2", b"a longer value2", None).await;
+        assert!(cache.stats().await.bytes > bytes);
+
+        cache.remove(b"key1").await;
+        let stats = cache.stats().await;
+        assert_eq!(stats.entries, 1);
+        assert_eq!(
+            stats.bytes,
+            cache
+                .partition
+                .iter()
+                .map(|item| {
+                    let (key, value) = item.unwrap();
+                    entry_size(&key, &value)
+                })
+                .sum::<u64>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_max_entries_evicts_lru() {
+        let dir = tempdir().unwrap();
+        let store = DiskStore::new(dir.path()).unwrap();
+        let cache = store
+            .get_collection_with_limits(
+                "test",
+                CollectionLimits {
+                    max_entries: Some(2),
+                    max_bytes: None,
+                },
+            )
+            .unwrap();
+
+        cache.put(b"key1", b"value1", None).await;
+        sleep(Duration::from_millis(5)).await;
+        cache.put(b"key2", b"value2", None).await;
+        sleep(Duration::from_millis(5)).await;
+        // Touch key1 so key2 becomes the least recently used
+        assert!(cache.get(b"key1").await.is_some());
+        sleep(Duration::from_millis(5)).await;
+        cache.put(b"key3", b"value3", None).await;
+
+        assert!(cache.get(b"key1").await.is_some());
+        assert!(cache.get(b"key2").await.is_none());
+        assert!(cache.get(b"key3").await.is_some());
+
+        let stats = cache.stats().await;
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.evictions, 1);
+
+        // Reopened, entries are ordered by their persisted access times, which
+        // recent reads leave as the time of the last write
+        drop(cache);
+        let limits = CollectionLimits {
+            max_entries: Some(2),
+            max_bytes: None,
+        };
+        let cache = store.get_collection_with_limits("test", limits).unwrap();
+        cache.put(b"key4", b"value4", None).await;
+        assert!(cache.get(b"key1").await.is_none());
+        assert!(cache.get(b"key3").await.is_some());
+    }
 }
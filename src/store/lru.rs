@@ -0,0 +1,104 @@
+use crate::core::cache::CollectionLimits;
+use std::collections::{BTreeMap, HashMap};
+
+/// Size and recency of the entries in a collection, kept up to date on every
+/// write so limits are checked and the least recently used entry is found
+/// without scanning the collection.
+#[derive(Debug, Default)]
+pub struct LruIndex {
+    clock: u64,
+    bytes: u64,
+    /// Last access and size of each key
+    entries: HashMap<Vec<u8>, (u64, u64)>,
+    /// Keys by last access, oldest first
+    order: BTreeMap<u64, Vec<u8>>,
+}
+
+impl LruIndex {
+    pub fn count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Records an entry of `size` bytes as the most recently used.
+    pub fn insert(&mut self, key: &[u8], size: u64) {
+        self.remove(key);
+        self.clock += 1;
+        self.entries.insert(key.to_vec(), (self.clock, size));
+        self.order.insert(self.clock, key.to_vec());
+        self.bytes += size;
+    }
+
+    /// Marks an entry as the most recently used.
+    pub fn touch(&mut self, key: &[u8]) {
+        let Some((access, _)) = self.entries.get_mut(key) else {
+            return;
+        };
+        if let Some(key) = self.order.remove(access) {
+            self.clock += 1;
+            *access = self.clock;
+            self.order.insert(self.clock, key);
+        }
+    }
+
+    /// Forgets an entry. Returns true if it was tracked.
+    pub fn remove(&mut self, key: &[u8]) -> bool {
+        let Some((access, size)) = self.entries.remove(key) else {
+            return false;
+        };
+        self.order.remove(&access);
+        self.bytes -= size;
+        true
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Forgets least recently used entries until the collection is within
+    /// `limits`, returning their keys.
+    pub fn evict(&mut self, limits: &CollectionLimits) -> Vec<Vec<u8>> {
+        let mut evicted = Vec::new();
+        while !limits.allows(self.count(), self.bytes) {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some((_, size)) = self.entries.remove(&key) {
+                self.bytes -= size;
+            }
+            evicted.push(key);
+        }
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_index_tracks_size_and_order() {
+        let mut index = LruIndex::default();
+        index.insert(b"a", 10);
+        index.insert(b"b", 20);
+        index.insert(b"c", 30);
+        // Rewriting an entry replaces its size
+        index.insert(b"c", 5);
+        assert_eq!((index.count(), index.bytes()), (3, 35));
+
+        index.touch(b"a");
+        let limits = CollectionLimits {
+            max_entries: Some(1),
+            max_bytes: None,
+        };
+        assert_eq!(index.evict(&limits), vec![b"b".to_vec(), b"c".to_vec()]);
+        assert_eq!((index.count(), index.bytes()), (1, 10));
+
+        assert!(index.remove(b"a"));
+        assert!(!index.remove(b"a"));
+        assert_eq!((index.count(), index.bytes()), (0, 0));
+    }
+}
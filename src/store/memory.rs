@@ -1,6 +1,11 @@
-use crate::core::cache::KeyValueCollection;
+use super::lru::LruIndex;
+use crate::core::cache::{
+    CollectionLimits, CollectionStats, KeyValueCollection, SWEEP_EVERY_N_PUTS,
+};
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
@@ -9,19 +14,61 @@ struct CacheValue<V> {
     expires_at: Option<Instant>,
 }
 
+impl<V> CacheValue<V> {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expiry| expiry < now)
+    }
+}
+
 /// In-memory cache implementation using HashMap and RwLock
 pub struct MemoryCollection {
     inner: RwLock<HashMap<Vec<u8>, CacheValue<Vec<u8>>>>,
+    /// Size and recency of the entries, locked after `inner`
+    usage: Mutex<LruIndex>,
+    limits: CollectionLimits,
+    puts_since_sweep: AtomicUsize,
+    expired: AtomicU64,
+    evictions: AtomicU64,
 }
 
 // ---- MemoryCollection implementation ----
 impl MemoryCollection {
     /// Creates a new MemoryCache instance
     pub fn new() -> Self {
+        Self::with_limits(CollectionLimits::default())
+    }
+
+    /// Creates a new MemoryCache instance bounded by `limits`
+    pub fn with_limits(limits: CollectionLimits) -> Self {
         Self {
             inner: RwLock::new(HashMap::new()),
+            usage: Mutex::new(LruIndex::default()),
+            limits,
+            puts_since_sweep: AtomicUsize::new(0),
+            expired: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
         }
     }
+
+    fn sweep_locked(&self, cache: &mut HashMap<Vec<u8>, CacheValue<Vec<u8>>>) -> usize {
+        let now = Instant::now();
+        let mut usage = self.usage.lock().unwrap();
+        let before = cache.len();
+        cache.retain(|key, entry| {
+            let expired = entry.is_expired(now);
+            if expired {
+                usage.remove(key);
+            }
+            !expired
+        });
+        let removed = before - cache.len();
+        self.expired.fetch_add(removed as u64, Ordering::Relaxed);
+        removed
+    }
+}
+
+fn entry_size(key: &[u8], value: &[u8]) -> u64 {
+    (key.len() + value.len()) as u64
 }
 
 impl Default for MemoryCollection {
@@ -33,17 +80,25 @@ impl Default for MemoryCollection {
 #[async_trait]
 impl KeyValueCollection for MemoryCollection {
     async fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
-        let cache = self.inner.read().await;
-        if let Some(entry) = cache.get(key) {
-            // Check if entry has expired
-            if let Some(expiry) = entry.expires_at
-                && expiry < Instant::now()
-            {
-                return None;
+        {
+            let cache = self.inner.read().await;
+            let entry = cache.get(key)?;
+            if !entry.is_expired(Instant::now()) {
+                self.usage.lock().unwrap().touch(key);
+                return Some(entry.value.clone());
             }
-            return Some(entry.value.clone());
         }
 
+        // Entry has expired, drop it so it doesn't linger in memory
+        let mut cache = self.inner.write().await;
+        if cache
+            .get(key)
+            .is_some_and(|entry| entry.is_expired(Instant::now()))
+        {
+            cache.remove(key);
+            self.usage.lock().unwrap().remove(key);
+            self.expired.fetch_add(1, Ordering::Relaxed);
+        }
         None
     }
 
@@ -56,16 +111,51 @@ impl KeyValueCollection for MemoryCollection {
 
         let mut cache = self.inner.write().await;
         cache.insert(key.into(), cache_value);
+        self.usage
+            .lock()
+            .unwrap()
+            .insert(key, entry_size(key, value));
+
+        if self.puts_since_sweep.fetch_add(1, Ordering::Relaxed) + 1 >= SWEEP_EVERY_N_PUTS {
+            self.puts_since_sweep.store(0, Ordering::Relaxed);
+            self.sweep_locked(&mut cache);
+        }
+        if self.limits.is_bounded() {
+            let evicted = self.usage.lock().unwrap().evict(&self.limits);
+            for key in &evicted {
+                cache.remove(key);
+            }
+            self.evictions
+                .fetch_add(evicted.len() as u64, Ordering::Relaxed);
+        }
     }
 
     async fn remove(&self, key: &[u8]) {
         let mut cache = self.inner.write().await;
         cache.remove(key);
+        self.usage.lock().unwrap().remove(key);
     }
 
     async fn clear(&self) {
         let mut cache = self.inner.write().await;
         cache.clear();
+        self.usage.lock().unwrap().clear();
+    }
+
+    async fn sweep(&self) -> usize {
+        let mut cache = self.inner.write().await;
+        self.sweep_locked(&mut cache)
+    }
+
+    async fn stats(&self) -> CollectionStats {
+        let usage = self.usage.lock().unwrap();
+        CollectionStats {
+            entries: usage.count(),
+            bytes: usage.bytes(),
+            expired: self.expired.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            stale_hits: 0,
+        }
     }
 }
 
@@ -124,6 +214,48 @@ mod tests {
         assert!(!cache.remove_collection("nonexistent"));
     }
 
+    #[tokio::test]
+    async fn test_cache_stats_and_sweep() {
+        let dir = tempdir().unwrap();
+        let cache = KeyValueStore::new(dir.path());
+
+        let disk_collection = cache.get_collection("test_disk", true, true).unwrap();
+        disk_collection
+            .put(b"key1", b"value1", Some(Duration::from_millis(10)))
+            .await;
+        disk_collection.put(b"key2", b"value2", None).await;
+        let mem_collection = cache.get_collection("test_mem", false, true).unwrap();
+        mem_collection.put(b"key1", b"value1", None).await;
+
+        sleep(Duration::from_millis(20)).await;
+        assert_eq!(cache.sweep().await, 1);
+
+        let stats = cache.stats().await;
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].0, "test_disk");
+        assert_eq!(stats[0].1.entries, 1);
+        assert_eq!(stats[0].1.expired, 1);
+        assert_eq!(stats[1].0, "test_mem");
+        assert_eq!(stats[1].1.entries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_store_sweep_counts_collections_swept_on_open() {
+        let dir = tempdir().unwrap();
+        {
+            let cache = KeyValueStore::new(dir.path());
+            let collection = cache.get_collection("test_disk", true, true).unwrap();
+            collection
+                .put(b"key1", b"value1", Some(Duration::from_millis(10)))
+                .await;
+            cache.persist();
+        }
+        sleep(Duration::from_millis(20)).await;
+
+        let cache = KeyValueStore::new(dir.path());
+        assert_eq!(cache.sweep().await, 1);
+    }
+
     #[tokio::test]
     async fn test_collection_get_put() {
         let cache = MemoryCollection::new();
@@ -223,4 +355,75 @@ mod tests {
 
         assert_eq!(value, Some(b"myvalue".to_vec()));
     }
+
+    #[tokio::test]
+    async fn test_collection_get_removes_expired_entry() {
+        let cache = MemoryCollection::new();
+
+        cache
+            .put(b"key1", b"value1", Some(Duration::from_millis(10)))
+            .await;
+        sleep(Duration::from_millis(20)).await;
+
+        assert!(cache.get(b"key1").await.is_none());
+        let stats = cache.stats().await;
+        assert_eq!(stats.entries, 0);
+        assert_eq!(stats.expired, 1);
+    }
+
+    #[tokio::test]
+    async fn test_collection_sweep() {
+        let cache = MemoryCollection::new();
+
+        cache
+            .put(b"key1", b"value1", Some(Duration::from_millis(10)))
+            .await;
+        cache.put(b"key2", b"value2", None).await;
+        sleep(Duration::from_millis(20)).await;
+
+        assert_eq!(cache.sweep().await, 1);
+        assert_eq!(cache.stats().await.entries, 1);
+        assert_eq!(cache.get(b"key2").await, Some(b"value2".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_collection_max_entries_evicts_lru() {
+        let cache = MemoryCollection::with_limits(CollectionLimits {
+            max_entries: Some(2),
+            max_bytes: None,
+        });
+
+        cache.put(b"key1", b"value1", None).await;
+        cache.put(b"key2", b"value2", None).await;
+        // Touch key1 so key2 becomes the least recently used
+        assert!(cache.get(b"key1").await.is_some());
+        cache.put(b"key3", b"value3", None).await;
+
+        assert!(cache.get(b"key1").await.is_some());
+        assert!(cache.get(b"key2").await.is_none());
+        assert!(cache.get(b"key3").await.is_some());
+
+        let stats = cache.stats().await;
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.evictions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_collection_max_bytes_evicts_lru() {
+        // Each entry is 4 bytes key + 6 bytes value
+        let cache = MemoryCollection::with_limits(CollectionLimits {
+            max_entries: None,
+            max_bytes: Some(25),
+        });
+
+        cache.put(b"key1", b"value1", None).await;
+        cache.put(b"key2", b"value2", None).await;
+        cache.put(b"key3", b"value3", None).await;
+
+        let stats = cache.stats().await;
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.bytes, 20);
+        assert_eq!(stats.evictions, 1);
+        assert!(cache.get(b"key1").await.is_none());
+    }
 }
@@ -1,7 +1,9 @@
 pub mod disk;
+mod lru;
 pub mod memory;
 
-use crate::core::cache::{KeyValueCollection, Store};
+use crate::core::cache::{CollectionStats, KeyValueCollection, Store};
+use crate::core::config::CacheConfig;
 use anyhow::Result;
 use disk::{DiskCollection, DiskStore};
 use memory::MemoryCollection;
@@ -10,19 +12,136 @@ use std::{
     collections::HashMap,
     sync::{Arc, RwLock},
 };
+use tracing::debug;
 
 /// A thread-safe key-value store that can hold multiple collections.
 pub struct KeyValueStore {
     collections: RwLock<HashMap<String, Arc<dyn Any + Send + Sync>>>,
     disk_store: Option<DiskStore>,
+    cache_config: CacheConfig,
 }
 
 impl KeyValueStore {
     pub fn new(path: &std::path::Path) -> Self {
+        Self::with_config(path, CacheConfig::default())
+    }
+
+    /// Creates a store whose collections are bounded by the limits in `cache_config`.
+    pub fn with_config(path: &std::path::Path, cache_config: CacheConfig) -> Self {
         Self {
             collections: RwLock::new(HashMap::new()),
             disk_store: DiskStore::new(path).ok(),
+            cache_config,
+        }
+    }
+
+    /// Keeps expired entries of persisted collections and serves them, for
+    /// offline use where nothing fresher can be fetched.
+    pub fn serving_stale(mut self, serve_stale: bool) -> Self {
+        if let Some(ds) = &mut self.disk_store {
+            ds.set_serve_stale(serve_stale);
+        }
+        self
+    }
+
+    /// Returns the number of reads served from expired entries so far.
+    pub async fn stale_hits(&self) -> u64 {
+        let collections: Vec<_> = {
+            let collections = self.collections.read().unwrap();
+            collections
+                .values()
+                .filter_map(|collection| as_collection(Arc::clone(collection)))
+                .collect()
+        };
+        let mut stale_hits = 0;
+        for collection in collections {
+            stale_hits += collection.stats().await.stale_hits;
+        }
+        stale_hits
+    }
+
+    /// Records where the data in a persisted collection comes from, so it can
+    /// be shown alongside cache statistics.
+    pub fn set_source(&self, collection: &str, source: &str) {
+        if let Some(ds) = &self.disk_store
+            && let Err(e) = ds.set_source(collection, source)
+        {
+            debug!("Failed to record source for {}: {}", collection, e);
+        }
+    }
+
+    /// Returns the recorded source for each persisted collection.
+    pub fn sources(&self) -> HashMap<String, String> {
+        self.disk_store
+            .as_ref()
+            .and_then(|ds| ds.sources().ok())
+            .unwrap_or_default()
+    }
+
+    /// Deletes a persisted collection if it exists. Returns true if it did.
+    pub fn remove_persisted(&self, name: &str) -> bool {
+        let Some(ds) = &self.disk_store else {
+            return false;
+        };
+        let mut collections = self.collections.write().unwrap();
+        if collections
+            .get(name)
+            .is_some_and(|collection| collection.downcast_ref::<DiskCollection>().is_some())
+        {
+            collections.remove(name);
+        }
+        drop(collections);
+        ds.delete_collection(name).unwrap_or_else(|e| {
+            debug!("Failed to delete collection {}: {}", name, e);
+            false
+        })
+    }
+
+    /// Removes expired entries from every collection, including persisted
+    /// collections which are not opened yet. Returns the number of entries removed.
+    pub async fn sweep(&self) -> usize {
+        // Opening a persisted collection sweeps it, so count those entries too
+        let mut removed = 0;
+        for collection in self.open_persisted() {
+            removed += collection.stats().await.expired as usize;
+        }
+        for (_, collection) in self.all_collections() {
+            removed += collection.sweep().await;
         }
+        removed
+    }
+
+    /// Returns statistics for every collection, sorted by collection name.
+    pub async fn stats(&self) -> Vec<(String, CollectionStats)> {
+        let mut stats = Vec::new();
+        for (name, collection) in self.all_collections() {
+            stats.push((name, collection.stats().await));
+        }
+        stats.sort_by(|(a, _), (b, _)| a.cmp(b));
+        stats
+    }
+
+    /// Opens the persisted collections not opened yet, returning them.
+    fn open_persisted(&self) -> Vec<Arc<dyn KeyValueCollection>> {
+        let Some(ds) = &self.disk_store else {
+            return Vec::new();
+        };
+        ds.list_collections()
+            .into_iter()
+            .filter(|name| !self.collections.read().unwrap().contains_key(name))
+            .filter_map(|name| self.get_collection(&name, true, true))
+            .collect()
+    }
+
+    fn all_collections(&self) -> Vec<(String, Arc<dyn KeyValueCollection>)> {
+        self.open_persisted();
+        let collections = self.collections.read().unwrap();
+        collections
+            .iter()
+            .filter_map(|(name, collection)| {
+                as_collection(Arc::clone(collection)).map(|c| (name.clone(), c))
+            })
+            .collect()
     }
 
     #[cfg(test)]
@@ -43,6 +162,16 @@ impl KeyValueStore {
     }
 }
 
+fn as_collection(collection: Arc<dyn Any + Send + Sync>) -> Option<Arc<dyn KeyValueCollection>> {
+    match collection.downcast::<DiskCollection>() {
+        Ok(disk) => Some(disk),
+        Err(other) => other
+            .downcast::<MemoryCollection>()
+            .ok()
+            .map(|memory| memory as Arc<dyn KeyValueCollection>),
+    }
+}
+
 // impl Default for KeyValueStore {
 //     fn default() -> Self {
 //         Self::new()
@@ -59,13 +188,14 @@ impl Store for KeyValueStore {
         if create_if_missing {
             let mut collections = self.collections.write().unwrap();
             if !collections.contains_key(name) {
+                let limits = self.cache_config.limits_for(name);
                 let new_collection: Option<Arc<dyn Any + Send + Sync>> = if persist {
                     self.disk_store
                         .as_ref()
-                        .and_then(|ds| ds.get_collection(name).ok())
+                        .and_then(|ds| ds.get_collection_with_limits(name, limits).ok())
                         .map(|collection| Arc::new(collection) as Arc<dyn Any + Send + Sync>)
                 } else {
-                    Some(Arc::new(MemoryCollection::new()))
+                    Some(Arc::new(MemoryCollection::with_limits(limits)))
                 };
 
                 if let Some(collection) = new_collection {
@@ -22,7 +22,7 @@ mod test_utils {
     // New helper for AMFI mock server in integration tests
     pub async fn create_amfi_mock_server(isin: &str, mock_response: &str) -> wiremock::MockServer {
         let mock_server = MockServer::start().await;
-        let url_path = format!("/{isin}"); // AMFI provider uses format!("{}/{}", self.base_url, identifier);
+        let url_path = format!("/nav/{isin}");
 
         Mock::given(method("GET"))
             .and(path(&url_path))
@@ -131,7 +131,7 @@ async fn test_real_kuvera_api() {
 #[test_log::test(tokio::test)]
 async fn test_full_app_flow_with_amfi_mock() {
     let isin = "INF789F01XA0";
-    let mock_response = r#"{"nav": 125.75}"#;
+    let mock_response = r#"{"nav": 125.75, "date": "2024-01-01"}"#;
 
     // Setup mock server for AMFI
     let mock_server = test_utils::create_amfi_mock_server(isin, mock_response).await;
@@ -161,7 +161,14 @@ async fn test_full_app_flow_with_amfi_mock() {
     fs::write(config_path, &config_content).expect("Failed to write config file");
 
     // Run app and verify success
-    let result = xmf::run_command(xmf::AppCommand::Summary, Some(config_path), false).await;
+    let result = xmf::run_command(
+        xmf::AppCommand::Summary,
+        Some(config_path),
+        false,
+        false,
+        &xmf::PortfolioView::default(),
+    )
+    .await;
     assert!(
         result.is_ok(),
         "Main function failed with: {:?}",
@@ -228,7 +235,14 @@ async fn test_full_app_flow_with_mock() {
     fs::write(config_path, &config_content).expect("Failed to write config file");
 
     // Run app and verify success
-    let result = xmf::run_command(xmf::AppCommand::Summary, Some(config_path), false).await;
+    let result = xmf::run_command(
+        xmf::AppCommand::Summary,
+        Some(config_path),
+        false,
+        false,
+        &xmf::PortfolioView::default(),
+    )
+    .await;
     assert!(
         result.is_ok(),
         "Main function failed with: {:?}",
@@ -385,10 +399,15 @@ async fn test_rolling_returns_command() {
 
     let result = xmf::run_command(
         AppCommand::Returns {
+            periods: Vec::new(),
             rolling_period: Some("1y".to_string()),
+            calendar: false,
+            fiscal: false,
         },
         Some(config_path),
         false,
+        false,
+        &xmf::PortfolioView::default(),
     )
     .await;
     assert!(
@@ -397,3 +416,277 @@ async fn test_rolling_returns_command() {
         result.err()
     );
 }
+
+fn write_stock_config(config_path: &std::path::Path, data_dir: &std::path::Path, base_url: &str) {
+    let config_content = format!(
+        r#"
+        portfolios:
+          - name: "Tech"
+            investments:
+              - symbol: "AAPLX"
+                units: 10
+        providers:
+          yahoo:
+            base_url: {}
+        currency: "USD"
+        data_path: "{}"
+    "#,
+        base_url,
+        data_dir.to_string_lossy().replace('\\', "\\\\")
+    );
+    fs::write(config_path, &config_content).expect("Failed to write config file");
+}
+
+#[test_log::test(tokio::test)]
+async fn test_unknown_symbol_exit_code() {
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_server)
+        .await;
+
+    let config_file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+    let temp_dir = tempfile::tempdir().unwrap();
+    write_stock_config(config_file.path(), temp_dir.path(), &mock_server.uri());
+
+    let result = xmf::run_command(
+        xmf::AppCommand::Summary,
+        Some(config_file.path()),
+        false,
+        false,
+        &xmf::PortfolioView::default(),
+    )
+    .await;
+    let err = result.expect_err("Unknown symbol should fail the command");
+    assert_eq!(
+        err.downcast_ref::<xmf::core::ProviderError>(),
+        Some(&xmf::core::ProviderError::NotFound(
+            "Unknown symbol: AAPLX".to_string()
+        ))
+    );
+    assert_eq!(xmf::exit_code(&err), 3);
+}
+
+#[test_log::test(tokio::test)]
+async fn test_offline_without_cache_exit_code() {
+    use wiremock::MockServer;
+
+    let mock_server = MockServer::start().await;
+    let config_file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+    let temp_dir = tempfile::tempdir().unwrap();
+    write_stock_config(config_file.path(), temp_dir.path(), &mock_server.uri());
+
+    let result = xmf::run_command(
+        xmf::AppCommand::Summary,
+        Some(config_file.path()),
+        false,
+        true,
+        &xmf::PortfolioView::default(),
+    )
+    .await;
+    let err = result.expect_err("Offline mode without cached data should fail");
+    assert_eq!(xmf::exit_code(&err), 4);
+    assert!(mock_server.received_requests().await.unwrap().is_empty());
+}
+
+#[test_log::test(tokio::test)]
+async fn test_consolidated_summary_merges_portfolios() {
+    let isin = "INF789F01XA0";
+    let mock_response = r#"{"nav": 125.75, "date": "2024-01-01"}"#;
+    let mock_server = test_utils::create_amfi_mock_server(isin, mock_response).await;
+
+    let config_file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+    let temp_dir = tempfile::tempdir().unwrap();
+    let config_content = format!(
+        r#"
+        portfolios:
+          - name: "Mine"
+            investments:
+              - isin: "{isin}"
+                units: 100.0
+          - name: "Spouse"
+            investments:
+              - isin: "{isin}"
+                units: 50.0
+        providers:
+          amfi:
+            base_url: {}
+        currency: "INR"
+        data_path: "{}"
+    "#,
+        mock_server.uri(),
+        temp_dir.path().to_string_lossy().replace('\\', "\\\\")
+    );
+    fs::write(config_file.path(), &config_content).expect("Failed to write config file");
+
+    let view = xmf::PortfolioView {
+        consolidated: true,
+        ..Default::default()
+    };
+    for command in [
+        xmf::AppCommand::Summary,
+        xmf::AppCommand::Change {
+            periods: Vec::new(),
+        },
+    ] {
+        let result = xmf::run_command(command, Some(config_file.path()), false, false, &view).await;
+        assert!(
+            result.is_ok(),
+            "Consolidated command failed: {:?}",
+            result.err()
+        );
+    }
+}
+
+#[test_log::test(tokio::test)]
+async fn test_consolidated_summary_with_different_targets() {
+    let isin = "INF789F01XA0";
+    let mock_response = r#"{"nav": 125.75, "date": "2024-01-01"}"#;
+    let mock_server = test_utils::create_amfi_mock_server(isin, mock_response).await;
+
+    let config_file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+    let temp_dir = tempfile::tempdir().unwrap();
+    let config_content = format!(
+        r#"
+        portfolios:
+          - name: "Mine"
+            target_allocation:
+              Equity: 100
+            investments:
+              - isin: "{isin}"
+                units: 100.0
+          - name: "Spouse"
+            target_allocation:
+              Equity: 50
+              Debt: 50
+            investments:
+              - isin: "{isin}"
+                units: 50.0
+        providers:
+          amfi:
+            base_url: {}
+        currency: "INR"
+        data_path: "{}"
+    "#,
+        mock_server.uri(),
+        temp_dir.path().to_string_lossy().replace('\\', "\\\\")
+    );
+    fs::write(config_file.path(), &config_content).expect("Failed to write config file");
+
+    let view = xmf::PortfolioView {
+        consolidated: true,
+        ..Default::default()
+    };
+    let result = xmf::run_command(
+        xmf::AppCommand::Summary,
+        Some(config_file.path()),
+        false,
+        false,
+        &view,
+    )
+    .await;
+    assert!(
+        result.is_ok(),
+        "Consolidated summary failed: {:?}",
+        result.err()
+    );
+
+    let result = xmf::run_command(
+        xmf::AppCommand::Rebalance {
+            tolerance: 5.0,
+            new_money: None,
+        },
+        Some(config_file.path()),
+        false,
+        false,
+        &view,
+    )
+    .await;
+    let err = result.expect_err("Rebalancing without a single target should fail");
+    assert!(err.to_string().contains("different target allocations"));
+}
+
+#[test_log::test(tokio::test)]
+async fn test_portfolio_filter_skips_unselected_providers() {
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let isin = "INF789F01XA0";
+    let mock_response = r#"{"nav": 125.75, "date": "2024-01-01"}"#;
+    let amfi_server = test_utils::create_amfi_mock_server(isin, mock_response).await;
+    let yahoo_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&yahoo_server)
+        .await;
+
+    let config_file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+    let temp_dir = tempfile::tempdir().unwrap();
+    let config_content = format!(
+        r#"
+        portfolios:
+          - name: "Funds"
+            investments:
+              - isin: "{isin}"
+                units: 100.0
+          - name: "Stocks"
+            investments:
+              - symbol: "AAPLX"
+                units: 10
+                tags: ["core"]
+        providers:
+          amfi:
+            base_url: {}
+          yahoo:
+            base_url: {}
+        currency: "INR"
+        data_path: "{}"
+    "#,
+        amfi_server.uri(),
+        yahoo_server.uri(),
+        temp_dir.path().to_string_lossy().replace('\\', "\\\\")
+    );
+    fs::write(config_file.path(), &config_content).expect("Failed to write config file");
+
+    let view = xmf::PortfolioView {
+        filter: xmf::core::config::PortfolioFilter {
+            names: vec!["fund*".to_string()],
+            tags: Vec::new(),
+        },
+        consolidated: false,
+    };
+    let result = xmf::run_command(
+        xmf::AppCommand::Summary,
+        Some(config_file.path()),
+        false,
+        false,
+        &view,
+    )
+    .await;
+    assert!(
+        result.is_ok(),
+        "Filtered summary failed: {:?}",
+        result.err()
+    );
+    assert!(yahoo_server.received_requests().await.unwrap().is_empty());
+
+    let view = xmf::PortfolioView {
+        filter: xmf::core::config::PortfolioFilter {
+            names: Vec::new(),
+            tags: vec!["missing".to_string()],
+        },
+        consolidated: false,
+    };
+    let result = xmf::run_command(
+        xmf::AppCommand::Summary,
+        Some(config_file.path()),
+        false,
+        false,
+        &view,
+    )
+    .await;
+    assert_eq!(xmf::exit_code(&result.unwrap_err()), 1);
+}